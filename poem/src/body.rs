@@ -6,7 +6,8 @@ use std::{
 };
 
 use bytes::{Bytes, BytesMut};
-use futures_util::{Stream, TryStreamExt};
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use http::{HeaderMap, header};
 use http_body_util::BodyExt;
 use hyper::body::{Body as _, Frame};
 use serde::{Serialize, de::DeserializeOwned};
@@ -263,6 +264,51 @@ impl Body {
             .map_err(crate::error::ParseXmlError::Parse)?)
     }
 
+    /// Reads the body if it is not larger than the limit, otherwise returns a
+    /// body that streams the original content instead.
+    ///
+    /// The `Content-Length` of the `headers` is checked first, so that a
+    /// large body is not read at all.
+    pub(crate) async fn into_bytes_within(
+        self,
+        headers: &HeaderMap,
+        limit: usize,
+    ) -> Result<Bytes, Body> {
+        let content_length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+        if content_length.is_some_and(|len| len > limit) {
+            return Err(self);
+        }
+
+        let mut stream = self.into_bytes_stream();
+        let mut chunks = Vec::new();
+        let mut size = 0;
+
+        while let Some(item) = stream.next().await {
+            match item {
+                Ok(chunk) if size + chunk.len() <= limit => {
+                    size += chunk.len();
+                    chunks.push(chunk);
+                }
+                item => {
+                    let head = futures_util::stream::iter(chunks.into_iter().map(Ok));
+                    return Err(Body::from_bytes_stream(
+                        head.chain(futures_util::stream::once(async move { item }))
+                            .chain(stream),
+                    ));
+                }
+            }
+        }
+
+        let mut body = Vec::with_capacity(size);
+        for chunk in chunks {
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body.into())
+    }
+
     /// Consumes this body object to return a reader.
     pub fn into_async_read(self) -> impl AsyncRead + Unpin + Send + 'static {
         tokio_util::io::StreamReader::new(self.into_bytes_stream())
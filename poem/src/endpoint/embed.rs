@@ -3,8 +3,9 @@ use std::marker::PhantomData;
 use rust_embed::RustEmbed;
 
 use crate::{
-    Endpoint, Error, Request, Response,
+    Endpoint, Error, FromRequest, IntoResponse, Request, Response,
     http::{Method, StatusCode, header},
+    web::StaticFileRequest,
 };
 
 /// An endpoint that wraps a single file from a `rust-embed` bundle.
@@ -35,23 +36,12 @@ impl<E: RustEmbed + Send + Sync> Endpoint for EmbeddedFileEndpoint<E> {
 
         match E::get(&self.path) {
             Some(content) => {
-                let hash = hex::encode(content.metadata.sha256_hash());
-                if req
-                    .headers()
-                    .get(header::IF_NONE_MATCH)
-                    .map(|etag| etag.to_str().unwrap_or("000000").eq(&hash))
-                    .unwrap_or(false)
-                {
-                    return Err(StatusCode::NOT_MODIFIED.into());
-                }
-
-                // otherwise, return 200 with etag hash
-                let body: Vec<u8> = content.data.into();
+                let etag = format!("\"{}\"", hex::encode(content.metadata.sha256_hash()));
                 let mime = mime_guess::from_path(&self.path).first_or_octet_stream();
-                Ok(Response::builder()
-                    .header(header::CONTENT_TYPE, mime.as_ref())
-                    .header(header::ETAG, hash)
-                    .body(body))
+                Ok(StaticFileRequest::from_request_without_body(&req)
+                    .await?
+                    .create_response_from_data_with_etag(content.data, mime.as_ref(), etag)?
+                    .into_response())
             }
             None => Err(StatusCode::NOT_FOUND.into()),
         }
@@ -1,4 +1,4 @@
-use std::{future::Future, marker::PhantomData, sync::Arc};
+use std::{future::Future, marker::PhantomData, sync::Arc, time::Duration};
 
 use futures_util::{FutureExt, future::BoxFuture};
 
@@ -9,7 +9,7 @@ use super::{
 use crate::{
     Error, IntoResponse, Middleware, Request, Response, Result,
     error::IntoResult,
-    middleware::{AddData, AddDataEndpoint},
+    middleware::{AddData, AddDataEndpoint, Timeout, TimeoutEndpoint},
 };
 
 /// An HTTP request handler.
@@ -757,6 +757,36 @@ pub trait EndpointExt: IntoEndpoint {
     {
         InspectError::new(self, f)
     }
+
+    /// Cancels this endpoint if it does not complete within `timeout`, similar
+    /// to `with(Timeout::new(timeout))`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use poem::{EndpointExt, handler, http::StatusCode, test::TestClient};
+    ///
+    /// #[handler]
+    /// async fn index() {
+    ///     tokio::time::sleep(Duration::from_secs(1)).await;
+    /// }
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let resp = TestClient::new(index.timeout(Duration::from_millis(10)))
+    ///     .get("/")
+    ///     .send()
+    ///     .await;
+    /// resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    /// # });
+    /// ```
+    fn timeout(self, timeout: Duration) -> TimeoutEndpoint<Self::Endpoint>
+    where
+        Self: Sized,
+    {
+        self.with(Timeout::new(timeout))
+    }
 }
 
 impl<T: IntoEndpoint> EndpointExt for T {}
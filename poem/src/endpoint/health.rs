@@ -0,0 +1,478 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use futures_util::future::{BoxFuture, join_all};
+use http::StatusCode;
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::{Endpoint, IntoResponse, Request, Response, Result, web::Json};
+
+type CheckFn = Box<dyn Fn() -> BoxFuture<'static, Result<()>> + Send + Sync>;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum CheckKind {
+    Liveness,
+    Readiness,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Clone, Serialize)]
+struct CheckReport {
+    status: Status,
+    kind: CheckKind,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    status: Status,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    shutting_down: bool,
+    checks: BTreeMap<&'a str, CheckReport>,
+}
+
+/// A named health check for the [`Health`] endpoint.
+pub struct HealthCheck {
+    name: String,
+    kind: CheckKind,
+    check: CheckFn,
+    timeout: Option<Duration>,
+    cache_ttl: Option<Duration>,
+    cached: Mutex<Option<(Instant, CheckReport)>>,
+}
+
+impl HealthCheck {
+    fn new<F, Fut>(name: impl Into<String>, kind: CheckKind, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            kind,
+            check: Box::new(move || Box::pin(check())),
+            timeout: None,
+            cache_ttl: None,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Create a liveness check.
+    ///
+    /// Liveness checks report whether the process is working at all, if one
+    /// of them fails the orchestrator should restart the process.
+    pub fn liveness<F, Fut>(name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self::new(name, CheckKind::Liveness, check)
+    }
+
+    /// Create a readiness check.
+    ///
+    /// Readiness checks report whether the process can serve traffic, for
+    /// example whether its database is reachable. If one of them fails the
+    /// load balancer should stop routing requests to the process.
+    pub fn readiness<F, Fut>(name: impl Into<String>, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Self::new(name, CheckKind::Readiness, check)
+    }
+
+    /// Sets the maximum duration of the check, default is `None`.
+    ///
+    /// A check that takes longer is reported as failed.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Caches the result of the check for the specified duration, default is
+    /// `None`.
+    ///
+    /// Use this for expensive checks, so that frequent probes do not overload
+    /// the dependencies.
+    #[must_use]
+    pub fn cache(self, ttl: Duration) -> Self {
+        Self {
+            cache_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    async fn run(&self) -> CheckReport {
+        if let Some(ttl) = self.cache_ttl {
+            if let Some((checked_at, report)) = &*self.cached.lock() {
+                if checked_at.elapsed() < ttl {
+                    return report.clone();
+                }
+            }
+        }
+
+        let start = Instant::now();
+        let res = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, (self.check)())
+                .await
+                .unwrap_or_else(|_| {
+                    Err(crate::Error::from_string(
+                        "timed out",
+                        StatusCode::SERVICE_UNAVAILABLE,
+                    ))
+                }),
+            None => (self.check)().await,
+        };
+        let report = CheckReport {
+            status: if res.is_ok() {
+                Status::Up
+            } else {
+                Status::Down
+            },
+            kind: self.kind,
+            duration_ms: start.elapsed().as_millis() as u64,
+            error: res.err().map(|err| err.to_string()),
+        };
+
+        if self.cache_ttl.is_some() {
+            *self.cached.lock() = Some((Instant::now(), report.clone()));
+        }
+        report
+    }
+}
+
+/// An endpoint that reports the health of the application.
+///
+/// The checks are run concurrently, and the report is returned as JSON with
+/// `200 OK` if all of them succeed, or `503 Service Unavailable` otherwise.
+///
+/// - `/live` runs the liveness checks.
+/// - `/ready` runs the liveness and readiness checks.
+/// - Any other path runs all the checks.
+///
+/// The report contains the status of every check, but not the error messages
+/// of the failed checks unless [`Health::expose_errors`] is enabled, since the
+/// endpoint is usually reachable without authentication.
+///
+/// When the [`Server`](crate::Server) begins graceful shutdown, `/ready`
+/// reports that the application is not ready, so that the load balancer
+/// drains the traffic before the server stops accepting connections. Use
+/// [`Server::shutdown_delay`](crate::Server::shutdown_delay) to give it time
+/// to notice.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     Route,
+///     endpoint::{Health, HealthCheck},
+///     http::StatusCode,
+///     test::TestClient,
+/// };
+///
+/// let health = Health::new()
+///     .check(HealthCheck::liveness("event_loop", || async { Ok(()) }))
+///     .check(
+///         HealthCheck::readiness("database", || async {
+///             Err(poem::Error::from_string(
+///                 "connection refused",
+///                 StatusCode::SERVICE_UNAVAILABLE,
+///             ))
+///         })
+///         .timeout(Duration::from_secs(1))
+///         .cache(Duration::from_secs(5)),
+///     );
+/// let app = Route::new().nest("/health", health);
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.get("/health/live").send().await.assert_status_is_ok();
+///
+/// let resp = cli.get("/health/ready").send().await;
+/// resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+/// let json = resp.json().await;
+/// json.value().object().get("status").assert_string("down");
+/// json.value()
+///     .object()
+///     .get("checks")
+///     .object()
+///     .get("database")
+///     .object()
+///     .get("status")
+///     .assert_string("down");
+/// # });
+/// ```
+#[derive(Default)]
+pub struct Health {
+    checks: Vec<HealthCheck>,
+    expose_errors: bool,
+}
+
+impl Health {
+    /// Create a `Health` endpoint without checks.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a check.
+    #[must_use]
+    pub fn check(mut self, check: HealthCheck) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Includes the error messages of the failed checks in the report,
+    /// default is `false`.
+    ///
+    /// The messages may reveal details of the infrastructure, only enable
+    /// this if the endpoint is not publicly reachable.
+    #[must_use]
+    pub fn expose_errors(self, expose_errors: bool) -> Self {
+        Self {
+            expose_errors,
+            ..self
+        }
+    }
+
+    async fn report(&self, kinds: &[CheckKind], shutting_down: bool) -> Response {
+        let checks = self
+            .checks
+            .iter()
+            .filter(|check| kinds.contains(&check.kind));
+        let reports = join_all(checks.clone().map(|check| check.run())).await;
+        let checks = checks
+            .map(|check| check.name.as_str())
+            .zip(reports)
+            .map(|(name, mut report)| {
+                if !self.expose_errors {
+                    report.error = None;
+                }
+                (name, report)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let status = if shutting_down || checks.values().any(|r| r.status == Status::Down) {
+            Status::Down
+        } else {
+            Status::Up
+        };
+        let report = Report {
+            status,
+            shutting_down,
+            checks,
+        };
+        Json(report)
+            .with_status(match status {
+                Status::Up => StatusCode::OK,
+                Status::Down => StatusCode::SERVICE_UNAVAILABLE,
+            })
+            .with_header(http::header::CACHE_CONTROL, "no-store")
+            .into_response()
+    }
+}
+
+impl Endpoint for Health {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        #[cfg(feature = "server")]
+        let shutting_down = req
+            .data::<crate::ServerHandle>()
+            .is_some_and(|handle| handle.is_shutting_down());
+        #[cfg(not(feature = "server"))]
+        let shutting_down = false;
+
+        // `/ready` and the full report run the same checks
+        Ok(if req.uri().path().trim_end_matches('/') == "/live" {
+            self.report(&[CheckKind::Liveness], false).await
+        } else {
+            self.report(&[CheckKind::Liveness, CheckKind::Readiness], shutting_down)
+                .await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use super::*;
+    use crate::{EndpointExt, Route, test::TestClient};
+
+    fn failing() -> Result<()> {
+        Err(crate::Error::from_string(
+            "failed",
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    }
+
+    #[tokio::test]
+    async fn report() {
+        let cli = TestClient::new(
+            Route::new().nest(
+                "/health",
+                Health::new()
+                    .check(HealthCheck::liveness("a", || async { Ok(()) }))
+                    .check(HealthCheck::readiness("b", || async { failing() })),
+            ),
+        );
+
+        let resp = cli.get("/health/live").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header("cache-control", "no-store");
+        let json = resp.json().await;
+        let value = json.value().object();
+        value.get("status").assert_string("up");
+        value.get("checks").object().assert_len(1);
+        value
+            .get("checks")
+            .object()
+            .get("a")
+            .object()
+            .get("kind")
+            .assert_string("liveness");
+
+        let resp = cli.get("/health/ready").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        let json = resp.json().await;
+        let value = json.value().object();
+        value.get("status").assert_string("down");
+        let b = value.get("checks").object().get("b").object();
+        b.get("status").assert_string("down");
+        b.get("kind").assert_string("readiness");
+        assert!(b.get_opt("error").is_none());
+
+        let resp = cli.get("/health").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.json()
+            .await
+            .value()
+            .object()
+            .get("checks")
+            .object()
+            .assert_len(2);
+    }
+
+    #[tokio::test]
+    async fn expose_errors() {
+        let cli = TestClient::new(
+            Health::new()
+                .check(HealthCheck::readiness("b", || async { failing() }))
+                .expose_errors(true),
+        );
+        let resp = cli.get("/ready").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.json()
+            .await
+            .value()
+            .object()
+            .get("checks")
+            .object()
+            .get("b")
+            .object()
+            .get("error")
+            .assert_string("failed");
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let cli = TestClient::new(
+            Health::new()
+                .check(
+                    HealthCheck::readiness("slow", || async {
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                        Ok(())
+                    })
+                    .timeout(Duration::from_millis(10)),
+                )
+                .expose_errors(true),
+        );
+        let resp = cli.get("/ready").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.json()
+            .await
+            .value()
+            .object()
+            .get("checks")
+            .object()
+            .get("slow")
+            .object()
+            .get("error")
+            .assert_string("timed out");
+    }
+
+    #[tokio::test]
+    async fn cache() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let cli = TestClient::new(
+            Health::new().check(
+                HealthCheck::readiness("db", {
+                    let calls = calls.clone();
+                    move || {
+                        let calls = calls.clone();
+                        async move {
+                            calls.fetch_add(1, Ordering::SeqCst);
+                            Ok(())
+                        }
+                    }
+                })
+                .cache(Duration::from_millis(100)),
+            ),
+        );
+
+        cli.get("/ready").send().await.assert_status_is_ok();
+        cli.get("/ready").send().await.assert_status_is_ok();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        cli.get("/ready").send().await.assert_status_is_ok();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn shutting_down() {
+        let handle = crate::ServerHandle::default();
+        let cli = TestClient::new(
+            Health::new()
+                .check(HealthCheck::liveness("a", || async { Ok(()) }))
+                .data(handle.clone()),
+        );
+
+        cli.get("/ready").send().await.assert_status_is_ok();
+
+        handle.shutdown();
+        let resp = cli.get("/ready").send().await;
+        resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        resp.json()
+            .await
+            .value()
+            .object()
+            .get("shutting_down")
+            .assert_bool(true);
+
+        // the process is still alive
+        cli.get("/live").send().await.assert_status_is_ok();
+    }
+}
@@ -10,12 +10,15 @@ mod catch_error;
 mod embed;
 #[allow(clippy::module_inception)]
 mod endpoint;
+mod health;
 mod inspect_all_err;
 mod inspect_err;
 mod map;
 mod map_to_response;
 #[cfg(feature = "prometheus")]
 mod prometheus_exporter;
+#[cfg(feature = "reverse-proxy")]
+mod reverse_proxy;
 #[cfg(feature = "static-files")]
 mod static_files;
 mod to_response;
@@ -34,12 +37,15 @@ pub use endpoint::{
     BoxEndpoint, DynEndpoint, EitherEndpoint, Endpoint, EndpointExt, IntoEndpoint, ToDynEndpoint,
     make, make_sync,
 };
+pub use health::{Health, HealthCheck};
 pub use inspect_all_err::InspectAllError;
 pub use inspect_err::InspectError;
 pub use map::Map;
 pub use map_to_response::MapToResponse;
 #[cfg(feature = "prometheus")]
 pub use prometheus_exporter::PrometheusExporter;
+#[cfg(feature = "reverse-proxy")]
+pub use reverse_proxy::ReverseProxy;
 #[cfg(feature = "static-files")]
 pub use static_files::{StaticFileEndpoint, StaticFilesEndpoint};
 pub use to_response::ToResponse;
@@ -0,0 +1,555 @@
+use std::{
+    net::SocketAddr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, Uri, header, uri::PathAndQuery};
+use http_body_util::BodyExt;
+use hyper_util::{
+    client::legacy::{Client, connect::HttpConnector},
+    rt::{TokioExecutor, TokioIo},
+};
+
+use crate::{Endpoint, Request, Response, Result, body::BoxBody, error::ReverseProxyError};
+
+/// The hop-by-hop headers, they are not forwarded.
+const HOP_BY_HOP_HEADERS: [HeaderName; 9] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// An endpoint that forwards the requests to upstream servers.
+///
+/// The request and response bodies are streamed, the hop-by-hop headers are
+/// removed, and the `X-Forwarded-For`, `X-Forwarded-Host`,
+/// `X-Forwarded-Proto` and `Forwarded` headers are added to the forwarded
+/// request. WebSocket and other HTTP upgrades are passed through.
+///
+/// The path of the request is appended to the path of the upstream URI, so
+/// when the endpoint is nested with [`Route::nest`](crate::Route::nest), the
+/// prefix is stripped. Use
+/// [`Route::nest_no_strip`](crate::Route::nest_no_strip) to forward the full
+/// path.
+///
+/// If multiple upstreams are specified, they are used in turn.
+///
+/// # Errors
+///
+/// - [`ReverseProxyError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{Route, endpoint::ReverseProxy};
+///
+/// // `/api/users` is forwarded to `http://10.0.0.1:8080/v1/users` or
+/// // `http://10.0.0.2:8080/v1/users`.
+/// let app = Route::new().nest(
+///     "/api",
+///     ReverseProxy::new("http://10.0.0.1:8080/v1").upstream("http://10.0.0.2:8080/v1"),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "reverse-proxy")))]
+pub struct ReverseProxy {
+    upstreams: Vec<Uri>,
+    next: AtomicUsize,
+    client: Client<HttpConnector, BoxBody>,
+    preserve_host: bool,
+    timeout: Option<Duration>,
+}
+
+impl ReverseProxy {
+    /// Create a `ReverseProxy` that forwards the requests to the specified
+    /// upstream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the upstream is not an absolute `http` URI.
+    pub fn new(upstream: impl AsRef<str>) -> Self {
+        Self {
+            upstreams: vec![parse_upstream(upstream.as_ref())],
+            next: AtomicUsize::new(0),
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            preserve_host: false,
+            timeout: None,
+        }
+    }
+
+    /// Adds an upstream.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the upstream is not an absolute `http` URI.
+    #[must_use]
+    pub fn upstream(mut self, upstream: impl AsRef<str>) -> Self {
+        self.upstreams.push(parse_upstream(upstream.as_ref()));
+        self
+    }
+
+    /// Forwards the `Host` header of the request, default is `false`, which
+    /// means that the `Host` header is set to the authority of the upstream.
+    #[must_use]
+    pub fn preserve_host(self, preserve_host: bool) -> Self {
+        Self {
+            preserve_host,
+            ..self
+        }
+    }
+
+    /// Sets the maximum time to wait for the response headers of the
+    /// upstream.
+    #[must_use]
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    fn upstream_uri(&self, uri: &Uri) -> Uri {
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.upstreams.len();
+        let upstream = &self.upstreams[idx];
+
+        let base = upstream.path().trim_end_matches('/');
+        let path_and_query = match uri.query() {
+            Some(query) => format!("{base}{}?{query}", uri.path()),
+            None => format!("{base}{}", uri.path()),
+        };
+
+        let mut parts = upstream.clone().into_parts();
+        parts.path_and_query = Some(
+            PathAndQuery::try_from(path_and_query)
+                .unwrap_or_else(|_| PathAndQuery::from_static("/")),
+        );
+        Uri::from_parts(parts).expect("valid uri")
+    }
+}
+
+fn parse_upstream(upstream: &str) -> Uri {
+    let uri = Uri::try_from(upstream)
+        .unwrap_or_else(|err| panic!("invalid upstream `{upstream}`: {err}"));
+    assert!(
+        uri.scheme_str() == Some("http") && uri.authority().is_some(),
+        "invalid upstream `{upstream}`: must be an absolute http uri"
+    );
+    uri
+}
+
+fn is_upgrade(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::UPGRADE)
+        && headers
+            .get_all(header::CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case("upgrade"))
+}
+
+/// Removes the hop-by-hop headers, including the ones listed in the
+/// `Connection` header.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::try_from(name.trim()).ok())
+        .collect::<Vec<_>>();
+    for name in listed.iter().chain(&HOP_BY_HOP_HEADERS) {
+        headers.remove(name);
+    }
+}
+
+/// Appends a value to a comma-separated list header, the existing values of
+/// the header are joined into a single one.
+fn append_list(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    let mut values = headers
+        .get_all(&name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect::<Vec<_>>();
+    values.push(value);
+    if let Ok(value) = HeaderValue::try_from(values.join(", ")) {
+        headers.insert(name, value);
+    }
+}
+
+/// Formats a `quoted-string` of the `Forwarded` header.
+fn quoted_string(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('"');
+    for ch in value.chars() {
+        if matches!(ch, '"' | '\\') {
+            s.push('\\');
+        }
+        s.push(ch);
+    }
+    s.push('"');
+    s
+}
+
+fn forwarded_node(addr: Option<&SocketAddr>) -> String {
+    match addr {
+        Some(SocketAddr::V4(addr)) => addr.ip().to_string(),
+        Some(SocketAddr::V6(addr)) => format!("\"[{}]\"", addr.ip()),
+        None => "unknown".to_string(),
+    }
+}
+
+impl Endpoint for ReverseProxy {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let upgrade = is_upgrade(req.headers());
+        let on_upgrade = if upgrade {
+            req.take_upgrade().ok()
+        } else {
+            None
+        };
+
+        let uri = self.upstream_uri(req.uri());
+        let remote_addr = req.remote_addr().as_socket_addr().copied();
+        let proto = req.scheme().to_string();
+        // HTTP/2 and HTTP/3 requests carry the host in the `:authority`
+        // pseudo-header instead of `Host`
+        let host = req
+            .header(header::HOST)
+            .map(ToString::to_string)
+            .or_else(|| req.uri().authority().map(ToString::to_string));
+
+        let mut headers = std::mem::take(req.headers_mut());
+        let upgrade_protocol = headers.get(header::UPGRADE).cloned();
+        remove_hop_by_hop_headers(&mut headers);
+        if let Some(protocol) = upgrade_protocol.filter(|_| upgrade) {
+            headers.insert(header::CONNECTION, HeaderValue::from_static("upgrade"));
+            headers.insert(header::UPGRADE, protocol);
+        }
+
+        if let Some(ip) = remote_addr.map(|addr| addr.ip()) {
+            append_list(&mut headers, X_FORWARDED_FOR, &ip.to_string());
+        }
+        let mut forwarded = format!("for={}", forwarded_node(remote_addr.as_ref()));
+        if let Some(host) = &host {
+            if let Ok(value) = HeaderValue::try_from(host) {
+                headers.insert(X_FORWARDED_HOST, value);
+            }
+            forwarded.push_str(&format!(";host={}", quoted_string(host)));
+        }
+        headers.insert(
+            X_FORWARDED_PROTO,
+            HeaderValue::try_from(&proto).expect("valid header value"),
+        );
+        forwarded.push_str(&format!(";proto={proto}"));
+        append_list(&mut headers, header::FORWARDED, &forwarded);
+
+        if !self.preserve_host {
+            if let Some(authority) = uri.authority() {
+                headers.insert(
+                    header::HOST,
+                    HeaderValue::try_from(authority.as_str()).expect("valid header value"),
+                );
+            }
+        }
+
+        let mut upstream_req = hyper::Request::new(req.take_body().into());
+        *upstream_req.method_mut() = req.method().clone();
+        *upstream_req.uri_mut() = uri;
+        *upstream_req.headers_mut() = headers;
+
+        let resp = self.client.request(upstream_req);
+        let mut resp = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, resp)
+                .await
+                .map_err(|_| ReverseProxyError::Timeout)?,
+            None => resp.await,
+        }
+        .map_err(ReverseProxyError::Upstream)?;
+
+        if resp.status() == StatusCode::SWITCHING_PROTOCOLS {
+            let Some(on_upgrade) = on_upgrade else {
+                return Err(StatusCode::BAD_GATEWAY.into());
+            };
+            let upstream_upgrade = hyper::upgrade::on(&mut resp);
+            tokio::spawn(async move {
+                match tokio::join!(on_upgrade, upstream_upgrade) {
+                    (Ok(mut client), Ok(upstream)) => {
+                        let mut upstream = TokioIo::new(upstream);
+                        _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+                    }
+                    (Err(err), _) => tracing::debug!(error = %err, "client upgrade failed"),
+                    (_, Err(err)) => tracing::debug!(error = %err, "upstream upgrade failed"),
+                }
+            });
+
+            let (parts, _) = resp.into_parts();
+            let mut resp = Response::builder().status(parts.status).finish();
+            *resp.headers_mut() = parts.headers;
+            return Ok(resp);
+        }
+
+        remove_hop_by_hop_headers(resp.headers_mut());
+        Ok(resp.map(|body| body.map_err(std::io::Error::other)).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+    use http::Version;
+
+    use super::*;
+    use crate::{
+        EndpointExt, Route, Server,
+        endpoint::{make, make_sync},
+        handler,
+        listener::{Acceptor, Listener, TcpListener},
+        test::TestClient,
+        web::Path,
+    };
+
+    async fn serve(ep: impl Endpoint + 'static) -> SocketAddr {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(ep));
+        addr
+    }
+
+    #[handler(internal)]
+    fn echo(req: &Request, path: Path<String>, body: String) -> String {
+        let header = |name| req.header(name).unwrap_or("-");
+        format!(
+            "{} /{}?{} host={} xff={} xfh={} xfp={} fwd={} conn={} custom={} body={}",
+            req.method(),
+            path.0,
+            req.uri().query().unwrap_or_default(),
+            header("host"),
+            header("x-forwarded-for"),
+            header("x-forwarded-host"),
+            header("x-forwarded-proto"),
+            header("forwarded"),
+            header("x-connection-only"),
+            header("x-custom"),
+            body,
+        )
+    }
+
+    #[tokio::test]
+    async fn forward() {
+        let addr = serve(Route::new().at("/base/*path", echo)).await;
+        let cli = TestClient::new(
+            Route::new().nest("/api", ReverseProxy::new(format!("http://{addr}/base/"))),
+        );
+
+        let resp = cli
+            .post("/api/users/1")
+            .query("a", &1)
+            .header("host", "example.com")
+            .header("x-forwarded-for", "10.0.0.1")
+            .header("connection", "x-connection-only")
+            .header("x-connection-only", "1")
+            .header("x-custom", "2")
+            .body("hello")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_text(format!(
+            "POST /users/1?a=1 host={addr} xff=10.0.0.1 xfh=example.com xfp=http \
+             fwd=for=unknown;host=\"example.com\";proto=http conn=- custom=2 body=hello"
+        ))
+        .await;
+
+        // the existing values are joined, and the host is escaped
+        let resp = cli
+            .get("/api/a")
+            .header("host", "a\"b\\c")
+            .header("forwarded", "for=10.0.0.1")
+            .header("forwarded", "for=10.0.0.2")
+            .send()
+            .await;
+        let text = resp.0.into_body().into_string().await.unwrap();
+        assert!(
+            text.contains(
+                " xfh=a\"b\\c xfp=http \
+                 fwd=for=10.0.0.1, for=10.0.0.2, for=unknown;host=\"a\\\"b\\\\c\";proto=http "
+            ),
+            "{text}"
+        );
+
+        let cli = TestClient::new(Route::new().nest(
+            "/api",
+            ReverseProxy::new(format!("http://{addr}/base")).preserve_host(true),
+        ));
+        let resp = cli.get("/api/a").header("host", "example.com").send().await;
+        let text = resp.0.into_body().into_string().await.unwrap();
+        assert!(text.starts_with("GET /a? host=example.com"), "{text}");
+    }
+
+    #[tokio::test]
+    async fn forward_authority() {
+        let addr = serve(Route::new().at("/*path", echo)).await;
+        let ep = ReverseProxy::new(format!("http://{addr}"));
+
+        let req = Request::builder()
+            .version(Version::HTTP_2)
+            .uri(Uri::from_static("http://example.com/a"))
+            .finish();
+        let text = ep
+            .get_response(req)
+            .await
+            .into_body()
+            .into_string()
+            .await
+            .unwrap();
+        assert!(
+            text.contains(
+                " xfh=example.com xfp=http fwd=for=unknown;host=\"example.com\";proto=http "
+            ),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn append_list_values() {
+        let mut headers = HeaderMap::new();
+        append_list(&mut headers, X_FORWARDED_FOR, "10.0.0.1");
+        assert_eq!(headers[X_FORWARDED_FOR], "10.0.0.1");
+
+        headers.append(X_FORWARDED_FOR, HeaderValue::from_static("10.0.0.2"));
+        append_list(&mut headers, X_FORWARDED_FOR, "10.0.0.3");
+        assert_eq!(
+            headers.get_all(X_FORWARDED_FOR).iter().collect::<Vec<_>>(),
+            vec!["10.0.0.1, 10.0.0.2, 10.0.0.3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn upstreams() {
+        let addr1 = serve(make_sync(|_| "a")).await;
+        let addr2 = serve(make_sync(|_| "b")).await;
+        let cli = TestClient::new(
+            ReverseProxy::new(format!("http://{addr1}")).upstream(format!("http://{addr2}")),
+        );
+
+        let mut texts = Vec::new();
+        for _ in 0..4 {
+            texts.push(
+                cli.get("/")
+                    .send()
+                    .await
+                    .0
+                    .into_body()
+                    .into_string()
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(texts, ["a", "b", "a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn upstream_error() {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        drop(acceptor);
+
+        let cli = TestClient::new(ReverseProxy::new(format!("http://{addr}")));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_GATEWAY);
+
+        let addr = serve(make(|_| async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            "late"
+        }))
+        .await;
+        let cli = TestClient::new(
+            ReverseProxy::new(format!("http://{addr}")).timeout(Duration::from_millis(50)),
+        );
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn streaming() {
+        let addr = serve(make_sync(|_| {
+            Response::builder().body(crate::Body::from_bytes_stream(
+                futures_util::stream::iter(["a", "b", "c"]).map(Ok::<_, std::io::Error>),
+            ))
+        }))
+        .await;
+        let cli = TestClient::new(ReverseProxy::new(format!("http://{addr}")).map_to_response());
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        let body = BoxBody::from(resp.0.into_body())
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(body, "abc");
+    }
+
+    #[cfg(feature = "websocket")]
+    #[tokio::test]
+    async fn websocket() {
+        use futures_util::SinkExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        use crate::{
+            IntoResponse,
+            web::websocket::{Message as WsMessage, WebSocket},
+        };
+
+        #[handler(internal)]
+        fn ws(ws: WebSocket) -> impl IntoResponse {
+            ws.on_upgrade(|mut socket| async move {
+                while let Some(Ok(WsMessage::Text(text))) = socket.next().await {
+                    if socket
+                        .send(WsMessage::Text(format!("echo: {text}")))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            })
+        }
+
+        let upstream = serve(Route::new().at("/ws", ws)).await;
+        let proxy =
+            serve(Route::new().nest("/proxy", ReverseProxy::new(format!("http://{upstream}"))))
+                .await;
+
+        let (mut client, resp) = tokio_tungstenite::connect_async(format!("ws://{proxy}/proxy/ws"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::SWITCHING_PROTOCOLS);
+
+        client.send(Message::Text("hello".into())).await.unwrap();
+        assert_eq!(
+            client.next().await.unwrap().unwrap(),
+            Message::Text("echo: hello".into())
+        );
+    }
+}
@@ -6,6 +6,8 @@ use std::{
 
 use http::header::LOCATION;
 
+#[cfg(feature = "compression")]
+use crate::web::CompressionAlgo;
 use crate::{
     Body, Endpoint, FromRequest, IntoResponse, Request, Response, Result,
     error::StaticFileError,
@@ -78,6 +80,8 @@ pub struct StaticFilesEndpoint {
     no_cache_index: bool,
     prefer_utf8: bool,
     redirect_to_slash: bool,
+    #[cfg(feature = "compression")]
+    precompressed: Vec<CompressionAlgo>,
 }
 
 impl StaticFilesEndpoint {
@@ -104,6 +108,8 @@ impl StaticFilesEndpoint {
             no_cache_index: false,
             prefer_utf8: true,
             redirect_to_slash: false,
+            #[cfg(feature = "compression")]
+            precompressed: Vec::new(),
         }
     }
 
@@ -170,6 +176,79 @@ impl StaticFilesEndpoint {
             ..self
         }
     }
+
+    /// Serve the brotli compressed `<file>.br` instead of `<file>` if it
+    /// exists and the client accepts it.
+    ///
+    /// The pre-compressed variant is selected according to the
+    /// `Accept-Encoding` header of the request, and the response has the
+    /// `Content-Encoding` and `Vary: Accept-Encoding` headers. `ETag` and
+    /// range requests apply to the served variant.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{Route, endpoint::StaticFilesEndpoint};
+    ///
+    /// let app = Route::new().nest(
+    ///     "/assets",
+    ///     StaticFilesEndpoint::new("/etc/www/assets")
+    ///         .precompressed_br()
+    ///         .precompressed_gzip(),
+    /// );
+    /// ```
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    #[must_use]
+    pub fn precompressed_br(self) -> Self {
+        self.precompressed(CompressionAlgo::BR)
+    }
+
+    /// Serve the gzip compressed `<file>.gz` instead of `<file>` if it exists
+    /// and the client accepts it.
+    ///
+    /// See also [`StaticFilesEndpoint::precompressed_br`].
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    #[must_use]
+    pub fn precompressed_gzip(self) -> Self {
+        self.precompressed(CompressionAlgo::GZIP)
+    }
+
+    /// Serve the zstd compressed `<file>.zst` instead of `<file>` if it exists
+    /// and the client accepts it.
+    ///
+    /// See also [`StaticFilesEndpoint::precompressed_br`].
+    #[cfg(feature = "compression")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "compression")))]
+    #[must_use]
+    pub fn precompressed_zstd(self) -> Self {
+        self.precompressed(CompressionAlgo::ZSTD)
+    }
+
+    #[cfg(feature = "compression")]
+    fn precompressed(mut self, algo: CompressionAlgo) -> Self {
+        if !self.precompressed.contains(&algo) {
+            self.precompressed.push(algo);
+        }
+        self
+    }
+
+    async fn file_response(&self, req: &Request, path: &Path, no_cache: bool) -> Result<Response> {
+        let static_file = StaticFileRequest::from_request_without_body(req).await?;
+        #[cfg(feature = "compression")]
+        if !self.precompressed.is_empty() {
+            return Ok(static_file.create_precompressed_response(
+                path,
+                &self.precompressed,
+                self.prefer_utf8,
+                no_cache,
+            )?);
+        }
+        Ok(static_file
+            .create_response(path, self.prefer_utf8, no_cache)?
+            .into_response())
+    }
 }
 
 impl Endpoint for StaticFilesEndpoint {
@@ -210,10 +289,9 @@ impl Endpoint for StaticFilesEndpoint {
                 if let Some(index_file) = &self.index_file {
                     let index_path = self.path.join(index_file);
                     if index_path.is_file() {
-                        return Ok(StaticFileRequest::from_request_without_body(&req)
-                            .await?
-                            .create_response(&index_path, self.prefer_utf8, self.no_cache_index)?
-                            .into_response());
+                        return self
+                            .file_response(&req, &index_path, self.no_cache_index)
+                            .await;
                     }
                 }
             }
@@ -221,10 +299,7 @@ impl Endpoint for StaticFilesEndpoint {
         }
 
         if file_path.is_file() {
-            Ok(StaticFileRequest::from_request_without_body(&req)
-                .await?
-                .create_response(&file_path, self.prefer_utf8, false)?
-                .into_response())
+            self.file_response(&req, &file_path, false).await
         } else {
             if self.redirect_to_slash
                 && !req.original_uri().path().ends_with('/')
@@ -240,10 +315,9 @@ impl Endpoint for StaticFilesEndpoint {
             if let Some(index_file) = &self.index_file {
                 let index_path = file_path.join(index_file);
                 if index_path.is_file() {
-                    return Ok(StaticFileRequest::from_request_without_body(&req)
-                        .await?
-                        .create_response(&index_path, self.prefer_utf8, self.no_cache_index)?
-                        .into_response());
+                    return self
+                        .file_response(&req, &index_path, self.no_cache_index)
+                        .await;
                 }
             }
 
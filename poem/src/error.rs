@@ -5,10 +5,11 @@ use std::{
     error::Error as StdError,
     fmt::{self, Debug, Display, Formatter},
     string::FromUtf8Error,
+    time::Duration,
 };
 
 use headers::{ContentRange, HeaderMapExt};
-use http::{Extensions, Method};
+use http::{Extensions, Method, header};
 
 use crate::{IntoResponse, Response, http::StatusCode};
 
@@ -935,6 +936,42 @@ impl ResponseError for WebSocketError {
     }
 }
 
+/// A possible error value occurred in the
+/// [`TypedWebSocketStream`](crate::web::websocket::TypedWebSocketStream).
+#[cfg(feature = "websocket")]
+#[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+#[derive(Debug, thiserror::Error)]
+pub enum TypedWebSocketError {
+    /// Io error
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to decode a message
+    #[error("failed to decode the message: {0}")]
+    Decode(String),
+
+    /// Failed to encode a message
+    #[error("failed to encode the message: {0}")]
+    Encode(String),
+
+    /// No message is received within the idle timeout
+    #[error("idle timeout")]
+    IdleTimeout,
+}
+
+#[cfg(feature = "websocket")]
+impl ResponseError for TypedWebSocketError {
+    fn status(&self) -> StatusCode {
+        match self {
+            TypedWebSocketError::Decode(_) => StatusCode::BAD_REQUEST,
+            TypedWebSocketError::IdleTimeout => StatusCode::REQUEST_TIMEOUT,
+            TypedWebSocketError::Io(_) | TypedWebSocketError::Encode(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
 /// A possible error value when upgrading connection.
 #[derive(Debug, thiserror::Error)]
 pub enum UpgradeError {
@@ -1036,6 +1073,146 @@ impl ResponseError for SizedLimitError {
     }
 }
 
+/// A possible error value occurred in the `Timeout` middleware.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum TimeoutError {
+    /// The endpoint did not complete within the time limit.
+    #[error("request timed out")]
+    Handler,
+
+    /// The request body was not received within the time limit.
+    #[error("reading request body timed out")]
+    ReadBody,
+}
+
+impl ResponseError for TimeoutError {
+    fn status(&self) -> StatusCode {
+        match self {
+            TimeoutError::Handler => StatusCode::SERVICE_UNAVAILABLE,
+            TimeoutError::ReadBody => StatusCode::REQUEST_TIMEOUT,
+        }
+    }
+}
+
+/// A possible error value occurred in the `ConcurrencyLimit` middleware.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum ConcurrencyLimitError {
+    /// The maximum number of in-flight requests has been reached.
+    #[error("service overloaded")]
+    Overloaded,
+}
+
+impl ResponseError for ConcurrencyLimitError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ConcurrencyLimitError::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// A possible error value occurred in the `CircuitBreaker` middleware.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum CircuitBreakerError {
+    /// The circuit is open, the requests are rejected without calling the
+    /// endpoint.
+    #[error("circuit breaker is open")]
+    Open {
+        /// The time until the circuit becomes half-open.
+        retry_after: Duration,
+    },
+}
+
+impl ResponseError for CircuitBreakerError {
+    fn status(&self) -> StatusCode {
+        match self {
+            CircuitBreakerError::Open { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        let mut resp = self.to_string().into_response();
+        resp.set_status(self.status());
+        let CircuitBreakerError::Open { retry_after } = self;
+        resp.headers_mut()
+            .insert(header::RETRY_AFTER, ceil_secs(*retry_after).into());
+        resp
+    }
+}
+
+/// A possible error value occurred in the `RateLimit` middleware.
+#[derive(Debug, thiserror::Error)]
+pub enum RateLimitError {
+    /// The client has sent too many requests.
+    #[error("too many requests")]
+    TooManyRequests {
+        /// The maximum number of requests in the quota.
+        limit: u32,
+
+        /// The time until the quota is completely replenished.
+        reset: Duration,
+
+        /// The time until the next request is allowed.
+        retry_after: Duration,
+    },
+
+    /// Redis error.
+    #[cfg(feature = "redis-session")]
+    #[error("redis: {0}")]
+    Redis(redis::RedisError),
+}
+
+impl ResponseError for RateLimitError {
+    fn status(&self) -> StatusCode {
+        match self {
+            RateLimitError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            #[cfg(feature = "redis-session")]
+            RateLimitError::Redis(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        let mut resp = self.to_string().into_response();
+        resp.set_status(self.status());
+        match self {
+            RateLimitError::TooManyRequests {
+                limit,
+                reset,
+                retry_after,
+            } => {
+                let headers = resp.headers_mut();
+                headers.insert("ratelimit-limit", (*limit).into());
+                headers.insert("ratelimit-remaining", 0.into());
+                headers.insert("ratelimit-reset", ceil_secs(*reset).into());
+                headers.insert(header::RETRY_AFTER, ceil_secs(*retry_after).into());
+            }
+            #[cfg(feature = "redis-session")]
+            RateLimitError::Redis(_) => {}
+        }
+        resp
+    }
+}
+
+/// Returns the number of seconds in the duration, rounded up.
+pub(crate) fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+/// A possible error value occurred in the `ETag` middleware.
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum ETagError {
+    /// The `If-Match` or `If-None-Match` precondition of the request failed.
+    #[error("precondition failed")]
+    PreconditionFailed,
+}
+
+impl ResponseError for ETagError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ETagError::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+        }
+    }
+}
+
 /// A possible error value occurred when adding a route.
 #[derive(Debug, thiserror::Error, Eq, PartialEq)]
 pub enum RouteError {
@@ -1064,6 +1241,43 @@ impl ResponseError for RouteError {
     }
 }
 
+/// A possible error value when generating a URL with
+/// [`UrlFor`](crate::web::UrlFor).
+#[derive(Debug, thiserror::Error, Eq, PartialEq)]
+pub enum UrlForError {
+    /// There is no route with this name.
+    #[error("unknown route: {0}")]
+    UnknownRoute(String),
+
+    /// A path parameter of the route is missing.
+    #[error("missing path parameter: {0}")]
+    MissingParam(String),
+
+    /// The route has no path parameter with this name.
+    #[error("unknown path parameter: {0}")]
+    UnknownParam(String),
+
+    /// The value does not match the regex of the path parameter.
+    #[error("invalid value for path parameter `{name}`: {value}")]
+    InvalidParam {
+        /// Parameter name
+        name: String,
+
+        /// Value
+        value: String,
+    },
+
+    /// The route contains a wildcard or a regex without a name.
+    #[error("the route contains an unnamed segment: {0}")]
+    UnnamedSegment(String),
+}
+
+impl ResponseError for UrlForError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// A possible error value occurred in the `Cors` middleware.
 #[derive(Debug, thiserror::Error, Eq, PartialEq)]
 pub enum CorsError {
@@ -1125,6 +1339,47 @@ impl ResponseError for I18NError {
     }
 }
 
+/// A possible error value occurred in the `ReverseProxy` endpoint.
+#[cfg(feature = "reverse-proxy")]
+#[derive(Debug, thiserror::Error)]
+pub enum ReverseProxyError {
+    /// Failed to send the request to the upstream.
+    #[error("upstream: {0}")]
+    Upstream(hyper_util::client::legacy::Error),
+
+    /// The upstream did not respond in time.
+    #[error("upstream timeout")]
+    Timeout,
+}
+
+#[cfg(feature = "reverse-proxy")]
+impl ResponseError for ReverseProxyError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ReverseProxyError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ReverseProxyError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+        }
+    }
+}
+
+/// A possible error value occurred with the `Flash` extractor or the
+/// `FlashMiddleware` middleware.
+#[cfg(feature = "cookie")]
+#[derive(Debug, thiserror::Error, Copy, Clone, Eq, PartialEq)]
+pub enum FlashError {
+    /// There is no session and no `CookieJarManager` with a key to store the
+    /// messages.
+    #[error("the flash messages require a session or the `CookieJarManager::with_key` middleware")]
+    MissingStore,
+}
+
+#[cfg(feature = "cookie")]
+impl ResponseError for FlashError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
 /// A possible error value occurred when deal with redis session.
 #[cfg(feature = "redis-session")]
 #[derive(Debug, thiserror::Error)]
@@ -1141,6 +1396,231 @@ impl ResponseError for RedisSessionError {
     }
 }
 
+/// A possible error value occurred when deal with file session.
+#[cfg(feature = "session")]
+#[derive(Debug, thiserror::Error)]
+pub enum FileSessionError {
+    /// Io error.
+    #[error("io: {0}")]
+    Io(std::io::Error),
+
+    /// The session id cannot be used as a file name.
+    #[error("invalid session id")]
+    InvalidSessionId,
+}
+
+#[cfg(feature = "session")]
+impl ResponseError for FileSessionError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred when deal with SQL session.
+#[cfg(feature = "sqlx-session")]
+#[derive(Debug, thiserror::Error)]
+pub enum SqlSessionError {
+    /// Sqlx error.
+    #[error("sqlx: {0}")]
+    Sqlx(sqlx::Error),
+}
+
+#[cfg(feature = "sqlx-session")]
+impl ResponseError for SqlSessionError {
+    fn status(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+}
+
+/// A possible error value occurred in the `Jwt` middleware.
+#[cfg(feature = "jwt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+#[derive(Debug, thiserror::Error)]
+pub enum JwtError {
+    /// The request does not contain a bearer token.
+    #[error("missing bearer token")]
+    MissingToken,
+
+    /// The token has expired.
+    #[error("token expired")]
+    Expired,
+
+    /// The token is not valid yet.
+    #[error("token not yet valid")]
+    NotYetValid,
+
+    /// The `aud` claim does not match the accepted audiences.
+    #[error("invalid audience")]
+    InvalidAudience,
+
+    /// The `iss` claim does not match the accepted issuers.
+    #[error("invalid issuer")]
+    InvalidIssuer,
+
+    /// The signature of the token is invalid.
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    /// The token is signed with an unknown key.
+    #[error("unknown signing key")]
+    UnknownKey,
+
+    /// The token is invalid.
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+
+    /// Failed to load the key set.
+    #[error("failed to load the key set: {0}")]
+    KeySet(String),
+}
+
+#[cfg(feature = "jwt")]
+impl ResponseError for JwtError {
+    fn status(&self) -> StatusCode {
+        match self {
+            JwtError::KeySet(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    fn as_response(&self) -> Response {
+        let mut resp = self.to_string().into_response();
+        resp.set_status(self.status());
+        let challenge = match self {
+            JwtError::KeySet(_) => return resp,
+            JwtError::MissingToken => "Bearer".to_string(),
+            _ => format!(
+                "Bearer error=\"invalid_token\", error_description=\"{}\"",
+                self.to_string().replace(['"', '\\'], "'")
+            ),
+        };
+        if let Ok(value) = challenge.parse() {
+            resp.headers_mut().insert(header::WWW_AUTHENTICATE, value);
+        }
+        resp
+    }
+}
+
+/// A possible error value occurred in the OAuth 2.0 login flow.
+#[cfg(feature = "oauth2")]
+#[cfg_attr(docsrs, doc(cfg(feature = "oauth2")))]
+#[derive(Debug, thiserror::Error)]
+pub enum OAuth2Error {
+    /// The state does not match the authorization request.
+    #[error("invalid state")]
+    InvalidState,
+
+    /// The provider responded to the authorization request with an error.
+    #[error("authorization failed: {error}")]
+    Authorization {
+        /// The error code.
+        error: String,
+
+        /// The description of the error.
+        description: Option<String>,
+    },
+
+    /// The authorization response does not contain the code.
+    #[error("missing authorization code")]
+    MissingCode,
+
+    /// The token endpoint responded with an error.
+    #[error("token request failed: {error}")]
+    Token {
+        /// The error code.
+        error: String,
+
+        /// The description of the error.
+        description: Option<String>,
+    },
+
+    /// Failed to send a request to the provider.
+    #[error("request failed: {0}")]
+    Request(String),
+
+    /// Failed to discover the metadata of the provider.
+    #[error("discovery failed: {0}")]
+    Discovery(String),
+
+    /// The ID token is invalid.
+    #[error("invalid id token: {0}")]
+    IdToken(#[from] JwtError),
+
+    /// The token response does not contain the ID token.
+    #[error("missing id token")]
+    MissingIdToken,
+
+    /// The nonce of the ID token does not match the authorization request.
+    #[error("invalid nonce")]
+    InvalidNonce,
+
+    /// The subject of the user is missing.
+    #[error("missing subject")]
+    MissingSubject,
+
+    /// The subject of the refreshed tokens does not match the logged-in user.
+    #[error("subject mismatch")]
+    SubjectMismatch,
+
+    /// The user is not logged in.
+    #[error("not logged in")]
+    NotLoggedIn,
+
+    /// The logged-in user does not have a refresh token.
+    #[error("missing refresh token")]
+    MissingRefreshToken,
+}
+
+#[cfg(feature = "oauth2")]
+impl ResponseError for OAuth2Error {
+    fn status(&self) -> StatusCode {
+        match self {
+            OAuth2Error::InvalidState
+            | OAuth2Error::Authorization { .. }
+            | OAuth2Error::MissingCode => StatusCode::BAD_REQUEST,
+            OAuth2Error::Request(_) => StatusCode::BAD_GATEWAY,
+            OAuth2Error::Discovery(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            OAuth2Error::Token { .. }
+            | OAuth2Error::IdToken(_)
+            | OAuth2Error::MissingIdToken
+            | OAuth2Error::InvalidNonce
+            | OAuth2Error::MissingSubject
+            | OAuth2Error::SubjectMismatch
+            | OAuth2Error::NotLoggedIn
+            | OAuth2Error::MissingRefreshToken => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// A possible error value occurred when extracting or checking the client
+/// certificate.
+#[cfg(feature = "client-cert")]
+#[cfg_attr(docsrs, doc(cfg(feature = "client-cert")))]
+#[derive(Debug, thiserror::Error)]
+pub enum ClientCertError {
+    /// The client did not present a certificate.
+    #[error("client certificate required")]
+    Missing,
+
+    /// The certificate does not match the policy of the route.
+    #[error("client certificate rejected")]
+    Rejected,
+
+    /// The certificate cannot be parsed.
+    #[error("invalid client certificate: {0}")]
+    Invalid(String),
+}
+
+#[cfg(feature = "client-cert")]
+impl ResponseError for ClientCertError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ClientCertError::Missing | ClientCertError::Rejected => StatusCode::FORBIDDEN,
+            ClientCertError::Invalid(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::{Error as IoError, ErrorKind};
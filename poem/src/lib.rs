@@ -232,21 +232,31 @@
 //! |------------------|--------------------------------|
 //! | server | Server and listener APIs(enable by default) |
 //! |compression  | Support decompress request body and compress response body |
+//! |client-cert       | Support for the client certificates of mutual TLS connections |
 //! |cookie            | Support for Cookie             |
 //! |csrf | Support for Cross-Site Request Forgery (CSRF) protection |
+//! |jwt               | Support for JSON Web Token (JWT) authentication |
+//! |http3             | Support for HTTP/3 server over QUIC with [`quinn`](https://crates.io/crates/quinn) |
 //! |multipart         | Support for Multipart          |
+//! |oauth2            | Support for OAuth 2.0 and OpenID Connect login flow |
 //! |native-tls        | Support for HTTP server over TLS with [`native-tls`](https://crates.io/crates/native-tls)  |
 //! |openssl-tls        | Support for HTTP server over TLS with [`openssl-tls`](https://crates.io/crates/openssl)  |
 //! |opentelemetry     | Support for opentelemetry    |
 //! |prometheus        | Support for Prometheus       |
 //! |redis-session     | Support for RedisSession     |
+//! |postgres-session  | Support for SqlStorage with PostgreSQL |
+//! |sqlite-session    | Support for SqlStorage with SQLite |
+//! |mysql-session     | Support for SqlStorage with MySQL |
+//! |reverse-proxy     | Support for the reverse proxy endpoint |
 //! |rustls            | Support for HTTP server over TLS with [`rustls`](https://crates.io/crates/rustls)  |
 //! |session           | Support for session    |
 //! |sse               | Support Server-Sent Events (SSE)       |
+//! |static-files      | Support static files endpoint |
 //! |tempfile          | Support for [`tempfile`](https://crates.io/crates/tempfile) |
 //! |test              | Test utilities to test your endpoints. |
 //! |tower-compat      | Adapters for `tower::Layer` and `tower::Service`. |
 //! |websocket         | Support for WebSocket          |
+//! |websocket-deflate | Support for the `permessage-deflate` WebSocket extension |
 //! | anyhow        | Integrate with the [`anyhow`](https://crates.io/crates/anyhow) crate. |
 //! | eyre06        | Integrate with version 0.6.x of the [`eyre`](https://crates.io/crates/eyre) crate. |
 //! | i18n          | Support for internationalization |
@@ -256,6 +266,9 @@
 //! | embed  | Integrate with [`rust-embed`](https://crates.io/crates/rust-embed) crate. |
 //! | xml | Integrate with [`quick-xml`](https://crates.io/crates/quick-xml) crate. |
 //! | yaml | Integrate with [`serde-yaml`](https://crates.io/crates/serde-yaml) crate.                   |
+//! | msgpack | Integrate with [`rmp-serde`](https://crates.io/crates/rmp-serde) crate. |
+//! | cbor | Integrate with [`ciborium`](https://crates.io/crates/ciborium) crate. |
+//! |requestid         | Associates an unique ID with each incoming request |
 //! |sonic-rs          | Uses [`sonic-rs`](https://github.com/cloudwego/sonic-rs) instead of `serde_json`. Pls, checkout `sonic-rs` requirements to properly enable `sonic-rs` capabilities |
 
 #![doc(html_favicon_url = "https://raw.githubusercontent.com/poem-web/poem/master/favicon.ico")]
@@ -275,6 +288,9 @@ pub mod i18n;
 #[cfg_attr(docsrs, doc(cfg(feature = "server")))]
 pub mod listener;
 pub mod middleware;
+#[cfg(feature = "oauth2")]
+#[cfg_attr(docsrs, doc(cfg(feature = "oauth2")))]
+pub mod oauth2;
 #[cfg(feature = "session")]
 #[cfg_attr(docsrs, doc(cfg(feature = "session")))]
 pub mod session;
@@ -285,6 +301,9 @@ pub mod web;
 
 #[doc(inline)]
 pub use http;
+#[cfg(feature = "jwt")]
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+pub use jsonwebtoken;
 
 mod addr;
 mod body;
@@ -303,9 +322,9 @@ pub use poem_derive::handler;
 pub use request::{OnUpgrade, Request, RequestBuilder, RequestParts, Upgraded};
 pub use response::{Response, ResponseBuilder, ResponseParts};
 pub use route::{
-    PathPattern, Route, RouteDomain, RouteMethod, RouteScheme, connect, delete, get, head, options,
-    patch, post, put, trace,
+    PathPattern, Route, RouteDomain, RouteInfo, RouteMethod, RouteScheme, connect, delete, get,
+    head, options, patch, post, put, trace,
 };
 #[cfg(feature = "server")]
-pub use server::Server;
+pub use server::{Server, ServerHandle};
 pub use web::{FromRequest, IntoResponse, RequestBody};
@@ -4,7 +4,7 @@ use std::{
     time::{Duration, UNIX_EPOCH},
 };
 
-use http::uri::Scheme;
+use http::{Extensions, uri::Scheme};
 use rcgen::{
     Certificate, CertificateParams, CustomExtension, DistinguishedName, PKCS_ECDSA_P256_SHA256,
 };
@@ -219,9 +219,17 @@ impl<T: Acceptor> Acceptor for AutoCertAcceptor<T> {
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
-        let (stream, local_addr, remote_addr, _) = self.inner.accept().await?;
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        let (stream, local_addr, remote_addr, _, extensions) =
+            self.inner.accept_with_extensions().await?;
         let stream = HandshakeStream::new(self.acceptor.accept(stream));
-        Ok((stream, local_addr, remote_addr, Scheme::HTTPS))
+        Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions))
     }
 }
 
@@ -3,7 +3,7 @@ use std::{
     task::{Context, Poll},
 };
 
-use http::uri::Scheme;
+use http::{Extensions, uri::Scheme};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
 
 use crate::{
@@ -47,14 +47,21 @@ impl<A: Acceptor, B: Acceptor> Acceptor for Combined<A, B> {
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         tokio::select! {
-            res = self.a.accept() => {
-                let (stream, local_addr, remote_addr, scheme) = res?;
-                Ok((CombinedStream::A(stream), local_addr, remote_addr, scheme))
+            res = self.a.accept_with_extensions() => {
+                let (stream, local_addr, remote_addr, scheme, extensions) = res?;
+                Ok((CombinedStream::A(stream), local_addr, remote_addr, scheme, extensions))
             }
-            res = self.b.accept() => {
-                let (stream, local_addr, remote_addr, scheme) = res?;
-                Ok((CombinedStream::B(stream), local_addr, remote_addr, scheme))
+            res = self.b.accept_with_extensions() => {
+                let (stream, local_addr, remote_addr, scheme, extensions) = res?;
+                Ok((CombinedStream::B(stream), local_addr, remote_addr, scheme, extensions))
             }
         }
     }
@@ -10,6 +10,9 @@ mod handshake_stream;
 mod native_tls;
 #[cfg(feature = "openssl-tls")]
 mod openssl_tls;
+mod proxy_protocol;
+#[cfg(feature = "http3")]
+mod quic;
 #[cfg(feature = "rustls")]
 mod rustls;
 mod tcp;
@@ -26,7 +29,7 @@ use std::{
 };
 
 use futures_util::{Future, FutureExt, TryFutureExt, future::BoxFuture};
-use http::uri::Scheme;
+use http::{Extensions, uri::Scheme};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult};
 
 #[cfg(feature = "acme-base")]
@@ -37,6 +40,8 @@ pub use self::handshake_stream::HandshakeStream;
 pub use self::native_tls::{NativeTlsAcceptor, NativeTlsConfig, NativeTlsListener};
 #[cfg(feature = "openssl-tls")]
 pub use self::openssl_tls::{OpensslTlsAcceptor, OpensslTlsConfig, OpensslTlsListener};
+#[cfg(feature = "http3")]
+pub use self::quic::{QuicAcceptor, QuicListener};
 #[cfg(feature = "rustls")]
 pub use self::rustls::{RustlsAcceptor, RustlsCertificate, RustlsConfig, RustlsListener};
 #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
@@ -45,6 +50,9 @@ pub use self::tls::IntoTlsConfigStream;
 pub use self::unix::{UnixAcceptor, UnixListener};
 pub use self::{
     combined::{Combined, CombinedStream},
+    proxy_protocol::{
+        ProxyHeader, ProxyProtocolAcceptor, ProxyProtocolListener, ProxyProtocolStream,
+    },
     tcp::{TcpAcceptor, TcpListener},
 };
 use crate::web::{LocalAddr, RemoteAddr};
@@ -108,6 +116,21 @@ pub trait DynAcceptor: Send {
     /// established, the corresponding IO stream and the remote peer’s
     /// address will be returned.
     fn accept(&mut self) -> BoxFuture<'_, IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme)>>;
+
+    /// Accepts a new incoming connection from this listener, along with the
+    /// extensions of the connection.
+    ///
+    /// See also [`Acceptor::accept_with_extensions`].
+    #[allow(clippy::type_complexity)]
+    fn accept_with_extensions(
+        &mut self,
+    ) -> BoxFuture<'_, IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)>> {
+        DynAcceptor::accept(self)
+            .map_ok(|(io, local_addr, remote_addr, scheme)| {
+                (io, local_addr, remote_addr, scheme, Extensions::new())
+            })
+            .boxed()
+    }
 }
 
 /// A [`Acceptor`] wrapper used to implement [`DynAcceptor`].
@@ -128,6 +151,19 @@ impl<A: Acceptor> DynAcceptor for ToDynAcceptor<A> {
         }
         .boxed()
     }
+
+    #[inline]
+    fn accept_with_extensions(
+        &mut self,
+    ) -> BoxFuture<'_, IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)>> {
+        async move {
+            let (io, local_addr, remote_addr, scheme, extensions) =
+                self.0.accept_with_extensions().await?;
+            let io = BoxIo::new(io);
+            Ok((io, local_addr, remote_addr, scheme, extensions))
+        }
+        .boxed()
+    }
 }
 
 impl Acceptor for dyn DynAcceptor + '_ {
@@ -142,6 +178,13 @@ impl Acceptor for dyn DynAcceptor + '_ {
     async fn accept(&mut self) -> IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme)> {
         DynAcceptor::accept(self).await
     }
+
+    #[inline]
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(BoxIo, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        DynAcceptor::accept_with_extensions(self).await
+    }
 }
 
 /// Represents a acceptor type.
@@ -160,6 +203,26 @@ pub trait Acceptor: Send {
     fn accept(
         &mut self,
     ) -> impl Future<Output = IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)>> + Send;
+
+    /// Accepts a new incoming connection from this listener, along with the
+    /// extensions of the connection.
+    ///
+    /// The extensions are added to every request received on the connection,
+    /// so that acceptors can make information about the connection available
+    /// to the endpoints.
+    ///
+    /// The default implementation calls [`Acceptor::accept`] and returns empty
+    /// extensions. Acceptors that wrap another acceptor should implement this
+    /// method to forward the extensions of the inner acceptor.
+    fn accept_with_extensions(
+        &mut self,
+    ) -> impl Future<Output = IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)>> + Send
+    {
+        self.accept()
+            .map_ok(|(io, local_addr, remote_addr, scheme)| {
+                (io, local_addr, remote_addr, scheme, Extensions::new())
+            })
+    }
 }
 
 /// An owned dynamically typed Acceptor for use in cases where you can’t
@@ -185,6 +248,18 @@ pub trait AcceptorExt: Acceptor {
         Box::new(ToDynAcceptor(self))
     }
 
+    /// Consume this acceptor and return a new acceptor which parses the PROXY
+    /// protocol header of the incoming connections.
+    ///
+    /// See [`ProxyProtocolListener`] for details.
+    #[must_use]
+    fn proxy_protocol(self) -> ProxyProtocolAcceptor<Self>
+    where
+        Self: Sized,
+    {
+        ProxyProtocolAcceptor::new(self)
+    }
+
     /// Consume this acceptor and return a new TLS acceptor with [`rustls`](https://crates.io/crates/rustls).
     #[cfg(feature = "rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
@@ -248,6 +323,27 @@ pub trait Listener: Send {
         Combined::new(self, other)
     }
 
+    /// Consume this listener and return a new listener which parses the
+    /// PROXY protocol header of the incoming connections.
+    ///
+    /// It must be applied before TLS, because the header is sent before the
+    /// TLS handshake.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::listener::{Listener, TcpListener};
+    ///
+    /// let listener = TcpListener::bind("0.0.0.0:80").proxy_protocol();
+    /// ```
+    #[must_use]
+    fn proxy_protocol(self) -> ProxyProtocolListener<Self>
+    where
+        Self: Sized,
+    {
+        ProxyProtocolListener::new(self)
+    }
+
     /// Consume this listener and return a new TLS listener with [`rustls`](https://crates.io/crates/rustls).
     #[cfg(feature = "rustls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "rustls")))]
@@ -353,6 +449,12 @@ impl<T: Acceptor + ?Sized> Acceptor for Box<T> {
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
         self.as_mut().accept().await
     }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        self.as_mut().accept_with_extensions().await
+    }
 }
 
 impl Acceptor for Infallible {
@@ -2,7 +2,7 @@ use futures_util::{
     Stream, StreamExt, TryFutureExt,
     stream::{BoxStream, Chain, Pending},
 };
-use http::uri::Scheme;
+use http::{Extensions, uri::Scheme};
 use tokio::io::{Error as IoError, Result as IoResult};
 use tokio_native_tls::{TlsStream, native_tls::Identity};
 
@@ -147,6 +147,13 @@ where
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         loop {
             tokio::select! {
                 res = self.config_stream.next() => {
@@ -166,15 +173,26 @@ where
                         unreachable!()
                     }
                 }
-                res = self.inner.accept() => {
-                    let (stream, local_addr, remote_addr, _) = res?;
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, _, extensions) = res?;
                     let tls_acceptor = match &self.current_tls_acceptor {
                         Some(tls_acceptor) => tls_acceptor.clone(),
                         None => return Err(IoError::other("no valid tls config.")),
                     };
-                    let fut = async move { tls_acceptor.accept(stream).map_err(|err| IoError::other(err.to_string())).await };
+                    #[cfg(feature = "client-cert")]
+                    let (extensions, peer) = crate::web::PeerCertificate::attach(extensions);
+                    let fut = async move {
+                        let stream = tls_acceptor.accept(stream).map_err(|err| IoError::other(err.to_string())).await?;
+                        #[cfg(feature = "client-cert")]
+                        if let Ok(Some(cert)) = stream.get_ref().peer_certificate() {
+                            if let Ok(der) = cert.to_der() {
+                                peer.set(vec![der]);
+                            }
+                        }
+                        Ok(stream)
+                    };
                     let stream = HandshakeStream::new(fut);
-                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
+                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions));
                 }
             }
         }
@@ -7,7 +7,7 @@ use futures_util::{
     Stream, StreamExt,
     stream::{BoxStream, Chain, Pending},
 };
-use http::uri::Scheme;
+use http::{Extensions, uri::Scheme};
 use openssl::{
     pkey::PKey,
     ssl::{Ssl, SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslRef},
@@ -192,6 +192,13 @@ where
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         loop {
             tokio::select! {
                 res = self.config_stream.next() => {
@@ -211,12 +218,14 @@ where
                         unreachable!()
                     }
                 }
-                res = self.inner.accept() => {
-                    let (stream, local_addr, remote_addr, _) = res?;
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, _, extensions) = res?;
                     let tls_acceptor = match &self.current_tls_acceptor {
                         Some(tls_acceptor) => tls_acceptor.clone(),
                         None => return Err(IoError::other("no valid tls config.")),
                     };
+                    #[cfg(feature = "client-cert")]
+                    let (extensions, peer) = crate::web::PeerCertificate::attach(extensions);
                     let fut = async move {
                         let ssl = Ssl::new(tls_acceptor.context()).map_err(|err|
                             IoError::other(err.to_string()))?;
@@ -225,9 +234,18 @@ where
                         use std::pin::Pin;
                         Pin::new(&mut tls_stream).accept().await.map_err(|err|
                             IoError::other(err.to_string()))?;
+                        #[cfg(feature = "client-cert")]
+                        if let Some(cert) = tls_stream.ssl().peer_certificate() {
+                            // the chain of the server side does not contain the peer certificate
+                            let chain = std::iter::once(cert.as_ref())
+                                .chain(tls_stream.ssl().peer_cert_chain().into_iter().flatten())
+                                .filter_map(|cert| cert.to_der().ok())
+                                .collect();
+                            peer.set(chain);
+                        }
                         Ok(tls_stream) };
                     let stream = HandshakeStream::new(fut);
-                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
+                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions));
                 }
             }
         }
@@ -0,0 +1,749 @@
+use std::{
+    io::{Error as IoError, ErrorKind, IoSlice},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use bytes::{Buf, Bytes, BytesMut};
+use futures_util::{FutureExt, StreamExt, future::BoxFuture, stream::FuturesUnordered};
+use http::{Extensions, uri::Scheme};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf, Result as IoResult};
+
+use crate::{
+    listener::{Acceptor, Listener},
+    web::{LocalAddr, RemoteAddr},
+};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+const V1_MAX_LENGTH: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+const V2_HEADER_LENGTH: usize = 16;
+
+const PP2_TYPE_AUTHORITY: u8 = 0x02;
+const PP2_TYPE_AWS: u8 = 0xEA;
+const PP2_SUBTYPE_AWS_VPCE_ID: u8 = 0x01;
+
+/// The information carried by a PROXY protocol header.
+///
+/// The [`ProxyProtocolAcceptor`] adds it to the extensions of every request
+/// received on the connection, use the [`Data`](crate::web::Data) extractor
+/// to get it.
+///
+/// # Example
+///
+/// ```
+/// use poem::{handler, listener::ProxyHeader, web::Data};
+///
+/// #[handler]
+/// fn index(header: Option<Data<&ProxyHeader>>) -> String {
+///     header
+///         .and_then(|header| header.aws_vpc_endpoint_id().map(ToString::to_string))
+///         .unwrap_or_default()
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: u8,
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+    tlvs: Vec<(u8, Bytes)>,
+}
+
+impl ProxyHeader {
+    /// Returns the version of the PROXY protocol, `1` or `2`.
+    #[inline]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the address of the client.
+    ///
+    /// Returns `None` if the proxy did not report the addresses, for example
+    /// for its own health checks.
+    #[inline]
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// Returns the address the client connected to.
+    #[inline]
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    /// Returns the value of the first TLV with the specified type.
+    pub fn tlv(&self, ty: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|(t, _)| *t == ty)
+            .map(|(_, value)| &**value)
+    }
+
+    /// Returns an iterator over the type and value of all TLVs.
+    pub fn tlvs(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        self.tlvs.iter().map(|(ty, value)| (*ty, &**value))
+    }
+
+    /// Returns the host name the client connected to (`PP2_TYPE_AUTHORITY`).
+    pub fn authority(&self) -> Option<&str> {
+        self.tlv(PP2_TYPE_AUTHORITY)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+
+    /// Returns the id of the VPC endpoint the client connected through, as
+    /// reported by the AWS Network Load Balancer.
+    pub fn aws_vpc_endpoint_id(&self) -> Option<&str> {
+        match self.tlv(PP2_TYPE_AWS)? {
+            [PP2_SUBTYPE_AWS_VPCE_ID, value @ ..] => std::str::from_utf8(value).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Parsed {
+    Incomplete,
+    NotProxy,
+    Header(ProxyHeader, usize),
+}
+
+fn invalid_header(msg: &str) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        format!("invalid PROXY protocol header: {msg}"),
+    )
+}
+
+fn has_prefix(data: &[u8], prefix: &[u8]) -> bool {
+    let len = data.len().min(prefix.len());
+    data[..len] == prefix[..len]
+}
+
+fn parse_header(data: &[u8]) -> IoResult<Parsed> {
+    if has_prefix(data, V1_PREFIX) {
+        parse_v1(data)
+    } else if has_prefix(data, V2_SIGNATURE) {
+        parse_v2(data)
+    } else {
+        Ok(Parsed::NotProxy)
+    }
+}
+
+fn parse_v1(data: &[u8]) -> IoResult<Parsed> {
+    let Some(end) = data
+        .windows(2)
+        .take(V1_MAX_LENGTH - 1)
+        .position(|w| w == b"\r\n")
+    else {
+        return if data.len() >= V1_MAX_LENGTH {
+            Err(invalid_header("line too long"))
+        } else {
+            Ok(Parsed::Incomplete)
+        };
+    };
+
+    let line = std::str::from_utf8(&data[..end]).map_err(|_| invalid_header("not utf-8"))?;
+    let mut parts = line.split(' ').skip(1);
+    let (source, destination) = match parts.next() {
+        Some("UNKNOWN") => (None, None),
+        Some(proto @ ("TCP4" | "TCP6")) => {
+            let mut next_ip = || {
+                parts
+                    .next()
+                    .and_then(|s| s.parse::<IpAddr>().ok())
+                    .filter(|ip| ip.is_ipv4() == (proto == "TCP4"))
+                    .ok_or_else(|| invalid_header("invalid address"))
+            };
+            let (src_ip, dst_ip) = (next_ip()?, next_ip()?);
+            let mut next_port = || {
+                parts
+                    .next()
+                    .and_then(|s| s.parse::<u16>().ok())
+                    .ok_or_else(|| invalid_header("invalid port"))
+            };
+            let (src_port, dst_port) = (next_port()?, next_port()?);
+            if parts.next().is_some() {
+                return Err(invalid_header("too many fields"));
+            }
+            (
+                Some(SocketAddr::new(src_ip, src_port)),
+                Some(SocketAddr::new(dst_ip, dst_port)),
+            )
+        }
+        _ => return Err(invalid_header("unknown protocol")),
+    };
+
+    Ok(Parsed::Header(
+        ProxyHeader {
+            version: 1,
+            source,
+            destination,
+            tlvs: Vec::new(),
+        },
+        end + 2,
+    ))
+}
+
+fn parse_v2(data: &[u8]) -> IoResult<Parsed> {
+    if data.len() < V2_HEADER_LENGTH {
+        return Ok(Parsed::Incomplete);
+    }
+
+    let version_command = data[12];
+    if version_command >> 4 != 2 {
+        return Err(invalid_header("unsupported version"));
+    }
+    let length = V2_HEADER_LENGTH + u16::from_be_bytes([data[14], data[15]]) as usize;
+    if data.len() < length {
+        return Ok(Parsed::Incomplete);
+    }
+    let mut payload = &data[V2_HEADER_LENGTH..length];
+
+    let header = match version_command & 0x0F {
+        // LOCAL, the connection was established by the proxy itself.
+        0x00 => ProxyHeader {
+            version: 2,
+            source: None,
+            destination: None,
+            tlvs: Vec::new(),
+        },
+        // PROXY
+        0x01 => {
+            let (source, destination) = match data[13] >> 4 {
+                // AF_INET
+                0x01 => {
+                    if payload.len() < 12 {
+                        return Err(invalid_header("address too short"));
+                    }
+                    let src = Ipv4Addr::from(payload.get_u32());
+                    let dst = Ipv4Addr::from(payload.get_u32());
+                    let (src_port, dst_port) = (payload.get_u16(), payload.get_u16());
+                    (
+                        Some(SocketAddr::new(src.into(), src_port)),
+                        Some(SocketAddr::new(dst.into(), dst_port)),
+                    )
+                }
+                // AF_INET6
+                0x02 => {
+                    if payload.len() < 36 {
+                        return Err(invalid_header("address too short"));
+                    }
+                    let src = Ipv6Addr::from(payload.get_u128());
+                    let dst = Ipv6Addr::from(payload.get_u128());
+                    let (src_port, dst_port) = (payload.get_u16(), payload.get_u16());
+                    (
+                        Some(SocketAddr::new(src.into(), src_port)),
+                        Some(SocketAddr::new(dst.into(), dst_port)),
+                    )
+                }
+                // AF_UNIX
+                0x03 => {
+                    if payload.len() < 216 {
+                        return Err(invalid_header("address too short"));
+                    }
+                    payload.advance(216);
+                    (None, None)
+                }
+                // AF_UNSPEC
+                _ => {
+                    payload = &[];
+                    (None, None)
+                }
+            };
+
+            let mut tlvs = Vec::new();
+            while !payload.is_empty() {
+                if payload.len() < 3 {
+                    return Err(invalid_header("truncated TLV"));
+                }
+                let ty = payload.get_u8();
+                let len = payload.get_u16() as usize;
+                if payload.len() < len {
+                    return Err(invalid_header("truncated TLV"));
+                }
+                tlvs.push((ty, Bytes::copy_from_slice(&payload[..len])));
+                payload.advance(len);
+            }
+
+            ProxyHeader {
+                version: 2,
+                source,
+                destination,
+                tlvs,
+            }
+        }
+        _ => return Err(invalid_header("unknown command")),
+    };
+
+    Ok(Parsed::Header(header, length))
+}
+
+async fn read_header<T: AsyncRead + Unpin>(
+    mut stream: T,
+    optional: bool,
+) -> IoResult<(ProxyProtocolStream<T>, Option<ProxyHeader>)> {
+    let mut buf = BytesMut::with_capacity(256);
+
+    loop {
+        match parse_header(&buf)? {
+            Parsed::Incomplete => {}
+            Parsed::NotProxy if optional => {
+                return Ok((ProxyProtocolStream::new(stream, buf.freeze()), None));
+            }
+            Parsed::NotProxy => return Err(invalid_header("missing header")),
+            Parsed::Header(header, len) => {
+                buf.advance(len);
+                return Ok((ProxyProtocolStream::new(stream, buf.freeze()), Some(header)));
+            }
+        }
+
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct ProxyProtocolConfig {
+    optional: bool,
+    header_timeout: Duration,
+}
+
+impl Default for ProxyProtocolConfig {
+    fn default() -> Self {
+        Self {
+            optional: false,
+            header_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A wrapper around an underlying listener which parses the [PROXY protocol](https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt)
+/// header of the incoming connections.
+///
+/// Both version 1 (text) and version 2 (binary) headers are supported. The
+/// [`RemoteAddr`] and [`LocalAddr`] of the connection are replaced with the
+/// addresses reported by the proxy, and the [`ProxyHeader`] is added to the
+/// extensions of the requests.
+///
+/// Connections whose header is missing, invalid or not received within the
+/// timeout are closed.
+///
+/// **The PROXY protocol must only be enabled if all the connections come from
+/// trusted proxies.**
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use poem::{
+///     Route, Server,
+///     listener::{Listener, TcpListener},
+/// };
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let listener = TcpListener::bind("0.0.0.0:3000")
+///     .proxy_protocol()
+///     .header_timeout(Duration::from_secs(3));
+/// Server::new(listener).run(Route::new()).await
+/// # });
+/// ```
+pub struct ProxyProtocolListener<T> {
+    inner: T,
+    config: ProxyProtocolConfig,
+}
+
+impl<T> ProxyProtocolListener<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self {
+            inner,
+            config: ProxyProtocolConfig::default(),
+        }
+    }
+
+    /// If `true`, connections without a PROXY protocol header are accepted
+    /// with their original addresses, default is `false`.
+    #[must_use]
+    pub fn optional(mut self, optional: bool) -> Self {
+        self.config.optional = optional;
+        self
+    }
+
+    /// Sets the time limit for receiving the PROXY protocol header, default is
+    /// 5 seconds.
+    #[must_use]
+    pub fn header_timeout(mut self, timeout: Duration) -> Self {
+        self.config.header_timeout = timeout;
+        self
+    }
+}
+
+impl<T: Listener> Listener for ProxyProtocolListener<T> {
+    type Acceptor = ProxyProtocolAcceptor<T::Acceptor>;
+
+    async fn into_acceptor(self) -> IoResult<Self::Acceptor> {
+        Ok(ProxyProtocolAcceptor {
+            inner: self.inner.into_acceptor().await?,
+            config: self.config,
+            pending: FuturesUnordered::new(),
+        })
+    }
+}
+
+type PendingConnection<T> = BoxFuture<
+    'static,
+    IoResult<(
+        ProxyProtocolStream<T>,
+        LocalAddr,
+        RemoteAddr,
+        Scheme,
+        Extensions,
+    )>,
+>;
+
+/// An acceptor which parses the PROXY protocol header of the incoming
+/// connections.
+///
+/// See also [`ProxyProtocolListener`].
+pub struct ProxyProtocolAcceptor<T: Acceptor> {
+    inner: T,
+    config: ProxyProtocolConfig,
+    pending: FuturesUnordered<PendingConnection<T::Io>>,
+}
+
+impl<T: Acceptor> ProxyProtocolAcceptor<T> {
+    pub(crate) fn new(inner: T) -> Self {
+        Self {
+            inner,
+            config: ProxyProtocolConfig::default(),
+            pending: FuturesUnordered::new(),
+        }
+    }
+
+    /// If `true`, connections without a PROXY protocol header are accepted
+    /// with their original addresses, default is `false`.
+    #[must_use]
+    pub fn optional(mut self, optional: bool) -> Self {
+        self.config.optional = optional;
+        self
+    }
+
+    /// Sets the time limit for receiving the PROXY protocol header, default is
+    /// 5 seconds.
+    #[must_use]
+    pub fn header_timeout(mut self, timeout: Duration) -> Self {
+        self.config.header_timeout = timeout;
+        self
+    }
+}
+
+impl<T: Acceptor> Acceptor for ProxyProtocolAcceptor<T> {
+    type Io = ProxyProtocolStream<T::Io>;
+
+    fn local_addr(&self) -> Vec<LocalAddr> {
+        self.inner.local_addr()
+    }
+
+    async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
+        // The headers are read concurrently, so that a slow client does not
+        // block the other connections.
+        loop {
+            tokio::select! {
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, scheme, extensions) = res?;
+                    let config = self.config;
+                    self.pending.push(async move {
+                        let (stream, header) = tokio::time::timeout(
+                            config.header_timeout,
+                            read_header(stream, config.optional),
+                        )
+                        .await
+                        .map_err(|_| IoError::from(ErrorKind::TimedOut))??;
+                        Ok(apply_header(stream, header, local_addr, remote_addr, scheme, extensions))
+                    }.boxed());
+                }
+                Some(res) = self.pending.next() => {
+                    match res {
+                        Ok(conn) => return Ok(conn),
+                        Err(err) => tracing::debug!(error = %err, "failed to read PROXY protocol header"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn apply_header<T>(
+    stream: ProxyProtocolStream<T>,
+    header: Option<ProxyHeader>,
+    mut local_addr: LocalAddr,
+    mut remote_addr: RemoteAddr,
+    scheme: Scheme,
+    mut extensions: Extensions,
+) -> (
+    ProxyProtocolStream<T>,
+    LocalAddr,
+    RemoteAddr,
+    Scheme,
+    Extensions,
+) {
+    if let Some(header) = header {
+        if let Some(source) = header.source {
+            remote_addr = RemoteAddr(source.into());
+        }
+        if let Some(destination) = header.destination {
+            local_addr = LocalAddr(destination.into());
+        }
+        extensions.insert(header);
+    }
+    (stream, local_addr, remote_addr, scheme, extensions)
+}
+
+/// A IO stream for ProxyProtocolAcceptor.
+pub struct ProxyProtocolStream<T> {
+    inner: T,
+    buffered: Bytes,
+}
+
+impl<T> ProxyProtocolStream<T> {
+    fn new(inner: T, buffered: Bytes) -> Self {
+        Self { inner, buffered }
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for ProxyProtocolStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let this = &mut *self;
+        if !this.buffered.is_empty() {
+            let len = this.buffered.len().min(buf.remaining());
+            buf.put_slice(&this.buffered.split_to(len));
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for ProxyProtocolStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<IoResult<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::listener::{AcceptorExt, TcpListener};
+
+    fn v2_header(command: u8, family: u8, addresses: &[u8], tlvs: &[u8]) -> Vec<u8> {
+        let mut data = V2_SIGNATURE.to_vec();
+        data.push(0x20 | command);
+        data.push(family);
+        data.extend_from_slice(&((addresses.len() + tlvs.len()) as u16).to_be_bytes());
+        data.extend_from_slice(addresses);
+        data.extend_from_slice(tlvs);
+        data
+    }
+
+    fn expect_header(data: &[u8]) -> (ProxyHeader, usize) {
+        match parse_header(data).unwrap() {
+            Parsed::Header(header, len) => (header, len),
+            parsed => panic!("unexpected result: {parsed:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_v1() {
+        let (header, len) =
+            expect_header(b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nGET /");
+        assert_eq!(len, 47);
+        assert_eq!(header.version(), 1);
+        assert_eq!(header.source(), Some("192.168.0.1:56324".parse().unwrap()));
+        assert_eq!(
+            header.destination(),
+            Some("192.168.0.11:443".parse().unwrap())
+        );
+
+        let (header, _) = expect_header(b"PROXY TCP6 ::1 ::2 1 2\r\n");
+        assert_eq!(header.source(), Some("[::1]:1".parse().unwrap()));
+
+        let (header, _) = expect_header(b"PROXY UNKNOWN\r\n");
+        assert_eq!(header.source(), None);
+
+        assert!(matches!(
+            parse_header(b"PROXY TCP4 192.168.0.1").unwrap(),
+            Parsed::Incomplete
+        ));
+        assert!(matches!(parse_header(b"PRO").unwrap(), Parsed::Incomplete));
+        assert!(matches!(
+            parse_header(b"GET / HTTP/1.1\r\n").unwrap(),
+            Parsed::NotProxy
+        ));
+        assert!(parse_header(b"PROXY TCP4 ::1 ::2 1 2\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 1.1.1.1 2.2.2.2 1\r\n").is_err());
+        assert!(parse_header(&[b'P', b'R', b'O', b'X', b'Y', b' ', b'A'].repeat(20)).is_err());
+    }
+
+    #[test]
+    fn parse_v2() {
+        let mut tlvs = vec![PP2_TYPE_AWS, 0, 9, PP2_SUBTYPE_AWS_VPCE_ID];
+        tlvs.extend_from_slice(b"vpce-123");
+        tlvs.extend_from_slice(&[PP2_TYPE_AUTHORITY, 0, 3]);
+        tlvs.extend_from_slice(b"a.b");
+        let data = v2_header(
+            0x01,
+            0x11,
+            &[127, 0, 0, 1, 10, 0, 0, 1, 0x1F, 0x90, 0x01, 0xBB],
+            &tlvs,
+        );
+
+        let (header, len) = expect_header(&data);
+        assert_eq!(len, data.len());
+        assert_eq!(header.version(), 2);
+        assert_eq!(header.source(), Some("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(header.destination(), Some("10.0.0.1:443".parse().unwrap()));
+        assert_eq!(header.aws_vpc_endpoint_id(), Some("vpce-123"));
+        assert_eq!(header.authority(), Some("a.b"));
+        assert_eq!(header.tlvs().count(), 2);
+
+        assert!(matches!(
+            parse_header(&data[..data.len() - 1]).unwrap(),
+            Parsed::Incomplete
+        ));
+
+        let (header, _) = expect_header(&v2_header(0x00, 0x00, &[], &[]));
+        assert_eq!(header.source(), None);
+
+        let mut addresses = [0; 36];
+        addresses[15] = 1;
+        addresses[31] = 2;
+        let (header, _) = expect_header(&v2_header(0x01, 0x21, &addresses, &[]));
+        assert_eq!(header.source(), Some("[::1]:0".parse().unwrap()));
+        assert_eq!(header.destination(), Some("[::2]:0".parse().unwrap()));
+
+        assert!(parse_header(&v2_header(0x01, 0x11, &[127, 0, 0, 1], &[])).is_err());
+        assert!(parse_header(&v2_header(0x01, 0x11, &[0; 12], &[1, 0, 5, 0])).is_err());
+        assert!(parse_header(&v2_header(0x02, 0x11, &[0; 12], &[])).is_err());
+    }
+
+    async fn connect_and_send(acceptor: &ProxyProtocolAcceptor<impl Acceptor>, data: &[u8]) {
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        let data = data.to_vec();
+        tokio::spawn(async move {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(&data).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        });
+    }
+
+    #[tokio::test]
+    async fn proxy_protocol_listener() {
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .proxy_protocol()
+            .into_acceptor()
+            .await
+            .unwrap();
+
+        connect_and_send(&acceptor, b"GET / HTTP/1.1\r\n").await;
+        connect_and_send(
+            &acceptor,
+            b"PROXY TCP4 192.168.0.1 192.168.0.11 56324 443\r\nhello",
+        )
+        .await;
+
+        let (mut stream, local_addr, remote_addr, _, extensions) =
+            acceptor.accept_with_extensions().await.unwrap();
+        assert_eq!(
+            local_addr.as_socket_addr(),
+            Some(&"192.168.0.11:443".parse().unwrap())
+        );
+        assert_eq!(
+            remote_addr.as_socket_addr(),
+            Some(&"192.168.0.1:56324".parse().unwrap())
+        );
+        assert_eq!(extensions.get::<ProxyHeader>().unwrap().version(), 1);
+
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn optional() {
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .proxy_protocol()
+            .optional(true)
+            .into_acceptor()
+            .await
+            .unwrap();
+
+        connect_and_send(&acceptor, b"hello").await;
+        let (mut stream, _, remote_addr, _, extensions) =
+            acceptor.accept_with_extensions().await.unwrap();
+        assert!(remote_addr.as_socket_addr().unwrap().ip().is_loopback());
+        assert!(extensions.get::<ProxyHeader>().is_none());
+
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+    }
+
+    #[tokio::test]
+    async fn header_timeout() {
+        let mut acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap()
+            .proxy_protocol()
+            .header_timeout(Duration::from_millis(50));
+
+        connect_and_send(&acceptor, b"PROXY TCP4").await;
+        assert!(
+            tokio::time::timeout(Duration::from_millis(300), acceptor.accept())
+                .await
+                .is_err()
+        );
+    }
+}
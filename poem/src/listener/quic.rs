@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use futures_util::{
+    FutureExt, StreamExt,
+    stream::{BoxStream, Chain, Pending},
+};
+use http::HeaderValue;
+use quinn::{
+    Endpoint, EndpointConfig, Incoming, ServerConfig, TokioRuntime,
+    crypto::rustls::QuicServerConfig,
+};
+use tokio::{
+    io::{Error as IoError, Result as IoResult},
+    net::{ToSocketAddrs, UdpSocket},
+};
+
+use crate::{
+    listener::{IntoTlsConfigStream, RustlsConfig},
+    web::LocalAddr,
+};
+
+/// A QUIC listener which serves HTTP/3.
+///
+/// The certificates are configured with [`RustlsConfig`], a stream of configs
+/// can be used to reload them at runtime, see also
+/// [`RustlsListener`](crate::listener::RustlsListener).
+///
+/// Use [`Server::http3`](crate::Server::http3) to serve HTTP/3 alongside the
+/// TCP listener of the server. The responses sent over TCP then include an
+/// `Alt-Svc` header, so that clients can switch to HTTP/3.
+///
+/// # Example
+///
+/// ```no_run
+/// use poem::{
+///     Route, Server,
+///     listener::{Listener, QuicListener, RustlsCertificate, RustlsConfig, TcpListener},
+/// };
+///
+/// fn config() -> RustlsConfig {
+///     RustlsConfig::new().fallback(
+///         RustlsCertificate::new()
+///             .cert(std::fs::read("cert.pem").unwrap())
+///             .key(std::fs::read("key.pem").unwrap()),
+///     )
+/// }
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// Server::new(TcpListener::bind("0.0.0.0:443").rustls(config()))
+///     .http3(QuicListener::bind("0.0.0.0:443", config()))
+///     .run(Route::new())
+///     .await
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+pub struct QuicListener<T, S> {
+    addr: T,
+    config_stream: S,
+}
+
+impl<T, S> QuicListener<T, S>
+where
+    T: ToSocketAddrs + Send,
+    S: IntoTlsConfigStream<RustlsConfig>,
+{
+    /// Binds to the provided address, and returns a [`QuicListener<T, S>`].
+    pub fn bind(addr: T, config_stream: S) -> Self {
+        Self {
+            addr,
+            config_stream,
+        }
+    }
+
+    /// Create a acceptor instance.
+    pub async fn into_acceptor(self) -> IoResult<QuicAcceptor> {
+        let mut config_stream = self.config_stream.into_stream()?.boxed();
+        let socket = UdpSocket::bind(self.addr).await?.into_std()?;
+        let endpoint = Endpoint::new(
+            EndpointConfig::default(),
+            None,
+            socket,
+            Arc::new(TokioRuntime),
+        )?;
+        let local_addr = LocalAddr(endpoint.local_addr()?.into());
+
+        // Apply the initial config immediately, otherwise the first connections
+        // would be refused.
+        if let Some(Some(tls_config)) = config_stream.next().now_or_never() {
+            endpoint.set_server_config(Some(create_server_config(&tls_config)?));
+            tracing::info!("tls config loaded.");
+        }
+
+        Ok(QuicAcceptor {
+            endpoint,
+            local_addr,
+            config_stream: config_stream.chain(futures_util::stream::pending()),
+        })
+    }
+}
+
+/// A acceptor that accepts QUIC connections.
+#[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+pub struct QuicAcceptor {
+    endpoint: Endpoint,
+    local_addr: LocalAddr,
+    config_stream: Chain<BoxStream<'static, RustlsConfig>, Pending<RustlsConfig>>,
+}
+
+impl QuicAcceptor {
+    /// Returns the local address that this acceptor is bound to.
+    pub fn local_addr(&self) -> LocalAddr {
+        self.local_addr.clone()
+    }
+
+    /// Returns the value of the `Alt-Svc` header advertising this acceptor.
+    pub(crate) fn alt_svc(&self) -> Option<HeaderValue> {
+        let port = self.local_addr.as_socket_addr()?.port();
+        HeaderValue::try_from(format!("h3=\":{port}\"; ma=86400")).ok()
+    }
+
+    pub(crate) async fn accept(&mut self) -> IoResult<Incoming> {
+        loop {
+            tokio::select! {
+                res = self.config_stream.next() => {
+                    if let Some(tls_config) = res {
+                        match create_server_config(&tls_config) {
+                            Ok(server_config) => {
+                                self.endpoint.set_server_config(Some(server_config));
+                                tracing::info!("tls config changed.");
+                            }
+                            Err(err) => tracing::error!(error = %err, "invalid tls config."),
+                        }
+                    } else {
+                        unreachable!()
+                    }
+                }
+                res = self.endpoint.accept() => {
+                    return res.ok_or_else(|| IoError::other("quic endpoint closed."));
+                }
+            }
+        }
+    }
+}
+
+fn create_server_config(tls_config: &RustlsConfig) -> IoResult<ServerConfig> {
+    let mut server_config = tls_config.create_server_config()?;
+    server_config.alpn_protocols = vec![b"h3".to_vec()];
+    let server_config = QuicServerConfig::try_from(server_config).map_err(IoError::other)?;
+    Ok(ServerConfig::with_crypto(Arc::new(server_config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Buf, Bytes};
+    use quinn::crypto::rustls::QuicClientConfig;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+    use tokio_rustls::rustls::ClientConfig;
+
+    use super::*;
+    use crate::{
+        Request, Server, handler,
+        listener::{Acceptor, Listener, RustlsCertificate, TcpListener, rustls::read_trust_anchor},
+    };
+
+    #[handler(internal)]
+    fn index(req: &Request, body: String) -> String {
+        format!("{:?} {}", req.version(), body)
+    }
+
+    #[tokio::test]
+    async fn http3() {
+        let config = RustlsConfig::new().fallback(
+            RustlsCertificate::new()
+                .cert(include_bytes!("certs/cert1.pem").as_ref())
+                .key(include_bytes!("certs/key1.pem").as_ref()),
+        );
+        let tcp_acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let tcp_addr = *tcp_acceptor.local_addr()[0].as_socket_addr().unwrap();
+        let quic_acceptor = QuicListener::bind("127.0.0.1:0", config)
+            .into_acceptor()
+            .await
+            .unwrap();
+        let quic_addr = *quic_acceptor.local_addr().as_socket_addr().unwrap();
+
+        tokio::spawn(
+            Server::new_with_acceptor(tcp_acceptor)
+                .http3_with_acceptor(quic_acceptor)
+                .run(index),
+        );
+
+        // The TCP listener advertises HTTP/3.
+        let mut stream = TcpStream::connect(tcp_addr).await.unwrap();
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut resp = String::new();
+        stream.read_to_string(&mut resp).await.unwrap();
+        assert!(resp.contains(&format!("alt-svc: h3=\":{}\"; ma=86400", quic_addr.port())));
+
+        let mut client_config = ClientConfig::builder()
+            .with_root_certificates(read_trust_anchor(include_bytes!("certs/chain1.pem")).unwrap())
+            .with_no_client_auth();
+        client_config.alpn_protocols = vec![b"h3".to_vec()];
+        let mut endpoint = Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+            QuicClientConfig::try_from(client_config).unwrap(),
+        )));
+
+        let conn = endpoint
+            .connect(quic_addr, "testserver.com")
+            .unwrap()
+            .await
+            .unwrap();
+        let (mut driver, mut send_request) = h3::client::new(h3_quinn::Connection::new(conn))
+            .await
+            .unwrap();
+        tokio::spawn(async move { driver.wait_idle().await });
+
+        let mut stream = send_request
+            .send_request(
+                http::Request::post("https://testserver.com/")
+                    .body(())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        stream
+            .send_data(Bytes::from_static(b"hello"))
+            .await
+            .unwrap();
+        stream.finish().await.unwrap();
+
+        let resp = stream.recv_response().await.unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let mut body = Vec::new();
+        while let Some(mut data) = stream.recv_data().await.unwrap() {
+            body.extend_from_slice(&data.copy_to_bytes(data.remaining()));
+        }
+        assert_eq!(body, b"HTTP/3.0 hello");
+    }
+}
@@ -4,7 +4,7 @@ use futures_util::{
     Stream, StreamExt,
     stream::{BoxStream, Chain, Pending},
 };
-use http::uri::Scheme;
+use http::{Extensions, uri::Scheme};
 use rustls_pemfile::Item;
 use tokio::io::{Error as IoError, Result as IoResult};
 use tokio_rustls::{
@@ -212,7 +212,7 @@ impl RustlsConfig {
         self
     }
 
-    fn create_server_config(&self) -> IoResult<ServerConfig> {
+    pub(crate) fn create_server_config(&self) -> IoResult<ServerConfig> {
         let fallback = self
             .fallback
             .as_ref()
@@ -275,7 +275,7 @@ fn make_server_config_builder() -> ConfigBuilder<ServerConfig, WantsVerifier> {
         .unwrap()
 }
 
-fn read_trust_anchor(mut trust_anchor: &[u8]) -> IoResult<RootCertStore> {
+pub(crate) fn read_trust_anchor(mut trust_anchor: &[u8]) -> IoResult<RootCertStore> {
     let mut store = RootCertStore::empty();
     let ders = rustls_pemfile::certs(&mut trust_anchor);
     for der in ders {
@@ -378,6 +378,13 @@ where
     }
 
     async fn accept(&mut self) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme)> {
+        let (stream, local_addr, remote_addr, scheme, _) = self.accept_with_extensions().await?;
+        Ok((stream, local_addr, remote_addr, scheme))
+    }
+
+    async fn accept_with_extensions(
+        &mut self,
+    ) -> IoResult<(Self::Io, LocalAddr, RemoteAddr, Scheme, Extensions)> {
         loop {
             tokio::select! {
                 res = self.config_stream.next() => {
@@ -398,15 +405,27 @@ where
                         unreachable!()
                     }
                 }
-                res = self.inner.accept() => {
-                    let (stream, local_addr, remote_addr, _) = res?;
+                res = self.inner.accept_with_extensions() => {
+                    let (stream, local_addr, remote_addr, _, extensions) = res?;
                     let tls_acceptor = match &self.current_tls_acceptor {
                         Some(tls_acceptor) => tls_acceptor,
                         None => return Err(IoError::other("no valid tls config.")),
                     };
 
-                    let stream = HandshakeStream::new(tls_acceptor.accept(stream));
-                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS));
+                    let handshake = tls_acceptor.accept(stream);
+                    #[cfg(feature = "client-cert")]
+                    let (extensions, handshake) = {
+                        let (extensions, peer) = crate::web::PeerCertificate::attach(extensions);
+                        (extensions, async move {
+                            let stream = handshake.await?;
+                            if let Some(certs) = stream.get_ref().1.peer_certificates() {
+                                peer.set(certs.iter().map(|cert| cert.to_vec()).collect());
+                            }
+                            Ok(stream)
+                        })
+                    };
+                    let stream = HandshakeStream::new(handshake);
+                    return Ok((stream, local_addr, remote_addr, Scheme::HTTPS, extensions));
                 }
             }
         }
@@ -470,4 +489,54 @@ mod tests {
         let (mut stream, _, _, _) = acceptor.accept().await.unwrap();
         assert_eq!(stream.read_i32().await.unwrap(), 10);
     }
+
+    #[cfg(feature = "client-cert")]
+    #[tokio::test]
+    async fn client_cert() {
+        use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+        use crate::web::{ClientCert, client_cert::tests::generate_client_cert};
+
+        let (ca, cert, key) = generate_client_cert("client", vec![]);
+        let listener = TcpListener::bind("127.0.0.1:0").rustls(
+            RustlsConfig::new()
+                .fallback(
+                    RustlsCertificate::new()
+                        .cert(include_bytes!("certs/cert1.pem").as_ref())
+                        .key(include_bytes!("certs/key1.pem").as_ref()),
+                )
+                .client_auth_required(ca),
+        );
+        let mut acceptor = listener.into_acceptor().await.unwrap();
+        let local_addr = acceptor.local_addr().pop().unwrap();
+
+        let client_cert = cert.clone();
+        tokio::spawn(async move {
+            let config = ClientConfig::builder()
+                .with_root_certificates(
+                    read_trust_anchor(include_bytes!("certs/chain1.pem")).unwrap(),
+                )
+                .with_client_auth_cert(
+                    vec![CertificateDer::from(client_cert)],
+                    PrivateKeyDer::try_from(key).unwrap(),
+                )
+                .unwrap();
+
+            let connector = tokio_rustls::TlsConnector::from(Arc::new(config));
+            let domain = ServerName::try_from("testserver.com").unwrap();
+            let stream = TcpStream::connect(*local_addr.as_socket_addr().unwrap())
+                .await
+                .unwrap();
+            let mut stream = connector.connect(domain, stream).await.unwrap();
+            stream.write_i32(10).await.unwrap();
+        });
+
+        let (mut stream, _, _, _, extensions) = acceptor.accept_with_extensions().await.unwrap();
+        assert!(ClientCert::from_extensions(&extensions).is_none());
+        assert_eq!(stream.read_i32().await.unwrap(), 10);
+
+        let peer = ClientCert::from_extensions(&extensions).unwrap();
+        assert_eq!(peer.common_name(), Some("client"));
+        assert_eq!(peer.der(), cert);
+    }
 }
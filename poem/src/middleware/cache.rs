@@ -0,0 +1,1139 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use bytes::Bytes;
+use headers::{HeaderMapExt, IfModifiedSince, IfNoneMatch};
+use http::{HeaderMap, HeaderName, Method, StatusCode, header};
+use parking_lot::Mutex;
+use tokio::sync::watch;
+
+use crate::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+
+/// A response stored by the [`Cache`] middleware.
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    /// The status code
+    pub status: StatusCode,
+    /// The response headers
+    pub headers: HeaderMap,
+    /// The response body
+    pub body: Bytes,
+    /// The time at which the response was received or last revalidated
+    pub stored_at: SystemTime,
+}
+
+/// An entry of a [`CacheStore`].
+#[derive(Debug, Clone)]
+pub enum CacheEntry {
+    /// A stored response.
+    Response(CachedResponse),
+    /// The responses for this key vary on the values of the specified request
+    /// headers, each variant is stored under its own key.
+    Variants(Vec<HeaderName>),
+}
+
+impl CacheEntry {
+    fn size(&self) -> usize {
+        match self {
+            CacheEntry::Response(resp) => {
+                resp.body.len()
+                    + resp
+                        .headers
+                        .iter()
+                        .map(|(name, value)| name.as_str().len() + value.len())
+                        .sum::<usize>()
+            }
+            CacheEntry::Variants(names) => names.iter().map(|name| name.as_str().len()).sum(),
+        }
+    }
+}
+
+/// Represents a back-end storage for the [`Cache`] middleware.
+pub trait CacheStore: Send + Sync {
+    /// Gets the entry of the specified key.
+    fn get<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> impl Future<Output = Result<Option<CacheEntry>>> + Send + 'a;
+
+    /// Sets the entry of the specified key, it can be discarded after `ttl`.
+    fn set<'a>(
+        &'a self,
+        key: &'a str,
+        entry: CacheEntry,
+        ttl: Duration,
+    ) -> impl Future<Output = Result<()>> + Send + 'a;
+
+    /// Removes the entry of the specified key.
+    fn remove<'a>(&'a self, key: &'a str) -> impl Future<Output = Result<()>> + Send + 'a;
+}
+
+struct MemoryEntry {
+    entry: CacheEntry,
+    size: usize,
+    expires_at: Instant,
+    tick: u64,
+}
+
+#[derive(Default)]
+struct InnerMemoryStore {
+    entries: HashMap<String, MemoryEntry>,
+    lru: BTreeMap<u64, String>,
+    tick: u64,
+    used: usize,
+}
+
+impl InnerMemoryStore {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.tick);
+            self.used -= entry.size;
+        }
+    }
+}
+
+/// A [`CacheStore`] that keeps the entries in memory, the least recently used
+/// entries are evicted when the total size exceeds the byte budget.
+pub struct MemoryCacheStore {
+    max_bytes: usize,
+    inner: Mutex<InnerMemoryStore>,
+}
+
+impl Default for MemoryCacheStore {
+    fn default() -> Self {
+        Self::new(64 * 1024 * 1024)
+    }
+}
+
+impl MemoryCacheStore {
+    /// Create a `MemoryCacheStore` with the specified byte budget.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            inner: Default::default(),
+        }
+    }
+
+    /// Returns the total size of the stored entries in bytes.
+    pub fn used_bytes(&self) -> usize {
+        self.inner.lock().used
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    async fn get<'a>(&'a self, key: &'a str) -> Result<Option<CacheEntry>> {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+
+        let Some(entry) = inner.entries.get_mut(key) else {
+            return Ok(None);
+        };
+        if entry.expires_at <= Instant::now() {
+            inner.remove(key);
+            return Ok(None);
+        }
+
+        inner.tick += 1;
+        inner.lru.remove(&entry.tick);
+        inner.lru.insert(inner.tick, key.to_string());
+        entry.tick = inner.tick;
+        Ok(Some(entry.entry.clone()))
+    }
+
+    async fn set<'a>(&'a self, key: &'a str, entry: CacheEntry, ttl: Duration) -> Result<()> {
+        let mut inner = self.inner.lock();
+        inner.remove(key);
+
+        let size = key.len() + entry.size();
+        if size > self.max_bytes {
+            return Ok(());
+        }
+
+        while inner.used + size > self.max_bytes {
+            let Some((_, key)) = inner.lru.pop_first() else {
+                break;
+            };
+            if let Some(entry) = inner.entries.remove(&key) {
+                inner.used -= entry.size;
+            }
+        }
+
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.lru.insert(tick, key.to_string());
+        inner.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                entry,
+                size,
+                expires_at: Instant::now() + ttl,
+                tick,
+            },
+        );
+        inner.used += size;
+        Ok(())
+    }
+
+    async fn remove<'a>(&'a self, key: &'a str) -> Result<()> {
+        self.inner.lock().remove(key);
+        Ok(())
+    }
+}
+
+/// The `Cache-Control` directives used by the cache.
+#[derive(Debug, Default)]
+struct CacheDirectives {
+    no_store: bool,
+    no_cache: bool,
+    private: bool,
+    public: bool,
+    must_revalidate: bool,
+    max_age: Option<u64>,
+    s_maxage: Option<u64>,
+    stale_while_revalidate: Option<u64>,
+}
+
+impl CacheDirectives {
+    fn parse(headers: &HeaderMap) -> Self {
+        let mut directives = CacheDirectives::default();
+
+        for value in headers.get_all(header::CACHE_CONTROL) {
+            let Ok(value) = value.to_str() else {
+                continue;
+            };
+            for directive in value.split(',') {
+                let (name, arg) = match directive.split_once('=') {
+                    Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+                    None => (directive.trim(), None),
+                };
+                let secs = || arg.and_then(|arg| arg.parse::<u64>().ok());
+
+                match name.to_ascii_lowercase().as_str() {
+                    "no-store" => directives.no_store = true,
+                    "no-cache" => directives.no_cache = true,
+                    "private" => directives.private = true,
+                    "public" => directives.public = true,
+                    "must-revalidate" | "proxy-revalidate" => directives.must_revalidate = true,
+                    "max-age" => directives.max_age = secs(),
+                    "s-maxage" => directives.s_maxage = secs(),
+                    "stale-while-revalidate" => directives.stale_while_revalidate = secs(),
+                    _ => {}
+                }
+            }
+        }
+
+        directives
+    }
+}
+
+/// The status codes which can be stored.
+fn is_cacheable_status(status: StatusCode) -> bool {
+    matches!(
+        status.as_u16(),
+        200 | 203 | 204 | 300 | 301 | 308 | 404 | 405 | 410 | 414 | 501
+    )
+}
+
+fn has_validator(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::ETAG) || headers.contains_key(header::LAST_MODIFIED)
+}
+
+fn age_header(headers: &HeaderMap) -> Duration {
+    headers
+        .typed_get::<headers::Age>()
+        .map(|age| Duration::from_secs(age.as_secs()))
+        .unwrap_or_default()
+}
+
+/// Returns the `Vary` header names of the response, or `None` if it varies on
+/// `*`.
+fn vary_names(headers: &HeaderMap) -> Option<Vec<HeaderName>> {
+    let mut names = Vec::new();
+    for value in headers.get_all(header::VARY) {
+        for name in value.to_str().unwrap_or_default().split(',') {
+            let name = name.trim();
+            if name == "*" {
+                return None;
+            }
+            if let Ok(name) = HeaderName::try_from(name) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+    }
+    Some(names)
+}
+
+fn variant_key(key: &str, names: &[HeaderName], headers: &HeaderMap) -> String {
+    let mut variant_key = key.to_string();
+    for name in names {
+        variant_key.push('\n');
+        variant_key.push_str(name.as_str());
+        variant_key.push(':');
+        for (idx, value) in headers.get_all(name).iter().enumerate() {
+            if idx > 0 {
+                variant_key.push(',');
+            }
+            variant_key.push_str(&String::from_utf8_lossy(value.as_bytes()));
+        }
+    }
+    variant_key
+}
+
+type KeyFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+/// Middleware for caching the responses of `GET` and `HEAD` requests.
+///
+/// The middleware acts as a shared HTTP cache, a response is stored if its
+/// `Cache-Control` header allows it (`s-maxage`, `max-age` or `Expires`), and
+/// is not `private` or `no-store`. Responses with `Set-Cookie`, or
+/// `Vary: *` are never stored, and responses to requests with an
+/// `Authorization` header are only stored if they are explicitly `public`.
+///
+/// - Responses with a `Vary` header are stored per variant.
+/// - Stale responses with an `ETag` or `Last-Modified` header are revalidated
+///   with a conditional request.
+/// - Stale responses within their `stale-while-revalidate` window are served
+///   immediately, while they are revalidated in the background.
+/// - Concurrent requests for a missing or stale response are coalesced, so that
+///   only one of them calls the inner endpoint.
+/// - A successful `POST`, `PUT`, `PATCH` or `DELETE` request invalidates the
+///   stored response of its URI.
+///
+/// The responses are kept in a [`MemoryCacheStore`] by default, use
+/// [`Cache::store`] to use another [`CacheStore`].
+///
+/// # Example
+///
+/// ```
+/// use std::sync::{
+///     Arc,
+///     atomic::{AtomicUsize, Ordering},
+/// };
+///
+/// use poem::{
+///     EndpointExt, Response, endpoint::make_sync, http::header, middleware::Cache,
+///     test::TestClient,
+/// };
+///
+/// let calls = Arc::new(AtomicUsize::new(0));
+/// let app = make_sync({
+///     let calls = calls.clone();
+///     move |_| {
+///         calls.fetch_add(1, Ordering::SeqCst);
+///         Response::builder()
+///             .header(header::CACHE_CONTROL, "max-age=60")
+///             .body("hello")
+///     }
+/// })
+/// .with(Cache::new());
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// for _ in 0..3 {
+///     cli.get("/").send().await.assert_text("hello").await;
+/// }
+/// assert_eq!(calls.load(Ordering::SeqCst), 1);
+/// # });
+/// ```
+pub struct Cache<S = MemoryCacheStore> {
+    store: Arc<S>,
+    key_fn: KeyFn,
+    default_ttl: Option<Duration>,
+    stale_ttl: Duration,
+    max_body_size: usize,
+    coalesce: bool,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cache {
+    /// Create `Cache` middleware.
+    pub fn new() -> Self {
+        Self {
+            store: Arc::new(MemoryCacheStore::default()),
+            key_fn: Arc::new(|req| {
+                let host = req.header(header::HOST).unwrap_or_default();
+                let path = req.uri().path_and_query().map(|pq| pq.as_str());
+                Some(format!("{host}{}", path.unwrap_or("/")))
+            }),
+            default_ttl: None,
+            stale_ttl: Duration::from_secs(60 * 60),
+            max_body_size: 1024 * 1024,
+            coalesce: true,
+        }
+    }
+}
+
+impl<S> Cache<S> {
+    /// Sets the store used to keep the responses.
+    #[must_use]
+    pub fn store<S2>(self, store: S2) -> Cache<S2> {
+        Cache {
+            store: Arc::new(store),
+            key_fn: self.key_fn,
+            default_ttl: self.default_ttl,
+            stale_ttl: self.stale_ttl,
+            max_body_size: self.max_body_size,
+            coalesce: self.coalesce,
+        }
+    }
+
+    /// Uses a closure to compute the cache key of the request, default is the
+    /// `Host` header followed by the path and query of the URI.
+    ///
+    /// If the closure returns `None`, the request bypasses the cache.
+    #[must_use]
+    pub fn key(self, f: impl Fn(&Request) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self {
+            key_fn: Arc::new(f),
+            ..self
+        }
+    }
+
+    /// Sets the freshness lifetime of the responses without `max-age`,
+    /// `s-maxage` or `Expires`, default is `None`, which means that such
+    /// responses are not stored.
+    #[must_use]
+    pub fn default_ttl(self, ttl: Duration) -> Self {
+        Self {
+            default_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    /// Sets how long the stale responses with an `ETag` or `Last-Modified`
+    /// header are kept for revalidation, default is 1 hour.
+    #[must_use]
+    pub fn stale_ttl(self, ttl: Duration) -> Self {
+        Self {
+            stale_ttl: ttl,
+            ..self
+        }
+    }
+
+    /// Sets the maximum size of the bodies that are stored, default is 1 MiB.
+    #[must_use]
+    pub fn max_body_size(self, size: usize) -> Self {
+        Self {
+            max_body_size: size,
+            ..self
+        }
+    }
+
+    /// Enables or disables request coalescing, default is `true`.
+    #[must_use]
+    pub fn coalesce(self, enable: bool) -> Self {
+        Self {
+            coalesce: enable,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint + 'static, S: CacheStore + 'static> Middleware<E> for Cache<S> {
+    type Output = CacheEndpoint<E, S>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CacheEndpoint {
+            inner: Arc::new(CacheInner {
+                ep,
+                store: self.store.clone(),
+                default_ttl: self.default_ttl,
+                stale_ttl: self.stale_ttl,
+                max_body_size: self.max_body_size,
+                in_flight: Default::default(),
+            }),
+            key_fn: self.key_fn.clone(),
+            coalesce: self.coalesce,
+        }
+    }
+}
+
+/// Endpoint for the Cache middleware.
+pub struct CacheEndpoint<E, S> {
+    inner: Arc<CacheInner<E, S>>,
+    key_fn: KeyFn,
+    coalesce: bool,
+}
+
+type InFlight = Arc<Mutex<HashMap<String, watch::Receiver<()>>>>;
+
+struct CacheInner<E, S> {
+    ep: E,
+    store: Arc<S>,
+    default_ttl: Option<Duration>,
+    stale_ttl: Duration,
+    max_body_size: usize,
+    in_flight: InFlight,
+}
+
+/// Removes the key from the in-flight requests when dropped, and wakes up the
+/// waiting requests.
+struct InFlightGuard {
+    key: String,
+    in_flight: InFlight,
+    _tx: watch::Sender<()>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.in_flight.lock().remove(&self.key);
+    }
+}
+
+enum Flight {
+    Leader(InFlightGuard),
+    Follower(watch::Receiver<()>),
+}
+
+/// The conditional headers of the client request, they are evaluated by the
+/// cache instead of the inner endpoint.
+struct Conditions {
+    if_none_match: Option<IfNoneMatch>,
+    if_modified_since: Option<IfModifiedSince>,
+}
+
+impl Conditions {
+    fn take(req: &mut Request) -> Self {
+        let headers = req.headers_mut();
+        let conditions = Self {
+            if_none_match: headers.typed_get(),
+            if_modified_since: headers.typed_get(),
+        };
+        headers.remove(header::IF_NONE_MATCH);
+        headers.remove(header::IF_MODIFIED_SINCE);
+        headers.remove(header::IF_RANGE);
+        conditions
+    }
+
+    fn is_not_modified(&self, headers: &HeaderMap) -> bool {
+        if let Some(if_none_match) = &self.if_none_match {
+            headers
+                .typed_get::<headers::ETag>()
+                .is_some_and(|etag| !if_none_match.precondition_passes(&etag))
+        } else if let Some(if_modified_since) = &self.if_modified_since {
+            headers
+                .typed_get::<headers::LastModified>()
+                .is_some_and(|modified| !if_modified_since.is_modified(modified.into()))
+        } else {
+            false
+        }
+    }
+
+    /// Replaces the response with `304 Not Modified` if the conditions match.
+    fn apply(&self, resp: Response) -> Response {
+        if resp.status() != StatusCode::OK || !self.is_not_modified(resp.headers()) {
+            return resp;
+        }
+
+        let mut not_modified = Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .finish();
+        for name in [
+            header::CACHE_CONTROL,
+            header::CONTENT_LOCATION,
+            header::DATE,
+            header::ETAG,
+            header::EXPIRES,
+            header::LAST_MODIFIED,
+            header::VARY,
+            header::AGE,
+        ] {
+            for value in resp.headers().get_all(&name) {
+                not_modified.headers_mut().append(&name, value.clone());
+            }
+        }
+        not_modified
+    }
+}
+
+enum Freshness {
+    Fresh,
+    StaleWhileRevalidate,
+    Stale,
+}
+
+impl<E: Endpoint, S: CacheStore> CacheInner<E, S> {
+    /// Returns the freshness lifetime of a response.
+    fn freshness_lifetime(
+        &self,
+        headers: &HeaderMap,
+        directives: &CacheDirectives,
+    ) -> Option<Duration> {
+        if directives.no_cache {
+            return Some(Duration::ZERO);
+        }
+        if let Some(secs) = directives.s_maxage.or(directives.max_age) {
+            return Some(Duration::from_secs(secs));
+        }
+        if let Some(expires) = headers.typed_get::<headers::Expires>() {
+            let date = headers
+                .typed_get::<headers::Date>()
+                .map(SystemTime::from)
+                .unwrap_or_else(SystemTime::now);
+            return Some(
+                SystemTime::from(expires)
+                    .duration_since(date)
+                    .unwrap_or_default(),
+            );
+        }
+        self.default_ttl
+    }
+
+    fn freshness(&self, resp: &CachedResponse) -> Freshness {
+        let directives = CacheDirectives::parse(&resp.headers);
+        let lifetime = self
+            .freshness_lifetime(&resp.headers, &directives)
+            .unwrap_or_default();
+        let age = current_age(resp);
+
+        if age < lifetime {
+            Freshness::Fresh
+        } else if !directives.must_revalidate
+            && directives
+                .stale_while_revalidate
+                .is_some_and(|secs| age < lifetime + Duration::from_secs(secs))
+        {
+            Freshness::StaleWhileRevalidate
+        } else {
+            Freshness::Stale
+        }
+    }
+
+    /// Returns how long the response should be kept by the store, or `None`
+    /// if it cannot be stored.
+    fn store_ttl(
+        &self,
+        req_headers: &HeaderMap,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Option<Duration> {
+        if !is_cacheable_status(status) || headers.contains_key(header::SET_COOKIE) {
+            return None;
+        }
+
+        let directives = CacheDirectives::parse(headers);
+        if directives.no_store || directives.private {
+            return None;
+        }
+        if req_headers.contains_key(header::AUTHORIZATION)
+            && !directives.public
+            && !directives.must_revalidate
+            && directives.s_maxage.is_none()
+        {
+            return None;
+        }
+
+        let lifetime = self.freshness_lifetime(headers, &directives)?;
+        let mut stale = Duration::from_secs(directives.stale_while_revalidate.unwrap_or_default());
+        if has_validator(headers) {
+            stale = stale.max(self.stale_ttl);
+        }
+        Some(lifetime + stale).filter(|ttl| !ttl.is_zero())
+    }
+
+    async fn lookup(&self, key: &str, headers: &HeaderMap) -> Option<CachedResponse> {
+        let entry = match self.store.get(key).await {
+            Ok(Some(CacheEntry::Variants(names))) => {
+                self.store.get(&variant_key(key, &names, headers)).await
+            }
+            res => res,
+        };
+        match entry {
+            Ok(Some(CacheEntry::Response(resp))) => Some(resp),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to read the cache store");
+                None
+            }
+        }
+    }
+
+    async fn save(&self, key: &str, req_headers: &HeaderMap, resp: CachedResponse, ttl: Duration) {
+        let Some(names) = vary_names(&resp.headers) else {
+            return;
+        };
+
+        let res = if names.is_empty() {
+            self.store.set(key, CacheEntry::Response(resp), ttl).await
+        } else {
+            let variant_key = variant_key(key, &names, req_headers);
+            match self.store.set(key, CacheEntry::Variants(names), ttl).await {
+                Ok(()) => {
+                    self.store
+                        .set(&variant_key, CacheEntry::Response(resp), ttl)
+                        .await
+                }
+                Err(err) => Err(err),
+            }
+        };
+        if let Err(err) = res {
+            tracing::warn!(error = %err, "failed to write the cache store");
+        }
+    }
+
+    fn join(&self, key: &str) -> Flight {
+        let mut in_flight = self.in_flight.lock();
+        if let Some(rx) = in_flight.get(key) {
+            return Flight::Follower(rx.clone());
+        }
+
+        let (tx, rx) = watch::channel(());
+        in_flight.insert(key.to_string(), rx);
+        Flight::Leader(InFlightGuard {
+            key: key.to_string(),
+            in_flight: self.in_flight.clone(),
+            _tx: tx,
+        })
+    }
+
+    /// Calls the inner endpoint, revalidating the stale response if it has a
+    /// validator, and stores the new response.
+    async fn fetch(
+        &self,
+        key: &str,
+        mut req: Request,
+        stale: Option<CachedResponse>,
+    ) -> Result<Response> {
+        if let Some(stale) = stale
+            .as_ref()
+            .filter(|stale| stale.status == StatusCode::OK)
+        {
+            let headers = req.headers_mut();
+            if let Some(etag) = stale.headers.get(header::ETAG) {
+                headers.insert(header::IF_NONE_MATCH, etag.clone());
+            } else if let Some(modified) = stale.headers.get(header::LAST_MODIFIED) {
+                headers.insert(header::IF_MODIFIED_SINCE, modified.clone());
+            }
+        }
+
+        let req_headers = req.headers().clone();
+        let mut resp = self.ep.call(req).await?.into_response();
+
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            if let Some(mut stale) = stale {
+                for name in resp.headers().keys() {
+                    if name != header::CONTENT_LENGTH {
+                        stale.headers.remove(name);
+                    }
+                }
+                for (name, value) in resp.headers() {
+                    if name != header::CONTENT_LENGTH {
+                        stale.headers.append(name, value.clone());
+                    }
+                }
+                stale.stored_at = SystemTime::now();
+
+                if let Some(ttl) = self.store_ttl(&req_headers, stale.status, &stale.headers) {
+                    self.save(key, &req_headers, stale.clone(), ttl).await;
+                }
+                return Ok(serve(&stale));
+            }
+        }
+
+        let Some(ttl) = self.store_ttl(&req_headers, resp.status(), resp.headers()) else {
+            // The stale response has been replaced by a response that cannot be stored.
+            if stale.is_some() {
+                if let Err(err) = self.store.remove(key).await {
+                    tracing::warn!(error = %err, "failed to write the cache store");
+                }
+            }
+            return Ok(resp);
+        };
+        if vary_names(resp.headers()).is_none() {
+            return Ok(resp);
+        }
+
+        match resp
+            .take_body()
+            .into_bytes_within(resp.headers(), self.max_body_size)
+            .await
+        {
+            Ok(body) => {
+                self.save(
+                    key,
+                    &req_headers,
+                    CachedResponse {
+                        status: resp.status(),
+                        headers: resp.headers().clone(),
+                        body: body.clone(),
+                        stored_at: SystemTime::now(),
+                    },
+                    ttl,
+                )
+                .await;
+                resp.set_body(body);
+            }
+            Err(body) => resp.set_body(body),
+        }
+        Ok(resp)
+    }
+}
+
+fn current_age(resp: &CachedResponse) -> Duration {
+    age_header(&resp.headers) + resp.stored_at.elapsed().unwrap_or_default()
+}
+
+/// Creates a response from the stored response.
+fn serve(resp: &CachedResponse) -> Response {
+    let mut headers = resp.headers.clone();
+    headers.typed_insert(headers::Age::from_secs(current_age(resp).as_secs()));
+
+    let mut builder = Response::builder().status(resp.status);
+    for (name, value) in &headers {
+        builder = builder.header(name, value);
+    }
+    builder.body(resp.body.clone())
+}
+
+impl<E: Endpoint + 'static, S: CacheStore + 'static> Endpoint for CacheEndpoint<E, S> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let inner = &self.inner;
+        let key = (self.key_fn)(&req);
+
+        if matches!(
+            *req.method(),
+            Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+        ) {
+            let resp = inner.ep.call(req).await?.into_response();
+            if let Some(key) = key.filter(|_| resp.status().is_success()) {
+                if let Err(err) = inner.store.remove(&key).await {
+                    tracing::warn!(error = %err, "failed to write the cache store");
+                }
+            }
+            return Ok(resp);
+        }
+
+        let req_directives = CacheDirectives::parse(req.headers());
+        let Some(key) = key.filter(|_| {
+            matches!(*req.method(), Method::GET | Method::HEAD) && !req_directives.no_store
+        }) else {
+            return inner.ep.call(req).await.map(IntoResponse::into_response);
+        };
+
+        let no_cache = req_directives.no_cache
+            || req_directives.max_age == Some(0)
+            || req
+                .headers()
+                .get(header::PRAGMA)
+                .is_some_and(|value| value.as_bytes().eq_ignore_ascii_case(b"no-cache"));
+        let mut stale = None;
+
+        if !no_cache {
+            if let Some(resp) = inner.lookup(&key, req.headers()).await {
+                match inner.freshness(&resp) {
+                    Freshness::Fresh => {
+                        return Ok(Conditions::take(&mut req).apply(serve(&resp)));
+                    }
+                    Freshness::StaleWhileRevalidate => {
+                        let conditions = Conditions::take(&mut req);
+                        if *req.method() == Method::GET {
+                            if let Flight::Leader(guard) = inner.join(&key) {
+                                let inner = inner.clone();
+                                let stale = resp.clone();
+                                tokio::spawn(async move {
+                                    if let Err(err) = inner.fetch(&key, req, Some(stale)).await {
+                                        tracing::warn!(error = %err, "failed to revalidate the cached response");
+                                    }
+                                    drop(guard);
+                                });
+                            }
+                        }
+                        return Ok(conditions.apply(serve(&resp)));
+                    }
+                    Freshness::Stale => stale = Some(resp),
+                }
+            }
+        }
+
+        if *req.method() == Method::HEAD {
+            return inner.ep.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let conditions = Conditions::take(&mut req);
+        let _guard = if self.coalesce && !no_cache {
+            match inner.join(&key) {
+                Flight::Leader(guard) => Some(guard),
+                Flight::Follower(mut rx) => {
+                    _ = rx.changed().await;
+                    if let Some(resp) = inner.lookup(&key, req.headers()).await {
+                        if let Freshness::Fresh = inner.freshness(&resp) {
+                            return Ok(conditions.apply(serve(&resp)));
+                        }
+                        stale = Some(resp);
+                    }
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let resp = inner.fetch(&key, req, stale).await?;
+        Ok(conditions.apply(resp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::{Body, EndpointExt, endpoint::make, test::TestClient};
+
+    fn counter() -> Arc<AtomicUsize> {
+        Arc::new(AtomicUsize::new(0))
+    }
+
+    #[tokio::test]
+    async fn cache_control() {
+        let calls = counter();
+        let app = make({
+            let calls = calls.clone();
+            move |req| {
+                let calls = calls.clone();
+                async move {
+                    let n = calls.fetch_add(1, Ordering::SeqCst);
+                    let cache_control = match req.uri().path() {
+                        "/public" => "max-age=60",
+                        "/private" => "private, max-age=60",
+                        "/no-store" => "no-store",
+                        _ => "no-cache",
+                    };
+                    Response::builder()
+                        .header(header::CACHE_CONTROL, cache_control)
+                        .body(n.to_string())
+                }
+            }
+        })
+        .with(Cache::new());
+        let cli = TestClient::new(app);
+
+        cli.get("/public").send().await.assert_text("0").await;
+        let resp = cli.get("/public").send().await;
+        resp.assert_header(header::AGE, "0");
+        resp.assert_text("0").await;
+        cli.head("/public").send().await.assert_status_is_ok();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // the client asks for a fresh response
+        cli.get("/public")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .send()
+            .await
+            .assert_text("1")
+            .await;
+        cli.get("/public").send().await.assert_text("1").await;
+
+        cli.get("/private").send().await.assert_text("2").await;
+        cli.get("/private").send().await.assert_text("3").await;
+        cli.get("/no-store").send().await.assert_text("4").await;
+        cli.get("/no-store").send().await.assert_text("5").await;
+
+        // invalidated by an unsafe method
+        cli.post("/public").send().await.assert_text("6").await;
+        cli.get("/public").send().await.assert_text("7").await;
+        cli.get("/public").send().await.assert_text("7").await;
+    }
+
+    #[tokio::test]
+    async fn vary() {
+        let calls = counter();
+        let app = make({
+            let calls = calls.clone();
+            move |req| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let lang = req.header("accept-language").unwrap_or("en").to_string();
+                async move {
+                    Response::builder()
+                        .header(header::CACHE_CONTROL, "max-age=60")
+                        .header(header::VARY, "Accept-Language")
+                        .body(lang)
+                }
+            }
+        })
+        .with(Cache::new());
+        let cli = TestClient::new(app);
+
+        for _ in 0..2 {
+            for lang in ["en", "fr"] {
+                cli.get("/")
+                    .header("accept-language", lang)
+                    .send()
+                    .await
+                    .assert_text(lang)
+                    .await;
+            }
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn revalidate() {
+        let calls = counter();
+        let not_modified = counter();
+        let app = make({
+            let calls = calls.clone();
+            let not_modified = not_modified.clone();
+            move |req| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let matched = req.header(header::IF_NONE_MATCH) == Some("\"v1\"");
+                if matched {
+                    not_modified.fetch_add(1, Ordering::SeqCst);
+                }
+                async move {
+                    let builder = Response::builder()
+                        .header(header::CACHE_CONTROL, "no-cache")
+                        .header(header::ETAG, "\"v1\"");
+                    if matched {
+                        builder.status(StatusCode::NOT_MODIFIED).finish()
+                    } else {
+                        builder.body("hello")
+                    }
+                }
+            }
+        })
+        .with(Cache::new());
+        let cli = TestClient::new(app);
+
+        cli.get("/").send().await.assert_text("hello").await;
+        cli.get("/").send().await.assert_text("hello").await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+
+        // the conditional request of the client is answered by the cache
+        cli.get("/")
+            .header(header::IF_NONE_MATCH, "\"v1\"")
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_MODIFIED);
+        assert_eq!(not_modified.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn stale_while_revalidate() {
+        let calls = counter();
+        let app = make({
+            let calls = calls.clone();
+            move |_| {
+                let n = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    Response::builder()
+                        .header(
+                            header::CACHE_CONTROL,
+                            "max-age=1, stale-while-revalidate=60",
+                        )
+                        .body(n.to_string())
+                }
+            }
+        })
+        .with(Cache::new());
+        let cli = TestClient::new(app);
+
+        cli.get("/").send().await.assert_text("0").await;
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+
+        // the stale response is served, and revalidated in the background
+        cli.get("/").send().await.assert_text("0").await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        cli.get("/").send().await.assert_text("1").await;
+    }
+
+    #[tokio::test]
+    async fn coalesce() {
+        let calls = counter();
+        let app = make({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Response::builder()
+                        .header(header::CACHE_CONTROL, "max-age=60")
+                        .body("hello")
+                }
+            }
+        })
+        .with(Cache::new());
+        let cli = TestClient::new(app);
+
+        let resps = futures_util::future::join_all((0..10).map(|_| cli.get("/").send())).await;
+        for resp in resps {
+            resp.assert_text("hello").await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn max_body_size() {
+        let calls = counter();
+        let app = make({
+            let calls = calls.clone();
+            move |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    Response::builder()
+                        .header(header::CACHE_CONTROL, "max-age=60")
+                        .body(Body::from_bytes_stream(futures_util::stream::iter([
+                            Ok::<_, std::io::Error>("hello, "),
+                            Ok("world!"),
+                        ])))
+                }
+            }
+        })
+        .with(Cache::new().max_body_size(8));
+        let cli = TestClient::new(app);
+
+        for _ in 0..2 {
+            cli.get("/").send().await.assert_text("hello, world!").await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn memory_store_lru() {
+        let store = MemoryCacheStore::new(100);
+        let entry = |len| {
+            CacheEntry::Response(CachedResponse {
+                status: StatusCode::OK,
+                headers: HeaderMap::new(),
+                body: vec![0; len].into(),
+                stored_at: SystemTime::now(),
+            })
+        };
+        let ttl = Duration::from_secs(60);
+
+        store.set("a", entry(39), ttl).await.unwrap();
+        store.set("b", entry(39), ttl).await.unwrap();
+        assert_eq!(store.used_bytes(), 80);
+        assert!(store.get("a").await.unwrap().is_some());
+
+        // `b` is the least recently used
+        store.set("c", entry(39), ttl).await.unwrap();
+        assert!(store.get("a").await.unwrap().is_some());
+        assert!(store.get("b").await.unwrap().is_none());
+        assert!(store.get("c").await.unwrap().is_some());
+
+        // larger than the budget
+        store.set("d", entry(100), ttl).await.unwrap();
+        assert!(store.get("d").await.unwrap().is_none());
+
+        store.set("e", entry(10), Duration::ZERO).await.unwrap();
+        assert!(store.get("e").await.unwrap().is_none());
+        store.remove("a").await.unwrap();
+        assert_eq!(store.used_bytes(), 40);
+    }
+}
@@ -0,0 +1,604 @@
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    Endpoint, IntoResponse, Middleware, Request, Response, Result, error::CircuitBreakerError,
+};
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CircuitState {
+    /// The requests are passed to the endpoint, and the outcomes are recorded.
+    Closed,
+    /// The requests are rejected without calling the endpoint.
+    Open,
+    /// A limited number of trial requests are passed to the endpoint, the
+    /// circuit is closed if they succeed, or opened again if one of them
+    /// fails.
+    HalfOpen,
+}
+
+impl Display for CircuitState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitState::Closed => f.write_str("closed"),
+            CircuitState::Open => f.write_str("open"),
+            CircuitState::HalfOpen => f.write_str("half-open"),
+        }
+    }
+}
+
+/// The outcomes recorded during one tenth of the window.
+struct Bucket {
+    start: Instant,
+    total: u32,
+    failures: u32,
+}
+
+enum State {
+    Closed { buckets: VecDeque<Bucket> },
+    Open { until: Instant },
+    HalfOpen { in_flight: u32, successes: u32 },
+}
+
+impl State {
+    fn kind(&self) -> CircuitState {
+        match self {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+type StateChangeFn = Arc<dyn Fn(CircuitState, CircuitState) + Send + Sync>;
+
+#[derive(Clone)]
+struct Config {
+    failure_rate: f64,
+    min_requests: u32,
+    window: Duration,
+    slow_call_duration: Option<Duration>,
+    open_duration: Duration,
+    half_open_requests: u32,
+    on_state_change: Option<StateChangeFn>,
+}
+
+struct Breaker {
+    config: Config,
+    state: Mutex<State>,
+}
+
+/// Whether the request was admitted in the closed or half-open state.
+#[derive(Copy, Clone)]
+enum Admission {
+    Closed,
+    Trial,
+}
+
+impl Breaker {
+    fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::Closed {
+                buckets: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn set_state(&self, state: &mut State, new_state: State) {
+        let (from, to) = (state.kind(), new_state.kind());
+        *state = new_state;
+        if from != to {
+            tracing::info!(from = %from, to = %to, "circuit breaker state changed");
+            if let Some(f) = &self.config.on_state_change {
+                f(from, to);
+            }
+        }
+    }
+
+    fn state(&self) -> CircuitState {
+        let mut state = self.state.lock();
+        if let State::Open { until } = *state {
+            if Instant::now() >= until {
+                self.set_state(
+                    &mut state,
+                    State::HalfOpen {
+                        in_flight: 0,
+                        successes: 0,
+                    },
+                );
+            }
+        }
+        state.kind()
+    }
+
+    fn admit(&self) -> Result<Admission, CircuitBreakerError> {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+
+        if let State::Open { until } = *state {
+            if now < until {
+                return Err(CircuitBreakerError::Open {
+                    retry_after: until - now,
+                });
+            }
+            self.set_state(
+                &mut state,
+                State::HalfOpen {
+                    in_flight: 0,
+                    successes: 0,
+                },
+            );
+        }
+
+        match &mut *state {
+            State::Closed { .. } => Ok(Admission::Closed),
+            State::HalfOpen {
+                in_flight,
+                successes,
+            } if *in_flight + *successes < self.config.half_open_requests => {
+                *in_flight += 1;
+                Ok(Admission::Trial)
+            }
+            _ => Err(CircuitBreakerError::Open {
+                retry_after: Duration::from_secs(1),
+            }),
+        }
+    }
+
+    fn open(&self, state: &mut State) {
+        self.set_state(
+            state,
+            State::Open {
+                until: Instant::now() + self.config.open_duration,
+            },
+        );
+    }
+
+    /// Records the outcome of a request, `None` means that the request was
+    /// cancelled.
+    fn record(&self, admission: Admission, failure: Option<bool>) {
+        let now = Instant::now();
+        let mut state = self.state.lock();
+
+        match (&mut *state, admission) {
+            (State::Closed { buckets }, Admission::Closed) => {
+                let Some(failure) = failure else {
+                    return;
+                };
+
+                let bucket_len = self.config.window / 10;
+                while buckets
+                    .front()
+                    .is_some_and(|bucket| now.duration_since(bucket.start) >= self.config.window)
+                {
+                    buckets.pop_front();
+                }
+                match buckets.back_mut() {
+                    Some(bucket) if now.duration_since(bucket.start) < bucket_len => {
+                        bucket.total += 1;
+                        bucket.failures += u32::from(failure);
+                    }
+                    _ => buckets.push_back(Bucket {
+                        start: now,
+                        total: 1,
+                        failures: u32::from(failure),
+                    }),
+                }
+
+                let (total, failures) = buckets.iter().fold((0, 0), |(total, failures), b| {
+                    (total + b.total, failures + b.failures)
+                });
+                if total >= self.config.min_requests
+                    && f64::from(failures) >= f64::from(total) * self.config.failure_rate
+                {
+                    self.open(&mut state);
+                }
+            }
+            (
+                State::HalfOpen {
+                    in_flight,
+                    successes,
+                },
+                Admission::Trial,
+            ) => {
+                *in_flight -= 1;
+                match failure {
+                    Some(true) => self.open(&mut state),
+                    Some(false) => {
+                        *successes += 1;
+                        if *successes >= self.config.half_open_requests {
+                            self.set_state(
+                                &mut state,
+                                State::Closed {
+                                    buckets: VecDeque::new(),
+                                },
+                            );
+                        }
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Records the outcome of an admitted request, the request is considered
+/// cancelled if it is dropped before completing.
+struct Guard<'a> {
+    breaker: &'a Breaker,
+    admission: Admission,
+    failure: Option<bool>,
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.breaker.record(self.admission, self.failure);
+    }
+}
+
+/// Middleware that stops calling the inner endpoint when it keeps failing.
+///
+/// The outcomes of the requests are recorded over a sliding window, a request
+/// fails if the endpoint returns an error or a response with a `5xx` status,
+/// or if it takes longer than [`CircuitBreaker::slow_call_duration`].
+///
+/// - When the failure rate reaches the threshold, the circuit opens, and the
+///   requests are rejected with a [`CircuitBreakerError::Open`] error (`503
+///   Service Unavailable` with a `Retry-After` header).
+/// - After [`CircuitBreaker::open_duration`], the circuit becomes half-open,
+///   and a few trial requests are passed to the endpoint. The circuit closes if
+///   all of them succeed, or opens again as soon as one of them fails.
+///
+/// The state can be read with [`CircuitBreaker::state`], or observed with
+/// [`CircuitBreaker::on_state_change`], for example to export it as a metric.
+/// The endpoints created by the same `CircuitBreaker` share its state.
+///
+/// # Errors
+///
+/// - [`CircuitBreakerError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     EndpointExt, Route, get, handler,
+///     http::StatusCode,
+///     middleware::{CircuitBreaker, CircuitState},
+///     test::TestClient,
+/// };
+///
+/// #[handler]
+/// fn index() -> StatusCode {
+///     StatusCode::INTERNAL_SERVER_ERROR
+/// }
+///
+/// let breaker = CircuitBreaker::new()
+///     .min_requests(2)
+///     .open_duration(Duration::from_secs(30));
+/// let app = Route::new().at("/", get(index)).with(breaker.clone());
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// for _ in 0..2 {
+///     cli.get("/")
+///         .send()
+///         .await
+///         .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+/// }
+/// assert_eq!(breaker.state(), CircuitState::Open);
+///
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+/// resp.assert_header("retry-after", "30");
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    breaker: Arc<Breaker>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreaker {
+    /// Create `CircuitBreaker` middleware.
+    pub fn new() -> Self {
+        Self {
+            breaker: Arc::new(Breaker::new(Config {
+                failure_rate: 0.5,
+                min_requests: 20,
+                window: Duration::from_secs(10),
+                slow_call_duration: None,
+                open_duration: Duration::from_secs(30),
+                half_open_requests: 1,
+                on_state_change: None,
+            })),
+        }
+    }
+
+    fn with_config(self, f: impl FnOnce(&mut Config)) -> Self {
+        let mut config = self.breaker.config.clone();
+        f(&mut config);
+        Self {
+            breaker: Arc::new(Breaker::new(config)),
+        }
+    }
+
+    /// Sets the failure rate (between `0.0` and `1.0`) at which the circuit
+    /// opens, default is `0.5`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rate is not greater than `0.0` and at most `1.0`.
+    #[must_use]
+    pub fn failure_rate(self, rate: f64) -> Self {
+        assert!(
+            rate > 0.0 && rate <= 1.0,
+            "the failure rate must be in (0.0, 1.0], got {rate}"
+        );
+        self.with_config(|config| config.failure_rate = rate)
+    }
+
+    /// Sets the minimum number of requests in the window before the failure
+    /// rate is evaluated, default is `20`.
+    #[must_use]
+    pub fn min_requests(self, n: u32) -> Self {
+        self.with_config(|config| config.min_requests = n)
+    }
+
+    /// Sets the duration of the sliding window, default is 10 seconds.
+    #[must_use]
+    pub fn window(self, window: Duration) -> Self {
+        self.with_config(|config| config.window = window)
+    }
+
+    /// Requests taking longer than the specified duration are recorded as
+    /// failures, default is `None`.
+    #[must_use]
+    pub fn slow_call_duration(self, duration: Duration) -> Self {
+        self.with_config(|config| config.slow_call_duration = Some(duration))
+    }
+
+    /// Sets how long the circuit stays open before becoming half-open,
+    /// default is 30 seconds.
+    #[must_use]
+    pub fn open_duration(self, duration: Duration) -> Self {
+        self.with_config(|config| config.open_duration = duration)
+    }
+
+    /// Sets the number of successful trial requests needed to close the
+    /// circuit, default is `1`.
+    #[must_use]
+    pub fn half_open_requests(self, n: u32) -> Self {
+        self.with_config(|config| config.half_open_requests = n.max(1))
+    }
+
+    /// Calls the specified function with the previous and the new states when
+    /// the state changes.
+    ///
+    /// The function is called while the state is locked, so it must not call
+    /// [`CircuitBreaker::state`].
+    #[must_use]
+    pub fn on_state_change(
+        self,
+        f: impl Fn(CircuitState, CircuitState) + Send + Sync + 'static,
+    ) -> Self {
+        self.with_config(|config| config.on_state_change = Some(Arc::new(f)))
+    }
+
+    /// Returns the current state.
+    pub fn state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for CircuitBreaker {
+    type Output = CircuitBreakerEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        CircuitBreakerEndpoint {
+            inner: ep,
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+/// Endpoint for the CircuitBreaker middleware.
+pub struct CircuitBreakerEndpoint<E> {
+    inner: E,
+    breaker: Arc<Breaker>,
+}
+
+impl<E> CircuitBreakerEndpoint<E> {
+    /// Returns the current state.
+    pub fn state(&self) -> CircuitState {
+        self.breaker.state()
+    }
+}
+
+impl<E: Endpoint> Endpoint for CircuitBreakerEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let mut guard = Guard {
+            breaker: &self.breaker,
+            admission: self.breaker.admit()?,
+            failure: None,
+        };
+
+        let start = Instant::now();
+        let res = self.inner.call(req).await.map(IntoResponse::into_response);
+        let slow = self
+            .breaker
+            .config
+            .slow_call_duration
+            .is_some_and(|duration| start.elapsed() > duration);
+        let status = match &res {
+            Ok(resp) => resp.status(),
+            Err(err) => err.status(),
+        };
+        guard.failure = Some(slow || status.is_server_error());
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{EndpointExt, endpoint::make, test::TestClient};
+
+    #[tokio::test]
+    async fn trip_and_recover() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let breaker = CircuitBreaker::new()
+            .min_requests(4)
+            .failure_rate(0.5)
+            .open_duration(Duration::from_millis(100))
+            .on_state_change({
+                let changes = changes.clone();
+                move |from, to| changes.lock().push((from, to))
+            });
+        let cli = TestClient::new(
+            make({
+                let failing = failing.clone();
+                move |_| {
+                    let failing = failing.load(Ordering::SeqCst);
+                    async move {
+                        if failing {
+                            StatusCode::INTERNAL_SERVER_ERROR
+                        } else {
+                            StatusCode::OK
+                        }
+                    }
+                }
+            })
+            .with(breaker.clone()),
+        );
+
+        // 1 failure out of 3 requests
+        failing.store(false, Ordering::SeqCst);
+        cli.get("/").send().await.assert_status_is_ok();
+        cli.get("/").send().await.assert_status_is_ok();
+        failing.store(true, Ordering::SeqCst);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        // 2 failures out of 4 requests
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+
+        // the trial request fails
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        // the trial request succeeds
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        failing.store(false, Ordering::SeqCst);
+        cli.get("/").send().await.assert_status_is_ok();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+
+        assert_eq!(
+            *changes.lock(),
+            [
+                (CircuitState::Closed, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Open),
+                (CircuitState::Open, CircuitState::HalfOpen),
+                (CircuitState::HalfOpen, CircuitState::Closed),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_calls() {
+        let breaker = CircuitBreaker::new()
+            .min_requests(1)
+            .slow_call_duration(Duration::from_millis(10));
+        let cli = TestClient::new(
+            make(|_| async {
+                tokio::time::sleep(Duration::from_millis(30)).await;
+                "slow"
+            })
+            .with(breaker.clone()),
+        );
+
+        cli.get("/").send().await.assert_status_is_ok();
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+
+    #[tokio::test]
+    async fn half_open_limit() {
+        let breaker = CircuitBreaker::new()
+            .min_requests(1)
+            .open_duration(Duration::from_millis(10));
+        let ep = Arc::new(
+            make(|req: Request| async move {
+                if req.uri().path() == "/fail" {
+                    return StatusCode::INTERNAL_SERVER_ERROR;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                StatusCode::OK
+            })
+            .with(breaker.clone()),
+        );
+
+        let resp = ep
+            .get_response(Request::builder().uri_str("/fail").finish())
+            .await;
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // only one trial request is allowed
+        let trial = tokio::spawn({
+            let ep = ep.clone();
+            async move { ep.get_response(Request::default()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let resp = ep.get_response(Request::default()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(trial.await.unwrap().status(), StatusCode::OK);
+        assert_eq!(ep.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn failure_rate_range() {
+        _ = CircuitBreaker::new().failure_rate(1.0);
+        for rate in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(std::panic::catch_unwind(|| CircuitBreaker::new().failure_rate(rate)).is_err());
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::{net::IpAddr, sync::Arc};
+
+use wildmatch::WildMatch;
+
+use crate::{
+    Endpoint, Middleware, Request, Result,
+    error::ClientCertError,
+    web::{ClientCert, SubjectAltName},
+};
+
+type CheckFn = Arc<dyn Fn(&ClientCert) -> bool + Send + Sync>;
+
+#[derive(Clone)]
+enum SanPattern {
+    Dns(WildMatch),
+    Email(WildMatch),
+    Uri(WildMatch),
+    Ip(IpAddr),
+}
+
+impl SanPattern {
+    fn matches(&self, san: &SubjectAltName) -> bool {
+        match (self, san) {
+            (SanPattern::Dns(pattern), SubjectAltName::Dns(value))
+            | (SanPattern::Email(pattern), SubjectAltName::Email(value))
+            | (SanPattern::Uri(pattern), SubjectAltName::Uri(value)) => pattern.matches(value),
+            (SanPattern::Ip(ip), SubjectAltName::Ip(value)) => ip == value,
+            _ => false,
+        }
+    }
+}
+
+/// Middleware which requires a client certificate, and checks it with the
+/// specified policy.
+///
+/// If several subject alternative names, common names or fingerprints are
+/// specified, the certificate must match one of them. A subject alternative
+/// name pattern only matches the names of the same type, for example
+/// [`RequireClientCert::san_dns`] does not match an email address. Responds
+/// with `403 Forbidden` if the certificate is missing or does not match the
+/// policy.
+///
+/// # Example
+///
+/// ```
+/// use poem::{EndpointExt, Route, get, handler, middleware::RequireClientCert, web::ClientCert};
+///
+/// #[handler]
+/// fn admin(cert: ClientCert) -> String {
+///     format!("hello {}", cert.subject())
+/// }
+///
+/// let app = Route::new().nest(
+///     "/admin",
+///     Route::new()
+///         .at("/", get(admin))
+///         .with(RequireClientCert::new().san_uri("spiffe://example.com/admin/*")),
+/// );
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "client-cert")))]
+#[derive(Default)]
+pub struct RequireClientCert {
+    sans: Vec<SanPattern>,
+    common_names: Vec<WildMatch>,
+    fingerprints: Vec<String>,
+    checks: Vec<CheckFn>,
+}
+
+impl RequireClientCert {
+    /// Create `RequireClientCert` middleware which accepts any verified
+    /// certificate.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Accepts the certificates with a DNS subject alternative name matching
+    /// the pattern, which can contain the `*` and `?` wildcards.
+    #[must_use]
+    pub fn san_dns(mut self, pattern: impl AsRef<str>) -> Self {
+        self.sans
+            .push(SanPattern::Dns(WildMatch::new(pattern.as_ref())));
+        self
+    }
+
+    /// Accepts the certificates with an email subject alternative name
+    /// matching the pattern, which can contain the `*` and `?` wildcards.
+    #[must_use]
+    pub fn san_email(mut self, pattern: impl AsRef<str>) -> Self {
+        self.sans
+            .push(SanPattern::Email(WildMatch::new(pattern.as_ref())));
+        self
+    }
+
+    /// Accepts the certificates with a URI subject alternative name matching
+    /// the pattern, which can contain the `*` and `?` wildcards.
+    #[must_use]
+    pub fn san_uri(mut self, pattern: impl AsRef<str>) -> Self {
+        self.sans
+            .push(SanPattern::Uri(WildMatch::new(pattern.as_ref())));
+        self
+    }
+
+    /// Accepts the certificates with the specified IP address subject
+    /// alternative name.
+    #[must_use]
+    pub fn san_ip(mut self, ip: IpAddr) -> Self {
+        self.sans.push(SanPattern::Ip(ip));
+        self
+    }
+
+    /// Accepts the certificates with a common name matching the pattern,
+    /// which can contain the `*` and `?` wildcards.
+    #[must_use]
+    pub fn common_name(mut self, pattern: impl AsRef<str>) -> Self {
+        self.common_names.push(WildMatch::new(pattern.as_ref()));
+        self
+    }
+
+    /// Accepts the certificate with the specified SHA-256 fingerprint, in
+    /// hexadecimal with optional colons.
+    #[must_use]
+    pub fn fingerprint(mut self, fingerprint: impl AsRef<str>) -> Self {
+        self.fingerprints.push(
+            fingerprint
+                .as_ref()
+                .chars()
+                .filter(|c| *c != ':')
+                .map(|c| c.to_ascii_lowercase())
+                .collect(),
+        );
+        self
+    }
+
+    /// Accepts the certificates for which the function returns `true`.
+    #[must_use]
+    pub fn check(mut self, f: impl Fn(&ClientCert) -> bool + Send + Sync + 'static) -> Self {
+        self.checks.push(Arc::new(f));
+        self
+    }
+
+    fn is_allowed(&self, cert: &ClientCert) -> bool {
+        let sans_allowed = self.sans.is_empty()
+            || cert
+                .subject_alt_names()
+                .iter()
+                .any(|san| self.sans.iter().any(|pattern| pattern.matches(san)));
+        let common_name_allowed = self.common_names.is_empty()
+            || cert.common_name().is_some_and(|common_name| {
+                self.common_names
+                    .iter()
+                    .any(|pattern| pattern.matches(common_name))
+            });
+        let fingerprint_allowed =
+            self.fingerprints.is_empty() || self.fingerprints.contains(&cert.fingerprint_hex());
+
+        sans_allowed
+            && common_name_allowed
+            && fingerprint_allowed
+            && self.checks.iter().all(|check| check(cert))
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for RequireClientCert {
+    type Output = RequireClientCertEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RequireClientCertEndpoint {
+            inner: ep,
+            policy: Arc::new(Self {
+                sans: self.sans.clone(),
+                common_names: self.common_names.clone(),
+                fingerprints: self.fingerprints.clone(),
+                checks: self.checks.clone(),
+            }),
+        }
+    }
+}
+
+/// Endpoint for the `RequireClientCert` middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "client-cert")))]
+pub struct RequireClientCertEndpoint<E> {
+    inner: E,
+    policy: Arc<RequireClientCert>,
+}
+
+impl<E: Endpoint> Endpoint for RequireClientCertEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let cert = ClientCert::from_extensions(req.extensions()).ok_or(ClientCertError::Missing)?;
+        if !self.policy.is_allowed(&cert) {
+            return Err(ClientCertError::Rejected.into());
+        }
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::SanType;
+
+    use super::*;
+    use crate::{
+        EndpointExt, Route, get, handler, http::StatusCode, test::TestClient,
+        web::client_cert::tests::generate_client_cert,
+    };
+
+    #[handler(internal)]
+    fn index(cert: ClientCert) -> String {
+        cert.common_name().unwrap().to_string()
+    }
+
+    async fn check(policy: RequireClientCert, cert: Option<&ClientCert>, status: StatusCode) {
+        let app = Route::new().at("/", get(index)).with(policy);
+        let resp = match cert {
+            Some(cert) => {
+                TestClient::new(app.data(cert.clone()))
+                    .get("/")
+                    .send()
+                    .await
+            }
+            None => TestClient::new(app).get("/").send().await,
+        };
+        resp.assert_status(status);
+    }
+
+    #[tokio::test]
+    async fn policy() {
+        let (_, der, _) = generate_client_cert(
+            "client",
+            vec![
+                SanType::DnsName("client.example.com".to_string()),
+                SanType::URI("spiffe://example.com/admin/client".to_string()),
+                SanType::Rfc822Name("client@mail.example.net".to_string()),
+                SanType::IpAddress("10.0.0.1".parse().unwrap()),
+            ],
+        );
+        let cert = ClientCert::from_der(der).unwrap();
+
+        check(RequireClientCert::new(), None, StatusCode::FORBIDDEN).await;
+        check(RequireClientCert::new(), Some(&cert), StatusCode::OK).await;
+        check(
+            RequireClientCert::new()
+                .san_dns("*.example.org")
+                .san_uri("spiffe://example.com/admin/*"),
+            Some(&cert),
+            StatusCode::OK,
+        )
+        .await;
+        check(
+            RequireClientCert::new().san_dns("*.example.org"),
+            Some(&cert),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+        // the patterns only match the names of the same type
+        check(
+            RequireClientCert::new().san_dns("*.example.net"),
+            Some(&cert),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+        check(
+            RequireClientCert::new().san_email("*@mail.example.net"),
+            Some(&cert),
+            StatusCode::OK,
+        )
+        .await;
+        check(
+            RequireClientCert::new().san_dns("spiffe://*"),
+            Some(&cert),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+        check(
+            RequireClientCert::new().san_ip("10.0.0.1".parse().unwrap()),
+            Some(&cert),
+            StatusCode::OK,
+        )
+        .await;
+        check(
+            RequireClientCert::new().san_dns("10.0.0.1"),
+            Some(&cert),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+        check(
+            RequireClientCert::new().common_name("cli*"),
+            Some(&cert),
+            StatusCode::OK,
+        )
+        .await;
+        check(
+            RequireClientCert::new()
+                .san_dns("*.example.com")
+                .common_name("server"),
+            Some(&cert),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+
+        let fingerprint = cert
+            .fingerprint()
+            .iter()
+            .map(|b| format!("{b:02X}"))
+            .collect::<Vec<_>>()
+            .join(":");
+        check(
+            RequireClientCert::new().fingerprint(fingerprint),
+            Some(&cert),
+            StatusCode::OK,
+        )
+        .await;
+        check(
+            RequireClientCert::new().fingerprint("00"),
+            Some(&cert),
+            StatusCode::FORBIDDEN,
+        )
+        .await;
+        check(
+            RequireClientCert::new().check(|cert| cert.issuer() == "CN=poem ca"),
+            Some(&cert),
+            StatusCode::OK,
+        )
+        .await;
+    }
+}
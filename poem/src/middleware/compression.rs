@@ -1,77 +1,11 @@
 use std::{collections::HashSet, str::FromStr};
 
-use headers::HeaderMap;
-
 use crate::{
     Body, Endpoint, IntoResponse, Middleware, Request, Response, Result,
     http::header,
-    web::{Compress, CompressionAlgo, CompressionLevel},
+    web::{Compress, CompressionAlgo, CompressionLevel, negotiate_encoding},
 };
 
-enum ContentCoding {
-    Brotli,
-    Deflate,
-    Gzip,
-    Star,
-    Zstd,
-}
-
-impl FromStr for ContentCoding {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.eq_ignore_ascii_case("deflate") {
-            Ok(ContentCoding::Deflate)
-        } else if s.eq_ignore_ascii_case("gzip") {
-            Ok(ContentCoding::Gzip)
-        } else if s.eq_ignore_ascii_case("br") {
-            Ok(ContentCoding::Brotli)
-        } else if s == "*" {
-            Ok(ContentCoding::Star)
-        } else if s == "zsdt" {
-            Ok(ContentCoding::Zstd)
-        } else {
-            Err(())
-        }
-    }
-}
-
-fn parse_accept_encoding(
-    headers: &HeaderMap,
-    enabled_algorithms: &HashSet<CompressionAlgo>,
-) -> Option<ContentCoding> {
-    headers
-        .get_all(header::ACCEPT_ENCODING)
-        .iter()
-        .filter_map(|hval| hval.to_str().ok())
-        .flat_map(|s| s.split(',').map(str::trim))
-        .filter_map(|v| {
-            let (e, q) = match v.split_once(";q=") {
-                Some((e, q)) => (e, (q.parse::<f32>().ok()? * 1000.0) as i32),
-                None => (v, 1000),
-            };
-            let coding: ContentCoding = e.parse().ok()?;
-            Some((coding, q))
-        })
-        .filter(|(encoding, _)| {
-            if !enabled_algorithms.is_empty() {
-                match encoding {
-                    ContentCoding::Brotli => enabled_algorithms.contains(&CompressionAlgo::BR),
-                    ContentCoding::Deflate => {
-                        enabled_algorithms.contains(&CompressionAlgo::DEFLATE)
-                    }
-                    ContentCoding::Gzip => enabled_algorithms.contains(&CompressionAlgo::GZIP),
-                    ContentCoding::Zstd => enabled_algorithms.contains(&CompressionAlgo::ZSTD),
-                    _ => true,
-                }
-            } else {
-                true
-            }
-        })
-        .max_by_key(|(coding, q)| (*q, coding_priority(coding)))
-        .map(|(coding, _)| coding)
-}
-
 /// Middleware to decompress the request body and compress the response body.
 ///
 /// The decompression algorithm is selected according to the request
@@ -132,17 +66,6 @@ pub struct CompressionEndpoint<E: Endpoint> {
     algorithms: HashSet<CompressionAlgo>,
 }
 
-#[inline]
-fn coding_priority(c: &ContentCoding) -> u8 {
-    match *c {
-        ContentCoding::Deflate => 1,
-        ContentCoding::Gzip => 2,
-        ContentCoding::Brotli => 3,
-        ContentCoding::Zstd => 4,
-        _ => 0,
-    }
-}
-
 impl<E: Endpoint> Endpoint for CompressionEndpoint<E> {
     type Output = Response;
 
@@ -159,13 +82,10 @@ impl<E: Endpoint> Endpoint for CompressionEndpoint<E> {
         }
 
         // negotiate content-encoding
-        let compress_algo =
-            parse_accept_encoding(req.headers(), &self.algorithms).map(|coding| match coding {
-                ContentCoding::Gzip => CompressionAlgo::GZIP,
-                ContentCoding::Deflate => CompressionAlgo::DEFLATE,
-                ContentCoding::Brotli => CompressionAlgo::BR,
-                ContentCoding::Star | ContentCoding::Zstd => CompressionAlgo::ZSTD,
-            });
+        let compress_algo = negotiate_encoding(
+            req.headers().get_all(header::ACCEPT_ENCODING),
+            &self.algorithms,
+        );
 
         let resp = self.ep.call(req).await?;
         match compress_algo {
@@ -222,6 +142,7 @@ mod tests {
         test_algo(CompressionAlgo::BR).await;
         test_algo(CompressionAlgo::DEFLATE).await;
         test_algo(CompressionAlgo::GZIP).await;
+        test_algo(CompressionAlgo::ZSTD).await;
     }
 
     #[tokio::test]
@@ -264,6 +185,86 @@ mod tests {
         assert_eq!(data, DATA_REV.as_bytes());
     }
 
+    #[tokio::test]
+    async fn test_star_priority() {
+        let ep = index.with(Compression::default());
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "*, gzip")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "gzip");
+
+        let ep = index.with(Compression::default().algorithms([CompressionAlgo::BR]));
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "gzip, *;q=0.5")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "br");
+    }
+
+    #[tokio::test]
+    async fn test_not_acceptable() {
+        let ep = index.with(Compression::default());
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "br;q=0, gzip;q=0.5")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "gzip");
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "br;q=0, *;q=0")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("Content-Encoding");
+        resp.assert_text(DATA_REV).await;
+    }
+
+    #[tokio::test]
+    async fn test_star_excludes_listed() {
+        let ep = index.with(Compression::default());
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "zstd;q=0, *")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header("Content-Encoding", "br");
+
+        let ep = index.with(Compression::default().algorithms([CompressionAlgo::GZIP]));
+        let cli = TestClient::new(ep);
+
+        let resp = cli
+            .post("/")
+            .header("Accept-Encoding", "gzip;q=0, *")
+            .body(DATA)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("Content-Encoding");
+        resp.assert_text(DATA_REV).await;
+    }
+
     #[tokio::test]
     async fn test_coding_priority() {
         let ep = index.with(Compression::default());
@@ -0,0 +1,187 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{Endpoint, Middleware, Request, Result, error::ConcurrencyLimitError};
+
+/// Middleware that limits the number of in-flight requests, and sheds the
+/// load when the limit is reached.
+///
+/// When the endpoint is saturated, the requests are rejected immediately with
+/// a [`ConcurrencyLimitError::Overloaded`] error (`503 Service Unavailable`),
+/// or after waiting for at most [`ConcurrencyLimit::wait_timeout`].
+///
+/// The limit is shared by all the requests handled by the endpoint which the
+/// middleware is applied to.
+///
+/// # Errors
+///
+/// - [`ConcurrencyLimitError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     EndpointExt, Route, get, handler, http::StatusCode, middleware::ConcurrencyLimit,
+///     test::TestClient,
+/// };
+///
+/// #[handler]
+/// async fn index() -> &'static str {
+///     tokio::time::sleep(Duration::from_millis(100)).await;
+///     "hello"
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .with(ConcurrencyLimit::new(1));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let (a, b) = tokio::join!(cli.get("/").send(), cli.get("/").send());
+/// a.assert_status_is_ok();
+/// b.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+/// # });
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct ConcurrencyLimit {
+    max_in_flight: usize,
+    wait_timeout: Option<Duration>,
+}
+
+impl ConcurrencyLimit {
+    /// Create `ConcurrencyLimit` middleware with the specified maximum number
+    /// of in-flight requests.
+    pub fn new(max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight,
+            wait_timeout: None,
+        }
+    }
+
+    /// Sets how long a request can wait for a slot before being rejected,
+    /// default is `None`, which means that the request is rejected
+    /// immediately.
+    #[must_use]
+    pub fn wait_timeout(self, timeout: Duration) -> Self {
+        Self {
+            wait_timeout: Some(timeout),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ConcurrencyLimit {
+    type Output = ConcurrencyLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ConcurrencyLimitEndpoint {
+            inner: ep,
+            semaphore: Arc::new(Semaphore::new(self.max_in_flight)),
+            max_in_flight: self.max_in_flight,
+            wait_timeout: self.wait_timeout,
+        }
+    }
+}
+
+/// Endpoint for the ConcurrencyLimit middleware.
+pub struct ConcurrencyLimitEndpoint<E> {
+    inner: E,
+    semaphore: Arc<Semaphore>,
+    max_in_flight: usize,
+    wait_timeout: Option<Duration>,
+}
+
+impl<E> ConcurrencyLimitEndpoint<E> {
+    /// Returns the number of in-flight requests.
+    pub fn in_flight(&self) -> usize {
+        self.max_in_flight - self.semaphore.available_permits()
+    }
+
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Some(permit);
+        }
+        let timeout = self.wait_timeout?;
+        tokio::time::timeout(timeout, self.semaphore.clone().acquire_owned())
+            .await
+            .ok()?
+            .ok()
+    }
+}
+
+impl<E: Endpoint> Endpoint for ConcurrencyLimitEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let Some(_permit) = self.acquire().await else {
+            return Err(ConcurrencyLimitError::Overloaded.into());
+        };
+        self.inner.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{EndpointExt, endpoint::make, test::TestClient};
+
+    fn slow() -> impl Endpoint<Output = &'static str> {
+        make(|_| async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            "hello"
+        })
+    }
+
+    #[tokio::test]
+    async fn shed() {
+        let cli = TestClient::new(slow().with(ConcurrencyLimit::new(2)));
+        let resps = futures_util::future::join_all((0..3).map(|_| cli.get("/").send())).await;
+        let statuses = resps.iter().map(|resp| resp.0.status()).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::OK,
+                StatusCode::OK,
+                StatusCode::SERVICE_UNAVAILABLE
+            ]
+        );
+
+        // the slots are released
+        cli.get("/").send().await.assert_status_is_ok();
+    }
+
+    #[tokio::test]
+    async fn wait_timeout() {
+        let cli = TestClient::new(
+            slow().with(ConcurrencyLimit::new(1).wait_timeout(Duration::from_millis(500))),
+        );
+        let (a, b) = tokio::join!(cli.get("/").send(), cli.get("/").send());
+        a.assert_status_is_ok();
+        b.assert_status_is_ok();
+
+        let cli = TestClient::new(
+            slow().with(ConcurrencyLimit::new(1).wait_timeout(Duration::from_millis(10))),
+        );
+        let (a, b) = tokio::join!(cli.get("/").send(), cli.get("/").send());
+        a.assert_status_is_ok();
+        b.assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn in_flight() {
+        let ep = Arc::new(slow().with(ConcurrencyLimit::new(2)));
+        let task = tokio::spawn({
+            let ep = ep.clone();
+            async move { ep.call(Request::default()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(ep.in_flight(), 1);
+        task.await.unwrap().unwrap();
+        assert_eq!(ep.in_flight(), 0);
+    }
+}
@@ -0,0 +1,407 @@
+use headers::{HeaderMapExt, IfMatch, IfNoneMatch};
+use http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+
+use crate::{Endpoint, IntoResponse, Middleware, Request, Response, Result, error::ETagError};
+
+/// Middleware for generating `ETag` headers and evaluating conditional
+/// requests.
+///
+/// The `ETag` of a successful `GET` or `HEAD` response is generated by hashing
+/// its body, unless the endpoint has already set one. Bodies larger than
+/// [`ETag::max_body_size`] are not hashed.
+///
+/// - `If-None-Match` is answered with `304 Not Modified` if the tag matches.
+/// - `If-Match` is answered with `412 Precondition Failed` if the tag does not
+///   match.
+///
+/// The preconditions of the other methods are left to the endpoint, unless
+/// [`ETag::check_unsafe_methods`] is enabled.
+///
+/// # Errors
+///
+/// - [`ETagError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route, get, handler, http::StatusCode, middleware::ETag, test::TestClient,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = Route::new().at("/", get(index)).with(ETag::new());
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status_is_ok();
+/// let etag = resp.0.headers().get("etag").unwrap().clone();
+///
+/// cli.get("/")
+///     .header("if-none-match", etag)
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_MODIFIED);
+/// # });
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct ETag {
+    weak: bool,
+    max_body_size: usize,
+    check_unsafe_methods: bool,
+}
+
+impl Default for ETag {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ETag {
+    /// Create `ETag` middleware.
+    pub fn new() -> Self {
+        Self {
+            weak: false,
+            max_body_size: 1024 * 1024,
+            check_unsafe_methods: false,
+        }
+    }
+
+    /// Generates weak tags (`W/"..."`), default is `false`.
+    ///
+    /// Use weak tags if the body can be transformed after this middleware,
+    /// for example by the [`Compression`](crate::middleware::Compression)
+    /// middleware. Weak tags never match `If-Match`.
+    #[must_use]
+    pub fn weak(self, weak: bool) -> Self {
+        Self { weak, ..self }
+    }
+
+    /// Sets the maximum size of the bodies that are hashed, default is 1 MiB.
+    #[must_use]
+    pub fn max_body_size(self, size: usize) -> Self {
+        Self {
+            max_body_size: size,
+            ..self
+        }
+    }
+
+    /// Evaluates the preconditions of the methods other than `GET` and
+    /// `HEAD` against the `ETag` of a `GET` request to the same URI, default
+    /// is `false`.
+    ///
+    /// It allows clients to use `If-Match` for optimistic concurrency
+    /// control, and `If-None-Match: *` to avoid overwriting an existing
+    /// resource, with endpoints that do not evaluate the preconditions
+    /// themselves.
+    ///
+    /// **NOTE**: The `GET` request is handled by the inner endpoint like any
+    /// other request, so it must be free of side effects, and it doubles the
+    /// work of the requests with preconditions. The resource may also be
+    /// modified by a concurrent request between the `GET` request and the
+    /// actual request, so this does not replace the checks of the endpoint
+    /// when the updates must be atomic.
+    #[must_use]
+    pub fn check_unsafe_methods(self, enable: bool) -> Self {
+        Self {
+            check_unsafe_methods: enable,
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for ETag {
+    type Output = ETagEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ETagEndpoint {
+            inner: ep,
+            weak: self.weak,
+            max_body_size: self.max_body_size,
+            check_unsafe_methods: self.check_unsafe_methods,
+        }
+    }
+}
+
+/// Endpoint for the ETag middleware.
+pub struct ETagEndpoint<E> {
+    inner: E,
+    weak: bool,
+    max_body_size: usize,
+    check_unsafe_methods: bool,
+}
+
+/// 64-bit FNV-1a, the tags must be the same across processes.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+struct Preconditions {
+    if_match: Option<IfMatch>,
+    if_none_match: Option<IfNoneMatch>,
+}
+
+impl Preconditions {
+    fn new(headers: &HeaderMap) -> Self {
+        Self {
+            if_match: headers.typed_get(),
+            if_none_match: headers.typed_get(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.if_match.is_none() && self.if_none_match.is_none()
+    }
+
+    /// Evaluates `If-Match` against the current tag of the resource, `None`
+    /// means that the resource does not exist.
+    fn if_match_passes(&self, etag: Option<&headers::ETag>) -> bool {
+        match (&self.if_match, etag) {
+            (None, _) => true,
+            (Some(if_match), Some(etag)) => if_match.precondition_passes(etag),
+            (Some(_), None) => false,
+        }
+    }
+
+    fn if_none_match_passes(&self, etag: Option<&headers::ETag>) -> bool {
+        match (&self.if_none_match, etag) {
+            (Some(if_none_match), Some(etag)) => if_none_match.precondition_passes(etag),
+            _ => true,
+        }
+    }
+}
+
+impl<E: Endpoint> ETagEndpoint<E> {
+    /// Returns the tag of the response, generating it if needed.
+    async fn etag(&self, resp: &mut Response) -> Option<headers::ETag> {
+        if !resp.status().is_success() {
+            return None;
+        }
+        if let Some(etag) = resp.headers().typed_get::<headers::ETag>() {
+            return Some(etag);
+        }
+
+        let body = match resp
+            .take_body()
+            .into_bytes_within(resp.headers(), self.max_body_size)
+            .await
+        {
+            Ok(body) => body,
+            Err(body) => {
+                resp.set_body(body);
+                return None;
+            }
+        };
+        let tag = format!(
+            "{}\"{:x}-{:016x}\"",
+            if self.weak { "W/" } else { "" },
+            body.len(),
+            fnv1a(&body)
+        );
+        resp.set_body(body);
+
+        let value = HeaderValue::try_from(tag).ok()?;
+        resp.headers_mut().insert(header::ETAG, value);
+        resp.headers().typed_get()
+    }
+}
+
+impl<E: Endpoint> Endpoint for ETagEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let preconditions = Preconditions::new(req.headers());
+
+        if matches!(*req.method(), Method::GET | Method::HEAD) {
+            let mut resp = self.inner.call(req).await?.into_response();
+            let etag = self.etag(&mut resp).await;
+
+            if etag.is_some() && !preconditions.if_match_passes(etag.as_ref()) {
+                return Err(ETagError::PreconditionFailed.into());
+            }
+            if !preconditions.if_none_match_passes(etag.as_ref()) {
+                let mut not_modified = Response::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .finish();
+                for name in [
+                    header::CACHE_CONTROL,
+                    header::CONTENT_LOCATION,
+                    header::DATE,
+                    header::ETAG,
+                    header::EXPIRES,
+                    header::VARY,
+                ] {
+                    for value in resp.headers().get_all(&name) {
+                        not_modified.headers_mut().append(&name, value.clone());
+                    }
+                }
+                return Ok(not_modified);
+            }
+            return Ok(resp);
+        }
+
+        if self.check_unsafe_methods && !preconditions.is_empty() {
+            let mut current_req = req.clone_without_body();
+            current_req.set_method(Method::GET);
+            current_req.headers_mut().remove(header::IF_MATCH);
+            current_req.headers_mut().remove(header::IF_NONE_MATCH);
+            let mut current = self.inner.call(current_req).await?.into_response();
+            let etag = self.etag(&mut current).await;
+
+            if !preconditions.if_match_passes(etag.as_ref())
+                || !preconditions.if_none_match_passes(etag.as_ref())
+            {
+                return Err(ETagError::PreconditionFailed.into());
+            }
+        }
+
+        self.inner.call(req).await.map(IntoResponse::into_response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::{EndpointExt, Route, endpoint::make_sync, get, handler, test::TestClient};
+
+    #[tokio::test]
+    async fn conditional_get() {
+        let cli = TestClient::new(make_sync(|_| "hello").with(ETag::new()).map_to_response());
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        let etag = resp.0.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(etag, format!("\"5-{:016x}\"", fnv1a(b"hello")));
+        resp.assert_text("hello").await;
+
+        let resp = cli
+            .get("/")
+            .header(header::IF_NONE_MATCH, etag.clone())
+            .send()
+            .await;
+        resp.assert_status(StatusCode::NOT_MODIFIED);
+        resp.assert_header(header::ETAG, etag.to_str().unwrap());
+
+        cli.get("/")
+            .header(header::IF_NONE_MATCH, "\"abc\"")
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .header(header::IF_MATCH, etag)
+            .send()
+            .await
+            .assert_status_is_ok();
+        cli.get("/")
+            .header(header::IF_MATCH, "\"abc\"")
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn weak() {
+        let cli = TestClient::new(make_sync(|_| "hello").with(ETag::new().weak(true)));
+
+        let resp = cli.get("/").send().await;
+        let etag = resp.0.headers().get(header::ETAG).unwrap().clone();
+        assert!(etag.to_str().unwrap().starts_with("W/\""));
+
+        // weak comparison
+        cli.get("/")
+            .header(
+                header::IF_NONE_MATCH,
+                etag.to_str().unwrap().trim_start_matches("W/"),
+            )
+            .send()
+            .await
+            .assert_status(StatusCode::NOT_MODIFIED);
+
+        // strong comparison
+        cli.get("/")
+            .header(header::IF_MATCH, etag)
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+    }
+
+    #[tokio::test]
+    async fn max_body_size() {
+        let cli = TestClient::new(make_sync(|_| "hello").with(ETag::new().max_body_size(4)));
+        let resp = cli.get("/").send().await;
+        resp.assert_header_is_not_exist(header::ETAG);
+        resp.assert_text("hello").await;
+    }
+
+    #[tokio::test]
+    async fn optimistic_concurrency() {
+        #[handler(internal)]
+        fn get_value(value: crate::web::Data<&Arc<Mutex<String>>>) -> String {
+            value.lock().clone()
+        }
+
+        #[handler(internal)]
+        fn put_value(value: crate::web::Data<&Arc<Mutex<String>>>, body: String) {
+            *value.lock() = body;
+        }
+
+        let value = Arc::new(Mutex::new("a".to_string()));
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", get(get_value).put(put_value))
+                .with(ETag::new().check_unsafe_methods(true))
+                .data(value.clone()),
+        );
+
+        let resp = cli.get("/").send().await;
+        let etag = resp.0.headers().get(header::ETAG).unwrap().clone();
+
+        cli.put("/")
+            .header(header::IF_MATCH, etag.clone())
+            .body("b")
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert_eq!(*value.lock(), "b");
+
+        // the tag is outdated
+        cli.put("/")
+            .header(header::IF_MATCH, etag)
+            .body("c")
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        cli.put("/")
+            .header(header::IF_NONE_MATCH, "*")
+            .body("c")
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        assert_eq!(*value.lock(), "b");
+
+        // the preconditions are left to the endpoint by default
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", get(get_value).put(put_value))
+                .with(ETag::new())
+                .data(value.clone()),
+        );
+        cli.put("/")
+            .header(header::IF_MATCH, "\"abc\"")
+            .body("c")
+            .send()
+            .await
+            .assert_status_is_ok();
+        assert_eq!(*value.lock(), "c");
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+    web::{FlashMessages, FlashStore},
+};
+
+/// Middleware for storing the [`FlashMessages`] returned by the handlers.
+///
+/// The messages are stored in the [`Session`](crate::session::Session) if the
+/// `CookieSession` or `ServerSession` middleware is used, otherwise in a
+/// signed cookie, which requires the
+/// [`CookieJarManager::with_key`](crate::middleware::CookieJarManager::with_key)
+/// middleware. Either of them must wrap this middleware.
+///
+/// # Errors
+///
+/// Returns [`FlashError::MissingStore`](crate::error::FlashError::MissingStore)
+/// if a response carries flash messages and there is nowhere to store them.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route, handler,
+///     middleware::{CookieJarManager, FlashMiddleware},
+///     post,
+///     web::{FlashMessages, Redirect, cookie::CookieKey},
+/// };
+///
+/// #[handler]
+/// fn save() -> (FlashMessages, Redirect) {
+///     (
+///         FlashMessages::new().success("The post has been saved."),
+///         Redirect::see_other("/"),
+///     )
+/// }
+///
+/// let app = Route::new()
+///     .at("/save", post(save))
+///     .with(FlashMiddleware::new())
+///     .with(CookieJarManager::with_key(CookieKey::generate()));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+#[derive(Default)]
+pub struct FlashMiddleware;
+
+impl FlashMiddleware {
+    /// Creates a new `FlashMiddleware` middleware.
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for FlashMiddleware {
+    type Output = FlashEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        FlashEndpoint { inner: ep }
+    }
+}
+
+/// Endpoint for the `FlashMiddleware` middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
+pub struct FlashEndpoint<E> {
+    inner: E,
+}
+
+impl<E: Endpoint> Endpoint for FlashEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let store = FlashStore::from_request(&req);
+        let mut resp = self.inner.call(req).await?.into_response();
+        if let Some(messages) = resp.extensions_mut().remove::<FlashMessages>() {
+            store?.append(messages);
+        }
+        Ok(resp)
+    }
+}
@@ -0,0 +1,843 @@
+use std::{
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use jsonwebtoken::{
+    Algorithm, DecodingKey, Validation,
+    errors::ErrorKind,
+    jwk::{AlgorithmParameters, EllipticCurve, JwkSet, PublicKeyUse},
+};
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    Endpoint, Error, FromRequest, Middleware, Request, RequestBody, Result,
+    error::JwtError,
+    http::{StatusCode, header},
+};
+
+/// The minimum interval between two refreshes of the key set caused by a
+/// token signed with an unknown key.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+struct JwtKey {
+    kid: Option<String>,
+    algorithms: Vec<Algorithm>,
+    key: DecodingKey,
+}
+
+/// A set of keys used to verify JSON Web Tokens.
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+#[derive(Default)]
+pub struct JwtKeys {
+    keys: Vec<JwtKey>,
+}
+
+impl JwtKeys {
+    /// Create an empty key set.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds a key which verifies the tokens signed with the specified
+    /// algorithm.
+    #[must_use]
+    pub fn key(self, key: DecodingKey, algorithm: Algorithm) -> Self {
+        self.add(None, key, vec![algorithm])
+    }
+
+    /// Adds a key which verifies the tokens signed with the specified
+    /// algorithm, and whose `kid` header is `kid`.
+    #[must_use]
+    pub fn key_with_id(
+        self,
+        kid: impl Into<String>,
+        key: DecodingKey,
+        algorithm: Algorithm,
+    ) -> Self {
+        self.add(Some(kid.into()), key, vec![algorithm])
+    }
+
+    /// Adds an HMAC secret which verifies the tokens signed with `HS256`,
+    /// `HS384` or `HS512`.
+    #[must_use]
+    pub fn secret(self, secret: impl AsRef<[u8]>) -> Self {
+        self.add(
+            None,
+            DecodingKey::from_secret(secret.as_ref()),
+            vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512],
+        )
+    }
+
+    /// Create a key set from a JSON Web Key Set.
+    ///
+    /// The keys which are not used for signatures or whose type is not
+    /// supported are ignored.
+    pub fn from_jwks(jwks: &JwkSet) -> Self {
+        let mut keys = Self::new();
+
+        for jwk in &jwks.keys {
+            if matches!(
+                jwk.common.public_key_use,
+                Some(PublicKeyUse::Encryption | PublicKeyUse::Other(_))
+            ) {
+                continue;
+            }
+
+            let algorithms = match jwk.common.key_algorithm {
+                Some(alg) => match alg.to_string().parse() {
+                    Ok(alg) => vec![alg],
+                    Err(_) => continue,
+                },
+                None => match &jwk.algorithm {
+                    AlgorithmParameters::RSA(_) => vec![
+                        Algorithm::RS256,
+                        Algorithm::RS384,
+                        Algorithm::RS512,
+                        Algorithm::PS256,
+                        Algorithm::PS384,
+                        Algorithm::PS512,
+                    ],
+                    AlgorithmParameters::EllipticCurve(params) => match params.curve {
+                        EllipticCurve::P256 => vec![Algorithm::ES256],
+                        EllipticCurve::P384 => vec![Algorithm::ES384],
+                        _ => continue,
+                    },
+                    AlgorithmParameters::OctetKeyPair(_) => vec![Algorithm::EdDSA],
+                    AlgorithmParameters::OctetKey(_) => {
+                        vec![Algorithm::HS256, Algorithm::HS384, Algorithm::HS512]
+                    }
+                },
+            };
+
+            if let Ok(key) = DecodingKey::from_jwk(jwk) {
+                keys = keys.add(jwk.common.key_id.clone(), key, algorithms);
+            }
+        }
+
+        keys
+    }
+
+    fn add(mut self, kid: Option<String>, key: DecodingKey, algorithms: Vec<Algorithm>) -> Self {
+        self.keys.push(JwtKey {
+            kid,
+            algorithms,
+            key,
+        });
+        self
+    }
+
+    /// Returns the keys which can verify a token with the specified header.
+    fn candidates<'a>(
+        &'a self,
+        kid: Option<&'a str>,
+        algorithm: Algorithm,
+    ) -> impl Iterator<Item = &'a JwtKey> {
+        self.keys.iter().filter(move |key| {
+            key.algorithms.contains(&algorithm)
+                && match (kid, &key.kid) {
+                    (Some(kid), Some(key_id)) => kid == key_id,
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                }
+        })
+    }
+}
+
+enum KeySource {
+    Static(Arc<JwtKeys>),
+    Url(String),
+    File(PathBuf),
+}
+
+/// The keys used by the [`Jwt`] middleware, loaded from a static key set or a
+/// JSON Web Key Set which is refreshed periodically.
+#[derive(Clone)]
+#[allow(clippy::type_complexity)]
+pub(crate) struct KeyStore {
+    source: Arc<KeySource>,
+    refresh_interval: Duration,
+    /// The loaded keys and the time they were loaded.
+    cache: Arc<Mutex<Option<(Instant, Arc<JwtKeys>)>>>,
+    refreshing: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl KeyStore {
+    fn new(source: KeySource) -> Self {
+        Self {
+            source: Arc::new(source),
+            refresh_interval: Duration::from_secs(60 * 60),
+            cache: Default::default(),
+            refreshing: Default::default(),
+        }
+    }
+
+    pub(crate) fn jwks_url(url: impl Into<String>) -> Self {
+        Self::new(KeySource::Url(url.into()))
+    }
+
+    /// Returns the keys, and reloads them if they are older than the refresh
+    /// interval.
+    ///
+    /// If `unknown_key` is `true`, the keys are reloaded if they are older
+    /// than [`MIN_REFRESH_INTERVAL`], in case the issuer has rotated its keys.
+    async fn keys(&self, unknown_key: bool) -> Result<Arc<JwtKeys>, JwtError> {
+        if let KeySource::Static(keys) = &*self.source {
+            return Ok(keys.clone());
+        }
+
+        let max_age = if unknown_key {
+            self.refresh_interval.min(MIN_REFRESH_INTERVAL)
+        } else {
+            self.refresh_interval
+        };
+        let fresh = || {
+            self.cache
+                .lock()
+                .as_ref()
+                .filter(|(loaded_at, _)| loaded_at.elapsed() < max_age)
+                .map(|(_, keys)| keys.clone())
+        };
+
+        if let Some(keys) = fresh() {
+            return Ok(keys);
+        }
+        let _guard = self.refreshing.lock().await;
+        if let Some(keys) = fresh() {
+            return Ok(keys);
+        }
+
+        let res = self.load().await;
+        let mut cache = self.cache.lock();
+        match (res, &mut *cache) {
+            (Ok(keys), cache) => {
+                let keys = Arc::new(keys);
+                *cache = Some((Instant::now(), keys.clone()));
+                Ok(keys)
+            }
+            (Err(err), Some((loaded_at, keys))) => {
+                tracing::warn!(error = %err, "failed to refresh the key set");
+                *loaded_at = Instant::now();
+                Ok(keys.clone())
+            }
+            (Err(err), None) => Err(err),
+        }
+    }
+
+    async fn load(&self) -> Result<JwtKeys, JwtError> {
+        let data = match &*self.source {
+            KeySource::Static(_) => unreachable!(),
+            KeySource::Url(url) => async {
+                reqwest::get(url)
+                    .await?
+                    .error_for_status()?
+                    .bytes()
+                    .await
+                    .map(Vec::from)
+            }
+            .await
+            .map_err(|err| JwtError::KeySet(err.to_string()))?,
+            KeySource::File(path) => tokio::fs::read(path)
+                .await
+                .map_err(|err| JwtError::KeySet(err.to_string()))?,
+        };
+        let jwks = serde_json::from_slice::<JwkSet>(&data)
+            .map_err(|err| JwtError::KeySet(err.to_string()))?;
+        Ok(JwtKeys::from_jwks(&jwks))
+    }
+}
+
+/// Verifies the signature and the claims of JSON Web Tokens.
+#[derive(Clone)]
+pub(crate) struct JwtVerifier {
+    keys: KeyStore,
+    validation: Validation,
+    algorithms: Option<Vec<Algorithm>>,
+}
+
+impl JwtVerifier {
+    pub(crate) fn new(keys: KeyStore) -> Self {
+        let mut validation = Validation::default();
+        validation.validate_nbf = true;
+        validation.validate_aud = false;
+        Self {
+            keys,
+            validation,
+            algorithms: None,
+        }
+    }
+
+    pub(crate) fn audience(&mut self, audience: &[String]) {
+        self.validation.set_audience(audience);
+        self.validation.validate_aud = true;
+        self.validation
+            .required_spec_claims
+            .insert("aud".to_string());
+    }
+
+    pub(crate) fn issuer(&mut self, issuer: &[String]) {
+        self.validation.set_issuer(issuer);
+        self.validation
+            .required_spec_claims
+            .insert("iss".to_string());
+    }
+
+    /// Verifies the token and returns its claims.
+    pub(crate) async fn verify(&self, token: &str) -> Result<Value, JwtError> {
+        let header = jsonwebtoken::decode_header(token).map_err(map_error)?;
+        if let Some(algorithms) = &self.algorithms {
+            if !algorithms.contains(&header.alg) {
+                return Err(JwtError::InvalidToken("invalid algorithm".to_string()));
+            }
+        }
+
+        let mut keys = self.keys.keys(false).await?;
+        if keys
+            .candidates(header.kid.as_deref(), header.alg)
+            .next()
+            .is_none()
+        {
+            keys = self.keys.keys(true).await?;
+        }
+
+        let mut validation = self.validation.clone();
+        validation.algorithms = vec![header.alg];
+        let mut res = Err(JwtError::UnknownKey);
+        for key in keys.candidates(header.kid.as_deref(), header.alg) {
+            res = jsonwebtoken::decode::<Value>(token, &key.key, &validation)
+                .map(|data| data.claims)
+                .map_err(map_error);
+            if !matches!(&res, Err(JwtError::InvalidSignature)) {
+                break;
+            }
+        }
+        res
+    }
+}
+
+fn map_error(err: jsonwebtoken::errors::Error) -> JwtError {
+    match err.kind() {
+        ErrorKind::ExpiredSignature => JwtError::Expired,
+        ErrorKind::ImmatureSignature => JwtError::NotYetValid,
+        ErrorKind::InvalidAudience => JwtError::InvalidAudience,
+        ErrorKind::InvalidIssuer => JwtError::InvalidIssuer,
+        ErrorKind::InvalidSignature => JwtError::InvalidSignature,
+        ErrorKind::InvalidAlgorithm => JwtError::InvalidToken("invalid algorithm".to_string()),
+        ErrorKind::MissingRequiredClaim(claim) => {
+            JwtError::InvalidToken(format!("missing required claim `{claim}`"))
+        }
+        _ => JwtError::InvalidToken(err.to_string()),
+    }
+}
+
+/// Middleware for JSON Web Token (JWT) authentication.
+///
+/// The middleware reads the bearer token from the `Authorization` header,
+/// verifies its signature and checks the `exp`, `nbf`, `aud` and `iss`
+/// claims. The claims of a valid token can be extracted with [`Claims`], and
+/// an invalid token is rejected with [`JwtError`] which responds with `401
+/// Unauthorized` and a `WWW-Authenticate` header.
+///
+/// The keys are either a static [`JwtKeys`], or a JSON Web Key Set loaded
+/// from a URL or a file, which is refreshed periodically and when a token is
+/// signed with an unknown key.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route, get, handler,
+///     http::{StatusCode, header},
+///     jsonwebtoken::{EncodingKey, Header, encode},
+///     middleware::{Claims, Jwt, JwtKeys},
+///     test::TestClient,
+/// };
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct User {
+///     sub: String,
+///     exp: u64,
+/// }
+///
+/// #[handler]
+/// fn index(claims: Claims<User>) -> String {
+///     format!("hello {}", claims.sub)
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .with(Jwt::new(JwtKeys::new().secret("secret")));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let token = encode(
+///     &Header::default(),
+///     &User {
+///         sub: "sunli".to_string(),
+///         exp: jsonwebtoken::get_current_timestamp() + 60,
+///     },
+///     &EncodingKey::from_secret(b"secret"),
+/// )
+/// .unwrap();
+/// let resp = cli
+///     .get("/")
+///     .header(header::AUTHORIZATION, format!("Bearer {token}"))
+///     .send()
+///     .await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("hello sunli").await;
+///
+/// let resp = cli.get("/").send().await;
+/// resp.assert_status(StatusCode::UNAUTHORIZED);
+/// resp.assert_header(header::WWW_AUTHENTICATE, "Bearer");
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+pub struct Jwt {
+    verifier: JwtVerifier,
+}
+
+impl Jwt {
+    /// Create `Jwt` middleware which verifies the tokens with the specified
+    /// keys.
+    pub fn new(keys: JwtKeys) -> Self {
+        Self {
+            verifier: JwtVerifier::new(KeyStore::new(KeySource::Static(Arc::new(keys)))),
+        }
+    }
+
+    /// Create `Jwt` middleware which verifies the tokens with the JSON Web
+    /// Key Set downloaded from the specified URL.
+    pub fn jwks_url(url: impl Into<String>) -> Self {
+        Self {
+            verifier: JwtVerifier::new(KeyStore::jwks_url(url)),
+        }
+    }
+
+    /// Create `Jwt` middleware which verifies the tokens with the JSON Web
+    /// Key Set read from the specified file.
+    pub fn jwks_file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            verifier: JwtVerifier::new(KeyStore::new(KeySource::File(path.into()))),
+        }
+    }
+
+    /// Sets the interval to reload the JSON Web Key Set. Default is `1 hour`.
+    #[must_use]
+    pub fn refresh_interval(mut self, interval: Duration) -> Self {
+        self.verifier.keys.refresh_interval = interval;
+        self
+    }
+
+    /// Sets the accepted algorithms. By default, all the algorithms supported
+    /// by the keys are accepted.
+    #[must_use]
+    pub fn algorithms(mut self, algorithms: impl IntoIterator<Item = Algorithm>) -> Self {
+        self.verifier.algorithms = Some(algorithms.into_iter().collect());
+        self
+    }
+
+    /// Sets the accepted audiences, the `aud` claim is required if this is
+    /// set.
+    #[must_use]
+    pub fn audience<T: Into<String>>(mut self, audience: impl IntoIterator<Item = T>) -> Self {
+        self.verifier
+            .audience(&audience.into_iter().map(Into::into).collect::<Vec<_>>());
+        self
+    }
+
+    /// Sets the accepted issuers, the `iss` claim is required if this is set.
+    #[must_use]
+    pub fn issuer<T: Into<String>>(mut self, issuer: impl IntoIterator<Item = T>) -> Self {
+        self.verifier
+            .issuer(&issuer.into_iter().map(Into::into).collect::<Vec<_>>());
+        self
+    }
+
+    /// Sets the clock skew allowed when checking the `exp` and `nbf` claims.
+    /// Default is `60 seconds`.
+    #[must_use]
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.verifier.validation.leeway = leeway.as_secs();
+        self
+    }
+
+    /// Sets the claims which must be present in the tokens, only `exp`,
+    /// `nbf`, `aud`, `iss` and `sub` are supported. Default is `exp`.
+    #[must_use]
+    pub fn required_claims<T: Into<String>>(mut self, claims: impl IntoIterator<Item = T>) -> Self {
+        self.verifier.validation.required_spec_claims =
+            claims.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Jwt {
+    type Output = JwtEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        JwtEndpoint {
+            inner: ep,
+            verifier: Arc::new(self.verifier.clone()),
+        }
+    }
+}
+
+/// Endpoint for the `Jwt` middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+pub struct JwtEndpoint<E> {
+    inner: E,
+    verifier: Arc<JwtVerifier>,
+}
+
+impl<E: Endpoint> Endpoint for JwtEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+            .map(|(_, token)| token.trim())
+            .ok_or(JwtError::MissingToken)?;
+        let claims = self.verifier.verify(token).await?;
+        req.extensions_mut().insert(JwtClaims(Arc::new(claims)));
+        self.inner.call(req).await
+    }
+}
+
+/// The claims of the verified token.
+#[derive(Clone)]
+struct JwtClaims(Arc<Value>);
+
+/// An extractor for the claims of the token verified by the [`Jwt`]
+/// middleware.
+#[cfg_attr(docsrs, doc(cfg(feature = "jwt")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Claims<T>(pub T);
+
+impl<T> Deref for Claims<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Claims<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'a, T: DeserializeOwned> FromRequest<'a> for Claims<T> {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let claims = req.extensions().get::<JwtClaims>().ok_or_else(|| {
+            tracing::error!("`Jwt` middleware is not active, while trying to extract `Claims`!");
+            Error::from_string("no associated claims", StatusCode::INTERNAL_SERVER_ERROR)
+        })?;
+        Ok(Claims(
+            T::deserialize(&*claims.0).map_err(|err| JwtError::InvalidToken(err.to_string()))?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use jsonwebtoken::{EncodingKey, Header, encode, get_current_timestamp};
+    use ring::{
+        rand::SystemRandom,
+        signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, Ed25519KeyPair, KeyPair},
+    };
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{EndpointExt, Route, get, handler, test::TestClient};
+
+    #[derive(Deserialize)]
+    struct User {
+        sub: String,
+    }
+
+    #[handler(internal)]
+    fn index(claims: Claims<User>) -> String {
+        claims.0.sub
+    }
+
+    fn hs256(claims: Value) -> String {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap()
+    }
+
+    async fn check(cli: &TestClient<impl Endpoint>, token: &str, error: Option<&str>) {
+        let resp = cli
+            .get("/")
+            .header(header::AUTHORIZATION, format!("Bearer {token}"))
+            .send()
+            .await;
+        match error {
+            Some(error) => {
+                resp.assert_status(StatusCode::UNAUTHORIZED);
+                resp.assert_header(
+                    header::WWW_AUTHENTICATE,
+                    format!("Bearer error=\"invalid_token\", error_description=\"{error}\""),
+                );
+            }
+            None => {
+                resp.assert_status_is_ok();
+                resp.assert_text("sunli").await;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn validate_claims() {
+        let cli = TestClient::new(
+            Route::new().at("/", get(index)).with(
+                Jwt::new(JwtKeys::new().secret("secret"))
+                    .audience(["poem"])
+                    .issuer(["https://issuer"])
+                    .leeway(Duration::from_secs(10)),
+            ),
+        );
+        let now = get_current_timestamp();
+        let claims = |exp: u64, nbf: u64, aud: &str| json!({ "sub": "sunli", "exp": exp, "nbf": nbf, "aud": aud, "iss": "https://issuer" });
+
+        check(&cli, &hs256(claims(now + 60, now, "poem")), None).await;
+        check(&cli, &hs256(claims(now - 5, now - 60, "poem")), None).await;
+        check(
+            &cli,
+            &hs256(claims(now - 20, now - 60, "poem")),
+            Some("token expired"),
+        )
+        .await;
+        check(
+            &cli,
+            &hs256(claims(now + 60, now + 20, "poem")),
+            Some("token not yet valid"),
+        )
+        .await;
+        check(
+            &cli,
+            &hs256(claims(now + 60, now, "other")),
+            Some("invalid audience"),
+        )
+        .await;
+        check(
+            &cli,
+            &hs256(json!({ "sub": "sunli", "exp": now + 60, "aud": "poem", "iss": "other" })),
+            Some("invalid issuer"),
+        )
+        .await;
+        check(
+            &cli,
+            &encode(
+                &Header::default(),
+                &claims(now + 60, now, "poem"),
+                &EncodingKey::from_secret(b"other"),
+            )
+            .unwrap(),
+            Some("invalid signature"),
+        )
+        .await;
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+        resp.assert_header(header::WWW_AUTHENTICATE, "Bearer");
+
+        let resp = cli
+            .get("/")
+            .header(header::AUTHORIZATION, "Basic YWJjOjEyMw==")
+            .send()
+            .await;
+        resp.assert_status(StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn algorithms() {
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", get(index))
+                .with(Jwt::new(JwtKeys::new().secret("secret")).algorithms([Algorithm::HS512])),
+        );
+        let claims = json!({ "sub": "sunli", "exp": get_current_timestamp() + 60 });
+        let key = EncodingKey::from_secret(b"secret");
+
+        check(
+            &cli,
+            &encode(&Header::new(Algorithm::HS512), &claims, &key).unwrap(),
+            None,
+        )
+        .await;
+        check(
+            &cli,
+            &encode(&Header::new(Algorithm::HS256), &claims, &key).unwrap(),
+            Some("invalid token: invalid algorithm"),
+        )
+        .await;
+    }
+
+    struct SigningKey {
+        kid: String,
+        alg: Algorithm,
+        key: EncodingKey,
+        jwk: Value,
+    }
+
+    impl SigningKey {
+        fn ed25519(kid: &str) -> Self {
+            let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+            let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+            Self {
+                kid: kid.to_string(),
+                alg: Algorithm::EdDSA,
+                key: EncodingKey::from_ed_der(pkcs8.as_ref()),
+                jwk: json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "kid": kid,
+                    "x": URL_SAFE_NO_PAD.encode(pair.public_key()),
+                }),
+            }
+        }
+
+        fn es256(kid: &str) -> Self {
+            let rng = SystemRandom::new();
+            let pkcs8 =
+                EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+            let pair =
+                EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+                    .unwrap();
+            let point = pair.public_key().as_ref();
+            Self {
+                kid: kid.to_string(),
+                alg: Algorithm::ES256,
+                key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+                jwk: json!({
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "alg": "ES256",
+                    "use": "sig",
+                    "kid": kid,
+                    "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+                }),
+            }
+        }
+
+        fn sign(&self) -> String {
+            let mut header = Header::new(self.alg);
+            header.kid = Some(self.kid.clone());
+            let claims = json!({ "sub": "sunli", "exp": get_current_timestamp() + 60 });
+            encode(&header, &claims, &self.key).unwrap()
+        }
+    }
+
+    fn jwks<'a>(keys: impl IntoIterator<Item = &'a SigningKey>) -> String {
+        json!({ "keys": keys.into_iter().map(|key| key.jwk.clone()).collect::<Vec<_>>() })
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn jwks_file() {
+        let key1 = SigningKey::ed25519("key1");
+        let key2 = SigningKey::es256("key2");
+        let dir = std::env::temp_dir().join(format!("poem-jwks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("jwks.json");
+        std::fs::write(&path, jwks([&key1])).unwrap();
+
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", get(index))
+                .with(Jwt::jwks_file(&path).refresh_interval(Duration::from_secs(0))),
+        );
+        check(&cli, &key1.sign(), None).await;
+        check(&cli, &key2.sign(), Some("unknown signing key")).await;
+
+        // the key set is reloaded when the keys are rotated
+        std::fs::write(&path, jwks([&key1, &key2])).unwrap();
+        check(&cli, &key2.sign(), None).await;
+        check(&cli, &key1.sign(), None).await;
+
+        // the previous key set is used if the file cannot be read
+        std::fs::remove_file(&path).unwrap();
+        check(&cli, &key2.sign(), None).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", get(index))
+                .with(Jwt::jwks_file(dir.join("missing.json"))),
+        );
+        cli.get("/")
+            .header(header::AUTHORIZATION, format!("Bearer {}", key1.sign()))
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn jwks_url() {
+        use crate::{
+            Server,
+            endpoint::make_sync,
+            listener::{Acceptor, Listener, TcpListener},
+        };
+
+        let key = SigningKey::es256("key");
+        let data = jwks([&key]);
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let handle = tokio::spawn(async move {
+            let _ = Server::new_with_acceptor(acceptor)
+                .run(Route::new().at("/jwks.json", make_sync(move |_| data.clone())))
+                .await;
+        });
+
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", get(index))
+                .with(Jwt::jwks_url(format!("http://{addr}/jwks.json"))),
+        );
+        check(&cli, &key.sign(), None).await;
+        check(
+            &cli,
+            &SigningKey::es256("key").sign(),
+            Some("invalid signature"),
+        )
+        .await;
+
+        handle.abort();
+    }
+
+    #[tokio::test]
+    async fn claims_without_middleware() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
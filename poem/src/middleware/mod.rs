@@ -1,26 +1,38 @@
 //! Commonly used middleware.
 
 mod add_data;
+mod cache;
 mod catch_panic;
+mod circuit_breaker;
+#[cfg(feature = "client-cert")]
+mod client_cert;
 #[cfg(feature = "compression")]
 mod compression;
+mod concurrency_limit;
 #[cfg(feature = "cookie")]
 mod cookie_jar_manager;
 mod cors;
 #[cfg(feature = "csrf")]
 mod csrf;
+mod etag;
+#[cfg(feature = "cookie")]
+mod flash;
 mod force_https;
+#[cfg(feature = "jwt")]
+mod jwt;
 mod normalize_path;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_metrics;
 #[cfg(feature = "opentelemetry")]
 mod opentelemetry_tracing;
 mod propagate_header;
+mod rate_limit;
 #[cfg(feature = "requestid")]
 mod requestid;
 mod sensitive_header;
 mod set_header;
 mod size_limit;
+mod timeout;
 #[cfg(feature = "tokio-metrics")]
 mod tokio_metrics_mw;
 #[cfg(feature = "tower-compat")]
@@ -29,16 +41,26 @@ mod tracing_mw;
 
 use std::marker::PhantomData;
 
+#[cfg(feature = "client-cert")]
+pub use self::client_cert::{RequireClientCert, RequireClientCertEndpoint};
 #[cfg(feature = "compression")]
 pub use self::compression::{Compression, CompressionEndpoint};
 #[cfg(feature = "cookie")]
 pub use self::cookie_jar_manager::{CookieJarManager, CookieJarManagerEndpoint};
 #[cfg(feature = "csrf")]
 pub use self::csrf::{Csrf, CsrfEndpoint};
+#[cfg(feature = "cookie")]
+pub use self::flash::{FlashEndpoint, FlashMiddleware};
+#[cfg(feature = "jwt")]
+pub use self::jwt::{Claims, Jwt, JwtEndpoint, JwtKeys};
+#[cfg(feature = "oauth2")]
+pub(crate) use self::jwt::{JwtVerifier, KeyStore};
 #[cfg(feature = "opentelemetry")]
 pub use self::opentelemetry_metrics::{OpenTelemetryMetrics, OpenTelemetryMetricsEndpoint};
 #[cfg(feature = "opentelemetry")]
 pub use self::opentelemetry_tracing::{OpenTelemetryTracing, OpenTelemetryTracingEndpoint};
+#[cfg(feature = "redis-session")]
+pub use self::rate_limit::RedisRateLimitStore;
 #[cfg(feature = "requestid")]
 pub use self::requestid::{ReqId, RequestId, RequestIdEndpoint, ReuseId};
 #[cfg(feature = "tokio-metrics")]
@@ -47,14 +69,23 @@ pub use self::tokio_metrics_mw::{TokioMetrics, TokioMetricsEndpoint};
 pub use self::tower_compat::TowerLayerCompatExt;
 pub use self::{
     add_data::{AddData, AddDataEndpoint},
+    cache::{Cache, CacheEndpoint, CacheEntry, CacheStore, CachedResponse, MemoryCacheStore},
     catch_panic::{CatchPanic, CatchPanicEndpoint, PanicHandler},
+    circuit_breaker::{CircuitBreaker, CircuitBreakerEndpoint, CircuitState},
+    concurrency_limit::{ConcurrencyLimit, ConcurrencyLimitEndpoint},
     cors::{Cors, CorsEndpoint},
+    etag::{ETag, ETagEndpoint},
     force_https::ForceHttps,
     normalize_path::{NormalizePath, NormalizePathEndpoint, TrailingSlash},
     propagate_header::{PropagateHeader, PropagateHeaderEndpoint},
+    rate_limit::{
+        MemoryRateLimitStore, Quota, RateLimit, RateLimitAlgorithm, RateLimitEndpoint,
+        RateLimitStatus, RateLimitStore,
+    },
     sensitive_header::{SensitiveHeader, SensitiveHeaderEndpoint},
     set_header::{SetHeader, SetHeaderEndpoint},
     size_limit::{SizeLimit, SizeLimitEndpoint},
+    timeout::{Timeout, TimeoutEndpoint},
     tracing_mw::{Tracing, TracingEndpoint},
 };
 use crate::endpoint::{EitherEndpoint, Endpoint};
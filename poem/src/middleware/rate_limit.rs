@@ -0,0 +1,703 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http::{HeaderName, HeaderValue};
+use parking_lot::Mutex;
+
+use crate::{
+    Endpoint, IntoResponse, Middleware, Request, Response, Result,
+    error::{RateLimitError, ceil_secs},
+    web::real_ip,
+};
+
+/// The number of requests allowed in a period of time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Quota {
+    burst: u32,
+    period: Duration,
+}
+
+impl Quota {
+    /// Create a quota that allows `burst` requests per `period`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `burst` or `period` is zero.
+    pub fn new(burst: u32, period: Duration) -> Self {
+        assert!(burst > 0, "burst must be greater than zero");
+        assert!(!period.is_zero(), "period must be greater than zero");
+        Self { burst, period }
+    }
+
+    /// Create a quota that allows `burst` requests per second.
+    pub fn per_second(burst: u32) -> Self {
+        Self::new(burst, Duration::from_secs(1))
+    }
+
+    /// Create a quota that allows `burst` requests per minute.
+    pub fn per_minute(burst: u32) -> Self {
+        Self::new(burst, Duration::from_secs(60))
+    }
+
+    /// Create a quota that allows `burst` requests per hour.
+    pub fn per_hour(burst: u32) -> Self {
+        Self::new(burst, Duration::from_secs(60 * 60))
+    }
+
+    /// Returns the maximum number of requests in the quota.
+    #[inline]
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Returns the period in which the quota is completely replenished.
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the time it takes to replenish one request.
+    #[inline]
+    fn interval(&self) -> Duration {
+        self.period / self.burst
+    }
+}
+
+/// The algorithm used by the [`RateLimit`] middleware.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum RateLimitAlgorithm {
+    /// Token bucket.
+    ///
+    /// The bucket holds up to `burst` tokens and is refilled continuously at a
+    /// rate of `burst` tokens per `period`.
+    #[default]
+    TokenBucket,
+
+    /// Generic cell rate algorithm.
+    ///
+    /// A sliding window that spaces requests evenly, only a single timestamp
+    /// is stored for each key.
+    Gcra,
+}
+
+/// The result of checking a key against its quota.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RateLimitStatus {
+    /// Whether the request is allowed.
+    pub allowed: bool,
+
+    /// The number of requests remaining in the quota.
+    pub remaining: u32,
+
+    /// The time until the quota is completely replenished.
+    pub reset: Duration,
+
+    /// The time until the next request is allowed, only meaningful if the
+    /// request is not allowed.
+    pub retry_after: Duration,
+}
+
+/// Represents a back-end storage for the [`RateLimit`] middleware.
+///
+/// The check must be atomic, so that concurrent requests with the same key
+/// can't exceed the quota.
+pub trait RateLimitStore: Send + Sync {
+    /// Consumes one request from the quota of the specified key.
+    fn check<'a>(
+        &'a self,
+        key: &'a str,
+        algorithm: RateLimitAlgorithm,
+        quota: Quota,
+    ) -> impl Future<Output = Result<RateLimitStatus>> + Send + 'a;
+}
+
+#[derive(Debug, Copy, Clone)]
+enum MemoryState {
+    TokenBucket { tokens: f64, updated_at: Duration },
+    Gcra { tat: Duration },
+}
+
+impl MemoryState {
+    /// Returns the time at which the quota is completely replenished.
+    fn expires_at(&self, quota: Quota) -> Duration {
+        match *self {
+            MemoryState::TokenBucket { tokens, updated_at } => {
+                updated_at + quota.interval().mul_f64(f64::from(quota.burst) - tokens)
+            }
+            MemoryState::Gcra { tat } => tat,
+        }
+    }
+}
+
+fn token_bucket(
+    state: Option<MemoryState>,
+    now: Duration,
+    quota: Quota,
+) -> (RateLimitStatus, MemoryState) {
+    let capacity = f64::from(quota.burst);
+    let interval = quota.interval();
+    let mut tokens = match state {
+        Some(MemoryState::TokenBucket { tokens, updated_at }) => {
+            let refilled = now.saturating_sub(updated_at).as_secs_f64() / interval.as_secs_f64();
+            (tokens + refilled).min(capacity)
+        }
+        _ => capacity,
+    };
+
+    let allowed = tokens >= 1.0;
+    let retry_after = if allowed {
+        tokens -= 1.0;
+        Duration::ZERO
+    } else {
+        interval.mul_f64(1.0 - tokens)
+    };
+
+    (
+        RateLimitStatus {
+            allowed,
+            remaining: tokens as u32,
+            reset: interval.mul_f64(capacity - tokens),
+            retry_after,
+        },
+        MemoryState::TokenBucket {
+            tokens,
+            updated_at: now,
+        },
+    )
+}
+
+fn gcra(state: Option<MemoryState>, now: Duration, quota: Quota) -> (RateLimitStatus, MemoryState) {
+    let interval = quota.interval();
+    let tat = match state {
+        Some(MemoryState::Gcra { tat }) => tat.max(now),
+        _ => now,
+    };
+    let new_tat = tat + interval;
+    let allow_at = new_tat.saturating_sub(quota.period);
+
+    if now < allow_at {
+        return (
+            RateLimitStatus {
+                allowed: false,
+                remaining: 0,
+                reset: tat - now,
+                retry_after: allow_at - now,
+            },
+            MemoryState::Gcra { tat },
+        );
+    }
+
+    let reset = new_tat - now;
+    let remaining = (quota.period - reset).as_nanos() / interval.as_nanos().max(1);
+    (
+        RateLimitStatus {
+            allowed: true,
+            remaining: remaining as u32,
+            reset,
+            retry_after: Duration::ZERO,
+        },
+        MemoryState::Gcra { tat: new_tat },
+    )
+}
+
+struct InnerMemoryStore {
+    states: HashMap<String, (MemoryState, Duration)>,
+    next_cleanup: Duration,
+}
+
+/// A rate limit store using memory.
+pub struct MemoryRateLimitStore {
+    start: Instant,
+    inner: Mutex<InnerMemoryStore>,
+}
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            inner: Mutex::new(InnerMemoryStore {
+                states: HashMap::new(),
+                next_cleanup: Duration::ZERO,
+            }),
+        }
+    }
+}
+
+impl MemoryRateLimitStore {
+    const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Create a `MemoryRateLimitStore`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn check<'a>(
+        &'a self,
+        key: &'a str,
+        algorithm: RateLimitAlgorithm,
+        quota: Quota,
+    ) -> Result<RateLimitStatus> {
+        let now = self.start.elapsed();
+        let mut inner = self.inner.lock();
+
+        if now >= inner.next_cleanup {
+            // Keys whose quota has been completely replenished are the same as
+            // missing keys.
+            inner.states.retain(|_, (_, expires_at)| *expires_at > now);
+            inner.next_cleanup = now + Self::CLEANUP_INTERVAL;
+        }
+
+        let state = inner.states.get(key).map(|(state, _)| *state);
+        let (status, state) = match algorithm {
+            RateLimitAlgorithm::TokenBucket => token_bucket(state, now, quota),
+            RateLimitAlgorithm::Gcra => gcra(state, now, quota),
+        };
+        inner
+            .states
+            .insert(key.to_string(), (state, state.expires_at(quota)));
+        Ok(status)
+    }
+}
+
+#[cfg(feature = "redis-session")]
+mod redis_store {
+    use redis::{Script, aio::ConnectionLike};
+
+    use super::*;
+
+    const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local state = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(state[1]) or capacity
+local updated_at = tonumber(state[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) / interval)
+local allowed = 0
+local retry_after = 0
+if tokens >= 1 then
+  tokens = tokens - 1
+  allowed = 1
+else
+  retry_after = math.ceil((1 - tokens) * interval)
+end
+local reset = math.ceil((capacity - tokens) * interval)
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', string.format('%d', now))
+redis.call('PEXPIRE', KEYS[1], math.ceil(reset / 1000) + 1)
+return {allowed, math.floor(tokens), reset, retry_after}
+"#;
+
+    const GCRA_SCRIPT: &str = r#"
+local period = tonumber(ARGV[1])
+local interval = tonumber(ARGV[2])
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local tat = math.max(tonumber(redis.call('GET', KEYS[1])) or now, now)
+local new_tat = tat + interval
+local allow_at = new_tat - period
+if now < allow_at then
+  return {0, 0, tat - now, allow_at - now}
+end
+redis.call('SET', KEYS[1], string.format('%d', new_tat), 'PX', math.ceil((new_tat - now) / 1000) + 1)
+return {1, math.floor((period - (new_tat - now)) / interval), new_tat - now, 0}
+"#;
+
+    /// A rate limit store using redis.
+    ///
+    /// The current time is taken from the redis server, so that multiple
+    /// instances of the service can share the same quotas.
+    ///
+    /// # Errors
+    ///
+    /// - [`RateLimitError`]
+    #[cfg_attr(docsrs, doc(cfg(feature = "redis-session")))]
+    pub struct RedisRateLimitStore<T> {
+        connection: T,
+        prefix: String,
+        token_bucket: Script,
+        gcra: Script,
+    }
+
+    impl<T> RedisRateLimitStore<T> {
+        /// Create a `RedisRateLimitStore`.
+        pub fn new(connection: T) -> Self {
+            Self {
+                connection,
+                prefix: "poem:ratelimit:".to_string(),
+                token_bucket: Script::new(TOKEN_BUCKET_SCRIPT),
+                gcra: Script::new(GCRA_SCRIPT),
+            }
+        }
+
+        /// Sets the prefix of the redis keys, default is `poem:ratelimit:`.
+        #[must_use]
+        pub fn prefix(self, prefix: impl Into<String>) -> Self {
+            Self {
+                prefix: prefix.into(),
+                ..self
+            }
+        }
+    }
+
+    impl<T: ConnectionLike + Clone + Sync + Send> RateLimitStore for RedisRateLimitStore<T> {
+        async fn check<'a>(
+            &'a self,
+            key: &'a str,
+            algorithm: RateLimitAlgorithm,
+            quota: Quota,
+        ) -> Result<RateLimitStatus> {
+            let interval = quota.interval().as_micros().max(1) as u64;
+            let mut invocation = match algorithm {
+                RateLimitAlgorithm::TokenBucket => {
+                    let mut invocation = self.token_bucket.prepare_invoke();
+                    invocation.arg(quota.burst);
+                    invocation
+                }
+                RateLimitAlgorithm::Gcra => {
+                    let mut invocation = self.gcra.prepare_invoke();
+                    invocation.arg(quota.period.as_micros() as u64);
+                    invocation
+                }
+            };
+            let (allowed, remaining, reset, retry_after): (u8, u32, u64, u64) = invocation
+                .key(format!("{}{}", self.prefix, key))
+                .arg(interval)
+                .invoke_async(&mut self.connection.clone())
+                .await
+                .map_err(RateLimitError::Redis)?;
+
+            Ok(RateLimitStatus {
+                allowed: allowed == 1,
+                remaining,
+                reset: Duration::from_micros(reset),
+                retry_after: Duration::from_micros(retry_after),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "redis-session")]
+pub use redis_store::RedisRateLimitStore;
+
+type KeyFn = Arc<dyn Fn(&Request) -> Option<String> + Send + Sync>;
+
+const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+/// Middleware for limiting the rate of requests.
+///
+/// Requests are grouped by a key extracted from the request, by default the
+/// IP address of the remote peer. Each key is allowed the requests of the
+/// [`Quota`], further requests are rejected with `429 Too Many Requests`.
+///
+/// The `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers
+/// are added to the responses, including the errors of the inner endpoint
+/// which are converted to responses, and the `Retry-After` header is added to
+/// the rejected responses.
+///
+/// The state is kept in a [`MemoryRateLimitStore`] by default, use
+/// [`RateLimit::store`] to share it between multiple instances of the service.
+///
+/// # Errors
+///
+/// - [`RateLimitError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route, get, handler,
+///     http::StatusCode,
+///     middleware::{Quota, RateLimit},
+///     test::TestClient,
+/// };
+///
+/// #[handler]
+/// fn index() -> &'static str {
+///     "hello"
+/// }
+///
+/// let app = Route::new().at("/", get(index)).with(
+///     RateLimit::new(Quota::per_minute(2))
+///         .key(|req| req.header("x-api-key").map(ToString::to_string)),
+/// );
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// for _ in 0..2 {
+///     let resp = cli.get("/").header("x-api-key", "abc").send().await;
+///     resp.assert_status_is_ok();
+///     resp.assert_header("ratelimit-limit", "2");
+/// }
+///
+/// let resp = cli.get("/").header("x-api-key", "abc").send().await;
+/// resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+/// resp.assert_header("ratelimit-remaining", "0");
+/// resp.assert_header_exist("retry-after");
+///
+/// // Other keys have their own quotas.
+/// let resp = cli.get("/").header("x-api-key", "def").send().await;
+/// resp.assert_status_is_ok();
+/// # });
+/// ```
+pub struct RateLimit<S = MemoryRateLimitStore> {
+    quota: Quota,
+    algorithm: RateLimitAlgorithm,
+    key_fn: KeyFn,
+    store: Arc<S>,
+}
+
+impl RateLimit {
+    /// Create `RateLimit` middleware with the specified quota.
+    pub fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            algorithm: RateLimitAlgorithm::default(),
+            key_fn: Arc::new(|req| {
+                req.remote_addr()
+                    .as_socket_addr()
+                    .map(|addr| addr.ip().to_string())
+            }),
+            store: Arc::new(MemoryRateLimitStore::new()),
+        }
+    }
+}
+
+impl<S> RateLimit<S> {
+    /// Sets the algorithm, default is [`RateLimitAlgorithm::TokenBucket`].
+    #[must_use]
+    pub fn algorithm(self, algorithm: RateLimitAlgorithm) -> Self {
+        Self { algorithm, ..self }
+    }
+
+    /// Uses a closure to extract the key from the request.
+    ///
+    /// If the closure returns `None`, the request is not limited.
+    #[must_use]
+    pub fn key(self, f: impl Fn(&Request) -> Option<String> + Send + Sync + 'static) -> Self {
+        Self {
+            key_fn: Arc::new(f),
+            ..self
+        }
+    }
+
+    /// Uses the client IP address reported by the
+    /// [`RealIp`](crate::web::RealIp) extractor as the key.
+    ///
+    /// **Only use this if the service is behind a trusted proxy, otherwise the
+    /// clients can set the headers to any value.**
+    #[must_use]
+    pub fn key_by_real_ip(self) -> Self {
+        self.key(|req| real_ip(req).map(|ip| ip.to_string()))
+    }
+
+    /// Uses the value of the specified header as the key.
+    #[must_use]
+    pub fn key_by_header(self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.key(move |req| req.header(&name).map(ToString::to_string))
+    }
+
+    /// Sets the store used to keep the state of the quotas.
+    ///
+    /// The same store can be shared by multiple `RateLimit` middlewares, use
+    /// a different key for each one of them.
+    #[must_use]
+    pub fn store<S2>(self, store: S2) -> RateLimit<S2> {
+        RateLimit {
+            quota: self.quota,
+            algorithm: self.algorithm,
+            key_fn: self.key_fn,
+            store: Arc::new(store),
+        }
+    }
+}
+
+impl<E: Endpoint, S: RateLimitStore> Middleware<E> for RateLimit<S> {
+    type Output = RateLimitEndpoint<E, S>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint {
+            inner: ep,
+            quota: self.quota,
+            algorithm: self.algorithm,
+            key_fn: self.key_fn.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+/// Endpoint for the RateLimit middleware.
+pub struct RateLimitEndpoint<E, S> {
+    inner: E,
+    quota: Quota,
+    algorithm: RateLimitAlgorithm,
+    key_fn: KeyFn,
+    store: Arc<S>,
+}
+
+impl<E: Endpoint, S: RateLimitStore> Endpoint for RateLimitEndpoint<E, S> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let Some(key) = (self.key_fn)(&req) else {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        };
+
+        let status = self.store.check(&key, self.algorithm, self.quota).await?;
+        if !status.allowed {
+            return Err(RateLimitError::TooManyRequests {
+                limit: self.quota.burst,
+                reset: status.reset,
+                retry_after: status.retry_after,
+            }
+            .into());
+        }
+
+        // The errors of the inner endpoint also count against the quota, so
+        // they are converted to responses to carry the headers.
+        let mut resp = self.inner.get_response(req).await;
+        let headers = resp.headers_mut();
+        headers.insert(RATELIMIT_LIMIT, HeaderValue::from(self.quota.burst));
+        headers.insert(RATELIMIT_REMAINING, HeaderValue::from(status.remaining));
+        headers.insert(RATELIMIT_RESET, HeaderValue::from(ceil_secs(status.reset)));
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{EndpointExt, endpoint::make_sync, test::TestClient};
+
+    fn secs(secs: f64) -> Duration {
+        Duration::from_secs_f64(secs)
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let quota = Quota::per_second(2);
+
+        let (status, state) = token_bucket(None, secs(0.0), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(status.reset, secs(0.5));
+
+        let (status, state) = token_bucket(Some(state), secs(0.0), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.reset, secs(1.0));
+
+        let (status, state) = token_bucket(Some(state), secs(0.25), quota);
+        assert!(!status.allowed);
+        assert_eq!(status.retry_after, secs(0.25));
+
+        let (status, _) = token_bucket(Some(state), secs(0.5), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 0);
+    }
+
+    #[test]
+    fn test_gcra() {
+        let quota = Quota::per_second(2);
+
+        let (status, state) = gcra(None, secs(0.0), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+        assert_eq!(status.reset, secs(0.5));
+
+        let (status, state) = gcra(Some(state), secs(0.0), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 0);
+        assert_eq!(status.reset, secs(1.0));
+
+        let (status, state) = gcra(Some(state), secs(0.25), quota);
+        assert!(!status.allowed);
+        assert_eq!(status.reset, secs(0.75));
+        assert_eq!(status.retry_after, secs(0.25));
+
+        let (status, state) = gcra(Some(state), secs(0.5), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 0);
+
+        let (status, _) = gcra(Some(state), secs(5.0), quota);
+        assert!(status.allowed);
+        assert_eq!(status.remaining, 1);
+    }
+
+    #[tokio::test]
+    async fn rate_limit() {
+        for algorithm in [RateLimitAlgorithm::TokenBucket, RateLimitAlgorithm::Gcra] {
+            let ep = make_sync(|_| "hello").with(
+                RateLimit::new(Quota::per_hour(2))
+                    .algorithm(algorithm)
+                    .key_by_header("x-user"),
+            );
+            let cli = TestClient::new(ep);
+
+            let resp = cli.get("/").header("x-user", "a").send().await;
+            resp.assert_status_is_ok();
+            resp.assert_header("ratelimit-limit", "2");
+            resp.assert_header("ratelimit-remaining", "1");
+            resp.assert_header("ratelimit-reset", "1800");
+
+            let resp = cli.get("/").header("x-user", "a").send().await;
+            resp.assert_status_is_ok();
+            resp.assert_header("ratelimit-remaining", "0");
+            resp.assert_header("ratelimit-reset", "3600");
+
+            let resp = cli.get("/").header("x-user", "a").send().await;
+            resp.assert_status(StatusCode::TOO_MANY_REQUESTS);
+            resp.assert_header("ratelimit-remaining", "0");
+            resp.assert_header("retry-after", "1800");
+
+            cli.get("/")
+                .header("x-user", "b")
+                .send()
+                .await
+                .assert_status_is_ok();
+
+            // Requests without a key are not limited.
+            for _ in 0..5 {
+                let resp = cli.get("/").send().await;
+                resp.assert_status_is_ok();
+                resp.assert_header_is_not_exist("ratelimit-limit");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn error_response() {
+        let ep = make_sync(|_| Err::<(), _>(crate::Error::from_status(StatusCode::NOT_FOUND)))
+            .with(RateLimit::new(Quota::per_hour(2)).key(|_| Some("a".into())));
+        let cli = TestClient::new(ep);
+
+        let resp = cli.get("/").send().await;
+        resp.assert_status(StatusCode::NOT_FOUND);
+        resp.assert_header("ratelimit-limit", "2");
+        resp.assert_header("ratelimit-remaining", "1");
+        resp.assert_header("ratelimit-reset", "1800");
+    }
+
+    #[tokio::test]
+    async fn downcast_error() {
+        let ep =
+            make_sync(|_| ()).with(RateLimit::new(Quota::per_hour(1)).key(|_| Some("a".into())));
+        ep.call(Request::default()).await.unwrap();
+        let err = ep.call(Request::default()).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RateLimitError>(),
+            Some(RateLimitError::TooManyRequests { limit: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,336 @@
+use std::{
+    io::{Error as IoError, ErrorKind},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use tokio::{sync::watch, time::Instant};
+
+use crate::{Body, Endpoint, Middleware, Request, Result, error::TimeoutError};
+
+/// Middleware that cancels the inner endpoint if it does not complete within
+/// the time limit.
+///
+/// When the limit is exceeded, the inner future is dropped and a
+/// [`TimeoutError::Handler`] error (`503 Service Unavailable`) is returned.
+///
+/// The time taken to receive the request body can be bounded separately with
+/// [`Timeout::read_body_timeout`], in which case a [`TimeoutError::ReadBody`]
+/// error (`408 Request Timeout`) is returned.
+///
+/// A `Timeout` applied to a nested endpoint overrides the limits of the outer
+/// `Timeout` for the requests it handles, starting from the moment the
+/// request reaches it.
+///
+/// # Errors
+///
+/// - [`TimeoutError`]
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     EndpointExt, Route, handler, http::StatusCode, middleware::Timeout, test::TestClient,
+/// };
+///
+/// #[handler]
+/// async fn slow() -> &'static str {
+///     tokio::time::sleep(Duration::from_millis(200)).await;
+///     "done"
+/// }
+///
+/// let app = Route::new()
+///     .at("/slow", slow)
+///     .nest(
+///         "/reports",
+///         Route::new()
+///             .at("/slow", slow)
+///             .with(Timeout::new(Duration::from_secs(10))),
+///     )
+///     .with(Timeout::new(Duration::from_millis(50)));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// cli.get("/slow")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+///
+/// let resp = cli.get("/reports/slow").send().await;
+/// resp.assert_status_is_ok();
+/// resp.assert_text("done").await;
+/// # });
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Timeout {
+    timeout: Duration,
+    read_body_timeout: Option<Duration>,
+}
+
+impl Timeout {
+    /// Create `Timeout` middleware with the specified time limit for the
+    /// inner endpoint.
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            read_body_timeout: None,
+        }
+    }
+
+    /// Sets the time limit for receiving the whole request body.
+    #[must_use]
+    pub fn read_body_timeout(self, timeout: Duration) -> Self {
+        Self {
+            read_body_timeout: Some(timeout),
+            ..self
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Timeout {
+    type Output = TimeoutEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        TimeoutEndpoint {
+            inner: ep,
+            timeout: self.timeout,
+            read_body_timeout: self.read_body_timeout,
+        }
+    }
+}
+
+/// Endpoint for the Timeout middleware.
+pub struct TimeoutEndpoint<E> {
+    inner: E,
+    timeout: Duration,
+    read_body_timeout: Option<Duration>,
+}
+
+/// The deadlines shared by all `Timeout` middlewares processing a request.
+#[derive(Clone)]
+struct TimeoutState(Arc<TimeoutStateInner>);
+
+struct TimeoutStateInner {
+    deadline: watch::Sender<Option<Instant>>,
+    body_deadline: watch::Sender<Option<Instant>>,
+    body_timed_out: AtomicBool,
+}
+
+impl TimeoutState {
+    fn new(deadline: Instant) -> Self {
+        Self(Arc::new(TimeoutStateInner {
+            deadline: watch::Sender::new(Some(deadline)),
+            body_deadline: watch::Sender::new(None),
+            body_timed_out: AtomicBool::new(false),
+        }))
+    }
+
+    fn set_body_deadline(&self, req: &mut Request, deadline: Instant) {
+        // The body only needs to be wrapped once, later changes to the deadline
+        // are observed by the wrapped body.
+        let wrapped = self.0.body_deadline.send_replace(Some(deadline)).is_some();
+        if !wrapped {
+            let body = req.take_body();
+            req.set_body(timeout_body(body, self.clone()));
+        }
+    }
+
+    fn body_timed_out(&self) -> bool {
+        self.0.body_timed_out.load(Ordering::Relaxed)
+    }
+}
+
+/// Waits until the current deadline in `rx` has passed, following any change
+/// of the deadline.
+async fn elapsed(rx: &mut watch::Receiver<Option<Instant>>) {
+    loop {
+        let deadline = *rx.borrow_and_update();
+        let sleep = async move {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+
+        tokio::select! {
+            _ = sleep => return,
+            res = rx.changed() => {
+                if res.is_err() {
+                    // The deadline can no longer change.
+                    if let Some(deadline) = deadline {
+                        tokio::time::sleep_until(deadline).await;
+                        return;
+                    }
+                    std::future::pending::<()>().await;
+                }
+            }
+        }
+    }
+}
+
+fn timeout_body(body: Body, state: TimeoutState) -> Body {
+    let rx = state.0.body_deadline.subscribe();
+    let stream = Box::pin(body.into_bytes_stream());
+
+    Body::from_bytes_stream(futures_util::stream::unfold(
+        Some((stream, state, rx)),
+        |st| async move {
+            let (mut stream, state, mut rx) = st?;
+            tokio::select! {
+                item = stream.next() => item.map(|item| (item, Some((stream, state, rx)))),
+                _ = elapsed(&mut rx) => {
+                    state.0.body_timed_out.store(true, Ordering::Relaxed);
+                    Some((Err(IoError::new(ErrorKind::TimedOut, TimeoutError::ReadBody)), None))
+                }
+            }
+        },
+    ))
+}
+
+impl<E: Endpoint> Endpoint for TimeoutEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let now = Instant::now();
+
+        if let Some(state) = req.extensions().get::<TimeoutState>().cloned() {
+            // Nested in another `Timeout`, override its limits.
+            state.0.deadline.send_replace(Some(now + self.timeout));
+            if let Some(read_body_timeout) = self.read_body_timeout {
+                state.set_body_deadline(&mut req, now + read_body_timeout);
+            }
+            return self.inner.call(req).await;
+        }
+
+        let state = TimeoutState::new(now + self.timeout);
+        if let Some(read_body_timeout) = self.read_body_timeout {
+            state.set_body_deadline(&mut req, now + read_body_timeout);
+        }
+        req.extensions_mut().insert(state.clone());
+
+        let mut rx = state.0.deadline.subscribe();
+        tokio::select! {
+            res = self.inner.call(req) => match res {
+                Err(_) if state.body_timed_out() => Err(TimeoutError::ReadBody.into()),
+                res => res,
+            },
+            _ = elapsed(&mut rx) => {
+                if state.body_timed_out() {
+                    Err(TimeoutError::ReadBody.into())
+                } else {
+                    Err(TimeoutError::Handler.into())
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+
+    use super::*;
+    use crate::{EndpointExt, Route, handler, test::TestClient, web::Path};
+
+    #[handler(internal)]
+    async fn sleep(Path(ms): Path<u64>) -> &'static str {
+        tokio::time::sleep(Duration::from_millis(ms)).await;
+        "ok"
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let cli = TestClient::new(
+            Route::new()
+                .at("/:ms", sleep)
+                .with(Timeout::new(Duration::from_millis(100))),
+        );
+
+        let resp = cli.get("/10").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("ok").await;
+
+        cli.get("/300")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn downcast_error() {
+        let ep = Route::new()
+            .at("/:ms", sleep)
+            .timeout(Duration::from_millis(10));
+        let err = ep
+            .call(Request::builder().uri_str("/100").finish())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TimeoutError>(),
+            Some(&TimeoutError::Handler)
+        );
+    }
+
+    #[tokio::test]
+    async fn nested_override() {
+        let cli = TestClient::new(
+            Route::new()
+                .at("/:ms", sleep)
+                .nest(
+                    "/long",
+                    Route::new()
+                        .at("/:ms", sleep)
+                        .with(Timeout::new(Duration::from_millis(500))),
+                )
+                .nest(
+                    "/short",
+                    Route::new()
+                        .at("/:ms", sleep)
+                        .with(Timeout::new(Duration::from_millis(20))),
+                )
+                .with(Timeout::new(Duration::from_millis(100))),
+        );
+
+        cli.get("/200")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+        cli.get("/long/200").send().await.assert_status_is_ok();
+        cli.get("/short/50")
+            .send()
+            .await
+            .assert_status(StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn read_body_timeout() {
+        #[handler(internal)]
+        async fn index(body: String) -> String {
+            body
+        }
+
+        let cli = TestClient::new(index.with(
+            Timeout::new(Duration::from_secs(5)).read_body_timeout(Duration::from_millis(50)),
+        ));
+
+        let resp = cli.post("/").body("abc").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("abc").await;
+
+        let slow_body = futures_util::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok::<_, IoError>("abc")
+        });
+        cli.post("/")
+            .body(Body::from_bytes_stream(slow_body))
+            .send()
+            .await
+            .assert_status(StatusCode::REQUEST_TIMEOUT);
+    }
+}
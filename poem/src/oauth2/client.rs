@@ -0,0 +1,470 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use ring::digest::{SHA256, digest};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::{IDENTITY_KEY, Identity};
+use crate::{
+    error::OAuth2Error,
+    http::header,
+    middleware::{JwtVerifier, KeyStore},
+    session::Session,
+    web::Redirect,
+};
+
+const PENDING_KEY: &str = "_poem_oauth2_pending";
+
+/// The state of an authorization request, stored in the session until the
+/// provider redirects back to the client.
+#[derive(Serialize, Deserialize)]
+struct PendingAuthorization {
+    state: String,
+    code_verifier: String,
+    nonce: Option<String>,
+}
+
+/// The metadata of an OpenID Connect provider.
+///
+/// Reference: <https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    /// The issuer identifier of the provider.
+    pub issuer: String,
+
+    /// The URL of the authorization endpoint.
+    pub authorization_endpoint: String,
+
+    /// The URL of the token endpoint.
+    pub token_endpoint: String,
+
+    /// The URL of the JSON Web Key Set used to sign the ID tokens.
+    pub jwks_uri: String,
+
+    /// The URL of the UserInfo endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub userinfo_endpoint: Option<String>,
+}
+
+/// A successful response of the token endpoint.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc6749#section-5.1>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenResponse {
+    /// The access token.
+    pub access_token: String,
+
+    /// The type of the access token, usually `Bearer`.
+    pub token_type: String,
+
+    /// The lifetime in seconds of the access token.
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+
+    /// The refresh token.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+
+    /// The OpenID Connect ID token.
+    #[serde(default)]
+    pub id_token: Option<String>,
+
+    /// The scopes of the access token.
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+/// The query parameters of the request to the redirect URI.
+///
+/// Reference: <https://datatracker.ietf.org/doc/html/rfc6749#section-4.1.2>
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthorizationResponse {
+    /// The authorization code.
+    #[serde(default)]
+    pub code: Option<String>,
+
+    /// The state sent in the authorization request.
+    #[serde(default)]
+    pub state: Option<String>,
+
+    /// The error code if the authorization failed.
+    #[serde(default)]
+    pub error: Option<String>,
+
+    /// The description of the error.
+    #[serde(default)]
+    pub error_description: Option<String>,
+}
+
+/// An OAuth 2.0 client implementing the authorization code flow with PKCE,
+/// and OpenID Connect authentication if it is created with
+/// [`OAuth2Client::discover`] or [`OAuth2Client::from_metadata`].
+///
+/// The `state`, the PKCE code verifier and the `nonce` are stored in the
+/// [`Session`] between [`OAuth2Client::authorize`] and
+/// [`OAuth2Client::callback`], and the [`Identity`] of the logged-in user is
+/// stored in the session by [`OAuth2Client::callback`].
+///
+/// The session cookie must not use `SameSite=Strict`, otherwise the browser
+/// does not send it when the provider redirects back to the client.
+#[derive(Clone)]
+pub struct OAuth2Client {
+    client_id: String,
+    client_secret: Option<String>,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+    redirect_uri: Option<String>,
+    scopes: Vec<String>,
+    id_token_verifier: Option<JwtVerifier>,
+    http: reqwest::Client,
+}
+
+impl OAuth2Client {
+    /// Create an OAuth 2.0 client with the authorization endpoint and the
+    /// token endpoint of the provider.
+    pub fn new(
+        client_id: impl Into<String>,
+        authorization_endpoint: impl Into<String>,
+        token_endpoint: impl Into<String>,
+    ) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: None,
+            authorization_endpoint: authorization_endpoint.into(),
+            token_endpoint: token_endpoint.into(),
+            userinfo_endpoint: None,
+            redirect_uri: None,
+            scopes: Vec::new(),
+            id_token_verifier: None,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Create an OpenID Connect client from the metadata of the provider.
+    ///
+    /// The client requests the `openid` scope, and validates the signature,
+    /// the issuer, the audience and the nonce of the ID tokens.
+    pub fn from_metadata(metadata: ProviderMetadata, client_id: impl Into<String>) -> Self {
+        let client_id = client_id.into();
+        let mut verifier = JwtVerifier::new(KeyStore::jwks_url(metadata.jwks_uri));
+        verifier.audience(std::slice::from_ref(&client_id));
+        verifier.issuer(&[metadata.issuer]);
+
+        Self {
+            scopes: vec!["openid".to_string()],
+            id_token_verifier: Some(verifier),
+            ..Self::new(
+                client_id,
+                metadata.authorization_endpoint,
+                metadata.token_endpoint,
+            )
+        }
+    }
+
+    /// Create an OpenID Connect client with the metadata downloaded from
+    /// `{issuer}/.well-known/openid-configuration`.
+    pub async fn discover(
+        issuer: impl AsRef<str>,
+        client_id: impl Into<String>,
+    ) -> Result<Self, OAuth2Error> {
+        let issuer = issuer.as_ref().trim_end_matches('/');
+        let metadata = reqwest::get(format!("{issuer}/.well-known/openid-configuration"))
+            .await
+            .and_then(|resp| resp.error_for_status())
+            .map_err(|err| OAuth2Error::Discovery(err.to_string()))?
+            .json::<ProviderMetadata>()
+            .await
+            .map_err(|err| OAuth2Error::Discovery(err.to_string()))?;
+        if metadata.issuer.trim_end_matches('/') != issuer {
+            return Err(OAuth2Error::Discovery(format!(
+                "issuer mismatch: {}",
+                metadata.issuer
+            )));
+        }
+        Ok(Self::from_metadata(metadata, client_id))
+    }
+
+    /// Sets the client secret, which is sent to the token endpoint in the
+    /// request body.
+    #[must_use]
+    pub fn client_secret(self, client_secret: impl Into<String>) -> Self {
+        Self {
+            client_secret: Some(client_secret.into()),
+            ..self
+        }
+    }
+
+    /// Sets the redirect URI, which must be registered with the provider.
+    #[must_use]
+    pub fn redirect_uri(self, redirect_uri: impl Into<String>) -> Self {
+        Self {
+            redirect_uri: Some(redirect_uri.into()),
+            ..self
+        }
+    }
+
+    /// Sets the requested scopes.
+    #[must_use]
+    pub fn scopes<T: Into<String>>(self, scopes: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            scopes: scopes.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// Sets the URL of the UserInfo endpoint, whose claims are added to the
+    /// [`Identity`].
+    ///
+    /// For the providers which do not support OpenID Connect, the subject of
+    /// the identity is the `sub` or the `id` field of the response.
+    #[must_use]
+    pub fn userinfo_endpoint(self, userinfo_endpoint: impl Into<String>) -> Self {
+        Self {
+            userinfo_endpoint: Some(userinfo_endpoint.into()),
+            ..self
+        }
+    }
+
+    /// Returns the URL of the authorization request, and stores the state of
+    /// the request in the session.
+    pub fn authorize_url(&self, session: &Session) -> String {
+        let state = random_string();
+        let code_verifier = random_string();
+        let code_challenge = URL_SAFE_NO_PAD.encode(digest(&SHA256, code_verifier.as_bytes()));
+        let nonce = self.id_token_verifier.as_ref().map(|_| random_string());
+        let scope = self.scopes.join(" ");
+
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("state", &state),
+            ("code_challenge", &code_challenge),
+            ("code_challenge_method", "S256"),
+        ];
+        if let Some(redirect_uri) = &self.redirect_uri {
+            params.push(("redirect_uri", redirect_uri));
+        }
+        if !scope.is_empty() {
+            params.push(("scope", &scope));
+        }
+        if let Some(nonce) = &nonce {
+            params.push(("nonce", nonce));
+        }
+        let separator = if self.authorization_endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let url = format!(
+            "{}{}{}",
+            self.authorization_endpoint,
+            separator,
+            serde_urlencoded::to_string(&params).unwrap_or_default()
+        );
+
+        session.set(
+            PENDING_KEY,
+            PendingAuthorization {
+                state,
+                code_verifier,
+                nonce,
+            },
+        );
+        url
+    }
+
+    /// Returns a redirect to the authorization request, and stores the state
+    /// of the request in the session.
+    pub fn authorize(&self, session: &Session) -> Redirect {
+        Redirect::see_other(self.authorize_url(session))
+    }
+
+    /// Completes the authorization with the parameters of the request to the
+    /// redirect URI.
+    ///
+    /// The authorization code is exchanged for the tokens, and the
+    /// [`Identity`] of the user is stored in the session and set as the
+    /// session user with [`Session::set_user`].
+    pub async fn callback(
+        &self,
+        session: &Session,
+        response: &AuthorizationResponse,
+    ) -> Result<Identity, OAuth2Error> {
+        let pending = session
+            .get::<PendingAuthorization>(PENDING_KEY)
+            .ok_or(OAuth2Error::InvalidState)?;
+        session.remove(PENDING_KEY);
+
+        if response.state.as_deref() != Some(pending.state.as_str()) {
+            return Err(OAuth2Error::InvalidState);
+        }
+        if let Some(error) = &response.error {
+            return Err(OAuth2Error::Authorization {
+                error: error.clone(),
+                description: response.error_description.clone(),
+            });
+        }
+        let code = response.code.as_deref().ok_or(OAuth2Error::MissingCode)?;
+
+        let mut params = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("code_verifier", &pending.code_verifier),
+        ];
+        if let Some(redirect_uri) = &self.redirect_uri {
+            params.push(("redirect_uri", redirect_uri));
+        }
+        let token = self.token_request(params).await?;
+        let identity = self.identity(token, pending.nonce.as_deref(), None).await?;
+
+        session.set(IDENTITY_KEY, &identity);
+        session.set_user(&identity.subject);
+        Ok(identity)
+    }
+
+    /// Requests a new access token with a refresh token.
+    pub async fn refresh_token(&self, refresh_token: &str) -> Result<TokenResponse, OAuth2Error> {
+        self.token_request(vec![
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .await
+    }
+
+    /// Refreshes the access token of the [`Identity`] stored in the session.
+    pub async fn refresh(&self, session: &Session) -> Result<Identity, OAuth2Error> {
+        let identity = Identity::from_session(session).ok_or(OAuth2Error::NotLoggedIn)?;
+        let refresh_token = identity
+            .refresh_token
+            .as_deref()
+            .ok_or(OAuth2Error::MissingRefreshToken)?;
+        let token = self.refresh_token(refresh_token).await?;
+        let identity = self.identity(token, None, Some(&identity)).await?;
+        session.set(IDENTITY_KEY, &identity);
+        Ok(identity)
+    }
+
+    async fn token_request(
+        &self,
+        mut params: Vec<(&str, &str)>,
+    ) -> Result<TokenResponse, OAuth2Error> {
+        #[derive(Deserialize)]
+        struct ErrorResponse {
+            error: String,
+            error_description: Option<String>,
+        }
+
+        params.push(("client_id", &self.client_id));
+        if let Some(client_secret) = &self.client_secret {
+            params.push(("client_secret", client_secret));
+        }
+        let resp = self
+            .http
+            .post(&self.token_endpoint)
+            .header(header::ACCEPT, "application/json")
+            .form(&params)
+            .send()
+            .await
+            .map_err(|err| OAuth2Error::Request(err.to_string()))?;
+        let status = resp.status();
+        let body = resp
+            .bytes()
+            .await
+            .map_err(|err| OAuth2Error::Request(err.to_string()))?;
+
+        // some providers respond to the failed requests with `200 OK`
+        if let Ok(resp) = serde_json::from_slice::<ErrorResponse>(&body) {
+            return Err(OAuth2Error::Token {
+                error: resp.error,
+                description: resp.error_description,
+            });
+        }
+        if !status.is_success() {
+            return Err(OAuth2Error::Request(format!(
+                "token endpoint responded with `{status}`"
+            )));
+        }
+        serde_json::from_slice(&body).map_err(|err| OAuth2Error::Request(err.to_string()))
+    }
+
+    /// Creates the identity of the user from the token response, with the
+    /// claims of the ID token and the UserInfo endpoint.
+    async fn identity(
+        &self,
+        token: TokenResponse,
+        nonce: Option<&str>,
+        previous: Option<&Identity>,
+    ) -> Result<Identity, OAuth2Error> {
+        let mut claims = Map::new();
+
+        if let Some(verifier) = &self.id_token_verifier {
+            match (&token.id_token, previous) {
+                (Some(id_token), _) => {
+                    if let Value::Object(id_token_claims) = verifier.verify(id_token).await? {
+                        claims = id_token_claims;
+                    }
+                    if let Some(nonce) = nonce {
+                        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+                            return Err(OAuth2Error::InvalidNonce);
+                        }
+                    }
+                }
+                // the ID token is optional in the refresh responses
+                (None, Some(previous)) => claims = previous.claims.clone(),
+                (None, None) => return Err(OAuth2Error::MissingIdToken),
+            }
+        }
+
+        if let Some(userinfo_endpoint) = &self.userinfo_endpoint {
+            let userinfo = self
+                .http
+                .get(userinfo_endpoint)
+                .bearer_auth(&token.access_token)
+                .header(header::ACCEPT, "application/json")
+                .send()
+                .await
+                .and_then(|resp| resp.error_for_status())
+                .map_err(|err| OAuth2Error::Request(err.to_string()))?
+                .json::<Map<String, Value>>()
+                .await
+                .map_err(|err| OAuth2Error::Request(err.to_string()))?;
+            for (name, value) in userinfo {
+                claims.entry(name).or_insert(value);
+            }
+        }
+
+        let subject = match claims.get("sub").or_else(|| claims.get("id")) {
+            Some(Value::String(subject)) => subject.clone(),
+            Some(Value::Number(subject)) => subject.to_string(),
+            _ => return Err(OAuth2Error::MissingSubject),
+        };
+        if let Some(previous) = previous {
+            if previous.subject != subject {
+                return Err(OAuth2Error::SubjectMismatch);
+            }
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Ok(Identity {
+            subject,
+            claims,
+            access_token: token.access_token,
+            expires_at: token.expires_in.map(|expires_in| now + expires_in),
+            refresh_token: token
+                .refresh_token
+                .or_else(|| previous.and_then(|previous| previous.refresh_token.clone())),
+            id_token: token
+                .id_token
+                .or_else(|| previous.and_then(|previous| previous.id_token.clone())),
+        })
+    }
+}
+
+fn random_string() -> String {
+    URL_SAFE_NO_PAD.encode(rand::random::<[u8; 32]>())
+}
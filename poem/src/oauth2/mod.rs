@@ -0,0 +1,500 @@
+//! OAuth 2.0 and OpenID Connect login flow.
+//!
+//! [`OAuth2Client`] implements the authorization code flow with PKCE, stores
+//! its state in the [`Session`], and stores the [`Identity`] of the logged-in
+//! user in the session, which can be extracted in the handlers.
+//!
+//! The identity contains the tokens of the user, so the session should be
+//! stored on the server side with
+//! [`ServerSession`](crate::session::ServerSession).
+//!
+//! # Example
+//!
+//! ```
+//! use poem::{
+//!     EndpointExt, Result, Route, get, handler,
+//!     oauth2::{AuthorizationResponse, Identity, OAuth2Client},
+//!     session::{CookieConfig, MemoryStorage, ServerSession, Session},
+//!     web::{Data, Query, Redirect},
+//! };
+//!
+//! #[handler]
+//! fn login(client: Data<&OAuth2Client>, session: &Session) -> Redirect {
+//!     client.authorize(session)
+//! }
+//!
+//! #[handler]
+//! async fn callback(
+//!     client: Data<&OAuth2Client>,
+//!     session: &Session,
+//!     Query(resp): Query<AuthorizationResponse>,
+//! ) -> Result<Redirect> {
+//!     client.callback(session, &resp).await?;
+//!     Ok(Redirect::see_other("/"))
+//! }
+//!
+//! #[handler]
+//! fn index(identity: Identity) -> String {
+//!     format!("hello {}", identity.subject)
+//! }
+//!
+//! let client = OAuth2Client::new(
+//!     "client-id",
+//!     "https://github.com/login/oauth/authorize",
+//!     "https://github.com/login/oauth/access_token",
+//! )
+//! .client_secret("client-secret")
+//! .redirect_uri("http://localhost:3000/callback")
+//! .scopes(["read:user"])
+//! .userinfo_endpoint("https://api.github.com/user");
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let app = Route::new()
+//!     .at("/", get(index))
+//!     .at("/login", get(login))
+//!     .at("/callback", get(callback))
+//!     .data(client)
+//!     .with(ServerSession::new(
+//!         CookieConfig::default(),
+//!         MemoryStorage::new(),
+//!     ));
+//! # });
+//! ```
+//!
+//! With an OpenID Connect provider, use [`OAuth2Client::discover`] to create
+//! the client from the metadata of the provider, the ID tokens are then
+//! validated with the keys of the provider.
+
+mod client;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub use client::{AuthorizationResponse, OAuth2Client, ProviderMetadata, TokenResponse};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+
+use crate::{FromRequest, Request, RequestBody, Result, error::OAuth2Error, session::Session};
+
+const IDENTITY_KEY: &str = "_poem_oauth2_identity";
+
+/// The identity of the user logged in with [`OAuth2Client::callback`].
+///
+/// It can be extracted in the handlers, and responds with `401 Unauthorized`
+/// if the user is not logged in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Identity {
+    /// The subject of the identity, the `sub` claim of the ID token or the
+    /// UserInfo response.
+    pub subject: String,
+
+    /// The claims of the ID token and the UserInfo response.
+    pub claims: Map<String, Value>,
+
+    /// The access token.
+    pub access_token: String,
+
+    /// The Unix timestamp when the access token expires.
+    pub expires_at: Option<u64>,
+
+    /// The refresh token.
+    pub refresh_token: Option<String>,
+
+    /// The OpenID Connect ID token.
+    pub id_token: Option<String>,
+}
+
+impl Identity {
+    /// Returns the identity stored in the session.
+    pub fn from_session(session: &Session) -> Option<Self> {
+        session.get(IDENTITY_KEY)
+    }
+
+    /// Removes the identity from the session.
+    pub fn remove_from_session(session: &Session) {
+        session.remove(IDENTITY_KEY);
+    }
+
+    /// Returns the claim with the specified name.
+    pub fn claim<T: DeserializeOwned>(&self, name: &str) -> Option<T> {
+        self.claims
+            .get(name)
+            .and_then(|value| T::deserialize(value).ok())
+    }
+
+    /// Returns `true` if the access token has expired.
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl<'a> FromRequest<'a> for Identity {
+    async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
+        let session = <&Session>::from_request(req, body).await?;
+        Ok(Identity::from_session(session).ok_or(OAuth2Error::NotLoggedIn)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+    use jsonwebtoken::{Algorithm, EncodingKey, Header, encode, get_current_timestamp};
+    use parking_lot::Mutex;
+    use ring::{
+        digest::{SHA256, digest},
+        rand::SystemRandom,
+        signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair},
+    };
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        EndpointExt, IntoResponse, Response, Route, Server, get, handler,
+        http::{StatusCode, header},
+        listener::{Acceptor, Listener, TcpListener},
+        post,
+        session::{CookieConfig, CookieSession},
+        test::TestClient,
+        web::{Data, Form, Json, Query, Redirect},
+    };
+
+    const CLIENT_ID: &str = "client";
+    const CLIENT_SECRET: &str = "secret";
+    const REDIRECT_URI: &str = "http://app/callback";
+
+    /// A stand-in OpenID Connect provider.
+    struct Provider {
+        issuer: String,
+        key: EncodingKey,
+        jwk: Value,
+        /// The code challenges and nonces of the authorization codes.
+        codes: Mutex<BTreeMap<String, (String, String)>>,
+    }
+
+    #[handler(internal)]
+    fn discovery(provider: Data<&Arc<Provider>>) -> Json<ProviderMetadata> {
+        let issuer = &provider.issuer;
+        Json(ProviderMetadata {
+            issuer: issuer.clone(),
+            authorization_endpoint: format!("{issuer}/authorize"),
+            token_endpoint: format!("{issuer}/token"),
+            jwks_uri: format!("{issuer}/jwks"),
+            userinfo_endpoint: Some(format!("{issuer}/userinfo")),
+        })
+    }
+
+    #[handler(internal)]
+    fn jwks(provider: Data<&Arc<Provider>>) -> Json<Value> {
+        Json(json!({ "keys": [provider.jwk] }))
+    }
+
+    #[handler(internal)]
+    fn authorize(
+        provider: Data<&Arc<Provider>>,
+        Query(params): Query<BTreeMap<String, String>>,
+    ) -> Redirect {
+        assert_eq!(params["response_type"], "code");
+        assert_eq!(params["client_id"], CLIENT_ID);
+        assert_eq!(params["redirect_uri"], REDIRECT_URI);
+        assert_eq!(params["scope"], "openid profile");
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let mut codes = provider.codes.lock();
+        let code = format!("code-{}", codes.len());
+        codes.insert(
+            code.clone(),
+            (params["code_challenge"].clone(), params["nonce"].clone()),
+        );
+        Redirect::see_other(format!(
+            "{REDIRECT_URI}?code={code}&state={}",
+            params["state"]
+        ))
+    }
+
+    #[handler(internal)]
+    fn token(
+        provider: Data<&Arc<Provider>>,
+        Form(params): Form<BTreeMap<String, String>>,
+    ) -> Response {
+        let error = || {
+            Json(json!({ "error": "invalid_grant" }))
+                .with_status(StatusCode::BAD_REQUEST)
+                .into_response()
+        };
+        if params.get("client_id").map(String::as_str) != Some(CLIENT_ID)
+            || params.get("client_secret").map(String::as_str) != Some(CLIENT_SECRET)
+        {
+            return error();
+        }
+
+        match params["grant_type"].as_str() {
+            "authorization_code" => {
+                let Some((code_challenge, nonce)) = provider.codes.lock().remove(&params["code"])
+                else {
+                    return error();
+                };
+                let verifier = &params["code_verifier"];
+                if URL_SAFE_NO_PAD.encode(digest(&SHA256, verifier.as_bytes())) != code_challenge {
+                    return error();
+                }
+
+                let mut header = Header::new(Algorithm::ES256);
+                header.kid = Some("key".to_string());
+                let id_token = encode(
+                    &header,
+                    &json!({
+                        "iss": provider.issuer,
+                        "aud": CLIENT_ID,
+                        "sub": "user-1",
+                        "exp": get_current_timestamp() + 60,
+                        "nonce": nonce,
+                        "name": "sunli",
+                    }),
+                    &provider.key,
+                )
+                .unwrap();
+                Json(json!({
+                    "access_token": "access-1",
+                    "token_type": "Bearer",
+                    "expires_in": 3600,
+                    "refresh_token": "refresh-1",
+                    "id_token": id_token,
+                }))
+                .into_response()
+            }
+            "refresh_token" if params["refresh_token"] == "refresh-1" => Json(json!({
+                "access_token": "access-2",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            }))
+            .into_response(),
+            _ => error(),
+        }
+    }
+
+    #[handler(internal)]
+    fn userinfo(req: &Request) -> Json<Value> {
+        assert!(
+            req.header(header::AUTHORIZATION)
+                .unwrap()
+                .starts_with("Bearer access-")
+        );
+        Json(json!({ "sub": "user-1", "email": "sunli@example.com" }))
+    }
+
+    async fn start_provider() -> SocketAddr {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        let pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        let point = pair.public_key().as_ref();
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let provider = Arc::new(Provider {
+            issuer: format!("http://{addr}"),
+            key: EncodingKey::from_ec_der(pkcs8.as_ref()),
+            jwk: json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "kid": "key",
+                "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+                "y": URL_SAFE_NO_PAD.encode(&point[33..]),
+            }),
+            codes: Default::default(),
+        });
+        let app = Route::new()
+            .at("/.well-known/openid-configuration", get(discovery))
+            .at("/jwks", get(jwks))
+            .at("/authorize", get(authorize))
+            .at("/token", post(token))
+            .at("/userinfo", get(userinfo))
+            .data(provider);
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(app));
+        addr
+    }
+
+    #[handler(internal)]
+    fn login(client: Data<&OAuth2Client>, session: &Session) -> Redirect {
+        client.authorize(session)
+    }
+
+    #[handler(internal)]
+    async fn callback(
+        client: Data<&OAuth2Client>,
+        session: &Session,
+        Query(resp): Query<AuthorizationResponse>,
+    ) -> Result<String> {
+        Ok(client.callback(session, &resp).await?.subject)
+    }
+
+    #[handler(internal)]
+    async fn refresh(client: Data<&OAuth2Client>, session: &Session) -> Result<String> {
+        Ok(client.refresh(session).await?.access_token)
+    }
+
+    #[handler(internal)]
+    fn me(identity: Identity, session: &Session) -> String {
+        assert_eq!(session.user().as_deref(), Some(identity.subject.as_str()));
+        format!(
+            "{} {} {}",
+            identity.claim::<String>("name").unwrap(),
+            identity.claim::<String>("email").unwrap(),
+            identity.access_token
+        )
+    }
+
+    fn session_cookie(resp: &crate::test::TestResponse) -> String {
+        resp.0
+            .headers()
+            .get(header::SET_COOKIE)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string()
+    }
+
+    #[tokio::test]
+    async fn login_flow() {
+        let addr = start_provider().await;
+        let client = OAuth2Client::discover(format!("http://{addr}/"), CLIENT_ID)
+            .await
+            .unwrap()
+            .client_secret(CLIENT_SECRET)
+            .redirect_uri(REDIRECT_URI)
+            .scopes(["openid", "profile"])
+            .userinfo_endpoint(format!("http://{addr}/userinfo"));
+        let cli = TestClient::new(
+            Route::new()
+                .at("/login", get(login))
+                .at("/callback", get(callback))
+                .at("/refresh", post(refresh))
+                .at("/me", get(me))
+                .data(client)
+                .with(CookieSession::new(CookieConfig::default())),
+        );
+
+        cli.get("/me")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+
+        let resp = cli.get("/login").send().await;
+        resp.assert_status(StatusCode::SEE_OTHER);
+        let cookie = session_cookie(&resp);
+        let authorize_url = resp.0.headers()[header::LOCATION].to_str().unwrap();
+        assert!(authorize_url.starts_with(&format!("http://{addr}/authorize?")));
+
+        // the browser follows the redirect to the provider
+        let resp = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(authorize_url)
+            .send()
+            .await
+            .unwrap();
+        let callback_url = resp.headers()[header::LOCATION].to_str().unwrap();
+        let callback_uri = callback_url.strip_prefix("http://app").unwrap();
+
+        // the state does not match
+        let resp = cli
+            .get(callback_uri.replace("state=", "state=x"))
+            .header(header::COOKIE, &cookie)
+            .send()
+            .await;
+        resp.assert_status(StatusCode::BAD_REQUEST);
+
+        let resp = cli.get("/login").send().await;
+        let cookie = session_cookie(&resp);
+        let authorize_url = resp.0.headers()[header::LOCATION].to_str().unwrap();
+        let resp = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(authorize_url)
+            .send()
+            .await
+            .unwrap();
+        let callback_url = resp.headers()[header::LOCATION].to_str().unwrap();
+        let callback_uri = callback_url.strip_prefix("http://app").unwrap();
+
+        let resp = cli
+            .get(callback_uri)
+            .header(header::COOKIE, &cookie)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        // the session id is regenerated when the user logs in
+        let cookie = session_cookie(&resp);
+        resp.assert_text("user-1").await;
+
+        // the authorization request can only be completed once
+        cli.get(callback_uri)
+            .header(header::COOKIE, &cookie)
+            .send()
+            .await
+            .assert_status(StatusCode::BAD_REQUEST);
+
+        let resp = cli.get("/me").header(header::COOKIE, &cookie).send().await;
+        resp.assert_status_is_ok();
+        resp.assert_text("sunli sunli@example.com access-1").await;
+
+        let resp = cli
+            .post("/refresh")
+            .header(header::COOKIE, &cookie)
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        let cookie = session_cookie(&resp);
+        resp.assert_text("access-2").await;
+
+        let resp = cli.get("/me").header(header::COOKIE, &cookie).send().await;
+        resp.assert_text("sunli sunli@example.com access-2").await;
+    }
+
+    #[tokio::test]
+    async fn authorization_error() {
+        let client = OAuth2Client::new("client", "http://provider/authorize?a=1", "");
+        let session = Session::default();
+        let url = client.authorize_url(&session);
+        assert!(url.starts_with("http://provider/authorize?a=1&response_type=code&"));
+        assert!(!url.contains("nonce="));
+
+        let state = url
+            .split('&')
+            .find_map(|param| param.strip_prefix("state="))
+            .unwrap();
+        let err = client
+            .callback(
+                &session,
+                &AuthorizationResponse {
+                    state: Some(state.to_string()),
+                    error: Some("access_denied".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, OAuth2Error::Authorization { error, .. } if error == "access_denied")
+        );
+        assert!(Identity::from_session(&session).is_none());
+    }
+}
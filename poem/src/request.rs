@@ -461,6 +461,28 @@ impl Request {
         &mut self.state
     }
 
+    /// Returns a copy of the request without the body.
+    pub(crate) fn clone_without_body(&self) -> Request {
+        Request {
+            method: self.method.clone(),
+            uri: self.uri.clone(),
+            version: self.version,
+            headers: self.headers.clone(),
+            extensions: self.extensions.clone(),
+            body: Body::empty(),
+            state: RequestState {
+                local_addr: self.state.local_addr.clone(),
+                remote_addr: self.state.remote_addr.clone(),
+                scheme: self.state.scheme.clone(),
+                original_uri: self.state.original_uri.clone(),
+                match_params: self.state.match_params.clone(),
+                #[cfg(feature = "cookie")]
+                cookie_jar: self.state.cookie_jar.clone(),
+                on_upgrade: Default::default(),
+            },
+        }
+    }
+
     /// Returns the parameters used by the extractor.
     pub fn split(mut self) -> (Request, RequestBody) {
         let body = self.take_body();
@@ -1,12 +1,35 @@
 use std::{
+    collections::HashMap,
     fmt::{self, Debug, Formatter},
     sync::Arc,
 };
 
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
 use regex::bytes::Regex;
 use smallvec::SmallVec;
 
-use crate::error::RouteError;
+use crate::error::{RouteError, UrlForError};
+
+/// The characters which are percent-encoded in the values of path parameters,
+/// everything except the unreserved characters and the sub-delimiters.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
 
 fn longest_common_prefix(a: &[u8], b: &[u8]) -> usize {
     a.iter().zip(b).take_while(|(a, b)| **a == **b).count()
@@ -132,19 +155,105 @@ enum NodeType {
     Regex,
 }
 
+/// A function which checks the value of a path parameter.
+pub(crate) type MatcherFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+fn builtin_matcher(name: &str) -> Option<fn(&str) -> bool> {
+    fn parse<T: std::str::FromStr>(value: &str) -> bool {
+        value.parse::<T>().is_ok()
+    }
+
+    fn uuid(value: &str) -> bool {
+        value.len() == 36
+            && value.bytes().enumerate().all(|(i, c)| match i {
+                8 | 13 | 18 | 23 => c == b'-',
+                _ => c.is_ascii_hexdigit(),
+            })
+    }
+
+    Some(match name {
+        "u8" => parse::<u8>,
+        "u16" => parse::<u16>,
+        "u32" => parse::<u32>,
+        "u64" => parse::<u64>,
+        "u128" => parse::<u128>,
+        "usize" => parse::<usize>,
+        "i8" => parse::<i8>,
+        "i16" => parse::<i16>,
+        "i32" => parse::<i32>,
+        "i64" => parse::<i64>,
+        "i128" => parse::<i128>,
+        "isize" => parse::<isize>,
+        "uuid" => uuid,
+        _ => return None,
+    })
+}
+
+/// The matchers which can be used instead of a regex, for example
+/// `:id<u64>`.
+#[derive(Default)]
+pub(crate) struct PathMatchers(HashMap<String, MatcherFn>);
+
+impl PathMatchers {
+    fn get(&self, name: &str) -> Option<MatcherFn> {
+        self.0
+            .get(name)
+            .cloned()
+            .or_else(|| builtin_matcher(name).map(|f| Arc::new(f) as MatcherFn))
+    }
+}
+
+impl Debug for PathMatchers {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+impl PartialEq for PathMatchers {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len() && self.0.keys().all(|name| other.0.contains_key(name))
+    }
+}
+
+impl Eq for PathMatchers {}
+
+enum Constraint {
+    Regex(Regex),
+    Matcher(MatcherFn),
+}
+
 struct PathRegex {
     re_str: String,
-    re: Regex,
+    constraint: Constraint,
 }
 
 impl PathRegex {
-    fn new(re_bytes: &[u8]) -> Option<Self> {
+    fn new(re_bytes: &[u8], matchers: &PathMatchers) -> Option<Self> {
         let re_str = std::str::from_utf8(re_bytes).ok()?;
+        let constraint = match matchers.get(re_str) {
+            Some(matcher) => Constraint::Matcher(matcher),
+            None => Constraint::Regex(Regex::new(re_str).ok()?),
+        };
         Some(PathRegex {
             re_str: re_str.to_string(),
-            re: Regex::new(re_str).ok()?,
+            constraint,
         })
     }
+
+    /// Returns the length of the value at the start of the path, a matcher
+    /// checks the whole segment.
+    fn match_len(&self, path: &[u8]) -> Option<usize> {
+        match &self.constraint {
+            Constraint::Regex(re) => re.captures(path).map(|captures| captures[0].len()),
+            Constraint::Matcher(matcher) => {
+                let len = find_slash(path).unwrap_or(path.len());
+                let value = percent_encoding::percent_decode(&path[..len])
+                    .decode_utf8()
+                    .ok()?;
+                matcher(&value).then_some(len)
+            }
+        }
+    }
 }
 
 impl Debug for PathRegex {
@@ -194,6 +303,22 @@ struct Node<T> {
 }
 
 impl<T> Node<T> {
+    /// Returns `true` if this node or one of its descendants compiled `re_str`
+    /// as a regex.
+    fn uses_regex(&self, re_str: &str) -> bool {
+        let uses = self
+            .re
+            .as_ref()
+            .is_some_and(|re| re.re_str == re_str && matches!(re.constraint, Constraint::Regex(_)));
+        uses || self.children.iter().any(|child| child.uses_regex(re_str))
+            || self
+                .param_children
+                .iter()
+                .chain(&self.regex_children)
+                .chain(&self.catch_all_child)
+                .any(|child| child.uses_regex(re_str))
+    }
+
     fn find_static_child(&self, prefix: u8) -> Option<usize> {
         (0..self.indices.len()).find(|&i| self.indices[i] == prefix)
     }
@@ -237,7 +362,7 @@ impl<T> Node<T> {
                         re: None,
                         param_children: ::std::mem::take(&mut child.param_children),
                         catch_all_child: child.catch_all_child.take(),
-                        regex_children: vec![],
+                        regex_children: ::std::mem::take(&mut child.regex_children),
                         data: child.data.take(),
                     };
 
@@ -405,8 +530,8 @@ impl<T> Node<T> {
         for regex_children in &self.regex_children {
             params.truncate(num_params);
 
-            if let Some(captures) = regex_children.re.as_ref().unwrap().re.captures(path) {
-                let value = &path[..captures[0].len()];
+            if let Some(len) = regex_children.re.as_ref().unwrap().match_len(path) {
+                let value = &path[..len];
                 if !regex_children.name.is_empty() {
                     params.push((&regex_children.name, value));
                 }
@@ -439,6 +564,145 @@ impl<T> Node<T> {
     }
 }
 
+#[derive(Clone)]
+enum TemplateSegment {
+    Static(String),
+    Param(String, Option<Regex>),
+    Typed(String, MatcherFn),
+    CatchAll(String),
+    Unnamed,
+}
+
+/// A path pattern used to generate URLs.
+#[derive(Clone)]
+pub(crate) struct PathTemplate {
+    pattern: Arc<str>,
+    segments: Vec<TemplateSegment>,
+}
+
+impl Debug for PathTemplate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PathTemplate").field(&self.pattern).finish()
+    }
+}
+
+impl PathTemplate {
+    pub(crate) fn parse(pattern: &str, matchers: &PathMatchers) -> Result<Self, RouteError> {
+        let raw_segments = parse_path_segments(pattern.as_bytes())
+            .map_err(|_| RouteError::InvalidPath(pattern.to_string()))?;
+        let to_string = |value: &[u8]| String::from_utf8_lossy(value).into_owned();
+
+        let mut segments = Vec::with_capacity(raw_segments.len());
+        for raw_segment in raw_segments {
+            segments.push(match raw_segment {
+                RawSegment::Static(value) => TemplateSegment::Static(to_string(value)),
+                RawSegment::Param(name) => TemplateSegment::Param(to_string(name), None),
+                RawSegment::CatchAll(Some(name)) => TemplateSegment::CatchAll(to_string(name)),
+                RawSegment::Regex(Some(name), re_bytes) => {
+                    let re_str = to_string(re_bytes);
+                    if let Some(matcher) = matchers.get(&re_str) {
+                        TemplateSegment::Typed(to_string(name), matcher)
+                    } else {
+                        // the value must be matched entirely, not just its prefix
+                        let re = Regex::new(&format!("^(?:{re_str})$")).map_err(|_| {
+                            RouteError::InvalidRegex {
+                                path: pattern.to_string(),
+                                regex: re_str.clone(),
+                            }
+                        })?;
+                        TemplateSegment::Param(to_string(name), Some(re))
+                    }
+                }
+                RawSegment::CatchAll(None) | RawSegment::Regex(None, _) => TemplateSegment::Unnamed,
+            });
+        }
+
+        Ok(Self {
+            pattern: pattern.into(),
+            segments,
+        })
+    }
+
+    /// Returns the template of the pattern nested in the specified prefix.
+    pub(crate) fn with_prefix(&self, prefix: &str) -> Self {
+        let mut segments = Vec::with_capacity(self.segments.len() + 1);
+        segments.push(TemplateSegment::Static(prefix.to_string()));
+        segments.extend(self.segments.iter().cloned());
+        Self {
+            pattern: format!("{prefix}{}", self.pattern).into(),
+            segments,
+        }
+    }
+
+    /// Generates a path with the specified parameters, the values are
+    /// percent-encoded and must match the regexes of the pattern.
+    pub(crate) fn format(&self, params: &[(String, String)]) -> Result<String, UrlForError> {
+        let get_param = |name: &str| {
+            params
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+                .ok_or_else(|| UrlForError::MissingParam(name.to_string()))
+        };
+
+        let mut path = String::new();
+        for segment in &self.segments {
+            match segment {
+                TemplateSegment::Static(value) => path.push_str(value),
+                TemplateSegment::Param(name, re) => {
+                    let value = get_param(name)?;
+                    let encoded =
+                        percent_encoding::utf8_percent_encode(value, PATH_SEGMENT).to_string();
+                    if let Some(re) = re {
+                        if !re.is_match(encoded.as_bytes()) {
+                            return Err(UrlForError::InvalidParam {
+                                name: name.clone(),
+                                value: value.to_string(),
+                            });
+                        }
+                    }
+                    path.push_str(&encoded);
+                }
+                TemplateSegment::Typed(name, matcher) => {
+                    let value = get_param(name)?;
+                    if !matcher(value) {
+                        return Err(UrlForError::InvalidParam {
+                            name: name.clone(),
+                            value: value.to_string(),
+                        });
+                    }
+                    path.extend(percent_encoding::utf8_percent_encode(value, PATH_SEGMENT));
+                }
+                TemplateSegment::CatchAll(name) => {
+                    let value = get_param(name)?;
+                    for (idx, part) in value.split('/').enumerate() {
+                        if idx > 0 {
+                            path.push('/');
+                        }
+                        path.extend(percent_encoding::utf8_percent_encode(part, PATH_SEGMENT));
+                    }
+                }
+                TemplateSegment::Unnamed => {
+                    return Err(UrlForError::UnnamedSegment(self.pattern.to_string()));
+                }
+            }
+        }
+
+        if let Some((name, _)) = params.iter().find(|(name, _)| {
+            !self.segments.iter().any(|segment| match segment {
+                TemplateSegment::Param(key, _)
+                | TemplateSegment::Typed(key, _)
+                | TemplateSegment::CatchAll(key) => key == name,
+                _ => false,
+            })
+        }) {
+            return Err(UrlForError::UnknownParam(name.clone()));
+        }
+
+        Ok(path)
+    }
+}
+
 pub(crate) type PathParams = Vec<(String, String)>;
 
 #[derive(Debug, Eq, PartialEq)]
@@ -450,6 +714,7 @@ pub(crate) struct Matches<'a, T> {
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) struct RadixTree<T> {
     root: Node<T>,
+    matchers: PathMatchers,
 }
 
 impl<T> Default for RadixTree<T> {
@@ -466,11 +731,26 @@ impl<T> Default for RadixTree<T> {
                 regex_children: vec![],
                 data: None,
             },
+            matchers: PathMatchers::default(),
         }
     }
 }
 
 impl<T> RadixTree<T> {
+    /// Registers a matcher, which is used by the paths added after it.
+    pub(crate) fn add_matcher(&mut self, name: String, matcher: MatcherFn) {
+        self.matchers.0.insert(name, matcher);
+    }
+
+    /// Returns `true` if a path added before used `name` as a regex.
+    pub(crate) fn uses_regex(&self, name: &str) -> bool {
+        self.root.uses_regex(name)
+    }
+
+    pub(crate) fn matchers(&self) -> &PathMatchers {
+        &self.matchers
+    }
+
     pub(crate) fn add(&mut self, path: &str, data: T) -> Result<(), RouteError> {
         let raw_segments = match parse_path_segments(path.as_bytes()) {
             Ok(raw_segments) => raw_segments,
@@ -484,7 +764,7 @@ impl<T> RadixTree<T> {
                 RawSegment::Param(name) => Segment::Param(name),
                 RawSegment::CatchAll(name) => Segment::CatchAll(name),
                 RawSegment::Regex(name, re_bytes) => {
-                    if let Some(re) = PathRegex::new(re_bytes) {
+                    if let Some(re) = PathRegex::new(re_bytes, &self.matchers) {
                         Segment::Regex(name, re)
                     } else {
                         return Err(RouteError::InvalidRegex {
@@ -656,7 +936,8 @@ mod tests {
                     catch_all_child: None,
                     regex_children: vec![],
                     data: None,
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         );
     }
@@ -748,7 +1029,8 @@ mod tests {
                     catch_all_child: None,
                     regex_children: vec![],
                     data: None
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         );
     }
@@ -791,7 +1073,8 @@ mod tests {
                     catch_all_child: None,
                     regex_children: vec![],
                     data: None
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         )
     }
@@ -865,7 +1148,8 @@ mod tests {
                     catch_all_child: None,
                     regex_children: vec![],
                     data: None
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         )
     }
@@ -931,7 +1215,8 @@ mod tests {
                     catch_all_child: None,
                     regex_children: vec![],
                     data: None
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         );
     }
@@ -963,7 +1248,8 @@ mod tests {
                     })),
                     regex_children: vec![],
                     data: None
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         );
     }
@@ -996,7 +1282,9 @@ mod tests {
                                 name: b"name".to_vec(),
                                 children: vec![],
                                 indices: vec![],
-                                re: Some(PathRegex::new(b"\\d+").unwrap()),
+                                re: Some(
+                                    PathRegex::new(b"\\d+", &PathMatchers::default()).unwrap()
+                                ),
                                 param_children: vec![],
                                 catch_all_child: None,
                                 regex_children: vec![],
@@ -1023,7 +1311,7 @@ mod tests {
                                 data: Some(NodeData::new(1, "/abc/<\\d+>/def"))
                             }],
                             indices: vec![b'/'],
-                            re: Some(PathRegex::new(b"\\d+").unwrap()),
+                            re: Some(PathRegex::new(b"\\d+", &PathMatchers::default()).unwrap()),
                             param_children: vec![],
                             catch_all_child: None,
                             regex_children: vec![],
@@ -1037,7 +1325,8 @@ mod tests {
                     catch_all_child: None,
                     regex_children: vec![],
                     data: None
-                }
+                },
+                matchers: PathMatchers::default(),
             }
         );
     }
@@ -1251,4 +1540,116 @@ mod tests {
         assert_eq!(matches.params[0].0, "id");
         assert_eq!(matches.params[0].1, "你好");
     }
+
+    #[test]
+    fn test_split_node_with_regex_child() {
+        let mut tree = RadixTree::default();
+        tree.add("/users/:id<\\d+>", 1).unwrap();
+        tree.add("/docs", 2).unwrap();
+        tree.add("/u", 3).unwrap();
+
+        let matches = tree.matches("/users/10").unwrap();
+        assert_eq!(matches.data.data, 1);
+        assert_eq!(matches.params, vec![("id".to_string(), "10".to_string())]);
+        assert!(tree.matches("/users/abc").is_none());
+        assert!(tree.matches("/10").is_none());
+        assert_eq!(tree.matches("/docs").unwrap().data.data, 2);
+        assert_eq!(tree.matches("/u").unwrap().data.data, 3);
+    }
+
+    #[test]
+    fn test_matchers() {
+        let mut tree = RadixTree::default();
+        tree.add_matcher(
+            "lang".to_string(),
+            Arc::new(|value: &str| matches!(value, "en" | "fr")),
+        );
+        tree.add("/users/:id<u64>", 1).unwrap();
+        tree.add("/users/:id<uuid>", 2).unwrap();
+        tree.add("/users/:name", 3).unwrap();
+        tree.add("/docs/:lang<lang>/:page", 4).unwrap();
+        tree.add("/docs/*path", 5).unwrap();
+        tree.add("/i8/:n<i8>", 6).unwrap();
+
+        let matches = tree.matches("/users/10").unwrap();
+        assert_eq!(matches.data.data, 1);
+        assert_eq!(matches.params, vec![("id".to_string(), "10".to_string())]);
+
+        let matches = tree
+            .matches("/users/67e55044-10b1-426f-9247-bb680e5fe0c8")
+            .unwrap();
+        assert_eq!(matches.data.data, 2);
+
+        let matches = tree.matches("/users/-10").unwrap();
+        assert_eq!(matches.data.data, 3);
+        assert_eq!(
+            matches.params,
+            vec![("name".to_string(), "-10".to_string())]
+        );
+
+        assert_eq!(tree.matches("/docs/fr/intro").unwrap().data.data, 4);
+        assert_eq!(tree.matches("/docs/de/intro").unwrap().data.data, 5);
+        assert_eq!(tree.matches("/docs/%65n/intro").unwrap().data.data, 4);
+
+        assert_eq!(tree.matches("/i8/-128").unwrap().data.data, 6);
+        assert!(tree.matches("/i8/128").is_none());
+        assert!(tree.matches("/i8/1/2").is_none());
+
+        assert!(!tree.uses_regex("\\d+"));
+        tree.add("/n/:n<\\d+>", 7).unwrap();
+        assert!(tree.uses_regex("\\d+"));
+        assert!(!tree.uses_regex("lang"));
+        assert!(!tree.uses_regex("u64"));
+    }
+
+    #[test]
+    fn test_path_template() {
+        let mut matchers = PathMatchers::default();
+        matchers.0.insert(
+            "lang".to_string(),
+            Arc::new(|value: &str| matches!(value, "en" | "fr")),
+        );
+        let template =
+            PathTemplate::parse("/:lang<lang>/:id<u64>/:name<[a-z%0-9]+>/*rest", &matchers)
+                .unwrap();
+        let params = |values: [&str; 4]| {
+            ["lang", "id", "name", "rest"]
+                .into_iter()
+                .zip(values)
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            template.format(&params(["en", "1", "a b", "c d/e?"])),
+            Ok("/en/1/a%20b/c%20d/e%3F".to_string())
+        );
+        assert_eq!(
+            template.format(&params(["de", "1", "a", "b"])),
+            Err(UrlForError::InvalidParam {
+                name: "lang".to_string(),
+                value: "de".to_string()
+            })
+        );
+        assert_eq!(
+            template.format(&params(["en", "-1", "a", "b"])),
+            Err(UrlForError::InvalidParam {
+                name: "id".to_string(),
+                value: "-1".to_string()
+            })
+        );
+        assert_eq!(
+            template.format(&params(["en", "1", "A", "b"])),
+            Err(UrlForError::InvalidParam {
+                name: "name".to_string(),
+                value: "A".to_string()
+            })
+        );
+        assert_eq!(
+            template
+                .with_prefix("/api")
+                .format(&params(["fr", "2", "a", "b"])),
+            Ok("/api/fr/2/a/b".to_string())
+        );
+    }
 }
@@ -6,8 +6,8 @@ mod router_domain;
 mod router_method;
 mod router_scheme;
 
-pub(crate) use internal::radix_tree::PathParams;
-pub use router::{PathPattern, Route};
+pub(crate) use internal::radix_tree::{PathParams, PathTemplate};
+pub use router::{PathPattern, Route, RouteInfo};
 #[allow(unreachable_pub)]
 pub use router_domain::RouteDomain;
 #[allow(unreachable_pub)]
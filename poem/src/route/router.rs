@@ -1,18 +1,26 @@
-use std::{str::FromStr, sync::Arc};
+use std::{any::Any, collections::HashMap, str::FromStr, sync::Arc};
 
 use regex::Regex;
 
 use crate::{
-    Endpoint, EndpointExt, IntoEndpoint, IntoResponse, Request, Response, Result,
+    Endpoint, EndpointExt, IntoEndpoint, IntoResponse, Request, Response, Result, RouteMethod,
     endpoint::BoxEndpoint,
     error::{NotFoundError, ParsePathError, RouteError},
-    http::{Uri, uri::PathAndQuery},
-    route::{check_result, internal::radix_tree::RadixTree},
+    http::{Method, Uri, uri::PathAndQuery},
+    route::{
+        check_result,
+        internal::radix_tree::{PathTemplate, RadixTree},
+    },
+    web::UrlFor,
 };
 
 #[derive(Debug, Clone, Copy)]
 struct PathPrefix(usize);
 
+/// The prefix stripped by the nests that the request went through.
+#[derive(Debug, Clone)]
+struct NestPrefix(Arc<str>);
+
 /// Routing object
 ///
 /// You can match the full path or wildcard path, and use the
@@ -56,7 +64,9 @@ struct PathPrefix(usize);
 ///     // match regex
 ///     .at("/d/<\\d+>", get(a))
 ///     // capture with regex
-///     .at("/e/:name<\\d+>", get(a));
+///     .at("/e/:name<\\d+>", get(a))
+///     // capture with a typed constraint, see `Route::matcher`
+///     .at("/f/:id<u64>", get(a));
 ///
 /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
 /// let cli = TestClient::new(app);
@@ -75,6 +85,13 @@ struct PathPrefix(usize);
 ///
 /// // /e/:name<\\d>
 /// cli.get("/e/123").send().await.assert_status_is_ok();
+///
+/// // /f/:id<u64>
+/// cli.get("/f/123").send().await.assert_status_is_ok();
+/// cli.get("/f/abc")
+///     .send()
+///     .await
+///     .assert_status(StatusCode::NOT_FOUND);
 /// # });
 /// ```
 ///
@@ -125,9 +142,63 @@ struct PathPrefix(usize);
 /// resp.assert_text("hello").await;
 /// # });
 /// ```
+///
+/// # Named routes
+///
+/// ```
+/// use poem::{Route, get, handler, web::UrlFor};
+///
+/// #[handler]
+/// fn user() {}
+///
+/// #[handler]
+/// fn index(url_for: UrlFor) -> String {
+///     url_for.url("user", [("id", "10")]).unwrap()
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .at_named("user", "/users/:id<\\d+>", get(user));
+///
+/// let routes = app
+///     .routes()
+///     .map(|route| route.pattern())
+///     .collect::<Vec<_>>();
+/// assert_eq!(routes, vec!["/", "/users/:id<\\d+>"]);
+/// ```
 #[derive(Default)]
 pub struct Route {
     tree: RadixTree<BoxEndpoint<'static>>,
+    routes: Vec<RouteInfo>,
+    names: Arc<HashMap<String, PathTemplate>>,
+}
+
+/// Information about a route, returned by [`Route::routes`].
+#[derive(Debug, Clone)]
+pub struct RouteInfo {
+    pattern: String,
+    methods: Vec<Method>,
+    name: Option<String>,
+}
+
+impl RouteInfo {
+    /// Returns the path pattern of the route.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns the methods handled by the route.
+    ///
+    /// It is empty if the endpoint is not a [`RouteMethod`], for example a
+    /// handler which accepts all methods.
+    pub fn methods(&self) -> &[Method] {
+        &self.methods
+    }
+
+    /// Returns the name of the route.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
 }
 
 impl Route {
@@ -151,16 +222,158 @@ impl Route {
     }
 
     /// Attempts to add an [Endpoint] to the specified path.
-    pub fn try_at<E>(mut self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
+    pub fn try_at<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.internal_at(None, &normalize_path(path.as_ref()), ep)
+    }
+
+    /// Add an [Endpoint] to the specified path with a name, which can be used
+    /// to generate URLs with the [`UrlFor`] extractor.
+    ///
+    /// # Panics
+    ///
+    /// Panic when there are duplicates in the routing table, or the name is
+    /// already used.
+    #[must_use]
+    pub fn at_named<E>(self, name: impl Into<String>, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        self.tree
-            .add(&normalize_path(path.as_ref()), ep.map_to_response().boxed())?;
+        check_result(self.try_at_named(name, path, ep))
+    }
+
+    /// Attempts to add an [Endpoint] to the specified path with a name.
+    ///
+    /// # Panics
+    ///
+    /// Panic when the name is already used.
+    pub fn try_at_named<E>(
+        self,
+        name: impl Into<String>,
+        path: impl AsRef<str>,
+        ep: E,
+    ) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.internal_at(Some(name.into()), &normalize_path(path.as_ref()), ep)
+    }
+
+    fn internal_at<E>(mut self, name: Option<String>, path: &str, ep: E) -> Result<Self, RouteError>
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        let ep = ep.into_endpoint();
+        let methods = (&ep as &dyn Any)
+            .downcast_ref::<RouteMethod>()
+            .map(RouteMethod::methods)
+            .unwrap_or_default();
+
+        if let Some(name) = &name {
+            let template = PathTemplate::parse(path, self.tree.matchers())?;
+            self.add_name(name.clone(), template);
+        }
+        self.tree.add(path, ep.map_to_response().boxed())?;
+        self.routes.push(RouteInfo {
+            pattern: path.to_string(),
+            methods,
+            name,
+        });
         Ok(self)
     }
 
+    fn add_name(&mut self, name: String, template: PathTemplate) {
+        assert!(
+            !self.names.contains_key(&name),
+            "duplicate route name: {name}"
+        );
+        Arc::make_mut(&mut self.names).insert(name, template);
+    }
+
+    /// Registers a matcher for the path parameters, which can be used by the
+    /// paths added after it with `:name<matcher>`.
+    ///
+    /// If a value does not match, the router tries the other routes, so a
+    /// route with a typed parameter can be used beside a route with an
+    /// untyped parameter. The built-in matchers are the integer types such as
+    /// `u64` and `i32`, and `uuid` for the hyphenated UUIDs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{Route, get, handler, test::TestClient, web::Path};
+    ///
+    /// #[handler]
+    /// fn user(Path(id): Path<u64>) -> String {
+    ///     format!("user {id}")
+    /// }
+    ///
+    /// #[handler]
+    /// fn docs(Path(lang): Path<String>) -> String {
+    ///     format!("docs {lang}")
+    /// }
+    ///
+    /// #[handler]
+    /// fn page(Path(name): Path<String>) -> String {
+    ///     format!("page {name}")
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .matcher("lang", |value| matches!(value, "en" | "fr"))
+    ///     .at("/users/:id<u64>", get(user))
+    ///     .at("/:lang<lang>", get(docs))
+    ///     .at("/:name", get(page));
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let cli = TestClient::new(app);
+    /// cli.get("/users/1").send().await.assert_text("user 1").await;
+    /// cli.get("/users/a")
+    ///     .send()
+    ///     .await
+    ///     .assert_status(poem::http::StatusCode::NOT_FOUND);
+    /// cli.get("/fr").send().await.assert_text("docs fr").await;
+    /// cli.get("/about")
+    ///     .send()
+    ///     .await
+    ///     .assert_text("page about")
+    ///     .await;
+    /// # });
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panic when a route added before already used the name as a regex.
+    #[must_use]
+    pub fn matcher(
+        mut self,
+        name: impl Into<String>,
+        matcher: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        let name = name.into();
+        assert!(
+            !self.tree.uses_regex(&name),
+            "the matcher `{name}` must be registered before the routes which use it"
+        );
+        self.tree.add_matcher(name, Arc::new(matcher));
+        self
+    }
+
+    /// Returns an iterator over the routes in the order they were added,
+    /// including the routes of the nested [`Route`] objects.
+    ///
+    /// The endpoints nested with [`Route::nest`] which are not a [`Route`],
+    /// for example a `Route` wrapped by a middleware, are returned as a
+    /// single route with a `*` wildcard, such as `/static/*`.
+    pub fn routes(&self) -> impl Iterator<Item = &RouteInfo> {
+        self.routes.iter()
+    }
+
     /// Add an [Endpoint] to the `/` path.
     ///
     /// Same as `self.at("/", ep)`.
@@ -179,7 +392,8 @@ impl Route {
     ///
     /// # Panics
     ///
-    /// Panic when there are duplicates in the routing table.
+    /// Panic when there are duplicates in the routing table, or a route
+    /// name of the nested [`Route`] is already used.
     #[must_use]
     pub fn nest<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
@@ -191,6 +405,10 @@ impl Route {
 
     /// Attempts to nest a `Endpoint` to the specified path and strip the
     /// prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panic when a route name of the nested [`Route`] is already used.
     pub fn try_nest<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
@@ -203,7 +421,8 @@ impl Route {
     ///
     /// # Panics
     ///
-    /// Panic when there are duplicates in the routing table.
+    /// Panic when there are duplicates in the routing table, or a route
+    /// name of the nested [`Route`] is already used.
     #[must_use]
     pub fn nest_no_strip<E>(self, path: impl AsRef<str>, ep: E) -> Self
     where
@@ -215,6 +434,10 @@ impl Route {
 
     /// Attempts to nest a `Endpoint` to the specified path, but do not strip
     /// the prefix.
+    ///
+    /// # Panics
+    ///
+    /// Panic when a route name of the nested [`Route`] is already used.
     pub fn try_nest_no_strip<E>(self, path: impl AsRef<str>, ep: E) -> Result<Self, RouteError>
     where
         E: IntoEndpoint,
@@ -228,15 +451,41 @@ impl Route {
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        let ep = Arc::new(ep.into_endpoint());
+        let ep = ep.into_endpoint();
         let mut path = path.to_string();
         if !path.ends_with('/') {
             path.push('/');
         }
 
+        match (&ep as &dyn Any).downcast_ref::<Route>() {
+            Some(route) => {
+                let prefix = match strip {
+                    false => "",
+                    true => &path[..path.len() - 1],
+                };
+                for info in &route.routes {
+                    let pattern = format!("{prefix}{}", info.pattern);
+                    if let Some(name) = &info.name {
+                        self.add_name(name.clone(), route.names[name].with_prefix(prefix));
+                    }
+                    self.routes.push(RouteInfo {
+                        pattern,
+                        ..info.clone()
+                    });
+                }
+            }
+            None => self.routes.push(RouteInfo {
+                pattern: format!("{path}*"),
+                methods: Vec::new(),
+                name: None,
+            }),
+        }
+        let ep = Arc::new(ep);
+
         struct Nest<T> {
             inner: T,
             root: bool,
+            prefix: Arc<str>,
             prefix_len: usize,
             prefix_for_path_pattern: usize,
         }
@@ -269,6 +518,13 @@ impl Route {
                 *req.uri_mut() = new_uri;
 
                 req.set_data(PathPrefix(self.prefix_for_path_pattern));
+                if !self.prefix.is_empty() {
+                    let prefix = match req.data::<NestPrefix>() {
+                        Some(parent) => format!("{}{}", parent.0, self.prefix).into(),
+                        None => self.prefix.clone(),
+                    };
+                    req.set_data(NestPrefix(prefix));
+                }
                 Ok(self.inner.call(req).await?.into_response())
             }
         }
@@ -294,12 +550,14 @@ impl Route {
             false => path.len() - 1,
             true => 0,
         };
+        let prefix: Arc<str> = path[..prefix_len].into();
 
         self.tree.add(
             &format!("{path}*--poem-rest"),
             Nest {
                 inner: ep.clone(),
                 root: false,
+                prefix: prefix.clone(),
                 prefix_len,
                 prefix_for_path_pattern,
             }
@@ -311,6 +569,7 @@ impl Route {
             Nest {
                 inner: ep,
                 root: true,
+                prefix,
                 prefix_len,
                 prefix_for_path_pattern,
             }
@@ -333,6 +592,21 @@ impl Endpoint for Route {
             Some(matches) => {
                 req.state_mut().match_params.extend(matches.params);
 
+                // The nested routes which are not known by the outer routes, for
+                // example because they are wrapped by a middleware, add their names
+                // with the prefix of the nests.
+                if !self.names.is_empty() {
+                    let prefix = req
+                        .data::<NestPrefix>()
+                        .map(|prefix| prefix.0.clone())
+                        .unwrap_or_else(|| "".into());
+                    let url_for = match req.data::<UrlFor>() {
+                        Some(url_for) => url_for.with_names(prefix, self.names.clone()),
+                        None => UrlFor::new(prefix, self.names.clone()),
+                    };
+                    req.set_data(url_for);
+                }
+
                 let pattern = match matches.data.pattern.strip_suffix("/*--poem-rest") {
                     Some(pattern) => pattern.into(),
                     None => matches.data.pattern.clone(),
@@ -387,7 +661,7 @@ mod tests {
     use http::StatusCode;
 
     use super::*;
-    use crate::{Error, endpoint::make_sync, handler, test::TestClient};
+    use crate::{Error, endpoint::make_sync, handler, test::TestClient, web::Path};
 
     #[test]
     fn test_normalize_path() {
@@ -524,6 +798,14 @@ mod tests {
         let _ = Route::new().at("/a/*:v", h).at("/a/*", h);
     }
 
+    #[test]
+    #[should_panic]
+    fn matcher_after_use() {
+        let _ = Route::new()
+            .at("/:lang<lang>", h)
+            .matcher("lang", |value| value == "en");
+    }
+
     #[tokio::test]
     async fn issue_174() {
         let app = Route::new().nest("/", make_sync(|_| "hello"));
@@ -781,4 +1063,242 @@ mod tests {
             "/nest_no_strip1/nest_no_strip2/:id"
         );
     }
+
+    #[test]
+    fn routes() {
+        let app = Route::new()
+            .at("/", make_sync(|_| ()))
+            .at_named(
+                "user",
+                "/users/:id",
+                crate::get(make_sync(|_| ())).post(make_sync(|_| ())),
+            )
+            .nest(
+                "/api",
+                Route::new()
+                    .at("/", crate::get(make_sync(|_| ())))
+                    .at_named("item", "/items/:id", crate::delete(make_sync(|_| ()))),
+            )
+            .nest_no_strip("/v2", Route::new().at("/v2/items", make_sync(|_| ())))
+            .nest("/static", make_sync(|_| ()));
+
+        let routes = app
+            .routes()
+            .map(|route| (route.pattern(), route.methods(), route.name()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            routes,
+            vec![
+                ("/", &[][..], None),
+                ("/users/:id", &[Method::GET, Method::POST][..], Some("user")),
+                ("/api/", &[Method::GET][..], None),
+                ("/api/items/:id", &[Method::DELETE][..], Some("item")),
+                ("/v2/items", &[][..], None),
+                ("/static/*", &[][..], None),
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_name_1() {
+        let _ = Route::new()
+            .at_named("a", "/a", make_sync(|_| ()))
+            .at_named("a", "/b", make_sync(|_| ()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn duplicate_name_2() {
+        let _ = Route::new()
+            .at_named("a", "/a", make_sync(|_| ()))
+            .nest("/b", Route::new().at_named("a", "/c", make_sync(|_| ())));
+    }
+
+    #[tokio::test]
+    async fn url_for() {
+        #[handler(internal)]
+        fn index(url_for: UrlFor) -> String {
+            let urls = [
+                url_for.url("user", [("id", "1")]),
+                url_for.url("item", [("id", "a/b c")]),
+                url_for.url("file", [("path", "a b/c")]),
+                url_for.url("user", [("id", "abc")]),
+                url_for.url("user", Vec::<(&str, &str)>::new()),
+                url_for.url("user", [("id", "1"), ("name", "a")]),
+                url_for.url("unnamed", Vec::<(&str, &str)>::new()),
+                url_for.url("unknown", Vec::<(&str, &str)>::new()),
+            ];
+            urls.into_iter()
+                .map(|url| url.unwrap_or_else(|err| err.to_string()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+
+        let app = Route::new()
+            .at("/", index)
+            .at_named("user", "/users/:id<\\d+>", make_sync(|_| ()))
+            .at_named("unnamed", "/a/<\\d+>", make_sync(|_| ()))
+            .nest(
+                "/api",
+                Route::new()
+                    .at_named("item", "/items/:id", make_sync(|_| ()))
+                    .at("/index", index),
+            )
+            .nest_no_strip(
+                "/files",
+                Route::new().at_named("file", "/files/*path", make_sync(|_| ())),
+            );
+        let cli = TestClient::new(app);
+
+        let expected = [
+            "/users/1",
+            "/api/items/a%2Fb%20c",
+            "/files/a%20b/c",
+            "invalid value for path parameter `id`: abc",
+            "missing path parameter: id",
+            "unknown path parameter: name",
+            "the route contains an unnamed segment: /a/<\\d+>",
+            "unknown route: unknown",
+        ]
+        .join("\n");
+        cli.get("/").send().await.assert_text(&expected).await;
+        // the outermost route generates the urls of the nested routes
+        cli.get("/api/index")
+            .send()
+            .await
+            .assert_text(&expected)
+            .await;
+
+        // without named routes
+        let cli = TestClient::new(Route::new().at("/", index));
+        cli.get("/")
+            .send()
+            .await
+            .assert_text(
+                [
+                    "unknown route: user",
+                    "unknown route: item",
+                    "unknown route: file",
+                    "unknown route: user",
+                    "unknown route: user",
+                    "unknown route: user",
+                    "unknown route: unnamed",
+                    "unknown route: unknown",
+                ]
+                .join("\n"),
+            )
+            .await;
+    }
+
+    #[tokio::test]
+    async fn url_for_wrapped_nest() {
+        #[handler(internal)]
+        fn index(url_for: UrlFor) -> String {
+            [
+                url_for.url("item", [("id", "1")]),
+                url_for.url("user", [("id", "2")]),
+            ]
+            .into_iter()
+            .map(|url| url.unwrap_or_else(|err| err.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n")
+        }
+
+        let items = || {
+            Route::new()
+                .at_named("item", "/items/:id", make_sync(|_| ()))
+                .at("/index", index)
+                .data(1)
+        };
+        let app = Route::new()
+            .at_named("home", "/", index)
+            .nest("/api", items())
+            .nest(
+                "/v1",
+                Route::new().nest(
+                    "/admin",
+                    Route::new()
+                        .at_named("user", "/users/:id", make_sync(|_| ()))
+                        .at("/index", index)
+                        .data(2),
+                ),
+            );
+        let cli = TestClient::new(app);
+
+        // the names of the wrapped routes are only known inside of them
+        cli.get("/")
+            .send()
+            .await
+            .assert_text("unknown route: item\nunknown route: user")
+            .await;
+        cli.get("/api/index")
+            .send()
+            .await
+            .assert_text("/api/items/1\nunknown route: user")
+            .await;
+        cli.get("/v1/admin/index")
+            .send()
+            .await
+            .assert_text("unknown route: item\n/v1/admin/users/2")
+            .await;
+
+        // the outer route has no names
+        let cli = TestClient::new(Route::new().nest("/api", items()));
+        cli.get("/api/index")
+            .send()
+            .await
+            .assert_text("/api/items/1\nunknown route: user")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn typed_params() {
+        #[handler(internal)]
+        fn item(Path((lang, id)): Path<(String, u64)>) -> String {
+            format!("item {lang} {id}")
+        }
+
+        #[handler(internal)]
+        fn index(url_for: UrlFor) -> String {
+            [
+                url_for.url("page", [("lang", "en"), ("id", "1")]),
+                url_for.url("page", [("lang", "de"), ("id", "1")]),
+            ]
+            .into_iter()
+            .map(|url| url.unwrap_or_else(|err| err.to_string()))
+            .collect::<Vec<_>>()
+            .join("\n")
+        }
+
+        let app = Route::new().at("/", index).nest(
+            "/docs",
+            Route::new()
+                .matcher("lang", |value| matches!(value, "en" | "fr"))
+                .at_named("page", "/:lang<lang>/:id<u64>", item)
+                .at("/:lang/:id", make_sync(|_| "fallback")),
+        );
+        let cli = TestClient::new(app);
+
+        cli.get("/")
+            .send()
+            .await
+            .assert_text("/docs/en/1\ninvalid value for path parameter `lang`: de")
+            .await;
+        cli.get("/docs/fr/10")
+            .send()
+            .await
+            .assert_text("item fr 10")
+            .await;
+        cli.get("/docs/de/10")
+            .send()
+            .await
+            .assert_text("fallback")
+            .await;
+        cli.get("/docs/fr/abc")
+            .send()
+            .await
+            .assert_text("fallback")
+            .await;
+    }
 }
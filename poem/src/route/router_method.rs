@@ -75,6 +75,13 @@ impl RouteMethod {
         self
     }
 
+    pub(crate) fn methods(&self) -> Vec<Method> {
+        self.methods
+            .iter()
+            .map(|(method, _)| method.clone())
+            .collect()
+    }
+
     /// Sets the endpoint for `GET`.
     #[must_use]
     pub fn get<E>(self, ep: E) -> Self
@@ -1,8 +1,10 @@
 use std::{
+    collections::HashMap,
     convert::Infallible,
     future::Future,
     io,
     io::IoSlice,
+    net::IpAddr,
     panic::AssertUnwindSafe,
     pin::Pin,
     sync::{
@@ -13,17 +15,22 @@ use std::{
 };
 
 use futures_util::FutureExt;
-use http::uri::Scheme;
+#[cfg(feature = "http3")]
+use futures_util::future::BoxFuture;
+use http::{Extensions, HeaderValue, header, uri::Scheme};
 use hyper::body::Incoming;
 use hyper_util::server::conn::auto;
+use parking_lot::Mutex;
 use pin_project_lite::pin_project;
 use tokio::{
     io::{AsyncRead, AsyncWrite, ReadBuf, Result as IoResult},
-    sync::{Notify, oneshot},
+    sync::{Notify, OwnedSemaphorePermit, Semaphore, oneshot},
     time::Duration,
 };
 use tokio_util::sync::CancellationToken;
 
+#[cfg(feature = "http3")]
+use crate::listener::{IntoTlsConfigStream, QuicAcceptor, QuicListener, RustlsConfig};
 use crate::{
     Endpoint, EndpointExt, IntoEndpoint, Response,
     endpoint::{DynEndpoint, ToDynEndpoint},
@@ -45,6 +52,12 @@ pub struct Server<L, A> {
     http2_max_concurrent_streams: Option<u32>,
     http2_max_pending_accept_reset_streams: Option<u32>,
     http2_max_header_list_size: u32,
+    max_connections: Option<usize>,
+    max_connections_per_ip: Option<usize>,
+    shutdown_delay: Option<Duration>,
+    handle: ServerHandle,
+    #[cfg(feature = "http3")]
+    http3: Option<BoxFuture<'static, IoResult<QuicAcceptor>>>,
 }
 
 impl<L: Listener> Server<L, Infallible> {
@@ -57,6 +70,12 @@ impl<L: Listener> Server<L, Infallible> {
             http2_max_concurrent_streams: None,
             http2_max_pending_accept_reset_streams: Some(20),
             http2_max_header_list_size: 16384,
+            max_connections: None,
+            max_connections_per_ip: None,
+            shutdown_delay: None,
+            handle: ServerHandle::default(),
+            #[cfg(feature = "http3")]
+            http3: None,
         }
     }
 }
@@ -71,6 +90,12 @@ impl<A: Acceptor> Server<Infallible, A> {
             http2_max_concurrent_streams: None,
             http2_max_pending_accept_reset_streams: Some(20),
             http2_max_header_list_size: 16384,
+            max_connections: None,
+            max_connections_per_ip: None,
+            shutdown_delay: None,
+            handle: ServerHandle::default(),
+            #[cfg(feature = "http3")]
+            http3: None,
         }
     }
 }
@@ -135,6 +160,106 @@ where
         }
     }
 
+    /// Also serve HTTP/3 with the specified QUIC listener.
+    ///
+    /// The responses sent by the TCP listener include an `Alt-Svc` header
+    /// advertising the port of the QUIC listener.
+    #[cfg(feature = "http3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+    #[must_use]
+    pub fn http3<T, S>(self, listener: QuicListener<T, S>) -> Self
+    where
+        T: tokio::net::ToSocketAddrs + Send + 'static,
+        S: IntoTlsConfigStream<RustlsConfig>,
+    {
+        Self {
+            http3: Some(listener.into_acceptor().boxed()),
+            ..self
+        }
+    }
+
+    /// Also serve HTTP/3 with the specified QUIC acceptor.
+    ///
+    /// See also [`Server::http3`].
+    #[cfg(feature = "http3")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "http3")))]
+    #[must_use]
+    pub fn http3_with_acceptor(self, acceptor: QuicAcceptor) -> Self {
+        Self {
+            http3: Some(futures_util::future::ready(Ok(acceptor)).boxed()),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of concurrent connections.
+    ///
+    /// When the limit is reached, the server stops accepting new connections
+    /// until an existing connection is closed.
+    #[must_use]
+    pub fn max_connections(self, max: usize) -> Self {
+        Self {
+            max_connections: Some(max),
+            ..self
+        }
+    }
+
+    /// Sets the maximum number of concurrent connections from the same remote
+    /// IP address.
+    ///
+    /// The connections exceeding the limit are closed immediately.
+    #[must_use]
+    pub fn max_connections_per_ip(self, max: usize) -> Self {
+        Self {
+            max_connections_per_ip: Some(max),
+            ..self
+        }
+    }
+
+    /// Sets how long the server keeps accepting new connections after graceful
+    /// shutdown is initiated, default is `None`.
+    ///
+    /// During this delay, [`ServerHandle::is_shutting_down`] returns `true`,
+    /// so that a readiness probe such as [`Health`](crate::endpoint::Health)
+    /// reports that the server is not ready, and the load balancer stops
+    /// routing traffic to it before the server stops accepting connections.
+    #[must_use]
+    pub fn shutdown_delay(self, delay: Duration) -> Self {
+        Self {
+            shutdown_delay: Some(delay),
+            ..self
+        }
+    }
+
+    /// Returns a handle to this server, which can be used to inspect the alive
+    /// connections and to initiate graceful shutdown.
+    ///
+    /// The handle is also added to the extensions of every request, it can be
+    /// extracted with [`Data<&ServerHandle>`](crate::web::Data).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use poem::{Route, Server, listener::TcpListener};
+    ///
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let server = Server::new(TcpListener::bind("0.0.0.0:3000"));
+    /// let handle = server.handle();
+    ///
+    /// tokio::spawn(async move {
+    ///     tokio::time::sleep(Duration::from_secs(60)).await;
+    ///     println!("alive connections: {}", handle.alive_connections());
+    ///     handle.shutdown();
+    /// });
+    ///
+    /// server.run(Route::new()).await
+    /// # });
+    /// ```
+    pub fn handle(&self) -> ServerHandle {
+        self.handle.clone()
+    }
+
     /// Run this server.
     pub async fn run<E>(self, ep: E) -> IoResult<()>
     where
@@ -146,6 +271,8 @@ where
     }
 
     /// Run this server and a signal to initiate graceful shutdown.
+    ///
+    /// Graceful shutdown is also initiated by [`ServerHandle::shutdown`].
     pub async fn run_with_graceful_shutdown<E>(
         self,
         ep: E,
@@ -164,105 +291,155 @@ where
             http2_max_concurrent_streams,
             http2_max_pending_accept_reset_streams,
             http2_max_header_list_size,
+            max_connections,
+            max_connections_per_ip,
+            shutdown_delay,
+            handle,
+            #[cfg(feature = "http3")]
+            http3,
         } = self;
         let name = name.as_deref();
-        let alive_connections = Arc::new(AtomicUsize::new(0));
-        let notify = Arc::new(Notify::new());
-        let timeout_token = CancellationToken::new();
-        let server_graceful_shutdown_token = CancellationToken::new();
+        let tracker = ConnectionTracker {
+            alive_tasks: Arc::new(AtomicUsize::new(0)),
+            notify: Arc::new(Notify::new()),
+            timeout_token: timeout.map(|_| CancellationToken::new()),
+            server_graceful_shutdown_token: handle.0.shutdown_token.clone(),
+        };
+        let admission = Admission {
+            semaphore: max_connections.map(|max| Arc::new(Semaphore::new(max))),
+            max_connections_per_ip,
+            handle: handle.clone(),
+        };
 
         let mut acceptor = match listener {
             Either::Listener(listener) => listener.into_acceptor().await?.boxed(),
             Either::Acceptor(acceptor) => acceptor.boxed(),
         };
 
+        #[cfg(feature = "http3")]
+        let http3_acceptor = match http3 {
+            Some(acceptor) => Some(acceptor.await?),
+            None => None,
+        };
+        #[cfg(feature = "http3")]
+        let alt_svc = http3_acceptor.as_ref().and_then(QuicAcceptor::alt_svc);
+        #[cfg(not(feature = "http3"))]
+        let alt_svc = None;
+
         tokio::pin!(signal);
 
         for addr in acceptor.local_addr() {
             tracing::info!(name = name, addr = %addr, "listening");
         }
+        #[cfg(feature = "http3")]
+        if let Some(http3_acceptor) = http3_acceptor {
+            tracing::info!(name = name, addr = %http3_acceptor.local_addr(), "listening (http3)");
+            // The accept loop is tracked like a connection, so that the server
+            // does not stop before it does.
+            tracker.spawn(run_http3(
+                http3_acceptor,
+                ep.clone(),
+                tracker.clone(),
+                admission.clone(),
+            ));
+        }
         tracing::info!(name = name, "server started");
 
+        let mut accept_delay = None;
+        let mut drain_deadline = None;
+        let mut signal_fired = false;
         loop {
+            let accept = async {
+                let permit = admission.acquire().await;
+                if let Some(delay) = accept_delay {
+                    tokio::time::sleep(delay).await;
+                }
+                (acceptor.accept_with_extensions().await, permit)
+            };
+
+            let drain = async {
+                match drain_deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => futures_util::future::pending().await,
+                }
+            };
+
             tokio::select! {
-                _ = &mut signal => {
-                    server_graceful_shutdown_token.cancel();
-                    if let Some(timeout) = timeout {
-                        tracing::info!(
-                            name = name,
-                            timeout_in_seconds = timeout.as_secs_f32(),
-                            "initiate graceful shutdown",
-                        );
-
-                        let timeout_token = timeout_token.clone();
-                        tokio::spawn(async move {
-                            tokio::time::sleep(timeout).await;
-                            timeout_token.cancel();
-                        });
-                    } else {
-                        tracing::info!(name = name, "initiate graceful shutdown");
+                // The signal future must not be polled again once it is completed.
+                _ = &mut signal, if !signal_fired => {
+                    signal_fired = true;
+                    handle.shutdown();
+                }
+                _ = handle.0.shutdown_requested.cancelled(), if drain_deadline.is_none() => match shutdown_delay {
+                    Some(delay) => {
+                        tracing::info!(name = name, delay_in_seconds = delay.as_secs_f32(), "graceful shutdown requested, delaying");
+                        drain_deadline = Some(tokio::time::Instant::now() + delay);
                     }
-                    break;
+                    None => break,
                 },
-                res = acceptor.accept() => {
-                    if let Ok((socket, local_addr, remote_addr, scheme)) = res {
-                        alive_connections.fetch_add(1, Ordering::Release);
-
-                        let ep = ep.clone();
-                        let alive_connections = alive_connections.clone();
-                        let notify = notify.clone();
-                        let timeout_token = timeout_token.clone();
-                        let server_graceful_shutdown_token = server_graceful_shutdown_token.clone();
-                        let server_graceful_shutdown_token_clone = server_graceful_shutdown_token.clone();
-
-                        let spawn_fut = AssertUnwindSafe(async move {
-                            let serve_connection = serve_connection(ConnectionOptions{
-                                socket,
-                                local_addr,
-                                remote_addr,
-                                scheme,
-                                ep,
-                                server_graceful_shutdown_token: server_graceful_shutdown_token.clone(),
-                                idle_connection_close_timeout: idle_timeout,
-                                http2_max_concurrent_streams,
-                                http2_max_pending_accept_reset_streams,
-                                http2_max_header_list_size,
-                            });
-
-                            if timeout.is_some() {
-                                tokio::select! {
-                                    _ = serve_connection => {}
-                                    _ = timeout_token.cancelled() => {}
-                                }
-                            } else {
-                               serve_connection.await;
-                            }
-                        });
-
-                        tokio::spawn(async move {
-                            let result = spawn_fut.catch_unwind().await;
-
-                            if alive_connections.fetch_sub(1, Ordering::Acquire) == 1 {
-                                // notify only if shutdown is initiated, to prevent notification when server is active.
-                                // It's a valid state to have 0 alive connections when server is not shutting down.
-                                if server_graceful_shutdown_token_clone.is_cancelled() {
-                                    notify.notify_one();
-                                }
-                            }
+                _ = drain => break,
+                (res, permit) = accept => match res {
+                    Ok((socket, local_addr, remote_addr, scheme, mut extensions)) => {
+                        accept_delay = None;
+                        extensions.insert(handle.clone());
+                        let Some(guard) = admission.admit(&remote_addr, permit) else {
+                            tracing::debug!(remote_addr = %remote_addr, "too many connections from the remote address");
+                            continue;
+                        };
 
-                            if let Err(err) = result {
-                                std::panic::resume_unwind(err);
-                            }
+                        let serve_connection = serve_connection(ConnectionOptions{
+                            socket,
+                            local_addr,
+                            remote_addr,
+                            scheme,
+                            extensions,
+                            ep: ep.clone(),
+                            server_graceful_shutdown_token: tracker.server_graceful_shutdown_token.clone(),
+                            idle_connection_close_timeout: idle_timeout,
+                            http2_max_concurrent_streams,
+                            http2_max_pending_accept_reset_streams,
+                            http2_max_header_list_size,
+                            alt_svc: alt_svc.clone(),
+                        });
+                        tracker.spawn(async move {
+                            serve_connection.await;
+                            drop(guard);
                         });
                     }
+                    Err(err) if is_too_many_open_files(&err) => {
+                        // Retrying immediately would spin, wait for some file
+                        // descriptors to be released.
+                        let delay = accept_delay
+                            .map(|delay: Duration| (delay * 2).min(Duration::from_secs(1)))
+                            .unwrap_or(Duration::from_millis(5));
+                        tracing::warn!(error = %err, delay_in_ms = delay.as_millis(), "failed to accept connection, backing off");
+                        accept_delay = Some(delay);
+                    }
+                    Err(err) => tracing::debug!(error = %err, "failed to accept connection"),
                 }
             }
         }
 
+        tracker.server_graceful_shutdown_token.cancel();
+        if let (Some(timeout), Some(timeout_token)) = (timeout, tracker.timeout_token.clone()) {
+            tracing::info!(
+                name = name,
+                timeout_in_seconds = timeout.as_secs_f32(),
+                "initiate graceful shutdown",
+            );
+
+            tokio::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                timeout_token.cancel();
+            });
+        } else {
+            tracing::info!(name = name, "initiate graceful shutdown");
+        }
+
         drop(acceptor);
-        if alive_connections.load(Ordering::Acquire) > 0 {
+        if tracker.alive_tasks.load(Ordering::Acquire) > 0 {
             tracing::info!(name = name, "wait for all connections to close.");
-            notify.notified().await;
+            tracker.notify.notified().await;
         }
 
         tracing::info!(name = name, "server stopped");
@@ -270,6 +447,198 @@ where
     }
 }
 
+/// A handle to a [`Server`].
+///
+/// It can be obtained with [`Server::handle`] before running the server.
+#[cfg_attr(docsrs, doc(cfg(feature = "server")))]
+#[derive(Clone, Default)]
+pub struct ServerHandle(Arc<ServerHandleInner>);
+
+#[derive(Default)]
+struct ServerHandleInner {
+    alive_connections: AtomicUsize,
+    connections_per_ip: Mutex<HashMap<IpAddr, usize>>,
+    shutdown_requested: CancellationToken,
+    shutdown_token: CancellationToken,
+}
+
+impl ServerHandle {
+    /// Returns the number of alive connections.
+    pub fn alive_connections(&self) -> usize {
+        self.0.alive_connections.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of alive connections from the specified remote IP
+    /// address.
+    pub fn alive_connections_from(&self, ip: IpAddr) -> usize {
+        self.0
+            .connections_per_ip
+            .lock()
+            .get(&ip)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Initiates graceful shutdown of the server.
+    ///
+    /// The server stops accepting new connections, after the
+    /// [`Server::shutdown_delay`] if any, and stops once all the alive
+    /// connections are closed.
+    pub fn shutdown(&self) {
+        self.0.shutdown_requested.cancel();
+    }
+
+    /// Returns `true` if graceful shutdown has been initiated.
+    pub fn is_shutting_down(&self) -> bool {
+        self.0.shutdown_requested.is_cancelled() || self.0.shutdown_token.is_cancelled()
+    }
+}
+
+/// Enforces the connection limits.
+#[derive(Clone)]
+struct Admission {
+    semaphore: Option<Arc<Semaphore>>,
+    max_connections_per_ip: Option<usize>,
+    handle: ServerHandle,
+}
+
+impl Admission {
+    /// Waits until a new connection is allowed by `max_connections`.
+    async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        match &self.semaphore {
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        }
+    }
+
+    /// Returns `Err(())` if a new connection is not allowed by
+    /// `max_connections`.
+    #[cfg(feature = "http3")]
+    fn try_acquire(&self) -> Result<Option<OwnedSemaphorePermit>, ()> {
+        match &self.semaphore {
+            Some(semaphore) => semaphore
+                .clone()
+                .try_acquire_owned()
+                .map(Some)
+                .map_err(|_| ()),
+            None => Ok(None),
+        }
+    }
+
+    /// Registers a new connection, returns `None` if it exceeds
+    /// `max_connections_per_ip`.
+    fn admit(
+        &self,
+        remote_addr: &RemoteAddr,
+        permit: Option<OwnedSemaphorePermit>,
+    ) -> Option<ConnectionGuard> {
+        let ip = remote_addr.as_socket_addr().map(|addr| addr.ip());
+        if let Some(ip) = ip {
+            let mut connections_per_ip = self.handle.0.connections_per_ip.lock();
+            // Checks the limit before inserting, so that rejected connections
+            // do not leave zero entries behind.
+            let count = connections_per_ip.get(&ip).copied().unwrap_or_default();
+            if matches!(self.max_connections_per_ip, Some(max) if count >= max) {
+                return None;
+            }
+            *connections_per_ip.entry(ip).or_default() += 1;
+        }
+
+        self.handle
+            .0
+            .alive_connections
+            .fetch_add(1, Ordering::Release);
+        Some(ConnectionGuard {
+            handle: self.handle.clone(),
+            ip,
+            _permit: permit,
+        })
+    }
+}
+
+/// Releases the connection slots when the connection is closed.
+struct ConnectionGuard {
+    handle: ServerHandle,
+    ip: Option<IpAddr>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.handle
+            .0
+            .alive_connections
+            .fetch_sub(1, Ordering::Release);
+        if let Some(ip) = self.ip {
+            let mut connections_per_ip = self.handle.0.connections_per_ip.lock();
+            if let Some(count) = connections_per_ip.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    connections_per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+fn is_too_many_open_files(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    return matches!(
+        err.raw_os_error(),
+        Some(nix::libc::EMFILE | nix::libc::ENFILE)
+    );
+    #[cfg(windows)]
+    return err.raw_os_error() == Some(windows_sys::Win32::Networking::WinSock::WSAEMFILE);
+    #[cfg(not(any(unix, windows)))]
+    return false;
+}
+
+/// Keeps track of the spawned tasks, to wait for them during graceful
+/// shutdown.
+#[derive(Clone)]
+struct ConnectionTracker {
+    alive_tasks: Arc<AtomicUsize>,
+    notify: Arc<Notify>,
+    timeout_token: Option<CancellationToken>,
+    server_graceful_shutdown_token: CancellationToken,
+}
+
+impl ConnectionTracker {
+    fn spawn(&self, fut: impl Future<Output = ()> + Send + 'static) {
+        self.alive_tasks.fetch_add(1, Ordering::Release);
+
+        let timeout_token = self.timeout_token.clone();
+        let spawn_fut = AssertUnwindSafe(async move {
+            if let Some(timeout_token) = timeout_token {
+                tokio::select! {
+                    _ = fut => {}
+                    _ = timeout_token.cancelled() => {}
+                }
+            } else {
+                fut.await;
+            }
+        });
+
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let result = spawn_fut.catch_unwind().await;
+
+            if tracker.alive_tasks.fetch_sub(1, Ordering::Acquire) == 1 {
+                // notify only if shutdown is initiated, to prevent notification when server is
+                // active. It's a valid state to have 0 alive connections when
+                // server is not shutting down.
+                if tracker.server_graceful_shutdown_token.is_cancelled() {
+                    tracker.notify.notify_one();
+                }
+            }
+
+            if let Err(err) = result {
+                std::panic::resume_unwind(err);
+            }
+        });
+    }
+}
+
 pin_project! {
     struct ClosingInactiveConnection<T> {
         #[pin]
@@ -386,12 +755,14 @@ struct ConnectionOptions<Io> {
     local_addr: LocalAddr,
     remote_addr: RemoteAddr,
     scheme: Scheme,
+    extensions: Extensions,
     ep: Arc<dyn DynEndpoint<Output = Response>>,
     server_graceful_shutdown_token: CancellationToken,
     idle_connection_close_timeout: Option<Duration>,
     http2_max_concurrent_streams: Option<u32>,
     http2_max_pending_accept_reset_streams: Option<u32>,
     http2_max_header_list_size: u32,
+    alt_svc: Option<HeaderValue>,
 }
 
 async fn serve_connection<Io>(opts: ConnectionOptions<Io>)
@@ -403,12 +774,14 @@ where
         local_addr,
         remote_addr,
         scheme,
+        extensions,
         ep,
         server_graceful_shutdown_token,
         idle_connection_close_timeout,
         http2_max_concurrent_streams,
         http2_max_pending_accept_reset_streams,
         http2_max_header_list_size,
+        alt_svc,
     } = opts;
 
     let connection_shutdown_token = CancellationToken::new();
@@ -416,17 +789,21 @@ where
     let service = hyper::service::service_fn({
         let remote_addr = remote_addr.clone();
 
-        move |req: http::Request<Incoming>| {
+        move |mut req: http::Request<Incoming>| {
+            req.extensions_mut().extend(extensions.clone());
             let ep = ep.clone();
             let local_addr = local_addr.clone();
             let remote_addr = remote_addr.clone();
             let scheme = scheme.clone();
+            let alt_svc = alt_svc.clone();
             async move {
-                Ok::<http::Response<_>, Infallible>(
-                    ep.get_response((req, local_addr, remote_addr, scheme).into())
-                        .await
-                        .into(),
-                )
+                let mut resp = ep
+                    .get_response((req, local_addr, remote_addr, scheme).into())
+                    .await;
+                if let Some(alt_svc) = alt_svc {
+                    resp.headers_mut().entry(header::ALT_SVC).or_insert(alt_svc);
+                }
+                Ok::<http::Response<_>, Infallible>(resp.into())
             }
         }
     });
@@ -476,3 +853,388 @@ where
     // requests.
     let _ = conn.await;
 }
+
+#[cfg(feature = "http3")]
+async fn run_http3(
+    mut acceptor: QuicAcceptor,
+    ep: Arc<dyn DynEndpoint<Output = Response>>,
+    tracker: ConnectionTracker,
+    admission: Admission,
+) {
+    let local_addr = acceptor.local_addr();
+
+    loop {
+        tokio::select! {
+            res = acceptor.accept() => match res {
+                Ok(incoming) => {
+                    let remote_addr = RemoteAddr(incoming.remote_address().into());
+                    let Some(guard) = admission
+                        .try_acquire()
+                        .ok()
+                        .and_then(|permit| admission.admit(&remote_addr, permit))
+                    else {
+                        tracing::debug!(remote_addr = %remote_addr, "too many connections, refusing http3 connection");
+                        incoming.refuse();
+                        continue;
+                    };
+
+                    let serve_connection = serve_http3_connection(
+                        incoming,
+                        local_addr.clone(),
+                        ep.clone(),
+                        admission.handle.clone(),
+                        tracker.clone(),
+                    );
+                    tracker.spawn(async move {
+                        serve_connection.await;
+                        drop(guard);
+                    });
+                }
+                Err(err) => {
+                    tracing::error!(error = %err, "failed to accept http3 connection");
+                    break;
+                }
+            },
+            _ = tracker.server_graceful_shutdown_token.cancelled() => break,
+        }
+    }
+}
+
+#[cfg(feature = "http3")]
+async fn serve_http3_connection(
+    incoming: quinn::Incoming,
+    local_addr: LocalAddr,
+    ep: Arc<dyn DynEndpoint<Output = Response>>,
+    handle: ServerHandle,
+    tracker: ConnectionTracker,
+) {
+    let server_graceful_shutdown_token = handle.0.shutdown_token.clone();
+    let remote_addr = RemoteAddr(incoming.remote_address().into());
+    let conn = match incoming.await {
+        Ok(conn) => h3_quinn::Connection::new(conn),
+        Err(err) => {
+            tracing::debug!(remote_addr = %remote_addr, error = %err, "quic handshake failed");
+            return;
+        }
+    };
+    let mut conn = match h3::server::Connection::<_, bytes::Bytes>::new(conn).await {
+        Ok(conn) => conn,
+        Err(err) => {
+            tracing::debug!(remote_addr = %remote_addr, error = %err, "http3 handshake failed");
+            return;
+        }
+    };
+
+    let mut shutting_down = false;
+    loop {
+        let res = tokio::select! {
+            res = conn.accept() => res,
+            _ = server_graceful_shutdown_token.cancelled(), if !shutting_down => {
+                // Reject new requests, `accept` returns `None` once the
+                // existing requests are completed.
+                shutting_down = true;
+                let _ = conn.shutdown(0).await;
+                continue;
+            }
+        };
+
+        match res {
+            Ok(Some(resolver)) => {
+                let ep = ep.clone();
+                let local_addr = local_addr.clone();
+                let remote_addr = remote_addr.clone();
+                let handle = handle.clone();
+                // The requests are tracked so that the graceful shutdown waits
+                // for them, like the connections.
+                tracker.spawn(async move {
+                    let (req, stream) = match resolver.resolve_request().await {
+                        Ok(res) => res,
+                        Err(err) => {
+                            tracing::debug!(error = %err, "failed to read http3 request");
+                            return;
+                        }
+                    };
+                    serve_http3_request(req, stream, local_addr, remote_addr, ep, handle).await;
+                });
+            }
+            Ok(None) => break,
+            Err(err) => {
+                if !err.is_h3_no_error() {
+                    tracing::debug!(remote_addr = %remote_addr, error = %err, "http3 connection error");
+                }
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "http3")]
+async fn serve_http3_request(
+    req: http::Request<()>,
+    stream: h3::server::RequestStream<h3_quinn::BidiStream<bytes::Bytes>, bytes::Bytes>,
+    local_addr: LocalAddr,
+    remote_addr: RemoteAddr,
+    ep: Arc<dyn DynEndpoint<Output = Response>>,
+    handle: ServerHandle,
+) {
+    use bytes::Buf;
+    use futures_util::StreamExt;
+
+    let (mut send_stream, recv_stream) = stream.split();
+    let body = crate::Body::from_bytes_stream(futures_util::stream::unfold(
+        Some(recv_stream),
+        |recv_stream| async move {
+            let mut recv_stream = recv_stream?;
+            match recv_stream.recv_data().await {
+                Ok(Some(mut data)) => {
+                    Some((Ok(data.copy_to_bytes(data.remaining())), Some(recv_stream)))
+                }
+                Ok(None) => None,
+                Err(err) => Some((Err(io::Error::other(err)), None)),
+            }
+        },
+    ));
+    let (mut parts, _) = req.into_parts();
+    parts.extensions.insert(handle);
+    let req =
+        crate::Request::from_parts((parts, local_addr, remote_addr, Scheme::HTTPS).into(), body);
+
+    let (parts, body) = ep.get_response(req).await.into_parts();
+    let mut resp = http::Response::new(());
+    *resp.status_mut() = parts.status;
+    *resp.headers_mut() = parts.headers;
+    if let Err(err) = send_stream.send_response(resp).await {
+        tracing::debug!(error = %err, "failed to send http3 response");
+        return;
+    }
+
+    let mut body = std::pin::pin!(body.into_bytes_stream());
+    while let Some(data) = body.next().await {
+        match data {
+            Ok(data) => {
+                if send_stream.send_data(data).await.is_err() {
+                    return;
+                }
+            }
+            Err(err) => {
+                tracing::debug!(error = %err, "failed to read response body");
+                send_stream.stop_stream(h3::error::Code::H3_INTERNAL_ERROR);
+                return;
+            }
+        }
+    }
+    let _ = send_stream.finish().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+    };
+
+    use super::*;
+    use crate::{handler, listener::TcpListener};
+
+    #[handler(internal)]
+    fn index() -> &'static str {
+        "ok"
+    }
+
+    async fn start_server(
+        f: impl FnOnce(
+            Server<Infallible, crate::listener::TcpAcceptor>,
+        ) -> Server<Infallible, crate::listener::TcpAcceptor>,
+    ) -> (
+        SocketAddr,
+        ServerHandle,
+        tokio::task::JoinHandle<IoResult<()>>,
+    ) {
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+        let server = f(Server::new_with_acceptor(acceptor));
+        let handle = server.handle();
+        (addr, handle, tokio::spawn(server.run(index)))
+    }
+
+    async fn request(stream: &mut TcpStream) -> IoResult<String> {
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nhost: localhost\r\n\r\n")
+            .await?;
+        let mut buf = [0; 1024];
+        let n = stream.read(&mut buf).await?;
+        Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+    }
+
+    #[tokio::test]
+    async fn max_connections() {
+        let (addr, handle, _) = start_server(|server| server.max_connections(1)).await;
+
+        let mut stream1 = TcpStream::connect(addr).await.unwrap();
+        assert!(request(&mut stream1).await.unwrap().ends_with("ok"));
+        assert_eq!(handle.alive_connections(), 1);
+
+        // The second connection waits for a free slot.
+        let mut stream2 = TcpStream::connect(addr).await.unwrap();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(200), request(&mut stream2))
+                .await
+                .is_err()
+        );
+
+        drop(stream1);
+        let mut buf = [0; 1024];
+        let n = stream2.read(&mut buf).await.unwrap();
+        assert!(String::from_utf8_lossy(&buf[..n]).ends_with("ok"));
+        assert_eq!(handle.alive_connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn max_connections_per_ip() {
+        let (addr, handle, _) = start_server(|server| server.max_connections_per_ip(1)).await;
+        let ip = addr.ip();
+
+        let mut stream1 = TcpStream::connect(addr).await.unwrap();
+        assert!(request(&mut stream1).await.unwrap().ends_with("ok"));
+        assert_eq!(handle.alive_connections_from(ip), 1);
+
+        // The second connection is closed immediately.
+        let mut stream2 = TcpStream::connect(addr).await.unwrap();
+        assert!(matches!(
+            request(&mut stream2).await.as_deref(),
+            Ok("") | Err(_)
+        ));
+        assert_eq!(handle.alive_connections_from(ip), 1);
+
+        drop(stream1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.alive_connections_from(ip), 0);
+        assert_eq!(handle.alive_connections(), 0);
+
+        let mut stream3 = TcpStream::connect(addr).await.unwrap();
+        assert!(request(&mut stream3).await.unwrap().ends_with("ok"));
+    }
+
+    #[tokio::test]
+    async fn max_connections_per_ip_zero() {
+        let (addr, handle, _) = start_server(|server| server.max_connections_per_ip(0)).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        assert!(matches!(
+            request(&mut stream).await.as_deref(),
+            Ok("") | Err(_)
+        ));
+        assert_eq!(handle.alive_connections(), 0);
+        assert!(handle.0.connections_per_ip.lock().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn too_many_open_files_backoff() {
+        struct EmfileAcceptor(Arc<AtomicUsize>);
+
+        impl Acceptor for EmfileAcceptor {
+            type Io = TcpStream;
+
+            fn local_addr(&self) -> Vec<LocalAddr> {
+                vec![]
+            }
+
+            async fn accept(&mut self) -> IoResult<(TcpStream, LocalAddr, RemoteAddr, Scheme)> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                // EMFILE
+                Err(io::Error::from_raw_os_error(nix::libc::EMFILE))
+            }
+        }
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let server = Server::new_with_acceptor(EmfileAcceptor(attempts.clone()));
+        let handle = server.handle();
+        let join_handle = tokio::spawn(server.run(index));
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        // 5ms, 10ms, 20ms, 40ms, 80ms, ... instead of spinning
+        let n = attempts.load(Ordering::SeqCst);
+        assert!((2..=10).contains(&n), "{n} attempts");
+
+        handle.shutdown();
+        join_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn handle_shutdown() {
+        let (addr, handle, join_handle) = start_server(|server| server).await;
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        assert!(request(&mut stream).await.unwrap().ends_with("ok"));
+
+        handle.shutdown();
+        assert!(handle.is_shutting_down());
+        tokio::time::timeout(Duration::from_secs(5), join_handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(handle.alive_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn signal_shutdown() {
+        for delay in [None, Some(Duration::from_millis(100))] {
+            let acceptor = TcpListener::bind("127.0.0.1:0")
+                .into_acceptor()
+                .await
+                .unwrap();
+            let addr = *acceptor.local_addr()[0].as_socket_addr().unwrap();
+            let server = Server::new_with_acceptor(acceptor);
+            let server = match delay {
+                Some(delay) => server.shutdown_delay(delay),
+                None => server,
+            };
+            let handle = server.handle();
+            let join_handle = tokio::spawn(server.run_with_graceful_shutdown(
+                index,
+                async { tokio::time::sleep(Duration::from_millis(20)).await },
+                None,
+            ));
+
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            assert!(request(&mut stream).await.unwrap().ends_with("ok"));
+            drop(stream);
+
+            tokio::time::timeout(Duration::from_secs(5), join_handle)
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            assert!(handle.is_shutting_down());
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_delay() {
+        let (addr, handle, join_handle) =
+            start_server(|server| server.shutdown_delay(Duration::from_millis(300))).await;
+
+        handle.shutdown();
+        assert!(handle.is_shutting_down());
+
+        // New connections are still accepted during the delay.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        assert!(request(&mut stream).await.unwrap().ends_with("ok"));
+        assert!(!join_handle.is_finished());
+        drop(stream);
+
+        tokio::time::timeout(Duration::from_secs(5), join_handle)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+}
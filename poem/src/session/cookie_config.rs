@@ -1,7 +1,12 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
+
+use serde_json::Value;
 
 use crate::web::cookie::{Cookie, CookieJar, CookieKey, SameSite};
 
+const CREATED_AT_KEY: &str = "_poem_created_at";
+const ACCESSED_AT_KEY: &str = "_poem_accessed_at";
+
 /// Cookie security for session.
 pub enum CookieSecurity {
     /// Use the raw cookie value.
@@ -25,6 +30,9 @@ pub struct CookieConfig {
     secure: bool,
     http_only: bool,
     max_age: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    absolute_timeout: Option<Duration>,
+    rolling: bool,
     same_site: Option<SameSite>,
     partitioned: bool,
 }
@@ -39,6 +47,9 @@ impl Default for CookieConfig {
             secure: true,
             http_only: true,
             max_age: None,
+            idle_timeout: None,
+            absolute_timeout: None,
+            rolling: false,
             same_site: None,
             partitioned: false,
         }
@@ -139,10 +150,119 @@ impl CookieConfig {
         }
     }
 
-    /// Returns the TTL(time-to-live) of the cookie.
+    /// Sets the idle timeout of the session, default is `None`.
+    ///
+    /// The session expires if it has not been used for the specified
+    /// duration, regardless of the `MaxAge` of the cookie.
+    #[must_use]
+    pub fn idle_timeout(self, value: impl Into<Option<Duration>>) -> Self {
+        Self {
+            idle_timeout: value.into(),
+            ..self
+        }
+    }
+
+    /// Sets the absolute timeout of the session, default is `None`.
+    ///
+    /// The session expires after the specified duration since it has been
+    /// created, even if it is in use. Regenerating the session id does not
+    /// extend its lifetime.
+    #[must_use]
+    pub fn absolute_timeout(self, value: impl Into<Option<Duration>>) -> Self {
+        Self {
+            absolute_timeout: value.into(),
+            ..self
+        }
+    }
+
+    /// Enables rolling expiry, default is `false`.
+    ///
+    /// If enabled, the session cookie is sent again and the TTL(time-to-live)
+    /// of the session is reset on every response, so that the `MaxAge` counts
+    /// from the last request instead of the last change.
+    #[must_use]
+    pub fn rolling(self, value: bool) -> Self {
+        Self {
+            rolling: value,
+            ..self
+        }
+    }
+
+    /// Returns `true` if the session must be saved on every request.
+    #[inline]
+    pub(crate) fn touch_on_access(&self) -> bool {
+        self.rolling || self.idle_timeout.is_some()
+    }
+
+    /// Returns `true` if the session cookie must be sent on every response.
     #[inline]
-    pub(crate) fn ttl(&self) -> Option<Duration> {
-        self.max_age
+    pub(crate) fn is_rolling(&self) -> bool {
+        self.rolling
+    }
+
+    /// Removes the timestamps from the session entries, and returns the
+    /// creation time of the session, or `None` if the session has expired.
+    ///
+    /// A session without the timestamp of a configured timeout is considered
+    /// as expired, so that the timeouts cannot be bypassed by dropping the
+    /// timestamps from a session cookie.
+    ///
+    /// The times are in milliseconds since the Unix epoch.
+    pub(crate) fn take_timestamps(
+        &self,
+        entries: &mut BTreeMap<String, Value>,
+        now: i64,
+    ) -> Option<i64> {
+        let created_at = entries
+            .remove(CREATED_AT_KEY)
+            .and_then(|value| value.as_i64());
+        let accessed_at = entries
+            .remove(ACCESSED_AT_KEY)
+            .and_then(|value| value.as_i64());
+        let is_expired = |timeout: Option<Duration>, since: Option<i64>| match (timeout, since) {
+            (Some(timeout), Some(since)) => {
+                Duration::from_millis(now.saturating_sub(since).max(0) as u64) >= timeout
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        if is_expired(self.idle_timeout, accessed_at)
+            || is_expired(self.absolute_timeout, created_at)
+        {
+            return None;
+        }
+        Some(created_at.unwrap_or(now))
+    }
+
+    /// Adds the timestamps to the session entries, if the session has
+    /// timeouts.
+    pub(crate) fn with_timestamps(
+        &self,
+        mut entries: BTreeMap<String, Value>,
+        created_at: i64,
+        now: i64,
+    ) -> BTreeMap<String, Value> {
+        if self.absolute_timeout.is_some() {
+            entries.insert(CREATED_AT_KEY.to_string(), created_at.into());
+        }
+        if self.idle_timeout.is_some() {
+            entries.insert(ACCESSED_AT_KEY.to_string(), now.into());
+        }
+        entries
+    }
+
+    /// Returns the TTL(time-to-live) of the session in the storage.
+    pub(crate) fn ttl(&self, created_at: i64, now: i64) -> Option<Duration> {
+        let remaining = self.absolute_timeout.map(|timeout| {
+            timeout.saturating_sub(Duration::from_millis(
+                now.saturating_sub(created_at).max(0) as u64
+            ))
+        });
+        [self.max_age, self.idle_timeout, remaining]
+            .into_iter()
+            .flatten()
+            .min()
     }
 
     /// Set the cookie value to `CookieJar`.
@@ -5,7 +5,7 @@ use serde_json::Value;
 use crate::{
     Endpoint, Middleware, Request, Result,
     middleware::{CookieJarManager, CookieJarManagerEndpoint},
-    session::{CookieConfig, Session, SessionStatus},
+    session::{CookieConfig, Session, SessionStatus, sweeper::now_millis},
 };
 
 /// Middleware for client-side(cookie) session.
@@ -47,6 +47,8 @@ impl<E: Endpoint> Endpoint for CookieSessionEndpoint<E> {
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
         let cookie_jar = req.cookie().clone();
+        let now = now_millis();
+        let mut created_at = now;
         let session = self
             .config
             .get_cookie_value(&cookie_jar)
@@ -60,29 +62,39 @@ impl<E: Endpoint> Endpoint for CookieSessionEndpoint<E> {
                     sonic_rs::from_str::<BTreeMap<String, Value>>(&value).ok()
                 }
             })
-            .map(Session::new)
-            .unwrap_or_default();
+            .and_then(|mut entries| {
+                created_at = self.config.take_timestamps(&mut entries, now)?;
+                Some(Session::new(entries))
+            });
+        let is_new = session.is_none();
+        let session = session.unwrap_or_default();
 
         req.extensions_mut().insert(session.clone());
         let resp = self.inner.call(req).await?;
 
+        let save = || {
+            let entries = self
+                .config
+                .with_timestamps(session.entries(), created_at, now);
+            let value = {
+                #[cfg(not(feature = "sonic-rs"))]
+                {
+                    serde_json::to_string(&entries).unwrap_or_default()
+                }
+                #[cfg(feature = "sonic-rs")]
+                {
+                    sonic_rs::to_string(&entries).unwrap_or_default()
+                }
+            };
+            self.config.set_cookie_value(&cookie_jar, &value);
+        };
+
         match session.status() {
-            SessionStatus::Changed | SessionStatus::Renewed => {
-                let value = {
-                    #[cfg(not(feature = "sonic-rs"))]
-                    {
-                        serde_json::to_string(&session.entries()).unwrap_or_default()
-                    }
-                    #[cfg(feature = "sonic-rs")]
-                    {
-                        sonic_rs::to_string(&session.entries()).unwrap_or_default()
-                    }
-                };
-                self.config.set_cookie_value(&cookie_jar, &value);
-            }
+            SessionStatus::Changed | SessionStatus::Renewed => save(),
             SessionStatus::Purged => {
                 self.config.remove_cookie(&cookie_jar);
             }
+            SessionStatus::Unchanged if !is_new && self.config.touch_on_access() => save(),
             SessionStatus::Unchanged => {}
         };
 
@@ -128,4 +140,43 @@ mod tests {
         client.call(&app, 5).await;
         client.assert_cookies(vec![]);
     }
+
+    #[tokio::test]
+    async fn idle_timeout() {
+        let app = Route::new().at("/:action", index).with(CookieSession::new(
+            CookieConfig::default().idle_timeout(std::time::Duration::from_millis(200)),
+        ));
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        client.call(&app, 2).await;
+        let value = client.cookie("poem-session").unwrap();
+        assert!(value.contains("_poem_accessed_at"));
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        // the expired session is empty
+        client.call(&app, 5).await;
+        client.assert_cookies(vec![]);
+    }
+
+    #[tokio::test]
+    async fn missing_timestamps() {
+        // a session without the timestamps of the timeouts has expired
+        for config in [
+            CookieConfig::default().idle_timeout(std::time::Duration::from_secs(10)),
+            CookieConfig::default().absolute_timeout(std::time::Duration::from_secs(10)),
+        ] {
+            let app = Route::new()
+                .at("/:action", index)
+                .with(CookieSession::new(config));
+            let mut client = TestClient::default();
+            client.call(&app, 1).await;
+            let value = client.cookie("poem-session").unwrap().to_string();
+            let mut entries: BTreeMap<String, Value> = serde_json::from_str(&value).unwrap();
+            entries.retain(|name, _| !name.starts_with("_poem_"));
+            client.set_cookie("poem-session", &serde_json::to_string(&entries).unwrap());
+            client.call(&app, 5).await;
+            client.assert_cookies(vec![]);
+        }
+    }
 }
@@ -0,0 +1,378 @@
+use std::{
+    collections::BTreeMap,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use rand::{Rng, distr::Alphanumeric, rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    Result,
+    error::FileSessionError,
+    session::{
+        SessionStorage,
+        session::session_user,
+        sweeper::{Sweeper, expires_at, now_millis},
+    },
+};
+
+const EXTENSION: &str = "json";
+
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    /// Milliseconds since the Unix epoch.
+    expires_at: Option<i64>,
+    entries: BTreeMap<String, Value>,
+}
+
+impl SessionFile {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// A session storage using the file system.
+///
+/// Each session is stored as a JSON file named after the session id in the
+/// specified directory, which is created if it does not exist. The files are
+/// written to a temporary file first and then renamed, so that a crash never
+/// leaves a partially written session.
+///
+/// The expired sessions are removed when they are loaded, and by a background
+/// task every [`FileStorage::sweep_interval`].
+///
+/// # Errors
+///
+/// - [`FileSessionError`]
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route,
+///     session::{CookieConfig, FileStorage, ServerSession},
+/// };
+///
+/// let app = Route::new().with(ServerSession::new(
+///     CookieConfig::default(),
+///     FileStorage::new("/var/lib/my-app/sessions"),
+/// ));
+/// ```
+pub struct FileStorage {
+    dir: PathBuf,
+    sweep_interval: Duration,
+    sweeper: Sweeper,
+}
+
+impl FileStorage {
+    /// Create a `FileStorage` which stores the sessions in the specified
+    /// directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            sweep_interval: Duration::from_secs(60),
+            sweeper: Sweeper::default(),
+        }
+    }
+
+    /// Sets the interval of the removal of the expired sessions, default is
+    /// 60 seconds.
+    #[must_use]
+    pub fn sweep_interval(self, interval: Duration) -> Self {
+        Self {
+            sweep_interval: interval,
+            ..self
+        }
+    }
+
+    /// Removes the expired sessions, and returns the number of removed
+    /// sessions.
+    pub async fn sweep(&self) -> Result<usize> {
+        Ok(sweep(&self.dir).await.map_err(FileSessionError::Io)?)
+    }
+
+    fn path(&self, session_id: &str) -> Option<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id.len() <= 128
+            && session_id
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_');
+        valid.then(|| self.dir.join(format!("{session_id}.{EXTENSION}")))
+    }
+
+    fn start_sweeper(&self) {
+        let dir = self.dir.clone();
+        self.sweeper.start(self.sweep_interval, move || {
+            let dir = dir.clone();
+            async move {
+                if let Err(err) = sweep(&dir).await {
+                    tracing::warn!(error = %err, "failed to remove the expired sessions");
+                }
+            }
+        });
+    }
+}
+
+async fn read_session_file(path: &Path) -> std::io::Result<Option<SessionFile>> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(serde_json::from_slice(&data).ok()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+async fn remove_file(path: &Path) -> std::io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+async fn sweep(dir: &Path) -> std::io::Result<usize> {
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    };
+    let now = now_millis();
+    let mut count = 0;
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        if let Some(file) = read_session_file(&path).await? {
+            if file.is_expired(now) {
+                remove_file(&path).await?;
+                count += 1;
+            }
+        }
+    }
+
+    Ok(count)
+}
+
+async fn user_sessions(dir: &Path, user_id: &str) -> std::io::Result<Vec<String>> {
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let now = now_millis();
+    let mut session_ids = Vec::new();
+
+    while let Some(entry) = read_dir.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(EXTENSION) {
+            continue;
+        }
+        let Some(session_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        if let Some(file) = read_session_file(&path).await? {
+            if !file.is_expired(now) && session_user(&file.entries) == Some(user_id) {
+                session_ids.push(session_id.to_string());
+            }
+        }
+    }
+
+    Ok(session_ids)
+}
+
+impl SessionStorage for FileStorage {
+    async fn load_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> Result<Option<BTreeMap<String, Value>>> {
+        self.start_sweeper();
+        let Some(path) = self.path(session_id) else {
+            return Ok(None);
+        };
+
+        match read_session_file(&path)
+            .await
+            .map_err(FileSessionError::Io)?
+        {
+            Some(file) if file.is_expired(now_millis()) => {
+                remove_file(&path).await.map_err(FileSessionError::Io)?;
+                Ok(None)
+            }
+            Some(file) => Ok(Some(file.entries)),
+            None => Ok(None),
+        }
+    }
+
+    async fn update_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> Result<()> {
+        self.start_sweeper();
+        let path = self
+            .path(session_id)
+            .ok_or(FileSessionError::InvalidSessionId)?;
+        let data = serde_json::to_vec(&SessionFile {
+            expires_at: expires_at(expires),
+            entries: entries.clone(),
+        })
+        .unwrap_or_default();
+
+        // the temporary file does not have the `json` extension, so it is never
+        // loaded or swept
+        let suffix = rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect::<String>();
+        let tmp_path = self.dir.join(format!(".{session_id}.{suffix}.tmp"));
+
+        if let Err(err) = tokio::fs::write(&tmp_path, &data).await {
+            if err.kind() != ErrorKind::NotFound {
+                return Err(FileSessionError::Io(err).into());
+            }
+            tokio::fs::create_dir_all(&self.dir)
+                .await
+                .map_err(FileSessionError::Io)?;
+            tokio::fs::write(&tmp_path, &data)
+                .await
+                .map_err(FileSessionError::Io)?;
+        }
+        if let Err(err) = tokio::fs::rename(&tmp_path, &path).await {
+            _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(FileSessionError::Io(err).into());
+        }
+        Ok(())
+    }
+
+    async fn remove_session<'a>(&'a self, session_id: &'a str) -> Result<()> {
+        if let Some(path) = self.path(session_id) {
+            remove_file(&path).await.map_err(FileSessionError::Io)?;
+        }
+        Ok(())
+    }
+
+    async fn user_sessions<'a>(&'a self, user_id: &'a str) -> Result<Vec<String>> {
+        Ok(user_sessions(&self.dir, user_id)
+            .await
+            .map_err(FileSessionError::Io)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        EndpointExt, Route,
+        session::{
+            CookieConfig, ServerSession,
+            test_harness::{TestClient, index},
+        },
+    };
+
+    fn temp_dir() -> PathBuf {
+        let name = rng()
+            .sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect::<String>();
+        std::env::temp_dir().join(format!("poem-sessions-{name}"))
+    }
+
+    #[tokio::test]
+    async fn file_session() {
+        let dir = temp_dir();
+        let app = Route::new().at("/:action", index).with(ServerSession::new(
+            CookieConfig::default(),
+            FileStorage::new(&dir),
+        ));
+        let mut client = TestClient::default();
+
+        client.call(&app, 0).await;
+        client.assert_cookies(vec![]);
+
+        client.call(&app, 1).await;
+        client.call(&app, 2).await;
+        client.call(&app, 7).await;
+        client.call(&app, 6).await;
+        client.call(&app, 3).await;
+        client.call(&app, 4).await;
+        client.call(&app, 5).await;
+        client.assert_cookies(vec![]);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn expiry() {
+        let dir = temp_dir();
+        let storage = FileStorage::new(&dir);
+        let mut values = BTreeMap::new();
+        values.insert("value".to_string(), "1".into());
+
+        storage
+            .update_session("a", &values, Some(Duration::from_millis(100)))
+            .await
+            .unwrap();
+        storage
+            .update_session("b", &values, Some(Duration::from_millis(100)))
+            .await
+            .unwrap();
+        storage.update_session("c", &values, None).await.unwrap();
+        assert_eq!(
+            storage.load_session("a").await.unwrap(),
+            Some(values.clone())
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(storage.load_session("a").await.unwrap(), None);
+        assert!(!dir.join("a.json").exists());
+
+        assert_eq!(storage.sweep().await.unwrap(), 1);
+        assert!(!dir.join("b.json").exists());
+        assert_eq!(
+            storage.load_session("c").await.unwrap(),
+            Some(values.clone())
+        );
+
+        storage.remove_session("c").await.unwrap();
+        assert_eq!(storage.load_session("c").await.unwrap(), None);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn sweeper() {
+        let dir = temp_dir();
+        let storage = FileStorage::new(&dir).sweep_interval(Duration::from_millis(100));
+        let values = BTreeMap::new();
+
+        storage
+            .update_session("a", &values, Some(Duration::from_millis(50)))
+            .await
+            .unwrap();
+        assert!(dir.join("a.json").exists());
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(!dir.join("a.json").exists());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn invalid_session_id() {
+        let dir = temp_dir();
+        let storage = FileStorage::new(&dir);
+        let values = BTreeMap::new();
+
+        assert_eq!(storage.load_session("../a").await.unwrap(), None);
+        assert!(storage.update_session("../a", &values, None).await.is_err());
+        storage.remove_session("../a").await.unwrap();
+    }
+}
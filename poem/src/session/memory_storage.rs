@@ -1,6 +1,6 @@
 use std::{
     cmp::Reverse,
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -9,14 +9,33 @@ use parking_lot::Mutex;
 use priority_queue::PriorityQueue;
 use serde_json::Value;
 
-use crate::{Result, session::SessionStorage};
+use crate::{
+    Result,
+    session::{SessionStorage, session::session_user},
+};
 
 struct InnerStorage {
     sessions: HashMap<String, BTreeMap<String, Value>>,
     timeout_queue: PriorityQueue<String, Reverse<Instant>>,
+    users: HashMap<String, HashSet<String>>,
 }
 
 impl InnerStorage {
+    fn remove(&mut self, session_id: &str) {
+        self.timeout_queue.remove(session_id);
+        let Some(entries) = self.sessions.remove(session_id) else {
+            return;
+        };
+        if let Some(user_id) = session_user(&entries) {
+            if let Some(sessions) = self.users.get_mut(user_id) {
+                sessions.remove(session_id);
+                if sessions.is_empty() {
+                    self.users.remove(user_id);
+                }
+            }
+        }
+    }
+
     fn cleanup(&mut self) {
         loop {
             let now = Instant::now();
@@ -25,7 +44,7 @@ impl InnerStorage {
                     break;
                 }
                 if let Some((session_id, _)) = self.timeout_queue.pop() {
-                    self.sessions.remove(&session_id);
+                    self.remove(&session_id);
                 }
             } else {
                 break;
@@ -44,6 +63,7 @@ impl Default for MemoryStorage {
         let inner = Arc::new(Mutex::new(InnerStorage {
             sessions: HashMap::new(),
             timeout_queue: PriorityQueue::new(),
+            users: HashMap::new(),
         }));
         tokio::spawn({
             let inner = Arc::downgrade(&inner);
@@ -84,10 +104,17 @@ impl SessionStorage for MemoryStorage {
         expires: Option<Duration>,
     ) -> Result<()> {
         let mut inner = self.inner.lock();
-        inner.timeout_queue.remove(session_id);
+        inner.remove(session_id);
         inner
             .sessions
             .insert(session_id.to_string(), entries.clone());
+        if let Some(user_id) = session_user(entries) {
+            inner
+                .users
+                .entry(user_id.to_string())
+                .or_default()
+                .insert(session_id.to_string());
+        }
         if let Some(expires) = expires {
             inner
                 .timeout_queue
@@ -98,10 +125,18 @@ impl SessionStorage for MemoryStorage {
 
     async fn remove_session<'a>(&'a self, session_id: &'a str) -> Result<()> {
         let mut inner = self.inner.lock();
-        inner.sessions.remove(session_id);
-        inner.timeout_queue.remove(session_id);
+        inner.remove(session_id);
         Ok(())
     }
+
+    async fn user_sessions<'a>(&'a self, user_id: &'a str) -> Result<Vec<String>> {
+        let inner = self.inner.lock();
+        Ok(inner
+            .users
+            .get(user_id)
+            .map(|sessions| sessions.iter().cloned().collect())
+            .unwrap_or_default())
+    }
 }
 
 #[cfg(test)]
@@ -3,10 +3,22 @@ use std::{collections::BTreeMap, time::Duration};
 use redis::{Cmd, aio::ConnectionLike};
 use serde_json::Value;
 
-use crate::{Result, error::RedisSessionError, session::session_storage::SessionStorage};
+use crate::{
+    Result,
+    error::RedisSessionError,
+    session::{session::session_user, session_storage::SessionStorage},
+};
+
+/// Returns the key of the set of the sessions of a user.
+fn user_key(user_id: &str) -> String {
+    format!("poem-session-user:{user_id}")
+}
 
 /// A session storage using redis.
 ///
+/// The ids of the sessions of a user are stored in a set with the
+/// `poem-session-user:{user_id}` key.
+///
 /// # Errors
 ///
 /// - [`RedisSessionError`]
@@ -58,12 +70,19 @@ impl<T: ConnectionLike + Clone + Sync + Send> SessionStorage for RedisStorage<T>
         #[cfg(feature = "sonic-rs")]
         let value = sonic_rs::to_string(entries).unwrap_or_default();
         let cmd = match expires {
-            Some(expires) => Cmd::set_ex(session_id, value, expires.as_secs()),
+            // `SETEX` rejects a TTL of zero seconds
+            Some(expires) => Cmd::pset_ex(session_id, value, (expires.as_millis() as u64).max(1)),
             None => Cmd::set(session_id, value),
         };
         cmd.query_async::<()>(&mut self.connection.clone())
             .await
             .map_err(RedisSessionError::Redis)?;
+        if let Some(user_id) = session_user(entries) {
+            Cmd::sadd(user_key(user_id), session_id)
+                .query_async::<()>(&mut self.connection.clone())
+                .await
+                .map_err(RedisSessionError::Redis)?;
+        }
         Ok(())
     }
 
@@ -74,6 +93,35 @@ impl<T: ConnectionLike + Clone + Sync + Send> SessionStorage for RedisStorage<T>
             .map_err(RedisSessionError::Redis)?;
         Ok(())
     }
+
+    async fn user_sessions<'a>(&'a self, user_id: &'a str) -> Result<Vec<String>> {
+        let key = user_key(user_id);
+        let session_ids: Vec<String> = Cmd::smembers(&key)
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(RedisSessionError::Redis)?;
+
+        // the set is not updated when the sessions expire or are removed
+        let mut sessions = Vec::with_capacity(session_ids.len());
+        for session_id in session_ids {
+            let data: Option<String> = Cmd::get(&session_id)
+                .query_async(&mut self.connection.clone())
+                .await
+                .map_err(RedisSessionError::Redis)?;
+            let is_alive = data
+                .and_then(|data| serde_json::from_str::<BTreeMap<String, Value>>(&data).ok())
+                .is_some_and(|entries| session_user(&entries) == Some(user_id));
+            if is_alive {
+                sessions.push(session_id);
+            } else {
+                Cmd::srem(&key, &session_id)
+                    .query_async::<()>(&mut self.connection.clone())
+                    .await
+                    .map_err(RedisSessionError::Redis)?;
+            }
+        }
+        Ok(sessions)
+    }
 }
 
 #[cfg(test)]
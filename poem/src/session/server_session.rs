@@ -6,7 +6,9 @@ use rand::{Rng, rng};
 use crate::{
     Endpoint, Middleware, Request, Result,
     middleware::{CookieJarManager, CookieJarManagerEndpoint},
-    session::{CookieConfig, Session, SessionStatus, session_storage::SessionStorage},
+    session::{
+        CookieConfig, Session, SessionStatus, session_storage::SessionStorage, sweeper::now_millis,
+    },
 };
 
 /// Middleware for server-side session.
@@ -23,6 +25,14 @@ impl<T> ServerSession<T> {
             storage: Arc::new(storage),
         }
     }
+
+    /// Returns the session storage.
+    ///
+    /// Use it to enumerate or remove the sessions of a user, for example to
+    /// log the user out everywhere after a password change.
+    pub fn storage(&self) -> Arc<T> {
+        self.storage.clone()
+    }
 }
 
 impl<T: SessionStorage, E: Endpoint> Middleware<E> for ServerSession<T> {
@@ -61,10 +71,24 @@ where
 
     async fn call(&self, mut req: Request) -> Result<Self::Output> {
         let cookie_jar = req.cookie().clone();
+        let now = now_millis();
+        let mut created_at = now;
         let mut session_id = self.config.get_cookie_value(&cookie_jar);
         let session = match &session_id {
             Some(id) => match self.storage.load_session(id).await? {
-                Some(entries) => Session::new(entries),
+                Some(mut entries) => match self.config.take_timestamps(&mut entries, now) {
+                    Some(time) => {
+                        created_at = time;
+                        Session::new(entries)
+                    }
+                    None => {
+                        // the session has expired
+                        self.storage.remove_session(id).await?;
+                        self.config.remove_cookie(&cookie_jar);
+                        session_id = None;
+                        Session::default()
+                    }
+                },
                 None => {
                     session_id = None;
                     Session::default()
@@ -76,31 +100,45 @@ where
         req.extensions_mut().insert(session.clone());
         let resp = self.inner.call(req).await?;
 
+        let entries = || {
+            self.config
+                .with_timestamps(session.entries(), created_at, now)
+        };
+        let ttl = self.config.ttl(created_at, now);
+
         match session.status() {
             SessionStatus::Changed => match session_id {
                 Some(session_id) => {
                     self.storage
-                        .update_session(&session_id, &session.entries(), self.config.ttl())
+                        .update_session(&session_id, &entries(), ttl)
                         .await?;
+                    if self.config.is_rolling() {
+                        self.config.set_cookie_value(&cookie_jar, &session_id);
+                    }
                 }
                 None => {
                     let session_id = generate_session_id();
                     self.config.set_cookie_value(&cookie_jar, &session_id);
                     self.storage
-                        .update_session(&session_id, &session.entries(), self.config.ttl())
+                        .update_session(&session_id, &entries(), ttl)
                         .await?;
                 }
             },
             SessionStatus::Renewed => {
-                if let Some(session_id) = session_id {
-                    self.storage.remove_session(&session_id).await?;
+                let new_session_id = generate_session_id();
+                self.config.set_cookie_value(&cookie_jar, &new_session_id);
+                match session_id {
+                    Some(session_id) => {
+                        self.storage
+                            .migrate_session(&session_id, &new_session_id, &entries(), ttl)
+                            .await?;
+                    }
+                    None => {
+                        self.storage
+                            .update_session(&new_session_id, &entries(), ttl)
+                            .await?;
+                    }
                 }
-
-                let session_id = generate_session_id();
-                self.config.set_cookie_value(&cookie_jar, &session_id);
-                self.storage
-                    .update_session(&session_id, &session.entries(), self.config.ttl())
-                    .await?;
             }
             SessionStatus::Purged => {
                 if let Some(session_id) = session_id {
@@ -108,9 +146,151 @@ where
                     self.config.remove_cookie(&cookie_jar);
                 }
             }
-            SessionStatus::Unchanged => {}
+            SessionStatus::Unchanged => {
+                if let Some(session_id) = session_id {
+                    if self.config.touch_on_access() {
+                        self.storage
+                            .update_session(&session_id, &entries(), ttl)
+                            .await?;
+                    }
+                    if self.config.is_rolling() {
+                        self.config.set_cookie_value(&cookie_jar, &session_id);
+                    }
+                }
+            }
         };
 
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        EndpointExt, Route, handler,
+        session::{FileStorage, MemoryStorage, test_harness::TestClient},
+        web::Path,
+    };
+
+    #[handler(internal)]
+    fn index(Path(action): Path<i32>, session: &Session) {
+        match action {
+            1 => session.set("a", 1),
+            2 => assert_eq!(session.get::<i32>("a"), Some(1)),
+            3 => assert_eq!(session.get::<i32>("a"), None),
+            4 => session.set_user("sunli"),
+            _ => {}
+        }
+    }
+
+    fn app<T: SessionStorage>(config: CookieConfig, storage: T) -> (impl Endpoint, Arc<T>) {
+        let session = ServerSession::new(config, storage);
+        let storage = session.storage();
+        (Route::new().at("/:action", index).with(session), storage)
+    }
+
+    #[tokio::test]
+    async fn idle_timeout() {
+        let (app, _) = app(
+            CookieConfig::default().idle_timeout(Duration::from_millis(300)),
+            MemoryStorage::new(),
+        );
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.call(&app, 2).await;
+        // the session has been used
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.call(&app, 2).await;
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        client.call(&app, 3).await;
+        client.assert_cookies(vec![]);
+    }
+
+    #[tokio::test]
+    async fn absolute_timeout() {
+        let (app, _) = app(
+            CookieConfig::default()
+                .idle_timeout(Duration::from_secs(10))
+                .absolute_timeout(Duration::from_millis(300)),
+            MemoryStorage::new(),
+        );
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.call(&app, 4).await;
+        client.call(&app, 2).await;
+
+        // regenerating the id does not extend the lifetime
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.call(&app, 3).await;
+    }
+
+    #[tokio::test]
+    async fn rolling() {
+        let dir = std::env::temp_dir().join(format!("poem-rolling-{}", generate_session_id()));
+        let (app, _) = app(
+            CookieConfig::default()
+                .max_age(Duration::from_millis(300))
+                .rolling(true),
+            FileStorage::new(&dir),
+        );
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.call(&app, 2).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.call(&app, 2).await;
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        client.call(&app, 3).await;
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn renew_on_set_user() {
+        let (app, storage) = app(CookieConfig::default(), MemoryStorage::new());
+        let mut client = TestClient::default();
+
+        client.call(&app, 1).await;
+        let old_id = client.cookie("poem-session").unwrap().to_string();
+
+        client.call(&app, 4).await;
+        let new_id = client.cookie("poem-session").unwrap().to_string();
+        assert_ne!(old_id, new_id);
+        assert_eq!(storage.load_session(&old_id).await.unwrap(), None);
+
+        // the data is migrated
+        client.call(&app, 2).await;
+        assert_eq!(storage.user_sessions("sunli").await.unwrap(), vec![new_id]);
+    }
+
+    #[tokio::test]
+    async fn remove_user_sessions() {
+        let (app, storage) = app(CookieConfig::default(), MemoryStorage::new());
+        let mut client1 = TestClient::default();
+        let mut client2 = TestClient::default();
+        let mut client3 = TestClient::default();
+
+        for client in [&mut client1, &mut client2, &mut client3] {
+            client.call(&app, 1).await;
+        }
+        client1.call(&app, 4).await;
+        client2.call(&app, 4).await;
+        assert_eq!(storage.user_sessions("sunli").await.unwrap().len(), 2);
+
+        storage.remove_user_sessions("sunli").await.unwrap();
+        assert!(storage.user_sessions("sunli").await.unwrap().is_empty());
+        client1.call(&app, 3).await;
+        client2.call(&app, 3).await;
+        client3.call(&app, 2).await;
+    }
+}
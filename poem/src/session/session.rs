@@ -26,6 +26,14 @@ pub enum SessionStatus {
     Unchanged,
 }
 
+/// The entry which stores the user of the session.
+pub(crate) const USER_KEY: &str = "_poem_user";
+
+/// Returns the user of the session entries.
+pub(crate) fn session_user(entries: &BTreeMap<String, Value>) -> Option<&str> {
+    entries.get(USER_KEY).and_then(Value::as_str)
+}
+
 struct SessionInner {
     status: SessionStatus,
     entries: BTreeMap<String, Value>,
@@ -124,6 +132,11 @@ impl Session {
     }
 
     /// Renews the session key, assigning existing session state to new key.
+    ///
+    /// With the `ServerSession` middleware, the state is migrated to the new
+    /// key in the storage and the old key is removed. Call this when the
+    /// privilege level of the session changes, to protect against session
+    /// fixation attacks. [`Session::set_user`] calls it automatically.
     pub fn renew(&self) {
         let mut inner = self.inner.write();
         if inner.status != SessionStatus::Purged {
@@ -131,6 +144,29 @@ impl Session {
         }
     }
 
+    /// Sets the user of this session, and renews the session key.
+    ///
+    /// The user is stored in the `_poem_user` entry, and the sessions of a
+    /// user can be enumerated and removed with
+    /// [`SessionStorage::user_sessions`](crate::session::SessionStorage::user_sessions)
+    /// and
+    /// [`SessionStorage::remove_user_sessions`](crate::session::SessionStorage::remove_user_sessions).
+    pub fn set_user(&self, user_id: impl Into<String>) {
+        let mut inner = self.inner.write();
+        if inner.status != SessionStatus::Purged {
+            inner
+                .entries
+                .insert(USER_KEY.to_string(), Value::String(user_id.into()));
+            inner.status = SessionStatus::Renewed;
+        }
+    }
+
+    /// Returns the user of this session.
+    pub fn user(&self) -> Option<String> {
+        let inner = self.inner.read();
+        session_user(&inner.entries).map(ToString::to_string)
+    }
+
     /// Removes session both client and server side.
     pub fn purge(&self) {
         let mut inner = self.inner.write();
@@ -202,6 +238,20 @@ mod tests {
         assert_eq!(session.entries().into_iter().collect::<Vec<_>>(), vec![]);
     }
 
+    #[test]
+    fn set_user() {
+        let session = Session::default();
+        assert_eq!(session.user(), None);
+
+        session.set_user("sunli");
+        assert_eq!(session.user().as_deref(), Some("sunli"));
+        assert_eq!(session.status(), SessionStatus::Renewed);
+
+        session.purge();
+        session.set_user("sunli");
+        assert_eq!(session.user(), None);
+    }
+
     #[test]
     fn purge_session() {
         let session = Session::default();
@@ -2,7 +2,7 @@ use std::{collections::BTreeMap, future::Future, time::Duration};
 
 use serde_json::Value;
 
-use crate::Result;
+use crate::{Error, Result, http::StatusCode};
 
 /// Represents a back-end session storage.
 pub trait SessionStorage: Send + Sync {
@@ -25,4 +25,59 @@ pub trait SessionStorage: Send + Sync {
         &'a self,
         session_id: &'a str,
     ) -> impl Future<Output = Result<()>> + Send + 'a;
+
+    /// Moves a session to a new session id.
+    ///
+    /// The default implementation inserts the new session and then removes
+    /// the old one.
+    fn migrate_session<'a>(
+        &'a self,
+        old_session_id: &'a str,
+        new_session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        async move {
+            self.update_session(new_session_id, entries, expires)
+                .await?;
+            self.remove_session(old_session_id).await
+        }
+    }
+
+    /// Returns the ids of the sessions of a user.
+    ///
+    /// The user of a session is set by
+    /// [`Session::set_user`](crate::session::Session::set_user), and is
+    /// stored in the `_poem_user` entry.
+    ///
+    /// The default implementation returns a `501 Not Implemented` error.
+    fn user_sessions<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> impl Future<Output = Result<Vec<String>>> + Send + 'a {
+        let _ = user_id;
+        async move {
+            Err(Error::from_string(
+                "the session storage does not support enumerating the sessions of a user",
+                StatusCode::NOT_IMPLEMENTED,
+            ))
+        }
+    }
+
+    /// Removes all the sessions of a user, for example to log the user out
+    /// everywhere.
+    ///
+    /// The default implementation removes the sessions returned by
+    /// [`SessionStorage::user_sessions`] one by one.
+    fn remove_user_sessions<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        async move {
+            for session_id in self.user_sessions(user_id).await? {
+                self.remove_session(&session_id).await?;
+            }
+            Ok(())
+        }
+    }
 }
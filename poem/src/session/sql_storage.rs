@@ -0,0 +1,389 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use serde_json::Value;
+use sqlx::{Database, Pool};
+
+use crate::{
+    Result,
+    error::SqlSessionError,
+    session::{
+        SessionStorage,
+        session::session_user,
+        sweeper::{Sweeper, expires_at, now_millis},
+    },
+};
+
+/// A session storage using a SQL database.
+///
+/// The sessions are stored as JSON in a table, which can be created with
+/// `SqlStorage::migrate`. The expired sessions are ignored when they are
+/// loaded, and deleted by a background task every
+/// [`SqlStorage::purge_interval`].
+///
+/// The supported databases are enabled by the following features:
+///
+/// | Database   | Feature          |
+/// |------------|------------------|
+/// | PostgreSQL | postgres-session |
+/// | SQLite     | sqlite-session   |
+/// | MySQL      | mysql-session    |
+///
+/// # Errors
+///
+/// - [`SqlSessionError`]
+///
+/// # Example
+///
+/// ```no_run
+/// # #[cfg(feature = "sqlite-session")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// use poem::{
+///     EndpointExt, Route,
+///     session::{CookieConfig, ServerSession, SqlStorage},
+/// };
+/// use sqlx::SqlitePool;
+///
+/// let pool = SqlitePool::connect("sqlite://sessions.db").await.unwrap();
+/// let storage = SqlStorage::new(pool);
+/// storage.migrate().await.unwrap();
+///
+/// let app = Route::new().with(ServerSession::new(CookieConfig::default(), storage));
+/// # });
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "sqlx-session")))]
+pub struct SqlStorage<DB: Database> {
+    pool: Pool<DB>,
+    table: String,
+    purge_interval: Duration,
+    sweeper: Sweeper,
+}
+
+impl<DB: Database> SqlStorage<DB> {
+    /// Create a `SqlStorage` which stores the sessions in the `sessions`
+    /// table.
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self {
+            pool,
+            table: "sessions".to_string(),
+            purge_interval: Duration::from_secs(60),
+            sweeper: Sweeper::default(),
+        }
+    }
+
+    /// Sets the name of the table, default is `sessions`.
+    ///
+    /// The name is used in the SQL statements as is, so it must be a valid
+    /// identifier and must not come from an untrusted source.
+    #[must_use]
+    pub fn table_name(self, table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            ..self
+        }
+    }
+
+    /// Sets the interval of the deletion of the expired sessions, default is
+    /// 60 seconds.
+    #[must_use]
+    pub fn purge_interval(self, interval: Duration) -> Self {
+        Self {
+            purge_interval: interval,
+            ..self
+        }
+    }
+}
+
+macro_rules! impl_sql_storage {
+    (
+        feature = $feature:literal,
+        database = $db:ty,
+        create = [$($create:literal),*],
+        load = $load:literal,
+        update = $update:literal,
+        remove = $remove:literal,
+        user_sessions = $user_sessions:literal,
+        remove_user_sessions = $remove_user_sessions:literal,
+        purge = $purge:literal $(,)?
+    ) => {
+        #[cfg(feature = $feature)]
+        #[cfg_attr(docsrs, doc(cfg(feature = $feature)))]
+        impl SqlStorage<$db> {
+            /// Creates the table of the sessions if it does not exist.
+            pub async fn migrate(&self) -> Result<()> {
+                $(
+                    sqlx::query(&format!($create, table = self.table))
+                        .execute(&self.pool)
+                        .await
+                        .map_err(SqlSessionError::Sqlx)?;
+                )*
+                Ok(())
+            }
+
+            /// Deletes the expired sessions, and returns the number of deleted
+            /// sessions.
+            pub async fn purge_expired(&self) -> Result<u64> {
+                Ok(Self::purge(&self.pool, &format!($purge, table = self.table))
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?)
+            }
+
+            async fn purge(pool: &Pool<$db>, sql: &str) -> sqlx::Result<u64> {
+                Ok(sqlx::query(sql)
+                    .bind(now_millis())
+                    .execute(pool)
+                    .await?
+                    .rows_affected())
+            }
+
+            fn start_sweeper(&self) {
+                let pool = self.pool.clone();
+                let sql = format!($purge, table = self.table);
+                self.sweeper.start(self.purge_interval, move || {
+                    let pool = pool.clone();
+                    let sql = sql.clone();
+                    async move {
+                        if let Err(err) = Self::purge(&pool, &sql).await {
+                            tracing::warn!(error = %err, "failed to delete the expired sessions");
+                        }
+                    }
+                });
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl SessionStorage for SqlStorage<$db> {
+            async fn load_session<'a>(
+                &'a self,
+                session_id: &'a str,
+            ) -> Result<Option<BTreeMap<String, Value>>> {
+                self.start_sweeper();
+                let entries = sqlx::query_scalar::<_, String>(&format!($load, table = self.table))
+                    .bind(session_id)
+                    .bind(now_millis())
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(entries.and_then(|entries| serde_json::from_str(&entries).ok()))
+            }
+
+            async fn update_session<'a>(
+                &'a self,
+                session_id: &'a str,
+                entries: &'a BTreeMap<String, Value>,
+                expires: Option<Duration>,
+            ) -> Result<()> {
+                self.start_sweeper();
+                sqlx::query(&format!($update, table = self.table))
+                    .bind(session_id)
+                    .bind(serde_json::to_string(entries).unwrap_or_default())
+                    .bind(expires_at(expires))
+                    .bind(session_user(entries))
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+
+            async fn remove_session<'a>(&'a self, session_id: &'a str) -> Result<()> {
+                sqlx::query(&format!($remove, table = self.table))
+                    .bind(session_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+
+            async fn user_sessions<'a>(&'a self, user_id: &'a str) -> Result<Vec<String>> {
+                Ok(sqlx::query_scalar::<_, String>(&format!($user_sessions, table = self.table))
+                    .bind(user_id)
+                    .bind(now_millis())
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?)
+            }
+
+            async fn remove_user_sessions<'a>(&'a self, user_id: &'a str) -> Result<()> {
+                sqlx::query(&format!($remove_user_sessions, table = self.table))
+                    .bind(user_id)
+                    .execute(&self.pool)
+                    .await
+                    .map_err(SqlSessionError::Sqlx)?;
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sql_storage!(
+    feature = "postgres-session",
+    database = sqlx::Postgres,
+    create = [
+        "CREATE TABLE IF NOT EXISTS {table} (id VARCHAR(128) PRIMARY KEY, entries TEXT NOT NULL, expires_at BIGINT, user_id VARCHAR(128))",
+        "CREATE INDEX IF NOT EXISTS {table}_expires_at ON {table} (expires_at)",
+        "CREATE INDEX IF NOT EXISTS {table}_user_id ON {table} (user_id)"
+    ],
+    load = "SELECT entries FROM {table} WHERE id = $1 AND (expires_at IS NULL OR expires_at > $2)",
+    update = "INSERT INTO {table} (id, entries, expires_at, user_id) VALUES ($1, $2, $3, $4) ON CONFLICT (id) DO UPDATE SET entries = EXCLUDED.entries, expires_at = EXCLUDED.expires_at, user_id = EXCLUDED.user_id",
+    remove = "DELETE FROM {table} WHERE id = $1",
+    user_sessions =
+        "SELECT id FROM {table} WHERE user_id = $1 AND (expires_at IS NULL OR expires_at > $2)",
+    remove_user_sessions = "DELETE FROM {table} WHERE user_id = $1",
+    purge = "DELETE FROM {table} WHERE expires_at <= $1",
+);
+
+impl_sql_storage!(
+    feature = "sqlite-session",
+    database = sqlx::Sqlite,
+    create = [
+        "CREATE TABLE IF NOT EXISTS {table} (id VARCHAR(128) PRIMARY KEY NOT NULL, entries TEXT NOT NULL, expires_at BIGINT, user_id VARCHAR(128))",
+        "CREATE INDEX IF NOT EXISTS {table}_expires_at ON {table} (expires_at)",
+        "CREATE INDEX IF NOT EXISTS {table}_user_id ON {table} (user_id)"
+    ],
+    load = "SELECT entries FROM {table} WHERE id = ? AND (expires_at IS NULL OR expires_at > ?)",
+    update = "INSERT INTO {table} (id, entries, expires_at, user_id) VALUES (?, ?, ?, ?) ON CONFLICT (id) DO UPDATE SET entries = excluded.entries, expires_at = excluded.expires_at, user_id = excluded.user_id",
+    remove = "DELETE FROM {table} WHERE id = ?",
+    user_sessions =
+        "SELECT id FROM {table} WHERE user_id = ? AND (expires_at IS NULL OR expires_at > ?)",
+    remove_user_sessions = "DELETE FROM {table} WHERE user_id = ?",
+    purge = "DELETE FROM {table} WHERE expires_at <= ?",
+);
+
+impl_sql_storage!(
+    feature = "mysql-session",
+    database = sqlx::MySql,
+    create = [
+        "CREATE TABLE IF NOT EXISTS {table} (id VARCHAR(128) PRIMARY KEY, entries TEXT NOT NULL, expires_at BIGINT, user_id VARCHAR(128), INDEX {table}_expires_at (expires_at), INDEX {table}_user_id (user_id))"
+    ],
+    load = "SELECT entries FROM {table} WHERE id = ? AND (expires_at IS NULL OR expires_at > ?)",
+    update = "INSERT INTO {table} (id, entries, expires_at, user_id) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE entries = VALUES(entries), expires_at = VALUES(expires_at), user_id = VALUES(user_id)",
+    remove = "DELETE FROM {table} WHERE id = ?",
+    user_sessions =
+        "SELECT id FROM {table} WHERE user_id = ? AND (expires_at IS NULL OR expires_at > ?)",
+    remove_user_sessions = "DELETE FROM {table} WHERE user_id = ?",
+    purge = "DELETE FROM {table} WHERE expires_at <= ?",
+);
+
+#[cfg(all(test, feature = "sqlite-session"))]
+mod tests {
+    use sqlx::{Sqlite, sqlite::SqlitePoolOptions};
+
+    use super::*;
+    use crate::{
+        EndpointExt, Route,
+        session::{
+            CookieConfig, ServerSession,
+            test_harness::{TestClient, index},
+        },
+    };
+
+    async fn storage() -> SqlStorage<Sqlite> {
+        // every connection to an in-memory database opens a new database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let storage = SqlStorage::new(pool).table_name("poem_sessions");
+        storage.migrate().await.unwrap();
+        // idempotent
+        storage.migrate().await.unwrap();
+        storage
+    }
+
+    #[tokio::test]
+    async fn sqlite_session() {
+        let app = Route::new()
+            .at("/:action", index)
+            .with(ServerSession::new(CookieConfig::default(), storage().await));
+        let mut client = TestClient::default();
+
+        client.call(&app, 0).await;
+        client.assert_cookies(vec![]);
+
+        client.call(&app, 1).await;
+        client.call(&app, 2).await;
+        client.call(&app, 7).await;
+        client.call(&app, 6).await;
+        client.call(&app, 3).await;
+        client.call(&app, 4).await;
+        client.call(&app, 5).await;
+        client.assert_cookies(vec![]);
+    }
+
+    #[tokio::test]
+    async fn expiry() {
+        let storage = storage().await;
+        let mut values = BTreeMap::new();
+        values.insert("value".to_string(), "1".into());
+
+        storage
+            .update_session("a", &values, Some(Duration::from_millis(100)))
+            .await
+            .unwrap();
+        storage
+            .update_session("b", &values, Some(Duration::from_millis(100)))
+            .await
+            .unwrap();
+        storage.update_session("c", &values, None).await.unwrap();
+        assert_eq!(
+            storage.load_session("a").await.unwrap(),
+            Some(values.clone())
+        );
+
+        // update
+        values.insert("value".to_string(), "2".into());
+        storage.update_session("c", &values, None).await.unwrap();
+        assert_eq!(
+            storage.load_session("c").await.unwrap(),
+            Some(values.clone())
+        );
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(storage.load_session("a").await.unwrap(), None);
+        assert_eq!(storage.purge_expired().await.unwrap(), 2);
+        assert_eq!(storage.purge_expired().await.unwrap(), 0);
+        assert_eq!(
+            storage.load_session("c").await.unwrap(),
+            Some(values.clone())
+        );
+
+        storage.remove_session("c").await.unwrap();
+        assert_eq!(storage.load_session("c").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn user_sessions() {
+        let storage = storage().await;
+        let mut values = BTreeMap::new();
+        values.insert("_poem_user".to_string(), "sunli".into());
+
+        storage.update_session("a", &values, None).await.unwrap();
+        storage.update_session("b", &values, None).await.unwrap();
+        storage
+            .update_session("c", &BTreeMap::new(), None)
+            .await
+            .unwrap();
+
+        let mut sessions = storage.user_sessions("sunli").await.unwrap();
+        sessions.sort();
+        assert_eq!(sessions, vec!["a", "b"]);
+
+        storage.remove_user_sessions("sunli").await.unwrap();
+        assert!(storage.user_sessions("sunli").await.unwrap().is_empty());
+        assert_eq!(storage.load_session("a").await.unwrap(), None);
+        assert!(storage.load_session("c").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn purge_task() {
+        let storage = storage().await.purge_interval(Duration::from_millis(100));
+        let values = BTreeMap::new();
+
+        storage
+            .update_session("a", &values, Some(Duration::from_millis(50)))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(storage.purge_expired().await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    future::Future,
+    sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::task::AbortHandle;
+
+/// A periodic cleanup task of a session storage.
+///
+/// The task is spawned on first use, so that the storage can be created
+/// outside of a Tokio runtime, and is aborted when the storage is dropped.
+#[derive(Default)]
+pub(crate) struct Sweeper(OnceLock<AbortHandle>);
+
+impl Sweeper {
+    pub(crate) fn start<F, Fut>(&self, interval: Duration, f: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.0.get_or_init(|| {
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    f().await;
+                }
+            })
+            .abort_handle()
+        });
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        if let Some(handle) = self.0.get() {
+            handle.abort();
+        }
+    }
+}
+
+/// Returns the number of milliseconds since the Unix epoch.
+pub(crate) fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+/// Returns the expiration time in milliseconds since the Unix epoch.
+pub(crate) fn expires_at(expires: Option<Duration>) -> Option<i64> {
+    expires.map(|expires| now_millis().saturating_add(expires.as_millis() as i64))
+}
@@ -128,6 +128,22 @@ impl<E: Endpoint> TestClient<E> {
         TestRequestBuilder::new(self, method, uri.into())
     }
 
+    /// Upgrades a `GET` request to the specified uri to WebSocket, see
+    /// [`TestWebSocket`](crate::test::TestWebSocket).
+    ///
+    /// The request is handled by the endpoint through an in-memory
+    /// connection. Use [`TestRequestBuilder::websocket`] to add headers to
+    /// the request.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the endpoint does not upgrade the connection.
+    #[cfg(all(feature = "websocket", feature = "server"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "websocket", feature = "server"))))]
+    pub async fn websocket(&self, uri: impl Into<String>) -> crate::test::TestWebSocket {
+        self.get(uri).websocket().await
+    }
+
     impl_methods!(
         /// Create a [`TestRequestBuilder`] with `GET` method.
         (get, GET),
@@ -114,9 +114,14 @@ mod form;
 mod json;
 mod request_builder;
 mod response;
+#[cfg(all(feature = "websocket", feature = "server"))]
+mod websocket;
 
 pub use client::TestClient;
 pub use form::{TestForm, TestFormField};
 pub use json::{TestJson, TestJsonArray, TestJsonObject, TestJsonValue};
 pub use request_builder::TestRequestBuilder;
 pub use response::TestResponse;
+#[cfg(all(feature = "websocket", feature = "server"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "websocket", feature = "server"))))]
+pub use websocket::TestWebSocket;
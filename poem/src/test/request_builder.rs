@@ -216,4 +216,21 @@ impl<'a, E> TestRequestBuilder<'a, E> {
         let resp = ep.get_response(req).await;
         TestResponse::new(resp)
     }
+
+    /// Sends this request to endpoint and upgrades the connection to
+    /// WebSocket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the endpoint does not upgrade the connection.
+    #[cfg(all(feature = "websocket", feature = "server"))]
+    #[cfg_attr(docsrs, doc(cfg(all(feature = "websocket", feature = "server"))))]
+    pub async fn websocket(self) -> crate::test::TestWebSocket
+    where
+        E: Endpoint,
+    {
+        let ep = &self.cli.ep;
+        let req = self.make_request();
+        crate::test::TestWebSocket::connect(ep, req).await
+    }
 }
@@ -0,0 +1,408 @@
+use std::{
+    convert::Infallible,
+    io::{Error as IoError, ErrorKind, Result as IoResult},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use http::HeaderMap;
+use hyper::body::Incoming;
+use parking_lot::Mutex;
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest};
+
+use crate::{
+    Endpoint, Request,
+    http::uri::Scheme,
+    test::TestJson,
+    web::{
+        LocalAddr, RemoteAddr,
+        websocket::{CloseCode, Message},
+    },
+};
+
+/// A WebSocket connection for testing, which implements
+/// [`Stream<Message>`] and [`Sink<Message>`].
+///
+/// # Example
+///
+/// ```
+/// use futures_util::{SinkExt, StreamExt};
+/// use poem::{
+///     IntoResponse, Route, get, handler,
+///     test::TestClient,
+///     web::websocket::{CloseCode, Message, WebSocket},
+/// };
+///
+/// #[handler]
+/// async fn index(ws: WebSocket) -> impl IntoResponse {
+///     ws.on_upgrade(|mut socket| async move {
+///         if let Some(Ok(Message::Text(text))) = socket.next().await {
+///             let _ = socket.send(Message::Text(text.to_uppercase())).await;
+///         }
+///         let _ = socket
+///             .send(Message::close_with(CloseCode::Normal, "bye"))
+///             .await;
+///     })
+/// }
+///
+/// let app = Route::new().at("/ws", get(index));
+/// let cli = TestClient::new(app);
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let mut ws = cli.websocket("/ws").await;
+/// ws.send_text("hello").await;
+/// ws.assert_text("HELLO").await;
+/// ws.assert_close(CloseCode::Normal).await;
+/// # });
+/// ```
+pub struct TestWebSocket {
+    inner: tokio_tungstenite::WebSocketStream<ClientIo>,
+    headers: HeaderMap,
+}
+
+/// The client side of the in-memory connection.
+///
+/// Like a TCP socket, the data written after the endpoint has dropped the
+/// connection is discarded, so that the buffered messages can still be read.
+struct ClientIo(DuplexStream);
+
+fn ignore_broken_pipe<T>(res: IoResult<T>, value: T) -> IoResult<T> {
+    match res {
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(value),
+        res => res,
+    }
+}
+
+impl AsyncRead for ClientIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ClientIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<IoResult<usize>> {
+        let res = ready!(Pin::new(&mut self.0).poll_write(cx, buf));
+        Poll::Ready(ignore_broken_pipe(res, buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let res = ready!(Pin::new(&mut self.0).poll_flush(cx));
+        Poll::Ready(ignore_broken_pipe(res, ()))
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let res = ready!(Pin::new(&mut self.0).poll_shutdown(cx));
+        Poll::Ready(ignore_broken_pipe(res, ()))
+    }
+}
+
+impl TestWebSocket {
+    /// Performs the upgrade handshake with the endpoint through an in-memory
+    /// connection.
+    pub(crate) async fn connect(ep: &impl Endpoint, mut req: Request) -> Self {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+
+        let uri = req
+            .uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str())
+            .unwrap_or("/");
+        let mut request = format!("ws://localhost{uri}")
+            .into_client_request()
+            .expect("valid uri");
+        request
+            .headers_mut()
+            .extend(std::mem::take(req.headers_mut()));
+
+        let extensions = Mutex::new(Some(std::mem::take(req.extensions_mut())));
+        let service = hyper::service::service_fn(|req: http::Request<Incoming>| {
+            let extensions = extensions.lock().take().unwrap_or_default();
+            async move {
+                let mut req: Request = (
+                    req,
+                    LocalAddr::default(),
+                    RemoteAddr::default(),
+                    Scheme::HTTP,
+                )
+                    .into();
+                req.extensions_mut().extend(extensions);
+                Ok::<http::Response<_>, Infallible>(ep.get_response(req).await.into())
+            }
+        });
+        let conn = hyper::server::conn::http1::Builder::new()
+            .serve_connection(hyper_util::rt::TokioIo::new(server_io), service)
+            .with_upgrades();
+
+        // the connection is finished after the upgrade, or when the handshake fails
+        // and the client side of the connection is dropped
+        let (_, res) = tokio::join!(
+            conn,
+            tokio_tungstenite::client_async(request, ClientIo(client_io))
+        );
+        match res {
+            Ok((inner, resp)) => Self {
+                inner,
+                headers: resp.headers().clone(),
+            },
+            Err(tungstenite::Error::Http(resp)) => {
+                panic!("websocket upgrade failed with status {}", resp.status())
+            }
+            Err(err) => panic!("websocket upgrade failed: {err}"),
+        }
+    }
+
+    /// Returns the headers of the upgrade response.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Sends a message.
+    pub async fn send_message(&mut self, msg: Message) {
+        self.inner
+            .send(msg.into())
+            .await
+            .expect("failed to send the message");
+    }
+
+    /// Sends a text message.
+    pub async fn send_text(&mut self, text: impl Into<String>) {
+        self.send_message(Message::text(text)).await;
+    }
+
+    /// Sends a binary message.
+    pub async fn send_binary(&mut self, data: impl Into<Vec<u8>>) {
+        self.send_message(Message::binary(data)).await;
+    }
+
+    /// Sends a text message containing the serialized JSON.
+    pub async fn send_json(&mut self, json: &impl Serialize) {
+        self.send_text(serde_json::to_string(json).expect("valid json"))
+            .await;
+    }
+
+    /// Sends a close message with the specified code and reason.
+    pub async fn close(&mut self, code: impl Into<CloseCode>, reason: impl Into<String>) {
+        self.send_message(Message::close_with(code, reason)).await;
+    }
+
+    /// Receives the next message, ignoring the ping and pong messages.
+    ///
+    /// Returns `None` if the connection is closed.
+    pub async fn receive(&mut self) -> Option<Message> {
+        loop {
+            match self.inner.next().await? {
+                Ok(tungstenite::Message::Ping(_) | tungstenite::Message::Pong(_)) => continue,
+                Ok(msg) => return Some(msg.into()),
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return None;
+                }
+                Err(err) => panic!("failed to receive the message: {err}"),
+            }
+        }
+    }
+
+    async fn expect_message(&mut self) -> Message {
+        self.receive().await.expect("expect message")
+    }
+
+    /// Asserts that the next message is a text message and it equals to
+    /// `text`.
+    pub async fn assert_text(&mut self, text: impl AsRef<str>) {
+        match self.expect_message().await {
+            Message::Text(value) => assert_eq!(value, text.as_ref()),
+            msg => panic!("expect text message, got {msg:?}"),
+        }
+    }
+
+    /// Asserts that the next message is a binary message and it equals to
+    /// `bytes`.
+    pub async fn assert_binary(&mut self, bytes: impl AsRef<[u8]>) {
+        match self.expect_message().await {
+            Message::Binary(value) => assert_eq!(value, bytes.as_ref()),
+            msg => panic!("expect binary message, got {msg:?}"),
+        }
+    }
+
+    /// Asserts that the next message is a text message containing JSON and it
+    /// equals to `json`.
+    pub async fn assert_json(&mut self, json: impl Serialize) {
+        assert_eq!(
+            self.receive_json::<Value>().await,
+            serde_json::to_value(json).expect("valid json")
+        );
+    }
+
+    /// Asserts that the next message is a close message with the specified
+    /// code.
+    pub async fn assert_close(&mut self, code: impl Into<CloseCode>) {
+        match self.expect_message().await {
+            Message::Close(Some((value, _))) => assert_eq!(value, code.into()),
+            msg => panic!("expect close message, got {msg:?}"),
+        }
+    }
+
+    /// Asserts that the next message is a close message with the specified
+    /// code and reason.
+    pub async fn assert_close_with(&mut self, code: impl Into<CloseCode>, reason: impl AsRef<str>) {
+        match self.expect_message().await {
+            Message::Close(Some((value, value_reason))) => {
+                assert_eq!(value, code.into());
+                assert_eq!(value_reason, reason.as_ref());
+            }
+            msg => panic!("expect close message, got {msg:?}"),
+        }
+    }
+
+    /// Asserts that the connection is closed.
+    pub async fn assert_closed(&mut self) {
+        loop {
+            match self.receive().await {
+                Some(Message::Close(_)) => {}
+                Some(msg) => panic!("expect the connection to be closed, got {msg:?}"),
+                None => return,
+            }
+        }
+    }
+
+    /// Receives the next message and deserializes it from JSON.
+    pub async fn receive_json<T: DeserializeOwned>(&mut self) -> T {
+        match self.expect_message().await {
+            Message::Text(text) => serde_json::from_str(&text).expect("valid json"),
+            Message::Binary(data) => serde_json::from_slice(&data).expect("valid json"),
+            msg => panic!("expect text or binary message, got {msg:?}"),
+        }
+    }
+
+    /// Receives the next message and returns the [`TestJson`].
+    pub async fn json(&mut self) -> TestJson {
+        self.receive_json().await
+    }
+}
+
+impl Stream for TestWebSocket {
+    type Item = IoResult<Message>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner
+            .poll_next_unpin(cx)
+            .map(|res| res.map(|res| res.map(Into::into).map_err(IoError::other)))
+    }
+}
+
+impl Sink<Message> for TestWebSocket {
+    type Error = IoError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready_unpin(cx).map_err(IoError::other)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        self.inner
+            .start_send_unpin(item.into())
+            .map_err(IoError::other)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_flush_unpin(cx).map_err(IoError::other)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close_unpin(cx).map_err(IoError::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        IntoResponse, Route, get, handler,
+        test::TestClient,
+        web::{Data, Query, websocket::WebSocket},
+    };
+
+    #[handler(internal)]
+    async fn index(
+        ws: WebSocket,
+        Query(params): Query<BTreeMap<String, String>>,
+        Data(greeting): Data<&&'static str>,
+    ) -> impl IntoResponse {
+        let greeting = format!("{greeting}, {}", params["name"]);
+        ws.protocols(["chat"]).on_upgrade(|mut socket| async move {
+            socket.send(Message::text(greeting)).await.unwrap();
+            while let Some(Ok(msg)) = socket.next().await {
+                match msg {
+                    Message::Text(text) if text == "bye" => {
+                        socket
+                            .send(Message::close_with(CloseCode::Away, "bye"))
+                            .await
+                            .unwrap();
+                    }
+                    Message::Text(text) => {
+                        let value = serde_json::from_str::<Value>(&text).unwrap();
+                        socket
+                            .send(Message::text(json!({ "echo": value }).to_string()))
+                            .await
+                            .unwrap();
+                    }
+                    Message::Binary(data) => socket.send(Message::Binary(data)).await.unwrap(),
+                    _ => {}
+                }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn websocket() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+
+        let mut ws = cli
+            .get("/?name=poem")
+            .header("sec-websocket-protocol", "chat")
+            .data("hello")
+            .websocket()
+            .await;
+        assert_eq!(ws.headers().get("sec-websocket-protocol").unwrap(), "chat");
+        ws.assert_text("hello, poem").await;
+
+        ws.send_json(&json!({ "a": 1 })).await;
+        ws.assert_json(json!({ "echo": { "a": 1 } })).await;
+
+        ws.send_json(&[1, 2]).await;
+        ws.json()
+            .await
+            .value()
+            .object()
+            .get("echo")
+            .assert_i64_array(&[1, 2]);
+
+        ws.send(Message::binary([1, 2, 3])).await.unwrap();
+        ws.assert_binary([1, 2, 3]).await;
+
+        ws.send_text("bye").await;
+        ws.assert_close_with(CloseCode::Away, "bye").await;
+        ws.assert_closed().await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "websocket upgrade failed with status 404")]
+    async fn not_found() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        cli.websocket("/missing").await;
+    }
+}
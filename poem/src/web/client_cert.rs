@@ -0,0 +1,283 @@
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+use std::sync::OnceLock;
+use std::{fmt::Write, net::IpAddr, sync::Arc};
+
+use http::Extensions;
+use ring::digest::{SHA256, digest};
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+
+use crate::{FromRequest, Request, RequestBody, Result, error::ClientCertError};
+
+/// A subject alternative name of a [`ClientCert`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SubjectAltName {
+    /// A DNS name.
+    Dns(String),
+    /// An email address.
+    Email(String),
+    /// A URI, for example a SPIFFE ID.
+    Uri(String),
+    /// An IP address.
+    Ip(IpAddr),
+}
+
+#[derive(Debug)]
+struct Inner {
+    chain: Vec<Vec<u8>>,
+    subject: String,
+    common_name: Option<String>,
+    issuer: String,
+    subject_alt_names: Vec<SubjectAltName>,
+    fingerprint: [u8; 32],
+}
+
+/// An extractor for the certificate presented by the client in a mutual TLS
+/// connection.
+///
+/// The certificate has been verified by the TLS listener, so the client
+/// authentication must be enabled, for example with
+/// [`RustlsConfig::client_auth_required`](crate::listener::RustlsConfig::client_auth_required).
+///
+/// Use `Option<ClientCert>` if the certificate is optional, the
+/// [`RequireClientCert`](crate::middleware::RequireClientCert) middleware
+/// checks the certificates for a group of routes.
+///
+/// In the tests, a certificate can be added to the requests with
+/// [`EndpointExt::data`](crate::EndpointExt::data).
+///
+/// # Example
+///
+/// ```
+/// use poem::{Route, get, handler, web::ClientCert};
+///
+/// #[handler]
+/// fn index(cert: ClientCert) -> String {
+///     format!("hello {}", cert.common_name().unwrap_or_default())
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "client-cert")))]
+#[derive(Debug, Clone)]
+pub struct ClientCert(Arc<Inner>);
+
+impl ClientCert {
+    /// Parses a DER encoded certificate.
+    pub fn from_der(der: impl Into<Vec<u8>>) -> Result<Self, ClientCertError> {
+        Self::from_chain(vec![der.into()])
+    }
+
+    /// Parses a chain of DER encoded certificates, the first one is the
+    /// client certificate.
+    pub fn from_chain(chain: Vec<Vec<u8>>) -> Result<Self, ClientCertError> {
+        let der = chain
+            .first()
+            .ok_or_else(|| ClientCertError::Invalid("empty certificate chain".to_string()))?;
+        let (_, cert) = X509Certificate::from_der(der)
+            .map_err(|err| ClientCertError::Invalid(err.to_string()))?;
+
+        let subject_alt_names = cert
+            .subject_alternative_name()
+            .map_err(|err| ClientCertError::Invalid(err.to_string()))?
+            .map(|ext| {
+                ext.value
+                    .general_names
+                    .iter()
+                    .filter_map(|name| match name {
+                        GeneralName::DNSName(name) => Some(SubjectAltName::Dns(name.to_string())),
+                        GeneralName::RFC822Name(email) => {
+                            Some(SubjectAltName::Email(email.to_string()))
+                        }
+                        GeneralName::URI(uri) => Some(SubjectAltName::Uri(uri.to_string())),
+                        GeneralName::IPAddress(ip) => match ip.len() {
+                            4 => <[u8; 4]>::try_from(*ip).ok().map(IpAddr::from),
+                            16 => <[u8; 16]>::try_from(*ip).ok().map(IpAddr::from),
+                            _ => None,
+                        }
+                        .map(SubjectAltName::Ip),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(ToString::to_string);
+        let subject = cert.subject().to_string();
+        let issuer = cert.issuer().to_string();
+
+        let mut fingerprint = [0; 32];
+        fingerprint.copy_from_slice(digest(&SHA256, der).as_ref());
+
+        Ok(Self(Arc::new(Inner {
+            chain,
+            subject,
+            common_name,
+            issuer,
+            subject_alt_names,
+            fingerprint,
+        })))
+    }
+
+    /// Returns the subject of the certificate, for example `CN=client,
+    /// O=poem`.
+    pub fn subject(&self) -> &str {
+        &self.0.subject
+    }
+
+    /// Returns the common name of the subject.
+    pub fn common_name(&self) -> Option<&str> {
+        self.0.common_name.as_deref()
+    }
+
+    /// Returns the issuer of the certificate.
+    pub fn issuer(&self) -> &str {
+        &self.0.issuer
+    }
+
+    /// Returns the subject alternative names of the certificate.
+    pub fn subject_alt_names(&self) -> &[SubjectAltName] {
+        &self.0.subject_alt_names
+    }
+
+    /// Returns the SHA-256 fingerprint of the certificate.
+    pub fn fingerprint(&self) -> &[u8; 32] {
+        &self.0.fingerprint
+    }
+
+    /// Returns the SHA-256 fingerprint of the certificate as a lowercase
+    /// hexadecimal string.
+    pub fn fingerprint_hex(&self) -> String {
+        self.0
+            .fingerprint
+            .iter()
+            .fold(String::with_capacity(64), |mut s, b| {
+                _ = write!(s, "{b:02x}");
+                s
+            })
+    }
+
+    /// Returns the DER encoded certificate.
+    pub fn der(&self) -> &[u8] {
+        &self.0.chain[0]
+    }
+
+    /// Returns the DER encoded certificate chain presented by the client, the
+    /// first one is the client certificate.
+    ///
+    /// The `native-tls` listener only provides the client certificate.
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.0.chain
+    }
+
+    pub(crate) fn from_extensions(extensions: &Extensions) -> Option<Self> {
+        let cert = extensions.get::<ClientCert>().cloned();
+        #[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+        let cert = cert.or_else(|| {
+            extensions
+                .get::<PeerCertificate>()
+                .and_then(|peer| peer.0.get().cloned())
+        });
+        cert
+    }
+}
+
+impl<'a> FromRequest<'a> for ClientCert {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(Self::from_extensions(req.extensions()).ok_or(ClientCertError::Missing)?)
+    }
+}
+
+/// The certificate of the client of a TLS connection, which is set when the
+/// handshake is completed.
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+#[derive(Clone, Default)]
+pub(crate) struct PeerCertificate(Arc<OnceLock<ClientCert>>);
+
+#[cfg(any(feature = "rustls", feature = "native-tls", feature = "openssl-tls"))]
+impl PeerCertificate {
+    /// Adds a `PeerCertificate` to the extensions of a connection.
+    pub(crate) fn attach(mut extensions: Extensions) -> (Extensions, Self) {
+        let peer = Self::default();
+        extensions.insert(peer.clone());
+        (extensions, peer)
+    }
+
+    pub(crate) fn set(&self, chain: Vec<Vec<u8>>) {
+        match ClientCert::from_chain(chain) {
+            Ok(cert) => _ = self.0.set(cert),
+            Err(err) => tracing::warn!(error = %err, "failed to parse the client certificate"),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+        SanType,
+    };
+
+    use super::*;
+
+    /// Generates a CA and a client certificate signed by it, returns the
+    /// CA certificate in PEM, and the client certificate and key in DER.
+    pub(crate) fn generate_client_cert(cn: &str, sans: Vec<SanType>) -> (String, Vec<u8>, Vec<u8>) {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "poem ca");
+        let ca = Certificate::from_params(params).unwrap();
+
+        let mut params = CertificateParams::new(vec![]);
+        params.distinguished_name.push(DnType::CommonName, cn);
+        params.subject_alt_names = sans;
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        let cert = Certificate::from_params(params).unwrap();
+
+        (
+            ca.serialize_pem().unwrap(),
+            cert.serialize_der_with_signer(&ca).unwrap(),
+            cert.serialize_private_key_der(),
+        )
+    }
+
+    #[test]
+    fn parse() {
+        let (_, der, _) = generate_client_cert(
+            "client",
+            vec![
+                SanType::DnsName("client.example.com".to_string()),
+                SanType::URI("spiffe://example.com/client".to_string()),
+                SanType::Rfc822Name("client@example.com".to_string()),
+                SanType::IpAddress("127.0.0.1".parse().unwrap()),
+            ],
+        );
+        let cert = ClientCert::from_der(der.clone()).unwrap();
+
+        assert_eq!(cert.subject(), "CN=client");
+        assert_eq!(cert.common_name(), Some("client"));
+        assert_eq!(cert.issuer(), "CN=poem ca");
+        assert_eq!(
+            cert.subject_alt_names(),
+            &[
+                SubjectAltName::Dns("client.example.com".to_string()),
+                SubjectAltName::Uri("spiffe://example.com/client".to_string()),
+                SubjectAltName::Email("client@example.com".to_string()),
+                SubjectAltName::Ip("127.0.0.1".parse().unwrap()),
+            ]
+        );
+        assert_eq!(cert.fingerprint(), digest(&SHA256, &der).as_ref());
+        assert_eq!(cert.fingerprint_hex().len(), 64);
+        assert_eq!(cert.der(), der);
+
+        assert!(matches!(
+            ClientCert::from_der(b"abc".to_vec()),
+            Err(ClientCertError::Invalid(_))
+        ));
+    }
+}
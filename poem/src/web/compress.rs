@@ -1,4 +1,5 @@
 use std::{
+    collections::HashSet,
     fmt::{self, Display, Formatter},
     pin::Pin,
     str::FromStr,
@@ -105,6 +106,78 @@ impl CompressionAlgo {
     }
 }
 
+#[inline]
+fn algo_priority(algo: CompressionAlgo) -> u8 {
+    match algo {
+        CompressionAlgo::DEFLATE => 1,
+        CompressionAlgo::GZIP => 2,
+        CompressionAlgo::BR => 3,
+        CompressionAlgo::ZSTD => 4,
+    }
+}
+
+/// Selects the preferred algorithm according to the values of the
+/// `Accept-Encoding` header.
+///
+/// Only the `enabled` algorithms are considered, or all of them if it is
+/// empty.
+pub(crate) fn negotiate_encoding<'a>(
+    accept_encoding: impl IntoIterator<Item = &'a HeaderValue>,
+    enabled: &HashSet<CompressionAlgo>,
+) -> Option<CompressionAlgo> {
+    let is_enabled = |algo| enabled.is_empty() || enabled.contains(&algo);
+    let parse_algo = |e: &str| {
+        [
+            CompressionAlgo::BR,
+            CompressionAlgo::DEFLATE,
+            CompressionAlgo::GZIP,
+            CompressionAlgo::ZSTD,
+        ]
+        .into_iter()
+        .find(|algo| e.eq_ignore_ascii_case(algo.as_str()))
+    };
+
+    let codings = accept_encoding
+        .into_iter()
+        .filter_map(|hval| hval.to_str().ok())
+        .flat_map(|s| s.split(',').map(str::trim))
+        .filter_map(|v| match v.split_once(";q=") {
+            Some((e, q)) => Some((e, (q.parse::<f32>().ok()? * 1000.0) as i32)),
+            None => Some((v, 1000)),
+        })
+        .collect::<Vec<_>>();
+    // `*` never stands for a coding listed explicitly, even with `q=0`
+    let listed = codings
+        .iter()
+        .filter_map(|(e, _)| parse_algo(e))
+        .collect::<HashSet<_>>();
+
+    codings
+        .into_iter()
+        .filter_map(|(e, q)| {
+            if q <= 0 {
+                // not acceptable
+                return None;
+            }
+            if e == "*" {
+                // the preferred enabled algorithm, but after the listed ones
+                let algo = [
+                    CompressionAlgo::ZSTD,
+                    CompressionAlgo::BR,
+                    CompressionAlgo::GZIP,
+                    CompressionAlgo::DEFLATE,
+                ]
+                .into_iter()
+                .find(|algo| is_enabled(*algo) && !listed.contains(algo))?;
+                return Some((algo, q, 0));
+            }
+            let algo = parse_algo(e)?;
+            is_enabled(algo).then_some((algo, q, algo_priority(algo)))
+        })
+        .max_by_key(|(_, q, priority)| (*q, *priority))
+        .map(|(algo, _, _)| algo)
+}
+
 impl Display for CompressionAlgo {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.as_str())
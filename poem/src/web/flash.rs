@@ -0,0 +1,500 @@
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "session")]
+use crate::session::Session;
+use crate::{
+    FromRequest, IntoResponse, Request, RequestBody, Response, Result,
+    error::FlashError,
+    web::cookie::{Cookie, CookieJar},
+};
+
+/// The session entry and the cookie which store the flash messages.
+const FLASH_KEY: &str = "_poem_flash";
+
+/// The level of a flash message.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlashLevel {
+    /// Debug
+    Debug,
+    /// Info
+    Info,
+    /// Success
+    Success,
+    /// Warning
+    Warning,
+    /// Error
+    Error,
+}
+
+impl Display for FlashLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FlashLevel::Debug => "debug",
+            FlashLevel::Info => "info",
+            FlashLevel::Success => "success",
+            FlashLevel::Warning => "warning",
+            FlashLevel::Error => "error",
+        })
+    }
+}
+
+/// A flash message.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FlashMessage {
+    /// The level of the message.
+    pub level: FlashLevel,
+    /// The message.
+    pub message: String,
+}
+
+/// A list of flash messages.
+///
+/// It is serialized as an array of `{"level": ..., "message": ...}` objects,
+/// so that it can be passed to a template as is.
+///
+/// # Response
+///
+/// Returning `(FlashMessages, T)` from a handler stores the messages for the
+/// next request, which can read them with the [`Flash`] extractor. This
+/// requires the [`FlashMiddleware`](crate::middleware::FlashMiddleware)
+/// middleware, the messages are ignored without it.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route, handler,
+///     middleware::{CookieJarManager, FlashMiddleware},
+///     post,
+///     web::{FlashMessages, Redirect, cookie::CookieKey},
+/// };
+///
+/// #[handler]
+/// fn save() -> (FlashMessages, Redirect) {
+///     (
+///         FlashMessages::new().success("The post has been saved."),
+///         Redirect::see_other("/posts"),
+///     )
+/// }
+///
+/// let app = Route::new()
+///     .at("/save", post(save))
+///     .with(FlashMiddleware::new())
+///     .with(CookieJarManager::with_key(CookieKey::generate()));
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FlashMessages(Vec<FlashMessage>);
+
+macro_rules! define_level_methods {
+    ($($(#[$docs:meta])* ($name:ident, $level:ident)),*) => {
+        $(
+        $(#[$docs])*
+        #[must_use]
+        pub fn $name(self, message: impl Into<String>) -> Self {
+            self.message(FlashLevel::$level, message)
+        }
+        )*
+    };
+}
+
+impl FlashMessages {
+    /// Create an empty list of flash messages.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a message with the specified level.
+    #[must_use]
+    pub fn message(mut self, level: FlashLevel, message: impl Into<String>) -> Self {
+        self.0.push(FlashMessage {
+            level,
+            message: message.into(),
+        });
+        self
+    }
+
+    define_level_methods!(
+        /// Appends a debug message.
+        (debug, Debug),
+        /// Appends an info message.
+        (info, Info),
+        /// Appends a success message.
+        (success, Success),
+        /// Appends a warning message.
+        (warning, Warning),
+        /// Appends an error message.
+        (error, Error)
+    );
+
+    /// Returns the number of messages.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if there are no messages.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns an iterator over the messages.
+    pub fn iter(&self) -> impl Iterator<Item = &FlashMessage> {
+        self.0.iter()
+    }
+
+    /// Returns an iterator over the messages with the specified level.
+    pub fn with_level(&self, level: FlashLevel) -> impl Iterator<Item = &FlashMessage> {
+        self.0.iter().filter(move |message| message.level == level)
+    }
+}
+
+impl IntoIterator for FlashMessages {
+    type Item = FlashMessage;
+    type IntoIter = std::vec::IntoIter<FlashMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a FlashMessages {
+    type Item = &'a FlashMessage;
+    type IntoIter = std::slice::Iter<'a, FlashMessage>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<T: IntoResponse> IntoResponse for (FlashMessages, T) {
+    fn into_response(self) -> Response {
+        let mut resp = self.1.into_response();
+        if !self.0.is_empty() {
+            resp.extensions_mut().insert(self.0);
+        }
+        resp
+    }
+}
+
+/// Where the flash messages are stored.
+pub(crate) enum Store {
+    #[cfg(feature = "session")]
+    Session(Session),
+    /// A cookie jar with a key.
+    CookieJar(CookieJar),
+}
+
+impl Store {
+    pub(crate) fn from_request(req: &Request) -> Result<Self, FlashError> {
+        #[cfg(feature = "session")]
+        if let Some(session) = req.extensions().get::<Session>() {
+            return Ok(Store::Session(session.clone()));
+        }
+        match &req.state().cookie_jar {
+            Some(cookie_jar) if cookie_jar.key.is_some() => {
+                Ok(Store::CookieJar(cookie_jar.clone()))
+            }
+            _ => Err(FlashError::MissingStore),
+        }
+    }
+
+    fn get(&self) -> Option<FlashMessages> {
+        match self {
+            #[cfg(feature = "session")]
+            Store::Session(session) => session.get(FLASH_KEY),
+            Store::CookieJar(cookie_jar) => cookie_jar
+                .signed()
+                .get(FLASH_KEY)
+                .and_then(|cookie| cookie.value().ok()),
+        }
+    }
+
+    fn set(&self, messages: &FlashMessages) {
+        match self {
+            #[cfg(feature = "session")]
+            Store::Session(session) => session.set(FLASH_KEY, messages),
+            Store::CookieJar(cookie_jar) => {
+                let mut cookie = Cookie::new(FLASH_KEY, messages);
+                cookie.set_path("/");
+                cookie.set_http_only(true);
+                cookie_jar.signed().add(cookie);
+            }
+        }
+    }
+
+    fn remove(&self) {
+        match self {
+            #[cfg(feature = "session")]
+            Store::Session(session) => session.remove(FLASH_KEY),
+            Store::CookieJar(cookie_jar) => {
+                let mut cookie = Cookie::named(FLASH_KEY);
+                cookie.set_path("/");
+                cookie.make_removal();
+                cookie_jar.add(cookie);
+            }
+        }
+    }
+
+    pub(crate) fn append(&self, messages: FlashMessages) {
+        let mut pending = self.get().unwrap_or_default();
+        pending.0.extend(messages);
+        self.set(&pending);
+    }
+}
+
+/// An extractor for the flash messages.
+///
+/// Extracting it consumes the messages stored by the previous request, and
+/// the messages added with it are available to the next request.
+///
+/// The messages are stored in the [`Session`](crate::session::Session) if the
+/// `CookieSession` or `ServerSession` middleware is used, otherwise in a
+/// signed cookie, which requires the
+/// [`CookieJarManager::with_key`](crate::middleware::CookieJarManager::with_key)
+/// middleware.
+///
+/// # Example
+///
+/// ```
+/// use poem::{
+///     EndpointExt, Route, get, handler,
+///     middleware::CookieJarManager,
+///     post,
+///     web::{Flash, Redirect, cookie::CookieKey},
+/// };
+///
+/// #[handler]
+/// fn index(flash: Flash) -> String {
+///     flash
+///         .messages()
+///         .iter()
+///         .map(|message| format!("{}: {}\n", message.level, message.message))
+///         .collect()
+/// }
+///
+/// #[handler]
+/// fn save(flash: Flash) -> Redirect {
+///     flash.success("The post has been saved.");
+///     Redirect::see_other("/")
+/// }
+///
+/// let app = Route::new()
+///     .at("/", get(index))
+///     .at("/save", post(save))
+///     .with(CookieJarManager::with_key(CookieKey::generate()));
+/// ```
+pub struct Flash {
+    messages: FlashMessages,
+    store: Store,
+}
+
+impl Flash {
+    /// Returns the messages stored by the previous request.
+    pub fn messages(&self) -> &FlashMessages {
+        &self.messages
+    }
+
+    /// Consumes this extractor and returns the messages stored by the
+    /// previous request.
+    pub fn into_messages(self) -> FlashMessages {
+        self.messages
+    }
+
+    /// Adds a message for the next request.
+    pub fn message(&self, level: FlashLevel, message: impl Into<String>) {
+        self.store
+            .append(FlashMessages::new().message(level, message));
+    }
+
+    /// Adds a debug message for the next request.
+    pub fn debug(&self, message: impl Into<String>) {
+        self.message(FlashLevel::Debug, message);
+    }
+
+    /// Adds an info message for the next request.
+    pub fn info(&self, message: impl Into<String>) {
+        self.message(FlashLevel::Info, message);
+    }
+
+    /// Adds a success message for the next request.
+    pub fn success(&self, message: impl Into<String>) {
+        self.message(FlashLevel::Success, message);
+    }
+
+    /// Adds a warning message for the next request.
+    pub fn warning(&self, message: impl Into<String>) {
+        self.message(FlashLevel::Warning, message);
+    }
+
+    /// Adds an error message for the next request.
+    pub fn error(&self, message: impl Into<String>) {
+        self.message(FlashLevel::Error, message);
+    }
+}
+
+impl<'a> FromRequest<'a> for Flash {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        let store = Store::from_request(req)?;
+        let messages = match store.get() {
+            Some(messages) => {
+                store.remove();
+                messages
+            }
+            None => FlashMessages::new(),
+        };
+        Ok(Flash { messages, store })
+    }
+}
+
+#[cfg(all(test, feature = "session"))]
+mod tests {
+    use super::*;
+    use crate::{
+        Endpoint, EndpointExt, Route, handler,
+        middleware::{CookieJarManager, FlashMiddleware},
+        session::{CookieConfig, CookieSession, MemoryStorage, ServerSession, test_harness},
+        web::{Path, cookie::CookieKey},
+    };
+
+    #[handler(internal)]
+    fn index(Path(action): Path<i32>, flash: Flash) -> Response {
+        match action {
+            1 => {
+                assert!(flash.messages().is_empty());
+                flash.info("a");
+                flash.error("b");
+                ().into_response()
+            }
+            2 => {
+                assert_eq!(
+                    flash.into_messages(),
+                    FlashMessages::new().info("a").error("b")
+                );
+                ().into_response()
+            }
+            3 => {
+                assert!(flash.messages().is_empty());
+                (FlashMessages::new().success("c"), ()).into_response()
+            }
+            4 => {
+                assert_eq!(flash.into_messages(), FlashMessages::new().success("c"));
+                ().into_response()
+            }
+            _ => ().into_response(),
+        }
+    }
+
+    async fn check(app: impl Endpoint) {
+        let mut client = test_harness::TestClient::default();
+        client.call(&app, 1).await;
+        client.call(&app, 2).await;
+        // the messages are consumed
+        client.call(&app, 1).await;
+        client.call(&app, 2).await;
+        client.call(&app, 3).await;
+        client.call(&app, 4).await;
+        client.call(&app, 3).await;
+        client.call(&app, 4).await;
+    }
+
+    #[tokio::test]
+    async fn cookie_session() {
+        check(
+            Route::new()
+                .at("/:action", index)
+                .with(FlashMiddleware::new())
+                .with(CookieSession::new(CookieConfig::default())),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn server_session() {
+        check(
+            Route::new()
+                .at("/:action", index)
+                .with(FlashMiddleware::new())
+                .with(ServerSession::new(
+                    CookieConfig::default(),
+                    MemoryStorage::new(),
+                )),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn signed_cookie() {
+        check(
+            Route::new()
+                .at("/:action", index)
+                .with(FlashMiddleware::new())
+                .with(CookieJarManager::with_key(CookieKey::generate())),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn missing_store() {
+        use crate::{http::StatusCode, test::TestClient};
+
+        #[handler(internal)]
+        fn extract(_flash: Flash) {}
+
+        #[handler(internal)]
+        fn respond() -> (FlashMessages, ()) {
+            (FlashMessages::new().info("a"), ())
+        }
+
+        let cli = TestClient::new(Route::new().at("/", extract));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let cli = TestClient::new(Route::new().at("/", extract).with(CookieJarManager::new()));
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", respond)
+                .with(FlashMiddleware::new())
+                .with(CookieJarManager::new()),
+        );
+        cli.get("/")
+            .send()
+            .await
+            .assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+        // the messages are ignored without the `FlashMiddleware`
+        let cli = TestClient::new(
+            Route::new()
+                .at("/", respond)
+                .with(CookieJarManager::with_key(CookieKey::generate())),
+        );
+        let resp = cli.get("/").send().await;
+        resp.assert_status_is_ok();
+        resp.assert_header_is_not_exist("set-cookie");
+    }
+
+    #[test]
+    fn serialize() {
+        let messages = FlashMessages::new().info("a").warning("b");
+        assert_eq!(
+            serde_json::to_string(&messages).unwrap(),
+            r#"[{"level":"info","message":"a"},{"level":"warning","message":"b"}]"#
+        );
+        assert_eq!(
+            messages
+                .with_level(FlashLevel::Warning)
+                .map(|message| message.message.as_str())
+                .collect::<Vec<_>>(),
+            vec!["b"]
+        );
+    }
+}
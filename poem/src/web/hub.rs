@@ -0,0 +1,725 @@
+//! Publish/subscribe hub to fan out messages to the WebSocket and SSE
+//! connections.
+//!
+//! # Example
+//!
+//! ```
+//! use futures_util::StreamExt;
+//! use poem::web::hub::Hub;
+//!
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let hub = Hub::new();
+//! let mut subscription = hub.join("room", "alice");
+//! assert_eq!(hub.presence("room"), vec!["alice"]);
+//!
+//! hub.publish("room", "hello".to_string());
+//! assert_eq!(subscription.next().await.as_deref(), Some("hello"));
+//! # });
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+use futures_util::Stream;
+use parking_lot::Mutex;
+
+/// The policy applied when the buffer of a subscriber is full.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SlowConsumerPolicy {
+    /// Drops the oldest message in the buffer.
+    DropOldest,
+    /// Drops the new message.
+    DropNewest,
+    /// Disconnects the subscriber, its stream ends after the buffered
+    /// messages.
+    Disconnect,
+}
+
+struct QueueState<T> {
+    buf: VecDeque<T>,
+    buffer_size: usize,
+    policy: SlowConsumerPolicy,
+    waker: Option<Waker>,
+    dropped: u64,
+    closed: bool,
+    disconnected: bool,
+}
+
+struct Subscriber<T> {
+    queue: Arc<Mutex<QueueState<T>>>,
+    member: Option<String>,
+}
+
+struct Topic<T> {
+    subscribers: HashMap<u64, Subscriber<T>>,
+    members: BTreeMap<String, usize>,
+}
+
+impl<T> Default for Topic<T> {
+    fn default() -> Self {
+        Self {
+            subscribers: Default::default(),
+            members: Default::default(),
+        }
+    }
+}
+
+impl<T> Topic<T> {
+    fn remove(&mut self, id: u64) -> Option<Subscriber<T>> {
+        let subscriber = self.subscribers.remove(&id)?;
+        if let Some(member) = &subscriber.member {
+            if let Some(count) = self.members.get_mut(member) {
+                *count -= 1;
+                if *count == 0 {
+                    self.members.remove(member);
+                }
+            }
+        }
+        Some(subscriber)
+    }
+}
+
+struct HubInner<T> {
+    topics: Mutex<HashMap<String, Topic<T>>>,
+    next_id: AtomicU64,
+}
+
+/// A publish/subscribe hub with named topics.
+///
+/// Each subscriber has its own bounded buffer, and its
+/// [`SlowConsumerPolicy`] is applied when it is full, so that a slow
+/// subscriber never blocks the publishers or the other subscribers. The
+/// topics are created by the first subscriber and removed with the last
+/// one.
+///
+/// The hub is cheap to clone, and the clones share the same topics. The
+/// buffer size and the policy of a hub are the defaults of the subscriptions
+/// created with it, and can be overridden for each subscription.
+pub struct Hub<T> {
+    inner: Arc<HubInner<T>>,
+    buffer_size: usize,
+    policy: SlowConsumerPolicy,
+}
+
+impl<T> Clone for Hub<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            buffer_size: self.buffer_size,
+            policy: self.policy,
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Default for Hub<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Send + 'static> Hub<T> {
+    /// Create a `Hub`.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(HubInner {
+                topics: Default::default(),
+                next_id: AtomicU64::new(0),
+            }),
+            buffer_size: 64,
+            policy: SlowConsumerPolicy::DropOldest,
+        }
+    }
+
+    /// Sets the size of the buffer of the subscriptions created with this hub,
+    /// default is `64`.
+    #[must_use]
+    pub fn buffer_size(self, size: usize) -> Self {
+        Self {
+            buffer_size: size.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the policy applied when the buffer of a subscription created with
+    /// this hub is full, default is [`SlowConsumerPolicy::DropOldest`].
+    #[must_use]
+    pub fn slow_consumer_policy(self, policy: SlowConsumerPolicy) -> Self {
+        Self { policy, ..self }
+    }
+
+    /// Subscribes to a topic.
+    pub fn subscribe(&self, topic: impl Into<String>) -> Subscription<T> {
+        self.add_subscriber(topic.into(), None)
+    }
+
+    /// Subscribes to a topic as a member, which is included in the
+    /// [`Hub::presence`] of the topic until the subscription is dropped.
+    pub fn join(&self, topic: impl Into<String>, member: impl Into<String>) -> Subscription<T> {
+        self.add_subscriber(topic.into(), Some(member.into()))
+    }
+
+    fn add_subscriber(&self, topic: String, member: Option<String>) -> Subscription<T> {
+        let id = self.inner.next_id.fetch_add(1, Ordering::Relaxed);
+        let queue = Arc::new(Mutex::new(QueueState {
+            buf: VecDeque::new(),
+            buffer_size: self.buffer_size,
+            policy: self.policy,
+            waker: None,
+            dropped: 0,
+            closed: false,
+            disconnected: false,
+        }));
+
+        let mut topics = self.inner.topics.lock();
+        let entry = topics.entry(topic.clone()).or_default();
+        if let Some(member) = &member {
+            *entry.members.entry(member.clone()).or_default() += 1;
+        }
+        entry.subscribers.insert(
+            id,
+            Subscriber {
+                queue: queue.clone(),
+                member: member.clone(),
+            },
+        );
+
+        Subscription {
+            hub: self.clone(),
+            topic,
+            member,
+            id,
+            queue,
+        }
+    }
+
+    /// Publishes a message to a topic, and returns the number of subscribers
+    /// which received it.
+    pub fn publish(&self, topic: &str, msg: T) -> usize {
+        let mut topics = self.inner.topics.lock();
+        let Some(entry) = topics.get_mut(topic) else {
+            return 0;
+        };
+
+        let mut count = 0;
+        let mut disconnected = Vec::new();
+        for (id, subscriber) in &entry.subscribers {
+            let mut queue = subscriber.queue.lock();
+            if queue.buf.len() >= queue.buffer_size {
+                match queue.policy {
+                    SlowConsumerPolicy::DropOldest => {
+                        // the buffer may have been shrunk
+                        while queue.buf.len() >= queue.buffer_size {
+                            queue.buf.pop_front();
+                            queue.dropped += 1;
+                        }
+                    }
+                    SlowConsumerPolicy::DropNewest => {
+                        queue.dropped += 1;
+                        continue;
+                    }
+                    SlowConsumerPolicy::Disconnect => {
+                        queue.closed = true;
+                        queue.disconnected = true;
+                        if let Some(waker) = queue.waker.take() {
+                            waker.wake();
+                        }
+                        disconnected.push(*id);
+                        continue;
+                    }
+                }
+            }
+            queue.buf.push_back(msg.clone());
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+            count += 1;
+        }
+
+        for id in disconnected {
+            entry.remove(id);
+        }
+        if entry.subscribers.is_empty() {
+            topics.remove(topic);
+        }
+        count
+    }
+
+    /// Closes a topic, the streams of its subscribers end after the buffered
+    /// messages.
+    pub fn close(&self, topic: &str) {
+        if let Some(entry) = self.inner.topics.lock().remove(topic) {
+            for subscriber in entry.subscribers.into_values() {
+                let mut queue = subscriber.queue.lock();
+                queue.closed = true;
+                if let Some(waker) = queue.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    /// Returns the members of a topic, in lexicographical order.
+    ///
+    /// A member is present as long as it has at least one subscription
+    /// created by [`Hub::join`].
+    pub fn presence(&self, topic: &str) -> Vec<String> {
+        self.inner
+            .topics
+            .lock()
+            .get(topic)
+            .map(|entry| entry.members.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns `true` if the member is present in a topic.
+    pub fn is_present(&self, topic: &str, member: &str) -> bool {
+        self.inner
+            .topics
+            .lock()
+            .get(topic)
+            .is_some_and(|entry| entry.members.contains_key(member))
+    }
+
+    /// Returns the number of subscribers of a topic.
+    pub fn subscriber_count(&self, topic: &str) -> usize {
+        self.inner
+            .topics
+            .lock()
+            .get(topic)
+            .map(|entry| entry.subscribers.len())
+            .unwrap_or_default()
+    }
+
+    /// Returns the names of the topics which have subscribers.
+    pub fn topics(&self) -> Vec<String> {
+        self.inner.topics.lock().keys().cloned().collect()
+    }
+}
+
+/// A subscription to a topic of the [`Hub`], which implements
+/// [`Stream<Item = T>`].
+///
+/// The stream ends when the topic is closed or the subscriber is
+/// disconnected by [`SlowConsumerPolicy::Disconnect`]. Dropping it
+/// unsubscribes from the topic.
+pub struct Subscription<T> {
+    hub: Hub<T>,
+    topic: String,
+    member: Option<String>,
+    id: u64,
+    queue: Arc<Mutex<QueueState<T>>>,
+}
+
+impl<T: Clone + Send + 'static> Subscription<T> {
+    /// Sets the size of the buffer of this subscription, overriding
+    /// [`Hub::buffer_size`].
+    #[must_use]
+    pub fn buffer_size(self, size: usize) -> Self {
+        self.queue.lock().buffer_size = size.max(1);
+        self
+    }
+
+    /// Sets the policy applied when the buffer of this subscription is full,
+    /// overriding [`Hub::slow_consumer_policy`].
+    #[must_use]
+    pub fn slow_consumer_policy(self, policy: SlowConsumerPolicy) -> Self {
+        self.queue.lock().policy = policy;
+        self
+    }
+
+    /// Returns the name of the topic.
+    pub fn topic(&self) -> &str {
+        &self.topic
+    }
+
+    /// Returns the member specified in [`Hub::join`].
+    pub fn member(&self) -> Option<&str> {
+        self.member.as_deref()
+    }
+
+    /// Returns the hub of this subscription.
+    pub fn hub(&self) -> &Hub<T> {
+        &self.hub
+    }
+
+    /// Publishes a message to the topic of this subscription, and returns the
+    /// number of subscribers which received it.
+    pub fn publish(&self, msg: T) -> usize {
+        self.hub.publish(&self.topic, msg)
+    }
+
+    /// Returns the number of messages dropped because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.queue.lock().dropped
+    }
+
+    /// Returns `true` if the subscriber was disconnected because the buffer
+    /// was full.
+    pub fn is_disconnected(&self) -> bool {
+        self.queue.lock().disconnected
+    }
+}
+
+impl<T: Clone + Send + 'static> Stream for Subscription<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut queue = self.queue.lock();
+        if let Some(msg) = queue.buf.pop_front() {
+            return Poll::Ready(Some(msg));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let mut topics = self.hub.inner.topics.lock();
+        if let Some(entry) = topics.get_mut(&self.topic) {
+            entry.remove(self.id);
+            if entry.subscribers.is_empty() {
+                topics.remove(&self.topic);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "sse")]
+impl<T: Clone + Send + 'static> Subscription<T> {
+    /// Consumes this subscription and returns a [`SSE`](crate::web::sse::SSE)
+    /// response which sends an event for each message.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     EndpointExt, Route, get, handler,
+    ///     web::{
+    ///         Data, Path,
+    ///         hub::Hub,
+    ///         sse::{Event, SSE},
+    ///     },
+    /// };
+    ///
+    /// #[handler]
+    /// fn events(Path(room): Path<String>, hub: Data<&Hub<String>>) -> SSE {
+    ///     hub.subscribe(room).into_sse(Event::message)
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .at("/events/:room", get(events))
+    ///     .data(Hub::<String>::new());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "sse")))]
+    pub fn into_sse<F>(self, f: F) -> crate::web::sse::SSE
+    where
+        F: FnMut(T) -> crate::web::sse::Event + Send + 'static,
+    {
+        use futures_util::StreamExt;
+
+        crate::web::sse::SSE::new(self.map(f))
+    }
+}
+
+#[cfg(feature = "websocket")]
+impl<T: Clone + Send + 'static> Subscription<T> {
+    /// Forwards the messages between this subscription and a WebSocket
+    /// connection until one of them is closed.
+    ///
+    /// The messages of the topic are converted with `encode` and sent to the
+    /// client, and the text and binary messages received from the client are
+    /// converted with `decode` and published to the topic, including to this
+    /// subscription.
+    ///
+    /// If the subscriber is disconnected by
+    /// [`SlowConsumerPolicy::Disconnect`], the connection is closed with
+    /// [`CloseCode::Policy`](crate::web::websocket::CloseCode::Policy).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use poem::{
+    ///     EndpointExt, IntoResponse, Route, get, handler,
+    ///     web::{
+    ///         Data, Path,
+    ///         hub::Hub,
+    ///         websocket::{Message, WebSocket},
+    ///     },
+    /// };
+    ///
+    /// #[handler]
+    /// fn chat(
+    ///     Path((room, user)): Path<(String, String)>,
+    ///     ws: WebSocket,
+    ///     hub: Data<&Hub<String>>,
+    /// ) -> impl IntoResponse {
+    ///     let subscription = hub.join(room, user);
+    ///     ws.on_upgrade(move |socket| {
+    ///         subscription.bridge_websocket(socket, Message::Text, |msg| match msg {
+    ///             Message::Text(text) => Some(text),
+    ///             _ => None,
+    ///         })
+    ///     })
+    /// }
+    ///
+    /// let app = Route::new()
+    ///     .at("/chat/:room/:user", get(chat))
+    ///     .data(Hub::<String>::new());
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
+    pub async fn bridge_websocket<F, G>(
+        mut self,
+        mut socket: crate::web::websocket::WebSocketStream,
+        mut encode: F,
+        mut decode: G,
+    ) where
+        F: FnMut(T) -> crate::web::websocket::Message,
+        G: FnMut(crate::web::websocket::Message) -> Option<T>,
+    {
+        use futures_util::{SinkExt, StreamExt};
+
+        use crate::web::websocket::{CloseCode, Message};
+
+        loop {
+            tokio::select! {
+                msg = self.next() => match msg {
+                    Some(msg) => {
+                        if socket.send(encode(msg)).await.is_err() {
+                            break;
+                        }
+                    }
+                    None => {
+                        let msg = if self.is_disconnected() {
+                            Message::close_with(CloseCode::Policy, "slow consumer")
+                        } else {
+                            Message::close_with(CloseCode::Away, "topic closed")
+                        };
+                        _ = socket.send(msg).await;
+                        break;
+                    }
+                },
+                msg = socket.next() => match msg {
+                    Some(Ok(msg @ (Message::Text(_) | Message::Binary(_)))) => {
+                        if let Some(msg) = decode(msg) {
+                            self.publish(msg);
+                        }
+                    }
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                },
+            }
+        }
+
+        // completes the closing handshake
+        _ = socket.close().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn publish_subscribe() {
+        let hub = Hub::new();
+        let mut a = hub.subscribe("a");
+        let mut a2 = hub.subscribe("a");
+        let mut b = hub.subscribe("b");
+
+        assert_eq!(hub.publish("a", 1), 2);
+        assert_eq!(hub.publish("b", 2), 1);
+        assert_eq!(hub.publish("c", 3), 0);
+        assert_eq!(a.publish(4), 2);
+
+        assert_eq!(a.next().await, Some(1));
+        assert_eq!(a.next().await, Some(4));
+        assert_eq!(a2.next().await, Some(1));
+        assert_eq!(b.next().await, Some(2));
+
+        let mut topics = hub.topics();
+        topics.sort();
+        assert_eq!(topics, vec!["a", "b"]);
+        assert_eq!(hub.subscriber_count("a"), 2);
+
+        drop(a2);
+        assert_eq!(hub.subscriber_count("a"), 1);
+        drop(b);
+        assert_eq!(hub.topics(), vec!["a"]);
+
+        hub.publish("a", 5);
+        hub.close("a");
+        assert_eq!(a.next().await, Some(5));
+        assert_eq!(a.next().await, None);
+        assert!(!a.is_disconnected());
+    }
+
+    #[tokio::test]
+    async fn slow_consumer() {
+        let hub = Hub::new()
+            .buffer_size(2)
+            .slow_consumer_policy(SlowConsumerPolicy::DropOldest);
+        let a = hub.subscribe("a");
+        for i in 0..4 {
+            hub.publish("a", i);
+        }
+        assert_eq!(a.dropped(), 2);
+        assert_eq!(a.take(2).collect::<Vec<_>>().await, vec![2, 3]);
+
+        let hub = hub.slow_consumer_policy(SlowConsumerPolicy::DropNewest);
+        let a = hub.subscribe("a");
+        for i in 0..4 {
+            hub.publish("a", i);
+        }
+        assert_eq!(a.dropped(), 2);
+        assert_eq!(a.take(2).collect::<Vec<_>>().await, vec![0, 1]);
+
+        let hub = hub.slow_consumer_policy(SlowConsumerPolicy::Disconnect);
+        let a = hub.subscribe("a");
+        let mut b = hub.subscribe("a");
+        hub.publish("a", 0);
+        hub.publish("a", 1);
+        assert_eq!(b.next().await, Some(0));
+        assert_eq!(hub.publish("a", 2), 1);
+        assert!(a.is_disconnected());
+        assert_eq!(hub.subscriber_count("a"), 1);
+        assert_eq!(a.collect::<Vec<_>>().await, vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn subscription_settings() {
+        let hub = Hub::new().buffer_size(2);
+        let a = hub.subscribe("a");
+        let b = hub
+            .subscribe("a")
+            .buffer_size(3)
+            .slow_consumer_policy(SlowConsumerPolicy::DropNewest);
+
+        // the settings of the hub handle do not change the existing
+        // subscriptions
+        let hub2 = hub.clone().buffer_size(1);
+        let c = hub2.subscribe("a");
+        for i in 0..4 {
+            hub2.publish("a", i);
+        }
+
+        assert_eq!(a.dropped(), 2);
+        assert_eq!(b.dropped(), 1);
+        assert_eq!(c.dropped(), 3);
+        assert_eq!(a.take(2).collect::<Vec<_>>().await, vec![2, 3]);
+        assert_eq!(b.take(3).collect::<Vec<_>>().await, vec![0, 1, 2]);
+        assert_eq!(c.take(1).collect::<Vec<_>>().await, vec![3]);
+    }
+
+    #[test]
+    fn presence() {
+        let hub = Hub::<i32>::new();
+        let a = hub.join("room", "alice");
+        let a2 = hub.join("room", "alice");
+        let b = hub.join("room", "bob");
+        let _c = hub.subscribe("room");
+        assert_eq!(a.member(), Some("alice"));
+        assert_eq!(hub.presence("room"), vec!["alice", "bob"]);
+
+        drop(a);
+        assert!(hub.is_present("room", "alice"));
+        drop(a2);
+        assert!(!hub.is_present("room", "alice"));
+        drop(b);
+        assert!(hub.presence("room").is_empty());
+        assert_eq!(hub.subscriber_count("room"), 1);
+    }
+
+    #[cfg(all(feature = "sse", feature = "test"))]
+    #[tokio::test]
+    async fn sse() {
+        use crate::{
+            EndpointExt, Route, get, handler,
+            test::TestClient,
+            web::{
+                Data,
+                sse::{Event, SSE},
+            },
+        };
+
+        #[handler(internal)]
+        fn index(hub: Data<&Hub<String>>) -> SSE {
+            let subscription = hub.subscribe("a");
+            hub.publish("a", "hello".to_string());
+            hub.close("a");
+            subscription.into_sse(Event::message)
+        }
+
+        let cli = TestClient::new(Route::new().at("/", get(index)).data(Hub::<String>::new()));
+        let events = cli
+            .get("/")
+            .send()
+            .await
+            .sse_stream()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events.len(), 1);
+    }
+
+    #[cfg(all(feature = "websocket", feature = "test", feature = "server"))]
+    #[tokio::test]
+    async fn websocket() {
+        use crate::{
+            EndpointExt, IntoResponse, Route, get, handler,
+            test::TestClient,
+            web::{
+                Data, Path,
+                websocket::{CloseCode, Message, WebSocket},
+            },
+        };
+
+        #[handler(internal)]
+        fn index(
+            Path(user): Path<String>,
+            ws: WebSocket,
+            hub: Data<&Hub<String>>,
+        ) -> impl IntoResponse {
+            let subscription = hub.join("room", user);
+            ws.on_upgrade(move |socket| {
+                subscription.bridge_websocket(socket, Message::Text, |msg| match msg {
+                    Message::Text(text) => Some(text),
+                    _ => None,
+                })
+            })
+        }
+
+        let hub = Hub::<String>::new();
+        let cli = TestClient::new(Route::new().at("/:user", get(index)).data(hub.clone()));
+
+        let mut alice = cli.websocket("/alice").await;
+        let mut bob = cli.websocket("/bob").await;
+        assert_eq!(hub.presence("room"), vec!["alice", "bob"]);
+
+        alice.send_text("hi").await;
+        alice.assert_text("hi").await;
+        bob.assert_text("hi").await;
+
+        bob.close(CloseCode::Normal, "").await;
+        bob.assert_closed().await;
+        while hub.is_present("room", "bob") {
+            tokio::task::yield_now().await;
+        }
+
+        hub.close("room");
+        alice
+            .assert_close_with(CloseCode::Away, "topic closed")
+            .await;
+    }
+}
@@ -2,13 +2,18 @@
 
 mod accept;
 mod addr;
+#[cfg(feature = "client-cert")]
+pub(crate) mod client_cert;
 #[cfg(feature = "compression")]
 mod compress;
 #[cfg(feature = "cookie")]
 #[cfg_attr(docsrs, doc(cfg(feature = "cookie")))]
 pub mod cookie;
 mod data;
+#[cfg(feature = "cookie")]
+mod flash;
 mod form;
+pub mod hub;
 mod json;
 #[cfg(feature = "multipart")]
 mod multipart;
@@ -32,6 +37,7 @@ pub use headers;
 #[cfg(feature = "csrf")]
 mod csrf;
 mod typed_header;
+mod url_for;
 #[cfg(feature = "websocket")]
 #[cfg_attr(docsrs, doc(cfg(feature = "websocket")))]
 pub mod websocket;
@@ -44,13 +50,25 @@ use bytes::Bytes;
 use futures_util::FutureExt;
 use http::header;
 
+#[cfg(all(
+    feature = "client-cert",
+    any(feature = "rustls", feature = "native-tls", feature = "openssl-tls")
+))]
+pub(crate) use self::client_cert::PeerCertificate;
+#[cfg(feature = "client-cert")]
+pub use self::client_cert::{ClientCert, SubjectAltName};
+#[cfg(feature = "compression")]
+pub(crate) use self::compress::negotiate_encoding;
 #[cfg(feature = "compression")]
 pub use self::compress::{Compress, CompressionAlgo};
 #[cfg(feature = "csrf")]
 pub use self::csrf::{CsrfToken, CsrfVerifier};
+#[cfg(feature = "cookie")]
+pub(crate) use self::flash::Store as FlashStore;
+#[cfg(feature = "cookie")]
+pub use self::flash::{Flash, FlashLevel, FlashMessage, FlashMessages};
 #[cfg(feature = "multipart")]
 pub use self::multipart::{Field, Multipart};
-pub(crate) use self::path::PathDeserializer;
 #[cfg(feature = "static-files")]
 pub use self::static_file::{StaticFileRequest, StaticFileResponse};
 #[cfg(feature = "tempfile")]
@@ -70,7 +88,9 @@ pub use self::{
     real_ip::RealIp,
     redirect::Redirect,
     typed_header::TypedHeader,
+    url_for::UrlFor,
 };
+pub(crate) use self::{path::PathDeserializer, real_ip::real_ip};
 use crate::{
     body::Body,
     error::{ReadBodyError, Result},
@@ -212,6 +232,13 @@ impl RequestBody {
 ///
 ///   _Requires `CookieSession` or `RedisSession` middleware._
 ///
+/// - **Flash**
+///
+///   Extracts the [`Flash`] messages from the incoming request.
+///
+///   _Requires `CookieSession`, `ServerSession` or `CookieJarManager`
+///   middleware._
+///
 /// - **Body**
 ///
 ///   Extracts the [`Body`] from the incoming request.
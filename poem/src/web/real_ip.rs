@@ -10,53 +10,57 @@ pub struct RealIp(pub Option<IpAddr>);
 
 impl<'a> FromRequest<'a> for RealIp {
     async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
-        if let Some(real_ip) = req
-            .headers()
-            .get("x-real-ip")
-            .and_then(|value| value.to_str().ok())
-            .and_then(|value| value.parse::<IpAddr>().ok())
-        {
-            return Ok(RealIp(Some(real_ip)));
-        }
+        Ok(RealIp(real_ip(req)))
+    }
+}
 
-        if let Some(forwarded) = req
-            .headers()
-            .get("forwarded")
-            .and_then(|value| value.to_str().ok())
-            .and_then(|value| rfc7239::parse(value).collect::<Result<Vec<_>, _>>().ok())
-        {
-            if let Some(real_ip) = forwarded
-                .into_iter()
-                .find_map(|item| match item.forwarded_for {
-                    Some(NodeIdentifier {
-                        name: NodeName::Ip(ip_addr),
-                        ..
-                    }) => Some(ip_addr),
-                    _ => None,
-                })
-            {
-                return Ok(RealIp(Some(real_ip)));
-            }
-        }
+pub(crate) fn real_ip(req: &Request) -> Option<IpAddr> {
+    if let Some(real_ip) = req
+        .headers()
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<IpAddr>().ok())
+    {
+        return Some(real_ip);
+    }
 
-        if let Some(real_ip) = req
-            .headers()
-            .get("x-forwarded-for")
-            .and_then(|value| value.to_str().ok())
-            .and_then(|value| {
-                value
-                    .split(',')
-                    .map(|value| value.trim())
-                    .find_map(|value| value.parse::<IpAddr>().ok())
+    if let Some(forwarded) = req
+        .headers()
+        .get("forwarded")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| rfc7239::parse(value).collect::<Result<Vec<_>, _>>().ok())
+    {
+        if let Some(real_ip) = forwarded
+            .into_iter()
+            .find_map(|item| match item.forwarded_for {
+                Some(NodeIdentifier {
+                    name: NodeName::Ip(ip_addr),
+                    ..
+                }) => Some(ip_addr),
+                _ => None,
             })
         {
-            return Ok(RealIp(Some(real_ip)));
+            return Some(real_ip);
         }
+    }
 
-        match req.remote_addr().0 {
-            Addr::SocketAddr(addr) => Ok(RealIp(Some(addr.ip()))),
-            _ => Ok(RealIp(None)),
-        }
+    if let Some(real_ip) = req
+        .headers()
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(',')
+                .map(|value| value.trim())
+                .find_map(|value| value.parse::<IpAddr>().ok())
+        })
+    {
+        return Some(real_ip);
+    }
+
+    match req.remote_addr().0 {
+        Addr::SocketAddr(addr) => Some(addr.ip()),
+        _ => None,
     }
 }
 
@@ -0,0 +1,50 @@
+use crate::{FromRequest, Request, RequestBody, Result};
+
+/// An extractor for the `Last-Event-ID` header, which is sent by the
+/// `EventSource` of the browsers when reconnecting, with the id of the last
+/// received event.
+///
+/// It contains `None` if the header is missing or is not valid UTF-8.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::stream;
+/// use poem::{
+///     Route, get, handler,
+///     test::TestClient,
+///     web::sse::{Event, LastEventId, SSE},
+/// };
+///
+/// #[handler]
+/// fn index(LastEventId(last_event_id): LastEventId) -> SSE {
+///     let start = last_event_id
+///         .and_then(|id| id.parse::<i32>().ok())
+///         .map(|id| id + 1)
+///         .unwrap_or_default();
+///     SSE::new(stream::iter(
+///         (start..3).map(|id| Event::message(id.to_string()).id(id.to_string())),
+///     ))
+/// }
+///
+/// let cli = TestClient::new(Route::new().at("/", get(index)));
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let resp = cli.get("/").header("Last-Event-ID", "0").send().await;
+/// resp.assert_text("id: 1\ndata: 1\n\nid: 2\ndata: 2\n\n")
+///     .await;
+/// # });
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct LastEventId(pub Option<String>);
+
+impl<'a> FromRequest<'a> for LastEventId {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(LastEventId(
+            req.headers()
+                .get("last-event-id")
+                .and_then(|value| value.to_str().ok())
+                .map(ToString::to_string),
+        ))
+    }
+}
@@ -1,15 +1,20 @@
 //! Server-Sent Events (SSE) types.
 
 mod event;
+mod last_event_id;
+mod replay;
 mod response;
 
 pub use event::Event;
+pub use last_event_id::LastEventId;
+pub use replay::SseReplayBuffer;
 pub use response::SSE;
 
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
+    use futures_util::StreamExt;
     use tokio::{io::AsyncReadExt, time::Instant};
 
     use super::*;
@@ -71,4 +76,70 @@ data: 5
             s = now;
         }
     }
+
+    #[tokio::test]
+    async fn replay_buffer() {
+        let buffer = SseReplayBuffer::new(3);
+        let mut live = buffer.subscribe(None);
+        for i in 1..=4 {
+            assert_eq!(buffer.push(Event::message(i.to_string())), i.to_string());
+        }
+        assert_eq!(buffer.push(Event::message("5").id("a")), "a");
+        assert_eq!(buffer.len(), 3);
+
+        let ids = |events: Vec<Event>| {
+            events
+                .into_iter()
+                .map(|event| match event {
+                    Event::Message { id, .. } => id,
+                    Event::Retry { .. } => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        };
+        // the live subscriber keeps up to the capacity of the buffer
+        assert_eq!(ids(live.by_ref().take(3).collect().await), ["3", "4", "a"]);
+        assert_eq!(
+            ids(buffer.subscribe(Some("3")).take(2).collect().await),
+            ["4", "a"]
+        );
+        // the event is no longer in the buffer
+        assert_eq!(
+            ids(buffer.subscribe(Some("1")).take(3).collect().await),
+            ["3", "4", "a"]
+        );
+
+        let mut resumed = buffer.subscribe(Some("a"));
+        let mut new = buffer.subscribe(None);
+        tokio::spawn({
+            let buffer = buffer.clone();
+            async move { buffer.push(Event::message("6")) }
+        });
+        assert_eq!(ids(vec![resumed.next().await.unwrap()]), ["6"]);
+        assert_eq!(ids(vec![new.next().await.unwrap()]), ["6"]);
+        assert_eq!(ids(vec![live.next().await.unwrap()]), ["6"]);
+    }
+
+    #[tokio::test]
+    async fn replay_buffer_sse() {
+        use crate::{EndpointExt, Route, get, handler, test::TestClient, web::Data};
+
+        #[handler(internal)]
+        fn index(last_event_id: LastEventId, buffer: Data<&SseReplayBuffer>) -> SSE {
+            buffer.sse(last_event_id)
+        }
+
+        let buffer = SseReplayBuffer::new(10)
+            .retry(Duration::from_secs(3))
+            .keep_alive(Duration::from_millis(100));
+        buffer.push(Event::message("a"));
+        buffer.push(Event::message("b"));
+        let cli = TestClient::new(Route::new().at("/", get(index)).data(buffer));
+
+        let resp = cli.get("/").header("Last-Event-ID", "1").send().await;
+        let mut body = resp.0.into_body().into_async_read();
+        let expected = "retry: 3000\n\nid: 2\ndata: b\n\n:\n\n";
+        let mut data = vec![0; expected.len()];
+        body.read_exact(&mut data).await.unwrap();
+        assert_eq!(String::from_utf8(data).unwrap(), expected);
+    }
 }
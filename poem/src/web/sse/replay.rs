@@ -0,0 +1,218 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use futures_util::{StreamExt, stream, stream::BoxStream};
+use parking_lot::Mutex;
+use tokio::sync::watch;
+
+use super::{Event, LastEventId, SSE};
+
+struct State {
+    /// The buffered events with their sequence numbers.
+    events: VecDeque<(u64, Event)>,
+    next_seq: u64,
+}
+
+struct Inner {
+    capacity: usize,
+    state: Mutex<State>,
+    /// The sequence number of the last event.
+    last_seq: watch::Sender<u64>,
+}
+
+/// A buffer which keeps the last events of a stream, so that the clients
+/// reconnecting with the `Last-Event-ID` header receive the events they
+/// missed.
+///
+/// The buffer is cheap to clone, and the clones share the same events. Use a
+/// buffer for each stream, for example for each chat room.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use poem::{
+///     EndpointExt, Route, get, handler, post,
+///     web::{
+///         Data,
+///         sse::{Event, LastEventId, SSE, SseReplayBuffer},
+///     },
+/// };
+///
+/// #[handler]
+/// fn events(last_event_id: LastEventId, buffer: Data<&SseReplayBuffer>) -> SSE {
+///     buffer.sse(last_event_id)
+/// }
+///
+/// #[handler]
+/// fn send(body: String, buffer: Data<&SseReplayBuffer>) {
+///     buffer.push(Event::message(body));
+/// }
+///
+/// let buffer = SseReplayBuffer::new(100)
+///     .keep_alive(Duration::from_secs(15))
+///     .retry(Duration::from_secs(3));
+/// let app = Route::new()
+///     .at("/events", get(events))
+///     .at("/send", post(send))
+///     .data(buffer);
+/// ```
+#[derive(Clone)]
+pub struct SseReplayBuffer {
+    inner: Arc<Inner>,
+    keep_alive: Option<Duration>,
+    retry: Option<Duration>,
+}
+
+impl SseReplayBuffer {
+    /// Create a `SseReplayBuffer` which keeps the last `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                capacity: capacity.max(1),
+                state: Mutex::new(State {
+                    events: VecDeque::new(),
+                    next_seq: 1,
+                }),
+                last_seq: watch::Sender::new(0),
+            }),
+            keep_alive: None,
+            retry: None,
+        }
+    }
+
+    /// Sets the keep alive interval of the responses returned by
+    /// [`SseReplayBuffer::sse`], see [`SSE::keep_alive`].
+    #[must_use]
+    pub fn keep_alive(self, duration: Duration) -> Self {
+        Self {
+            keep_alive: Some(duration),
+            ..self
+        }
+    }
+
+    /// Sets the reconnection time sent at the beginning of the responses
+    /// returned by [`SseReplayBuffer::sse`], see [`Event::retry`].
+    #[must_use]
+    pub fn retry(self, duration: Duration) -> Self {
+        Self {
+            retry: Some(duration),
+            ..self
+        }
+    }
+
+    /// Appends an event to the buffer and sends it to the subscribers, the
+    /// oldest event is removed if the buffer is full.
+    ///
+    /// If the event is a message without an id, the sequence number of the
+    /// event is used as the id. Returns the id of the event.
+    pub fn push(&self, event: Event) -> String {
+        let mut state = self.inner.state.lock();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+
+        let event = match event {
+            Event::Message { id, event, data } if id.is_empty() => Event::Message {
+                id: seq.to_string(),
+                event,
+                data,
+            },
+            event => event,
+        };
+        let id = event_id(&event).unwrap_or_default().to_string();
+
+        if state.events.len() >= self.inner.capacity {
+            state.events.pop_front();
+        }
+        state.events.push_back((seq, event));
+        self.inner.last_seq.send_replace(seq);
+        id
+    }
+
+    /// Returns the number of buffered events.
+    pub fn len(&self) -> usize {
+        self.inner.state.lock().events.len()
+    }
+
+    /// Returns `true` if there are no buffered events.
+    pub fn is_empty(&self) -> bool {
+        self.inner.state.lock().events.is_empty()
+    }
+
+    /// Returns a stream of the events after the event with the specified id,
+    /// followed by the new events.
+    ///
+    /// If the id is `None`, the stream only contains the new events. If the
+    /// event is no longer in the buffer, all the buffered events are
+    /// replayed.
+    ///
+    /// The events pushed while the stream is not polled are kept up to the
+    /// capacity of the buffer.
+    pub fn subscribe(&self, last_event_id: Option<&str>) -> BoxStream<'static, Event> {
+        let state = self.inner.state.lock();
+        let next_seq = match last_event_id {
+            Some(last_event_id) => state
+                .events
+                .iter()
+                .find(|(_, event)| event_id(event) == Some(last_event_id))
+                .or_else(|| state.events.front())
+                .map(|(seq, event)| {
+                    if event_id(event) == Some(last_event_id) {
+                        seq + 1
+                    } else {
+                        *seq
+                    }
+                })
+                .unwrap_or(state.next_seq),
+            None => state.next_seq,
+        };
+        let last_seq = self.inner.last_seq.subscribe();
+        drop(state);
+
+        stream::unfold(
+            (self.inner.clone(), next_seq, last_seq),
+            |(inner, next_seq, mut last_seq)| async move {
+                loop {
+                    let next = {
+                        let state = inner.state.lock();
+                        state.events.front().and_then(|(front_seq, _)| {
+                            // skips the events which have been removed from the buffer
+                            let index = next_seq.saturating_sub(*front_seq) as usize;
+                            state.events.get(index).cloned()
+                        })
+                    };
+                    if let Some((seq, event)) = next {
+                        return Some((event, (inner, seq + 1, last_seq)));
+                    }
+                    last_seq.changed().await.ok()?;
+                }
+            },
+        )
+        .boxed()
+    }
+
+    /// Returns a [`SSE`] response which sends the events after the last
+    /// event received by the client, followed by the new events.
+    ///
+    /// The response starts with the reconnection time set by
+    /// [`SseReplayBuffer::retry`], and uses the keep alive interval set by
+    /// [`SseReplayBuffer::keep_alive`].
+    pub fn sse(&self, last_event_id: LastEventId) -> SSE {
+        let retry = self
+            .retry
+            .map(|retry| Event::retry(retry.as_millis() as u64));
+        let stream = stream::iter(retry).chain(self.subscribe(last_event_id.0.as_deref()));
+        let sse = SSE::new(stream);
+        match self.keep_alive {
+            Some(keep_alive) => sse.keep_alive(keep_alive),
+            None => sse,
+        }
+    }
+}
+
+fn event_id(event: &Event) -> Option<&str> {
+    match event {
+        Event::Message { id, .. } => Some(id),
+        Event::Retry { .. } => None,
+    }
+}
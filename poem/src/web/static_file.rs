@@ -1,6 +1,9 @@
+#[cfg(feature = "compression")]
+use std::collections::HashSet;
 use std::{
     collections::Bound,
     fs::Metadata,
+    hash::{BuildHasher, Hasher, RandomState},
     io::{Seek, SeekFrom},
     path::Path,
     str::FromStr,
@@ -8,15 +11,22 @@ use std::{
 };
 
 use bytes::Bytes;
+use futures_util::{StreamExt, TryStreamExt};
 use headers::{
-    ContentRange, ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince,
-    Range,
+    ContentRange, ETag, HeaderMapExt, IfMatch, IfModifiedSince, IfNoneMatch, IfRange,
+    IfUnmodifiedSince, LastModified, Range,
 };
 use http::{StatusCode, header};
 use httpdate::HttpDate;
 use mime::Mime;
-use tokio::{fs::File, io::AsyncReadExt};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
 
+#[cfg(feature = "compression")]
+use crate::web::{CompressionAlgo, negotiate_encoding};
 use crate::{
     Body, FromRequest, IntoResponse, Request, RequestBody, Response, Result, error::StaticFileError,
 };
@@ -47,9 +57,14 @@ pub enum StaticFileResponse {
 
 impl StaticFileResponse {
     /// Set the content type
+    ///
+    /// It has no effect on `multipart/byteranges` responses, whose parts
+    /// already include their content type.
     pub fn with_content_type(mut self, ct: impl Into<String>) -> Self {
         if let StaticFileResponse::Ok { content_type, .. } = &mut self {
-            *content_type = Some(ct.into());
+            if !is_multipart_byteranges(content_type.as_deref()) {
+                *content_type = Some(ct.into());
+            }
         }
         self
     }
@@ -67,6 +82,7 @@ impl IntoResponse for StaticFileResponse {
                 content_range,
                 cache_control,
             } => {
+                let multipart = is_multipart_byteranges(content_type.as_deref());
                 let mut builder = Response::builder()
                     .header(header::ACCEPT_RANGES, "bytes")
                     .header(header::CONTENT_LENGTH, content_length);
@@ -85,6 +101,9 @@ impl IntoResponse for StaticFileResponse {
                         .status(StatusCode::PARTIAL_CONTENT)
                         .typed_header(ContentRange::bytes(range, size).unwrap());
                 }
+                if multipart {
+                    builder = builder.status(StatusCode::PARTIAL_CONTENT);
+                }
                 if let Some(cache_control) = cache_control {
                     builder = builder.header(header::CACHE_CONTROL, cache_control);
                 }
@@ -104,6 +123,9 @@ pub struct StaticFileRequest {
     if_none_match: Option<IfNoneMatch>,
     if_modified_since: Option<IfModifiedSince>,
     range: Option<Range>,
+    if_range: Option<IfRange>,
+    #[cfg(feature = "compression")]
+    accept_encoding: Vec<http::HeaderValue>,
 }
 
 impl<'a> FromRequest<'a> for StaticFileRequest {
@@ -114,65 +136,191 @@ impl<'a> FromRequest<'a> for StaticFileRequest {
             if_none_match: req.headers().typed_get::<IfNoneMatch>(),
             if_modified_since: req.headers().typed_get::<IfModifiedSince>(),
             range: req.headers().typed_get::<Range>(),
+            if_range: req.headers().typed_get::<IfRange>(),
+            #[cfg(feature = "compression")]
+            accept_encoding: req
+                .headers()
+                .get_all(header::ACCEPT_ENCODING)
+                .iter()
+                .cloned()
+                .collect(),
         })
     }
 }
 
 impl StaticFileRequest {
+    /// Create static file response, serving the pre-compressed variant of the
+    /// file with one of the specified algorithms instead if it exists and the
+    /// client accepts it.
+    ///
+    /// The variants are the sibling files with the extension of the algorithm
+    /// appended, e.g. `app.js.br` for `app.js`, see
+    /// [`StaticFilesEndpoint::precompressed_br`](crate::endpoint::StaticFilesEndpoint::precompressed_br).
+    #[cfg(feature = "compression")]
+    pub(crate) fn create_precompressed_response(
+        self,
+        path: &Path,
+        algorithms: &[CompressionAlgo],
+        prefer_utf8: bool,
+        no_cache: bool,
+    ) -> Result<Response, StaticFileError> {
+        if !path.is_file() {
+            return Err(StaticFileError::NotFound);
+        }
+
+        let (variant_path, content_encoding, vary) = self.select_variant(path, algorithms);
+        let mut resp = self
+            .create_file_response(path, &variant_path, prefer_utf8, no_cache)?
+            .into_response();
+        if let Some(algo) = content_encoding {
+            if resp.status() != StatusCode::NOT_MODIFIED {
+                resp.headers_mut().insert(
+                    header::CONTENT_ENCODING,
+                    http::HeaderValue::from_static(algo.as_str()),
+                );
+            }
+        }
+        if vary {
+            resp.headers_mut().insert(
+                header::VARY,
+                http::HeaderValue::from_static(header::ACCEPT_ENCODING.as_str()),
+            );
+        }
+        Ok(resp)
+    }
+
+    /// Returns the path of the variant of the file to serve, its
+    /// `Content-Encoding` and whether the response varies on
+    /// `Accept-Encoding`.
+    #[cfg(feature = "compression")]
+    fn select_variant(
+        &self,
+        path: &Path,
+        algorithms: &[CompressionAlgo],
+    ) -> (std::path::PathBuf, Option<CompressionAlgo>, bool) {
+        let variant_path = |algo: CompressionAlgo| {
+            let mut variant = path.as_os_str().to_owned();
+            variant.push(match algo {
+                CompressionAlgo::BR => ".br",
+                CompressionAlgo::DEFLATE => ".zz",
+                CompressionAlgo::GZIP => ".gz",
+                CompressionAlgo::ZSTD => ".zst",
+            });
+            std::path::PathBuf::from(variant)
+        };
+
+        let available = algorithms
+            .iter()
+            .copied()
+            .filter(|algo| variant_path(*algo).is_file())
+            .collect::<HashSet<_>>();
+        if available.is_empty() {
+            return (path.to_path_buf(), None, false);
+        }
+
+        match negotiate_encoding(&self.accept_encoding, &available) {
+            Some(algo) if available.contains(&algo) => (variant_path(algo), Some(algo), true),
+            _ => (path.to_path_buf(), None, true),
+        }
+    }
+
     /// Create static file response from bytes.
     pub fn create_response_from_data(
         self,
         data: impl AsRef<[u8]>,
     ) -> Result<StaticFileResponse, StaticFileError> {
-        let data = data.as_ref();
+        self.create_data_response(data.as_ref(), None, None)
+    }
 
-        // content length
-        let mut content_length = data.len() as u64;
-        let mut content_range = None;
+    /// Create static file response from bytes, with the specified content
+    /// type and `ETag`.
+    ///
+    /// The `If-Match`, `If-None-Match` and `If-Range` headers of the request
+    /// are evaluated against the `ETag`.
+    pub fn create_response_from_data_with_etag(
+        self,
+        data: impl AsRef<[u8]>,
+        content_type: impl Into<String>,
+        etag: impl Into<String>,
+    ) -> Result<StaticFileResponse, StaticFileError> {
+        self.create_data_response(data.as_ref(), Some(content_type.into()), Some(etag.into()))
+    }
 
-        let body = if let Some((start, end)) = self
-            .range
-            .and_then(|range| range.satisfiable_ranges(data.len() as u64).next())
-        {
-            let start = match start {
-                Bound::Included(n) => n,
-                Bound::Excluded(n) => n + 1,
-                Bound::Unbounded => 0,
-            };
-            let end = match end {
-                Bound::Included(n) => n + 1,
-                Bound::Excluded(n) => n,
-                Bound::Unbounded => content_length,
-            };
-            if end < start || end > content_length {
-                return Err(StaticFileError::RangeNotSatisfiable {
-                    size: content_length,
-                });
+    fn create_data_response(
+        mut self,
+        data: &[u8],
+        content_type: Option<String>,
+        etag_str: Option<String>,
+    ) -> Result<StaticFileResponse, StaticFileError> {
+        if let Some(etag) = etag_str.as_deref().and_then(|s| ETag::from_str(s).ok()) {
+            if let Some(if_match) = &self.if_match {
+                if !if_match.precondition_passes(&etag) {
+                    return Err(StaticFileError::PreconditionFailed);
+                }
             }
 
-            if start != 0 || end != content_length {
-                content_range = Some((start..end, content_length));
+            if let Some(if_non_match) = &self.if_none_match {
+                if !if_non_match.precondition_passes(&etag) {
+                    return Ok(StaticFileResponse::NotModified);
+                }
             }
 
-            content_length = end - start;
-            Body::from_bytes(Bytes::copy_from_slice(
-                &data[start as usize..(start + content_length) as usize],
-            ))
+            self.check_if_range(Some(&etag), None);
         } else {
-            Body::from_bytes(Bytes::copy_from_slice(data))
+            self.check_if_range(None, None);
+        }
+
+        let size = data.len() as u64;
+        let ranges = resolve_ranges(self.range.as_ref(), size)?;
+        let mut content_range = None;
+        let mut content_type = content_type;
+
+        let (body, content_length) = match ranges.as_slice() {
+            [] => (Body::from_bytes(Bytes::copy_from_slice(data)), size),
+            [range] => {
+                content_range = Some((range.clone(), size));
+                (
+                    Body::from_bytes(Bytes::copy_from_slice(
+                        &data[range.start as usize..range.end as usize],
+                    )),
+                    range.end - range.start,
+                )
+            }
+            _ => {
+                let multipart = MultipartByteRanges::new(&ranges, size, content_type.as_deref());
+                let content_length = multipart.content_length();
+                let mut body = Vec::with_capacity(content_length as usize);
+                for (range, part_header) in ranges.iter().zip(&multipart.part_headers) {
+                    body.extend_from_slice(part_header);
+                    body.extend_from_slice(&data[range.start as usize..range.end as usize]);
+                }
+                body.extend_from_slice(&multipart.end);
+
+                content_type = Some(multipart.content_type());
+                (Body::from_vec(body), content_length)
+            }
         };
 
         Ok(StaticFileResponse::Ok {
             body,
             content_length,
-            content_type: None,
-            etag: None,
+            content_type,
+            etag: etag_str,
             last_modified: None,
             content_range,
             cache_control: None,
         })
     }
 
+    /// Ignores the `Range` header if the `If-Range` precondition fails.
+    fn check_if_range(&mut self, etag: Option<&ETag>, last_modified: Option<&LastModified>) {
+        if let Some(if_range) = &self.if_range {
+            if if_range.is_modified(etag, last_modified) {
+                self.range = None;
+            }
+        }
+    }
+
     /// Create static file response.
     ///
     /// # Arguments
@@ -191,13 +339,22 @@ impl StaticFileRequest {
         if !path.exists() || !path.is_file() {
             return Err(StaticFileError::NotFound);
         }
+        self.create_file_response(path, path, prefer_utf8, no_cache)
+    }
+
+    /// Creates the response for `path` from the content of `file_path`, which
+    /// is either `path` or one of its pre-compressed variants.
+    fn create_file_response(
+        mut self,
+        path: &Path,
+        file_path: &Path,
+        prefer_utf8: bool,
+        no_cache: bool,
+    ) -> Result<StaticFileResponse, StaticFileError> {
         let guess = mime_guess::from_path(path);
-        let mut file = std::fs::File::open(path)?;
+        let mut file = std::fs::File::open(file_path)?;
         let metadata = file.metadata()?;
 
-        // content length
-        let mut content_length = metadata.len();
-
         // content type
         let content_type = guess.first().map(|mime| {
             if prefer_utf8 {
@@ -215,62 +372,78 @@ impl StaticFileRequest {
             etag_str = etag(ino(&metadata), &modified, metadata.len());
             let etag = ETag::from_str(&etag_str).unwrap();
 
-            if let Some(if_match) = self.if_match {
+            if let Some(if_match) = &self.if_match {
                 if !if_match.precondition_passes(&etag) {
                     return Err(StaticFileError::PreconditionFailed);
                 }
             }
 
-            if let Some(if_unmodified_since) = self.if_unmodified_since {
+            if let Some(if_unmodified_since) = &self.if_unmodified_since {
                 if !if_unmodified_since.precondition_passes(modified) {
                     return Err(StaticFileError::PreconditionFailed);
                 }
             }
 
-            if let Some(if_non_match) = self.if_none_match {
+            if let Some(if_non_match) = &self.if_none_match {
                 if !if_non_match.precondition_passes(&etag) {
                     return Ok(StaticFileResponse::NotModified);
                 }
-            } else if let Some(if_modified_since) = self.if_modified_since {
+            } else if let Some(if_modified_since) = &self.if_modified_since {
                 if !if_modified_since.is_modified(modified) {
                     return Ok(StaticFileResponse::NotModified);
                 }
             }
 
+            self.check_if_range(Some(&etag), Some(&LastModified::from(modified)));
             last_modified_str = HttpDate::from(modified).to_string();
+        } else {
+            self.check_if_range(None, None);
         }
 
+        let size = metadata.len();
+        let ranges = resolve_ranges(self.range.as_ref(), size)?;
         let mut content_range = None;
-
-        let body = if let Some((start, end)) = self
-            .range
-            .and_then(|range| range.satisfiable_ranges(metadata.len()).next())
-        {
-            let start = match start {
-                Bound::Included(n) => n,
-                Bound::Excluded(n) => n + 1,
-                Bound::Unbounded => 0,
-            };
-            let end = match end {
-                Bound::Included(n) => n + 1,
-                Bound::Excluded(n) => n,
-                Bound::Unbounded => metadata.len(),
-            };
-            if end < start || end > metadata.len() {
-                return Err(StaticFileError::RangeNotSatisfiable {
-                    size: metadata.len(),
-                });
+        let mut content_type = content_type;
+
+        let (body, content_length) = match ranges.as_slice() {
+            [] => (Body::from_async_read(File::from_std(file)), size),
+            [range] => {
+                content_range = Some((range.clone(), size));
+                file.seek(SeekFrom::Start(range.start))?;
+                (
+                    Body::from_async_read(File::from_std(file).take(range.end - range.start)),
+                    range.end - range.start,
+                )
             }
-
-            if start != 0 || end != metadata.len() {
-                content_range = Some((start..end, metadata.len()));
+            _ => {
+                let multipart = MultipartByteRanges::new(&ranges, size, content_type.as_deref());
+                let content_length = multipart.content_length();
+                // The parts share the file handle, and are read one after another.
+                let parts = ranges
+                    .clone()
+                    .into_iter()
+                    .zip(multipart.part_headers.clone())
+                    .map(move |(range, part_header)| {
+                        let file = file.try_clone();
+                        futures_util::stream::once(async move { Ok(part_header) }).chain(
+                            futures_util::stream::once(async move {
+                                let mut file = File::from_std(file?);
+                                file.seek(SeekFrom::Start(range.start)).await?;
+                                Ok::<_, std::io::Error>(ReaderStream::new(
+                                    file.take(range.end - range.start),
+                                ))
+                            })
+                            .try_flatten(),
+                        )
+                    });
+                let end = multipart.end.clone();
+                let stream = futures_util::stream::iter(parts)
+                    .flatten()
+                    .chain(futures_util::stream::once(async move { Ok(end) }));
+
+                content_type = Some(multipart.content_type());
+                (Body::from_bytes_stream(stream), content_length)
             }
-
-            content_length = end - start;
-            file.seek(SeekFrom::Start(start))?;
-            Body::from_async_read(File::from_std(file).take(end - start))
-        } else {
-            Body::from_async_read(File::from_std(file))
         };
 
         Ok(StaticFileResponse::Ok {
@@ -351,6 +524,119 @@ fn etag(ino: u64, modified: &SystemTime, len: u64) -> String {
     )
 }
 
+/// The maximum number of ranges served in a `multipart/byteranges` response,
+/// requests with more ranges are answered with the full representation.
+const MAX_RANGES: usize = 64;
+
+/// Returns the ranges to send, sorted and coalesced.
+///
+/// An empty result means that the full representation is sent.
+fn resolve_ranges(
+    range: Option<&Range>,
+    size: u64,
+) -> Result<Vec<std::ops::Range<u64>>, StaticFileError> {
+    let Some(range) = range else {
+        return Ok(Vec::new());
+    };
+
+    let mut ranges = Vec::new();
+    for (start, end) in range.satisfiable_ranges(size) {
+        let start = match start {
+            Bound::Included(n) => n,
+            Bound::Excluded(n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match end {
+            Bound::Included(n) => n + 1,
+            Bound::Excluded(n) => n,
+            Bound::Unbounded => size,
+        };
+        if end < start || end > size {
+            return Err(StaticFileError::RangeNotSatisfiable { size });
+        }
+        ranges.push(start..end);
+    }
+
+    ranges.sort_by_key(|range| range.start);
+    let mut coalesced: Vec<std::ops::Range<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match coalesced.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => coalesced.push(range),
+        }
+    }
+
+    if coalesced.len() > MAX_RANGES
+        || matches!(coalesced.as_slice(), [range] if range.start == 0 && range.end == size)
+    {
+        coalesced.clear();
+    }
+    Ok(coalesced)
+}
+
+const MULTIPART_BYTERANGES: &str = "multipart/byteranges";
+
+/// Returns `true` if the content type is the one of a `multipart/byteranges`
+/// body, which is only used for the responses to multi-range requests.
+fn is_multipart_byteranges(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case(MULTIPART_BYTERANGES))
+}
+
+/// The framing of a `multipart/byteranges` body.
+struct MultipartByteRanges {
+    boundary: String,
+    part_headers: Vec<Bytes>,
+    end: Bytes,
+    data_length: u64,
+}
+
+impl MultipartByteRanges {
+    fn new(ranges: &[std::ops::Range<u64>], size: u64, content_type: Option<&str>) -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(size);
+        let boundary = format!("{:016x}", hasher.finish());
+
+        let part_headers = ranges
+            .iter()
+            .map(|range| {
+                let mut header = format!("\r\n--{boundary}\r\n");
+                if let Some(content_type) = content_type {
+                    header.push_str(&format!("content-type: {content_type}\r\n"));
+                }
+                header.push_str(&format!(
+                    "content-range: bytes {}-{}/{size}\r\n\r\n",
+                    range.start,
+                    range.end - 1
+                ));
+                Bytes::from(header)
+            })
+            .collect();
+        let end = Bytes::from(format!("\r\n--{boundary}--\r\n"));
+
+        Self {
+            boundary,
+            part_headers,
+            end,
+            data_length: ranges.iter().map(|range| range.end - range.start).sum(),
+        }
+    }
+
+    fn content_type(&self) -> String {
+        format!("{MULTIPART_BYTERANGES}; boundary={}", self.boundary)
+    }
+
+    fn content_length(&self) -> u64 {
+        self.part_headers
+            .iter()
+            .map(|header| header.len() as u64)
+            .sum::<u64>()
+            + self.end.len() as u64
+            + self.data_length
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{path::Path, time::Duration};
@@ -574,6 +860,191 @@ mod tests {
         }
     }
 
+    fn expected_byteranges(
+        content_type: &str,
+        part_content_type: Option<&str>,
+        data: &[u8],
+        ranges: &[std::ops::Range<usize>],
+    ) -> Vec<u8> {
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+        let mut body = Vec::new();
+        for range in ranges {
+            body.extend_from_slice(format!("\r\n--{boundary}\r\n").as_bytes());
+            if let Some(part_content_type) = part_content_type {
+                body.extend_from_slice(format!("content-type: {part_content_type}\r\n").as_bytes());
+            }
+            body.extend_from_slice(
+                format!(
+                    "content-range: bytes {}-{}/{}\r\n\r\n",
+                    range.start,
+                    range.end - 1,
+                    data.len()
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(&data[range.clone()]);
+        }
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+        body
+    }
+
+    #[tokio::test]
+    async fn test_range_multipart() {
+        let data = std::fs::read("Cargo.toml").unwrap();
+
+        // overlapping and adjacent ranges are coalesced
+        let static_file = StaticFileRequest::from_request_without_body(
+            &Request::builder()
+                .header(header::RANGE, "bytes=20-29, 0-4, 3-9, 30-34")
+                .finish(),
+        )
+        .await
+        .unwrap();
+        let resp = static_file
+            .create_response(Path::new("Cargo.toml"), false, false)
+            .unwrap()
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert!(resp.headers().get(header::CONTENT_RANGE).is_none());
+
+        let content_type = resp.content_type().unwrap().to_string();
+        let part_content_type = mime_guess::from_path("Cargo.toml").first_or_octet_stream();
+        let expected = expected_byteranges(
+            &content_type,
+            Some(part_content_type.as_ref()),
+            &data,
+            &[0..10, 20..35],
+        );
+        assert_eq!(
+            resp.headers()
+                .get(header::CONTENT_LENGTH)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            expected.len().to_string()
+        );
+        assert_eq!(resp.into_body().into_vec().await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_range_multipart_from_data() {
+        let data = b"hello, world!";
+        let static_file = StaticFileRequest::from_request_without_body(
+            &Request::builder()
+                .header(header::RANGE, "bytes=0-1, -3")
+                .finish(),
+        )
+        .await
+        .unwrap();
+        let resp = static_file
+            .create_response_from_data(data)
+            .unwrap()
+            .with_content_type("text/plain")
+            .into_response();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        let content_type = resp.content_type().unwrap().to_string();
+        assert!(content_type.starts_with("multipart/byteranges; boundary="));
+        let expected = expected_byteranges(&content_type, None, data, &[0..2, 10..13]);
+        assert_eq!(resp.into_body().into_vec().await.unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_if_range() {
+        let resp = check_response(Request::default()).await.unwrap();
+        let etag = resp.etag();
+        let modified = resp.last_modified();
+
+        for value in [etag.as_str(), modified.as_str()] {
+            let resp = check_response(
+                Request::builder()
+                    .typed_header(Range::bytes(0..10).unwrap())
+                    .header(header::IF_RANGE, value)
+                    .finish(),
+            )
+            .await
+            .unwrap();
+            match resp {
+                StaticFileResponse::Ok { content_range, .. } => {
+                    assert_eq!(content_range.unwrap().0, 0..10);
+                }
+                StaticFileResponse::NotModified => panic!(),
+            }
+        }
+
+        // the validator does not match, so the full content is sent
+        let resp = check_response(
+            Request::builder()
+                .typed_header(Range::bytes(0..10).unwrap())
+                .header(header::IF_RANGE, "\"abc\"")
+                .finish(),
+        )
+        .await
+        .unwrap();
+        match resp {
+            StaticFileResponse::Ok { content_range, .. } => assert!(content_range.is_none()),
+            StaticFileResponse::NotModified => panic!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_data_with_etag() {
+        let data = b"hello, world!";
+        let create = |req: Request| async move {
+            StaticFileRequest::from_request_without_body(&req)
+                .await
+                .unwrap()
+                .create_response_from_data_with_etag(data, "text/plain", "\"v1\"")
+        };
+
+        let resp = create(
+            Request::builder()
+                .header(header::IF_NONE_MATCH, "\"v1\"")
+                .finish(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(resp, StaticFileResponse::NotModified));
+
+        let err = create(
+            Request::builder()
+                .header(header::IF_MATCH, "\"v2\"")
+                .finish(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, StaticFileError::PreconditionFailed));
+
+        let resp = create(
+            Request::builder()
+                .typed_header(Range::bytes(0..5).unwrap())
+                .header(header::IF_RANGE, "\"v1\"")
+                .finish(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(resp.into_body().into_string().await.unwrap(), "hello");
+
+        let resp = create(
+            Request::builder()
+                .typed_header(Range::bytes(0..5).unwrap())
+                .header(header::IF_RANGE, "\"v2\"")
+                .finish(),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.content_type(), Some("text/plain"));
+        assert_eq!(
+            resp.into_body().into_string().await.unwrap(),
+            "hello, world!"
+        );
+    }
+
     #[tokio::test]
     async fn test_cache_control() {
         let static_file = StaticFileRequest::from_request_without_body(&Request::default())
@@ -604,4 +1075,86 @@ mod tests {
             _ => panic!(),
         }
     }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_precompressed() {
+        use crate::{endpoint::StaticFilesEndpoint, test::TestClient};
+
+        let dir = std::env::temp_dir().join(format!("poem-precompressed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.js"), "plain").unwrap();
+        std::fs::write(dir.join("app.js.br"), "brdata").unwrap();
+        std::fs::write(dir.join("app.js.gz"), "gzdata").unwrap();
+        std::fs::write(dir.join("other.js"), "other").unwrap();
+
+        let cli = TestClient::new(
+            StaticFilesEndpoint::new(&dir)
+                .precompressed_br()
+                .precompressed_gzip()
+                .precompressed_zstd(),
+        );
+
+        let resp = cli
+            .get("/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip, br, zstd")
+            .send()
+            .await;
+        resp.assert_status_is_ok();
+        resp.assert_header(header::CONTENT_ENCODING, "br");
+        resp.assert_header(header::VARY, "accept-encoding");
+        resp.assert_content_type("text/javascript");
+        let br_etag = resp.0.headers().get(header::ETAG).cloned().unwrap();
+        resp.assert_text("brdata").await;
+
+        let resp = cli
+            .get("/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip, br;q=0.5")
+            .send()
+            .await;
+        resp.assert_header(header::CONTENT_ENCODING, "gzip");
+        let gz_etag = resp.0.headers().get(header::ETAG).cloned().unwrap();
+        assert_ne!(br_etag, gz_etag);
+        resp.assert_text("gzdata").await;
+
+        // conditional and range requests apply to the selected variant
+        let resp = cli
+            .get("/app.js")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .header(header::IF_NONE_MATCH, gz_etag)
+            .send()
+            .await;
+        resp.assert_status(StatusCode::NOT_MODIFIED);
+        resp.assert_header(header::VARY, "accept-encoding");
+        resp.assert_header_is_not_exist(header::CONTENT_ENCODING);
+        let resp = cli
+            .get("/app.js")
+            .header(header::ACCEPT_ENCODING, "br")
+            .typed_header(Range::bytes(0..2).unwrap())
+            .send()
+            .await;
+        resp.assert_status(StatusCode::PARTIAL_CONTENT);
+        resp.assert_header(header::CONTENT_RANGE, "bytes 0-1/6");
+        resp.assert_text("br").await;
+
+        let resp = cli
+            .get("/app.js")
+            .header(header::ACCEPT_ENCODING, "br;q=0, deflate, *;q=0")
+            .send()
+            .await;
+        resp.assert_header_is_not_exist(header::CONTENT_ENCODING);
+        resp.assert_header(header::VARY, "accept-encoding");
+        resp.assert_text("plain").await;
+
+        let resp = cli
+            .get("/other.js")
+            .header(header::ACCEPT_ENCODING, "br")
+            .send()
+            .await;
+        resp.assert_header_is_not_exist(header::CONTENT_ENCODING);
+        resp.assert_header_is_not_exist(header::VARY);
+        resp.assert_text("other").await;
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,90 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{FromRequest, Request, RequestBody, Result, error::UrlForError, route::PathTemplate};
+
+type Names = Arc<HashMap<String, PathTemplate>>;
+
+/// An extractor that generates the paths of the routes added with
+/// [`Route::at_named`](crate::Route::at_named).
+///
+/// The names are looked up in the outermost [`Route`](crate::Route) first,
+/// which also knows the names of the routes nested in it directly, then in the
+/// nested routes that the request went through, such as the ones wrapped by a
+/// middleware. The values of the path parameters are percent-encoded, and must
+/// match the regexes of the pattern.
+///
+/// # Example
+///
+/// ```
+/// use poem::{Route, get, handler, test::TestClient, web::UrlFor};
+///
+/// #[handler]
+/// fn file() {}
+///
+/// #[handler]
+/// fn index(url_for: UrlFor) -> String {
+///     url_for
+///         .url("file", [("user", "a b"), ("path", "docs/readme.md")])
+///         .unwrap()
+/// }
+///
+/// let app = Route::new().at("/", get(index)).nest(
+///     "/users",
+///     Route::new().at_named("file", "/:user/files/*path", get(file)),
+/// );
+///
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let cli = TestClient::new(app);
+/// cli.get("/")
+///     .send()
+///     .await
+///     .assert_text("/users/a%20b/files/docs/readme.md")
+///     .await;
+/// # });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct UrlFor {
+    /// The names of the routes, with the prefix of the nests they are in, from
+    /// the outermost route.
+    scopes: Vec<(Arc<str>, Names)>,
+}
+
+impl UrlFor {
+    pub(crate) fn new(prefix: Arc<str>, names: Names) -> Self {
+        Self {
+            scopes: vec![(prefix, names)],
+        }
+    }
+
+    pub(crate) fn with_names(&self, prefix: Arc<str>, names: Names) -> Self {
+        let mut scopes = self.scopes.clone();
+        scopes.push((prefix, names));
+        Self { scopes }
+    }
+
+    /// Generates the path of the route with the specified name and path
+    /// parameters.
+    pub fn url<I, K, V>(&self, name: &str, params: I) -> Result<String, UrlForError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: ToString,
+    {
+        let (prefix, template) = self
+            .scopes
+            .iter()
+            .find_map(|(prefix, names)| Some((prefix, names.get(name)?)))
+            .ok_or_else(|| UrlForError::UnknownRoute(name.to_string()))?;
+        let params = params
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.to_string()))
+            .collect::<Vec<_>>();
+        Ok(format!("{prefix}{}", template.format(&params)?))
+    }
+}
+
+impl<'a> FromRequest<'a> for UrlFor {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> Result<Self> {
+        Ok(req.data::<UrlFor>().cloned().unwrap_or_default())
+    }
+}
@@ -0,0 +1,730 @@
+use std::{
+    io::{Cursor, Error as IoError, ErrorKind, Result as IoResult},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bytes::{Buf, BytesMut};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::tungstenite::protocol::{
+    Role,
+    frame::{
+        FrameHeader,
+        coding::{Data, OpCode},
+    },
+};
+use tokio_util::io::poll_read_buf;
+
+use crate::http::HeaderValue;
+
+const EXTENSION_NAME: &str = "permessage-deflate";
+
+/// The bytes removed from the end of each compressed message.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// The size of the pending output above which the writes are not accepted
+/// until it is flushed.
+const WRITE_HIGH_WATER_MARK: usize = 64 * 1024;
+
+/// Configuration of the `permessage-deflate` extension defined in
+/// [RFC 7692](https://datatracker.ietf.org/doc/html/rfc7692).
+///
+/// The server accepts the first offer in the `Sec-WebSocket-Extensions`
+/// header that is compatible with this configuration. The window bits are
+/// the base-2 logarithm of the LZ77 sliding window size, smaller windows use
+/// less memory per connection at the cost of the compression ratio.
+///
+/// # Example
+///
+/// ```
+/// use futures_util::{SinkExt, StreamExt};
+/// use poem::{
+///     IntoResponse, Route, get, handler,
+///     web::websocket::{DeflateConfig, Message, WebSocket},
+/// };
+///
+/// #[handler]
+/// async fn index(ws: WebSocket) -> impl IntoResponse {
+///     ws.deflate(DeflateConfig::new().server_max_window_bits(10))
+///         .on_upgrade(|mut socket| async move {
+///             if let Some(Ok(Message::Text(text))) = socket.next().await {
+///                 let _ = socket.send(Message::Text(text)).await;
+///             }
+///         })
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DeflateConfig {
+    compression_level: u32,
+    server_max_window_bits: u8,
+    client_max_window_bits: u8,
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        Self {
+            compression_level: 6,
+            server_max_window_bits: 15,
+            client_max_window_bits: 15,
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+        }
+    }
+}
+
+impl DeflateConfig {
+    /// Create a `DeflateConfig` with the default parameters.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the compression level of the messages sent by the server, from
+    /// `0` to `9`, default is `6`.
+    #[must_use]
+    pub fn compression_level(self, level: u32) -> Self {
+        Self {
+            compression_level: level.min(9),
+            ..self
+        }
+    }
+
+    /// Sets the maximum window bits used to compress the messages sent by the
+    /// server, from `9` to `15`, default is `15`.
+    #[must_use]
+    pub fn server_max_window_bits(self, bits: u8) -> Self {
+        Self {
+            server_max_window_bits: bits.clamp(9, 15),
+            ..self
+        }
+    }
+
+    /// Sets the maximum window bits used by the client to compress the
+    /// messages, from `9` to `15`, default is `15`.
+    ///
+    /// It only applies to the clients which include the
+    /// `client_max_window_bits` parameter in their offer.
+    #[must_use]
+    pub fn client_max_window_bits(self, bits: u8) -> Self {
+        Self {
+            client_max_window_bits: bits.clamp(9, 15),
+            ..self
+        }
+    }
+
+    /// If enabled, the server resets the compression context after each
+    /// message, default is `false`.
+    #[must_use]
+    pub fn server_no_context_takeover(self, enable: bool) -> Self {
+        Self {
+            server_no_context_takeover: enable,
+            ..self
+        }
+    }
+
+    /// If enabled, the client has to reset the compression context after each
+    /// message, default is `false`.
+    #[must_use]
+    pub fn client_no_context_takeover(self, enable: bool) -> Self {
+        Self {
+            client_no_context_takeover: enable,
+            ..self
+        }
+    }
+
+    /// Returns the parameters of the first acceptable offer in the
+    /// `Sec-WebSocket-Extensions` headers.
+    pub(crate) fn negotiate<'a>(
+        &self,
+        headers: impl IntoIterator<Item = &'a HeaderValue>,
+    ) -> Option<DeflateParams> {
+        headers
+            .into_iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(|offer| {
+                let mut items = offer.split(';').map(str::trim);
+                if !items.next()?.eq_ignore_ascii_case(EXTENSION_NAME) {
+                    return None;
+                }
+                self.accept(items)
+            })
+    }
+
+    fn accept<'a>(&self, params: impl Iterator<Item = &'a str>) -> Option<DeflateParams> {
+        let mut server_max_window_bits = None;
+        let mut client_max_window_bits = None;
+        let mut server_no_context_takeover = false;
+        let mut client_no_context_takeover = false;
+
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            match (name, value) {
+                ("server_no_context_takeover", None) if !server_no_context_takeover => {
+                    server_no_context_takeover = true
+                }
+                ("client_no_context_takeover", None) if !client_no_context_takeover => {
+                    client_no_context_takeover = true
+                }
+                ("server_max_window_bits", Some(value)) if server_max_window_bits.is_none() => {
+                    server_max_window_bits = Some(parse_window_bits(value)?)
+                }
+                ("client_max_window_bits", value) if client_max_window_bits.is_none() => {
+                    client_max_window_bits = Some(value.map(parse_window_bits).unwrap_or(Some(15))?)
+                }
+                _ => return None,
+            }
+        }
+
+        // zlib does not support compressing with a window of 256 bytes
+        let server_max_window_bits = server_max_window_bits
+            .unwrap_or(15)
+            .min(self.server_max_window_bits);
+        if server_max_window_bits < 9 {
+            return None;
+        }
+
+        Some(DeflateParams {
+            compression_level: self.compression_level,
+            server_max_window_bits,
+            client_max_window_bits: client_max_window_bits
+                .map(|bits| bits.min(self.client_max_window_bits))
+                .unwrap_or(15),
+            server_no_context_takeover: server_no_context_takeover
+                || self.server_no_context_takeover,
+            client_no_context_takeover: client_no_context_takeover
+                || self.client_no_context_takeover,
+        })
+    }
+}
+
+fn parse_window_bits(value: &str) -> Option<u8> {
+    value
+        .parse::<u8>()
+        .ok()
+        .filter(|bits| (8..=15).contains(bits))
+}
+
+/// The negotiated parameters of the `permessage-deflate` extension.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DeflateParams {
+    compression_level: u32,
+    /// The window bits used to compress the messages sent by the server.
+    pub server_max_window_bits: u8,
+    /// The window bits used to compress the messages sent by the client.
+    pub client_max_window_bits: u8,
+    /// The server resets the compression context after each message.
+    pub server_no_context_takeover: bool,
+    /// The client resets the compression context after each message.
+    pub client_no_context_takeover: bool,
+}
+
+impl DeflateParams {
+    /// Returns the value of the `Sec-WebSocket-Extensions` response header.
+    pub(crate) fn to_header_value(self) -> HeaderValue {
+        let mut value = EXTENSION_NAME.to_string();
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+        if self.server_max_window_bits < 15 {
+            value.push_str(&format!(
+                "; server_max_window_bits={}",
+                self.server_max_window_bits
+            ));
+        }
+        if self.client_max_window_bits < 15 {
+            value.push_str(&format!(
+                "; client_max_window_bits={}",
+                self.client_max_window_bits
+            ));
+        }
+        HeaderValue::from_str(&value).unwrap()
+    }
+}
+
+/// A stream which compresses and decompresses the data frames between the
+/// connection and `tungstenite`, which does not support extensions.
+pub(crate) struct DeflateStream<S> {
+    inner: S,
+    codec: Option<Box<Codec>>,
+}
+
+impl<S> DeflateStream<S> {
+    pub(crate) fn new(
+        inner: S,
+        role: Role,
+        params: Option<DeflateParams>,
+        max_message_size: Option<usize>,
+    ) -> Self {
+        Self {
+            inner,
+            codec: params.map(|params| Box::new(Codec::new(role, params, max_message_size))),
+        }
+    }
+}
+
+struct Codec {
+    max_message_size: usize,
+    compress: Compress,
+    compress_reset: bool,
+    decompress: Decompress,
+    decompress_reset: bool,
+    read_in: BytesMut,
+    read_out: BytesMut,
+    read_compressed: bool,
+    read_message_size: usize,
+    write_in: BytesMut,
+    write_out: BytesMut,
+    write_compressed: bool,
+}
+
+impl Codec {
+    fn new(role: Role, params: DeflateParams, max_message_size: Option<usize>) -> Self {
+        let (compress_bits, compress_reset, decompress_bits, decompress_reset) = match role {
+            Role::Server => (
+                params.server_max_window_bits,
+                params.server_no_context_takeover,
+                params.client_max_window_bits,
+                params.client_no_context_takeover,
+            ),
+            Role::Client => (
+                params.client_max_window_bits,
+                params.client_no_context_takeover,
+                params.server_max_window_bits,
+                params.server_no_context_takeover,
+            ),
+        };
+
+        Self {
+            max_message_size: max_message_size.unwrap_or(usize::MAX),
+            compress: Compress::new_with_window_bits(
+                Compression::new(params.compression_level),
+                false,
+                compress_bits.max(9),
+            ),
+            compress_reset,
+            // a larger window can decompress the data compressed with a window
+            // of 256 bytes
+            decompress: Decompress::new_with_window_bits(false, decompress_bits.max(9)),
+            decompress_reset,
+            read_in: BytesMut::new(),
+            read_out: BytesMut::new(),
+            read_compressed: false,
+            read_message_size: 0,
+            write_in: BytesMut::new(),
+            write_out: BytesMut::new(),
+            write_compressed: false,
+        }
+    }
+
+    /// Decompresses a frame received from the peer, returns `false` if the
+    /// frame is incomplete.
+    fn decode_frame(&mut self) -> IoResult<bool> {
+        let Some((mut header, header_len, len)) =
+            parse_frame(&self.read_in, self.max_message_size)?
+        else {
+            return Ok(false);
+        };
+
+        let compressed = match header.opcode {
+            OpCode::Data(Data::Text | Data::Binary) => {
+                self.read_compressed = header.rsv1;
+                self.read_message_size = 0;
+                header.rsv1
+            }
+            OpCode::Data(Data::Continue) if header.rsv1 => {
+                return Err(invalid_data("RSV1 is set on a continuation frame"));
+            }
+            OpCode::Data(Data::Continue) => self.read_compressed,
+            _ => false,
+        };
+        if !compressed {
+            self.read_out
+                .extend_from_slice(&self.read_in.split_to(header_len + len));
+            return Ok(true);
+        }
+
+        self.read_in.advance(header_len);
+        let mut payload = self.read_in.split_to(len);
+        if let Some(mask) = header.mask {
+            apply_mask(&mut payload, mask);
+        }
+
+        let limit = self.max_message_size - self.read_message_size;
+        let mut data = Vec::with_capacity(len * 2);
+        inflate(&mut self.decompress, &payload, &mut data, limit)?;
+        if header.is_final {
+            inflate(&mut self.decompress, &TRAILER, &mut data, limit)?;
+            if self.decompress_reset {
+                self.decompress.reset(false);
+            }
+        }
+        self.read_message_size += data.len();
+
+        if let Some(mask) = header.mask {
+            apply_mask(&mut data, mask);
+        }
+        header.rsv1 = false;
+        write_frame(&mut self.read_out, &header, &data)?;
+        Ok(true)
+    }
+
+    /// Compresses a frame sent by `tungstenite`, returns `false` if the frame
+    /// is incomplete.
+    fn encode_frame(&mut self) -> IoResult<bool> {
+        let Some((mut header, header_len, len)) = parse_frame(&self.write_in, usize::MAX)? else {
+            return Ok(false);
+        };
+
+        let compressed = match header.opcode {
+            OpCode::Data(Data::Text | Data::Binary) => {
+                self.write_compressed = true;
+                true
+            }
+            OpCode::Data(Data::Continue) => self.write_compressed,
+            _ => false,
+        };
+        if !compressed {
+            self.write_out
+                .extend_from_slice(&self.write_in.split_to(header_len + len));
+            return Ok(true);
+        }
+
+        self.write_in.advance(header_len);
+        let mut payload = self.write_in.split_to(len);
+        if let Some(mask) = header.mask {
+            apply_mask(&mut payload, mask);
+        }
+
+        let mut data = Vec::with_capacity(len / 2 + 64);
+        deflate(&mut self.compress, &payload, &mut data)?;
+        if header.is_final {
+            if data.ends_with(&TRAILER) {
+                data.truncate(data.len() - TRAILER.len());
+            }
+            if self.compress_reset {
+                self.compress.reset();
+            }
+        }
+
+        if let Some(mask) = header.mask {
+            apply_mask(&mut data, mask);
+        }
+        header.rsv1 = header.opcode != OpCode::Data(Data::Continue);
+        write_frame(&mut self.write_out, &header, &data)?;
+        Ok(true)
+    }
+}
+
+/// Parses the header of a complete frame, and returns it with the lengths of
+/// the header and the payload.
+fn parse_frame(buf: &[u8], max_size: usize) -> IoResult<Option<(FrameHeader, usize, usize)>> {
+    let mut cursor = Cursor::new(buf);
+    let Some((header, len)) = FrameHeader::parse(&mut cursor).map_err(invalid_data)? else {
+        return Ok(None);
+    };
+    let header_len = cursor.position() as usize;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= max_size)
+        .ok_or_else(|| invalid_data("the frame is too large"))?;
+    if buf.len() < header_len + len {
+        return Ok(None);
+    }
+    Ok(Some((header, header_len, len)))
+}
+
+fn write_frame(output: &mut BytesMut, header: &FrameHeader, payload: &[u8]) -> IoResult<()> {
+    let mut buf = Vec::with_capacity(header.len(payload.len() as u64) + payload.len());
+    header
+        .format(payload.len() as u64, &mut buf)
+        .map_err(|err| IoError::other(err.to_string()))?;
+    buf.extend_from_slice(payload);
+    output.extend_from_slice(&buf);
+    Ok(())
+}
+
+fn apply_mask(buf: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte ^= mask[i & 3];
+    }
+}
+
+fn inflate(
+    decompress: &mut Decompress,
+    mut input: &[u8],
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> IoResult<()> {
+    loop {
+        if output.len() == output.capacity() {
+            output.reserve(input.len().max(4096));
+        }
+        let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+        let status = decompress
+            .decompress_vec(input, output, FlushDecompress::Sync)
+            .map_err(invalid_data)?;
+        input = &input[(decompress.total_in() - total_in) as usize..];
+        if output.len() > limit {
+            return Err(invalid_data("the message is too large"));
+        }
+        if status == Status::StreamEnd {
+            // the last block of a message may have the `BFINAL` bit set
+            decompress.reset(false);
+            if input.is_empty() {
+                return Ok(());
+            }
+        } else if input.is_empty() && output.len() < output.capacity() {
+            return Ok(());
+        } else if decompress.total_in() == total_in && decompress.total_out() == total_out {
+            return Err(invalid_data("invalid compressed data"));
+        }
+    }
+}
+
+fn deflate(compress: &mut Compress, mut input: &[u8], output: &mut Vec<u8>) -> IoResult<()> {
+    loop {
+        if output.capacity() - output.len() < 64 {
+            output.reserve(input.len() / 2 + 64);
+        }
+        let total_in = compress.total_in();
+        compress
+            .compress_vec(input, output, FlushCompress::Sync)
+            .map_err(IoError::other)?;
+        input = &input[(compress.total_in() - total_in) as usize..];
+        if input.is_empty() && output.len() < output.capacity() {
+            return Ok(());
+        }
+    }
+}
+
+fn invalid_data(err: impl ToString) -> IoError {
+    IoError::new(ErrorKind::InvalidData, err.to_string())
+}
+
+fn poll_drain<S: AsyncWrite + Unpin>(
+    inner: &mut S,
+    cx: &mut Context<'_>,
+    buf: &mut BytesMut,
+) -> Poll<IoResult<()>> {
+    while !buf.is_empty() {
+        let n = ready!(Pin::new(&mut *inner).poll_write(cx, buf))?;
+        if n == 0 {
+            return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+        }
+        buf.advance(n);
+    }
+    Poll::Ready(Ok(()))
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<IoResult<()>> {
+        let Self { inner, codec } = self.get_mut();
+        let Some(codec) = codec else {
+            return Pin::new(inner).poll_read(cx, buf);
+        };
+
+        loop {
+            if !codec.read_out.is_empty() {
+                let n = codec.read_out.len().min(buf.remaining());
+                buf.put_slice(&codec.read_out[..n]);
+                codec.read_out.advance(n);
+                return Poll::Ready(Ok(()));
+            }
+            if codec.decode_frame()? {
+                continue;
+            }
+            codec.read_in.reserve(8192);
+            if ready!(poll_read_buf(Pin::new(&mut *inner), cx, &mut codec.read_in))? == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let Self { inner, codec } = self.get_mut();
+        let Some(codec) = codec else {
+            return Pin::new(inner).poll_write(cx, buf);
+        };
+
+        if codec.write_out.len() >= WRITE_HIGH_WATER_MARK {
+            ready!(poll_drain(inner, cx, &mut codec.write_out))?;
+        }
+        codec.write_in.extend_from_slice(buf);
+        while codec.encode_frame()? {}
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let Self { inner, codec } = self.get_mut();
+        if let Some(codec) = codec {
+            ready!(poll_drain(inner, cx, &mut codec.write_out))?;
+        }
+        Pin::new(inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let Self { inner, codec } = self.get_mut();
+        if let Some(codec) = codec {
+            ready!(poll_drain(inner, cx, &mut codec.write_out))?;
+        }
+        Pin::new(inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::Control;
+
+    use super::*;
+
+    fn negotiate(config: DeflateConfig, offer: &'static str) -> Option<String> {
+        config
+            .negotiate([&HeaderValue::from_static(offer)])
+            .map(|params| params.to_header_value().to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn negotiation() {
+        let config = DeflateConfig::new();
+        assert_eq!(negotiate(config, "foo"), None);
+        assert_eq!(
+            negotiate(config, "permessage-deflate").as_deref(),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            negotiate(
+                config,
+                "permessage-deflate; client_max_window_bits; server_no_context_takeover"
+            )
+            .as_deref(),
+            Some("permessage-deflate; server_no_context_takeover")
+        );
+        assert_eq!(
+            negotiate(
+                config.client_max_window_bits(10),
+                "permessage-deflate; client_max_window_bits"
+            )
+            .as_deref(),
+            Some("permessage-deflate; client_max_window_bits=10")
+        );
+        // the client does not support `client_max_window_bits`
+        assert_eq!(
+            negotiate(config.client_max_window_bits(10), "permessage-deflate").as_deref(),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            negotiate(
+                config
+                    .server_max_window_bits(12)
+                    .client_no_context_takeover(true),
+                "permessage-deflate; server_max_window_bits=\"14\""
+            )
+            .as_deref(),
+            Some("permessage-deflate; client_no_context_takeover; server_max_window_bits=12")
+        );
+
+        // falls back to the next offer
+        assert_eq!(
+            negotiate(
+                config,
+                "permessage-deflate; server_max_window_bits=8, permessage-deflate; \
+                 server_max_window_bits=10"
+            )
+            .as_deref(),
+            Some("permessage-deflate; server_max_window_bits=10")
+        );
+        assert_eq!(
+            negotiate(config, "permessage-deflate; foo, permessage-deflate").as_deref(),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            negotiate(
+                config,
+                "permessage-deflate; server_no_context_takeover; server_no_context_takeover"
+            ),
+            None
+        );
+        assert_eq!(
+            negotiate(config, "permessage-deflate; server_max_window_bits=16"),
+            None
+        );
+        assert_eq!(
+            negotiate(config, "permessage-deflate; server_max_window_bits"),
+            None
+        );
+    }
+
+    #[test]
+    fn codec() {
+        let params = DeflateConfig::new()
+            .client_no_context_takeover(true)
+            .negotiate([&HeaderValue::from_static("permessage-deflate")])
+            .unwrap();
+        let mut client = Codec::new(Role::Client, params, None);
+        let mut server = Codec::new(Role::Server, params, Some(1024));
+
+        let text = "hello, hello, hello, hello, hello".repeat(4);
+        for _ in 0..3 {
+            let mut frame = BytesMut::new();
+            let header = FrameHeader {
+                opcode: OpCode::Data(Data::Text),
+                mask: Some([1, 2, 3, 4]),
+                ..FrameHeader::default()
+            };
+            write_frame(&mut frame, &header, text.as_bytes()).unwrap();
+
+            client.write_in.extend_from_slice(&frame);
+            assert!(client.encode_frame().unwrap());
+            assert!(client.write_out.len() < frame.len());
+            assert_eq!(client.write_out[0] & 0x40, 0x40);
+
+            server.read_in.extend_from_slice(&client.write_out.split());
+            assert!(server.decode_frame().unwrap());
+            assert_eq!(server.read_out.split(), frame);
+        }
+
+        // control frames are not compressed
+        let mut frame = BytesMut::new();
+        let header = FrameHeader {
+            opcode: OpCode::Control(Control::Ping),
+            ..FrameHeader::default()
+        };
+        write_frame(&mut frame, &header, b"ping").unwrap();
+        server.write_in.extend_from_slice(&frame);
+        assert!(server.encode_frame().unwrap());
+        assert_eq!(server.write_out.split(), frame);
+
+        // the decompressed message exceeds the maximum message size
+        let mut frame = BytesMut::new();
+        let header = FrameHeader {
+            opcode: OpCode::Data(Data::Binary),
+            mask: Some([1, 2, 3, 4]),
+            ..FrameHeader::default()
+        };
+        write_frame(&mut frame, &header, &[0; 2048]).unwrap();
+        client.write_in.extend_from_slice(&frame);
+        assert!(client.encode_frame().unwrap());
+        server.read_in.extend_from_slice(&client.write_out.split());
+        assert_eq!(
+            server.decode_frame().unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -4,6 +4,8 @@ use futures_util::{FutureExt, future::BoxFuture};
 use headers::HeaderMapExt;
 use tokio_tungstenite::tungstenite::protocol::{Role, WebSocketConfig};
 
+#[cfg(feature = "websocket-deflate")]
+use super::{DeflateConfig, deflate::DeflateStream};
 use super::{WebSocketStream, utils::sign};
 use crate::{
     Body, FromRequest, IntoResponse, OnUpgrade, Request, RequestBody, Response, Result,
@@ -24,7 +26,11 @@ pub struct WebSocket {
     on_upgrade: OnUpgrade,
     protocols: Option<Box<[Cow<'static, str>]>>,
     sec_websocket_protocol: Option<HeaderValue>,
+    #[cfg(feature = "websocket-deflate")]
+    sec_websocket_extensions: Vec<HeaderValue>,
     config: Option<WebSocketConfig>,
+    #[cfg(feature = "websocket-deflate")]
+    deflate: Option<DeflateConfig>,
 }
 
 impl WebSocket {
@@ -57,13 +63,24 @@ impl WebSocket {
             .ok_or(WebSocketError::InvalidProtocol)?;
 
         let sec_websocket_protocol = req.headers().get(header::SEC_WEBSOCKET_PROTOCOL).cloned();
+        #[cfg(feature = "websocket-deflate")]
+        let sec_websocket_extensions = req
+            .headers()
+            .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+            .iter()
+            .cloned()
+            .collect();
 
         Ok(Self {
             key,
             on_upgrade: req.take_upgrade()?,
             protocols: None,
             sec_websocket_protocol,
+            #[cfg(feature = "websocket-deflate")]
+            sec_websocket_extensions,
             config: None,
+            #[cfg(feature = "websocket-deflate")]
+            deflate: None,
         })
     }
 }
@@ -119,6 +136,38 @@ impl WebSocket {
         }
     }
 
+    /// Enable the `permessage-deflate` extension, which compresses the
+    /// messages if the client supports it.
+    ///
+    /// The negotiated parameters are returned by
+    /// [`WebSocketStream::deflate_params`].
+    ///
+    /// ```
+    /// use poem::{
+    ///     IntoResponse, Route, get, handler,
+    ///     web::websocket::{DeflateConfig, WebSocket},
+    /// };
+    ///
+    /// #[handler]
+    /// async fn index(ws: WebSocket) -> impl IntoResponse {
+    ///     ws.deflate(DeflateConfig::new().client_no_context_takeover(true))
+    ///         .on_upgrade(|socket| async move {
+    ///             // ...
+    ///         })
+    /// }
+    ///
+    /// let app = Route::new().at("/", get(index));
+    /// ```
+    #[must_use]
+    #[cfg(feature = "websocket-deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket-deflate")))]
+    pub fn deflate(self, config: DeflateConfig) -> Self {
+        Self {
+            deflate: Some(config),
+            ..self
+        }
+    }
+
     /// Finalize upgrading the connection and call the provided `callback` with
     /// the stream.
     ///
@@ -200,6 +249,17 @@ where
             );
         }
 
+        // check requested extensions
+        #[cfg(feature = "websocket-deflate")]
+        let deflate = self
+            .websocket
+            .deflate
+            .and_then(|config| config.negotiate(&self.websocket.sec_websocket_extensions));
+        #[cfg(feature = "websocket-deflate")]
+        if let Some(deflate) = deflate {
+            builder = builder.header(header::SEC_WEBSOCKET_EXTENSIONS, deflate.to_header_value());
+        }
+
         let resp = builder.body(Body::empty());
 
         tokio::spawn(async move {
@@ -208,13 +268,23 @@ where
                 Err(_) => return,
             };
 
+            #[cfg(feature = "websocket-deflate")]
+            let upgraded = DeflateStream::new(
+                upgraded,
+                Role::Server,
+                deflate,
+                self.websocket.config.unwrap_or_default().max_message_size,
+            );
             let stream = tokio_tungstenite::WebSocketStream::from_raw_socket(
                 upgraded,
                 Role::Server,
                 self.websocket.config,
             )
             .await;
-            (self.callback)(WebSocketStream::new(stream)).await;
+            let stream = WebSocketStream::new(stream);
+            #[cfg(feature = "websocket-deflate")]
+            let stream = stream.with_deflate(deflate);
+            (self.callback)(stream).await;
         });
 
         resp
@@ -21,15 +21,31 @@
 //! let app = Route::new().at("/", get(index));
 //! ```
 
+#[cfg(feature = "websocket-deflate")]
+mod deflate;
 mod extractor;
 mod message;
 mod stream;
+mod typed;
 mod utils;
 
+#[cfg(feature = "websocket-deflate")]
+pub use deflate::{DeflateConfig, DeflateParams};
 pub use extractor::{BoxWebSocketUpgraded, WebSocket, WebSocketUpgraded};
 pub use message::{CloseCode, Message};
 pub use stream::WebSocketStream;
 pub use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+#[cfg(feature = "cbor")]
+pub use typed::CborCodec;
+#[cfg(feature = "msgpack")]
+pub use typed::MsgPackCodec;
+pub use typed::{JsonCodec, TypedWebSocketStream, WebSocketCodec};
+
+/// The connection which the `WebSocket` frames are read from and written to.
+#[cfg(feature = "websocket-deflate")]
+type Transport = deflate::DeflateStream<crate::Upgraded>;
+#[cfg(not(feature = "websocket-deflate"))]
+type Transport = crate::Upgraded;
 
 #[cfg(test)]
 mod tests {
@@ -154,4 +170,95 @@ mod tests {
 
         handle.abort();
     }
+
+    #[cfg(feature = "websocket-deflate")]
+    #[tokio::test]
+    async fn test_websocket_deflate() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_tungstenite::tungstenite::{self, protocol::Role};
+
+        use crate::web::websocket::deflate::DeflateStream;
+
+        #[handler(internal)]
+        async fn index(ws: WebSocket) -> impl IntoResponse {
+            ws.deflate(DeflateConfig::new().client_no_context_takeover(true))
+                .on_upgrade(|mut stream| async move {
+                    assert_eq!(
+                        stream
+                            .deflate_params()
+                            .map(|params| params.server_max_window_bits),
+                        Some(10)
+                    );
+                    while let Some(Ok(msg)) = stream.next().await {
+                        if let Message::Text(text) = msg {
+                            if stream.send(Message::Text(text.repeat(2))).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                })
+        }
+
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor
+            .local_addr()
+            .remove(0)
+            .as_socket_addr()
+            .cloned()
+            .unwrap();
+        let handle = tokio::spawn(async move {
+            let _ = Server::new_with_acceptor(acceptor).run(index).await;
+        });
+
+        let mut tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        tcp.write_all(
+            b"GET / HTTP/1.1\r\n\
+            host: localhost\r\n\
+            connection: upgrade\r\n\
+            upgrade: websocket\r\n\
+            sec-websocket-version: 13\r\n\
+            sec-websocket-key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            sec-websocket-extensions: permessage-deflate; server_max_window_bits=10\r\n\r\n",
+        )
+        .await
+        .unwrap();
+
+        let mut resp = Vec::new();
+        while !resp.ends_with(b"\r\n\r\n") {
+            resp.push(tcp.read_u8().await.unwrap());
+        }
+        let resp = String::from_utf8(resp).unwrap().to_ascii_lowercase();
+        assert!(resp.starts_with("http/1.1 101"));
+        assert!(resp.contains(
+            "sec-websocket-extensions: permessage-deflate; client_no_context_takeover; \
+             server_max_window_bits=10\r\n"
+        ));
+
+        let params = DeflateConfig::new()
+            .client_no_context_takeover(true)
+            .server_max_window_bits(10)
+            .negotiate([&HeaderValue::from_static("permessage-deflate")]);
+        let mut client_stream = tokio_tungstenite::WebSocketStream::from_raw_socket(
+            DeflateStream::new(tcp, Role::Client, params, None),
+            Role::Client,
+            None,
+        )
+        .await;
+
+        for text in ["abc", "hello world"] {
+            client_stream
+                .send(tungstenite::Message::Text(text.into()))
+                .await
+                .unwrap();
+            assert_eq!(
+                client_stream.next().await.unwrap().unwrap(),
+                tungstenite::Message::Text(text.repeat(2).into())
+            );
+        }
+
+        handle.abort();
+    }
 }
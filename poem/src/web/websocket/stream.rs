@@ -6,24 +6,44 @@ use std::{
 
 use futures_util::{Sink, SinkExt, Stream, StreamExt};
 
-use super::{Message, WebSocketConfig, utils::tungstenite_error_to_io_error};
-use crate::Upgraded;
+#[cfg(feature = "websocket-deflate")]
+use super::DeflateParams;
+use super::{Message, Transport, WebSocketConfig, utils::tungstenite_error_to_io_error};
 
 /// A `WebSocket` stream, which implements [`Stream<Message>`] and
 /// [`Sink<Message>`].
 pub struct WebSocketStream {
-    inner: tokio_tungstenite::WebSocketStream<Upgraded>,
+    inner: tokio_tungstenite::WebSocketStream<Transport>,
+    #[cfg(feature = "websocket-deflate")]
+    deflate: Option<DeflateParams>,
 }
 
 impl WebSocketStream {
-    pub(crate) fn new(inner: tokio_tungstenite::WebSocketStream<Upgraded>) -> Self {
-        Self { inner }
+    pub(crate) fn new(inner: tokio_tungstenite::WebSocketStream<Transport>) -> Self {
+        Self {
+            inner,
+            #[cfg(feature = "websocket-deflate")]
+            deflate: None,
+        }
+    }
+
+    #[cfg(feature = "websocket-deflate")]
+    pub(crate) fn with_deflate(self, deflate: Option<DeflateParams>) -> Self {
+        Self { deflate, ..self }
     }
 
     /// Returns a reference to the configuration of the stream.
     pub fn get_config(&self) -> &WebSocketConfig {
         self.inner.get_config()
     }
+
+    /// Returns the negotiated parameters of the `permessage-deflate`
+    /// extension, or `None` if the messages are not compressed.
+    #[cfg(feature = "websocket-deflate")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "websocket-deflate")))]
+    pub fn deflate_params(&self) -> Option<&DeflateParams> {
+        self.deflate.as_ref()
+    }
 }
 
 impl Stream for WebSocketStream {
@@ -0,0 +1,498 @@
+use std::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::Duration,
+};
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::time::{Instant, Interval, MissedTickBehavior, Sleep};
+
+use super::{CloseCode, Message, WebSocketStream};
+use crate::error::TypedWebSocketError;
+
+/// A codec used by [`TypedWebSocketStream`] to encode and decode the
+/// messages.
+pub trait WebSocketCodec: Unpin {
+    /// Encodes a value into a message.
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Message, TypedWebSocketError>;
+
+    /// Decodes a value from a text or binary message.
+    fn decode<T: DeserializeOwned>(&self, msg: &Message) -> Result<T, TypedWebSocketError>;
+}
+
+/// A codec which encodes the values as JSON text messages.
+///
+/// Both text and binary messages are decoded.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct JsonCodec;
+
+impl WebSocketCodec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Message, TypedWebSocketError> {
+        serde_json::to_string(value)
+            .map(Message::Text)
+            .map_err(|err| TypedWebSocketError::Encode(err.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, msg: &Message) -> Result<T, TypedWebSocketError> {
+        serde_json::from_slice(msg.as_bytes())
+            .map_err(|err| TypedWebSocketError::Decode(err.to_string()))
+    }
+}
+
+/// A codec which encodes the values as MessagePack binary messages.
+///
+/// The structs are encoded as maps, so that the fields can be reordered.
+#[cfg(feature = "msgpack")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack")))]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MsgPackCodec;
+
+#[cfg(feature = "msgpack")]
+impl WebSocketCodec for MsgPackCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Message, TypedWebSocketError> {
+        rmp_serde::to_vec_named(value)
+            .map(Message::Binary)
+            .map_err(|err| TypedWebSocketError::Encode(err.to_string()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, msg: &Message) -> Result<T, TypedWebSocketError> {
+        rmp_serde::from_slice(msg.as_bytes())
+            .map_err(|err| TypedWebSocketError::Decode(err.to_string()))
+    }
+}
+
+/// A codec which encodes the values as CBOR binary messages.
+#[cfg(feature = "cbor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "cbor")))]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl WebSocketCodec for CborCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Message, TypedWebSocketError> {
+        let mut data = Vec::new();
+        ciborium::into_writer(value, &mut data)
+            .map_err(|err| TypedWebSocketError::Encode(err.to_string()))?;
+        Ok(Message::Binary(data))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, msg: &Message) -> Result<T, TypedWebSocketError> {
+        ciborium::from_reader(msg.as_bytes())
+            .map_err(|err| TypedWebSocketError::Decode(err.to_string()))
+    }
+}
+
+/// A typed `WebSocket` stream, which implements
+/// [`Stream<Result<In, TypedWebSocketError>>`] and [`Sink<Out>`].
+///
+/// It is created by [`WebSocketStream::typed`].
+///
+/// The inbound text and binary messages are decoded with the codec, a message
+/// which cannot be decoded yields a [`TypedWebSocketError::Decode`] error
+/// without ending the stream. The ping and pong messages are handled
+/// internally, and the stream ends when a close message is received.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use futures_util::{SinkExt, StreamExt};
+/// use poem::{IntoResponse, Route, get, handler, web::websocket::WebSocket};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct Request {
+///     name: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Response {
+///     greeting: String,
+/// }
+///
+/// #[handler]
+/// async fn index(ws: WebSocket) -> impl IntoResponse {
+///     ws.on_upgrade(|socket| async move {
+///         let mut socket = socket
+///             .typed::<Request, Response>()
+///             .keep_alive(Duration::from_secs(30))
+///             .idle_timeout(Duration::from_secs(60));
+///
+///         while let Some(req) = socket.next().await {
+///             let Ok(req) = req else {
+///                 continue;
+///             };
+///             let resp = Response {
+///                 greeting: format!("Hello, {}!", req.name),
+///             };
+///             if socket.send(resp).await.is_err() {
+///                 break;
+///             }
+///         }
+///     })
+/// }
+///
+/// let app = Route::new().at("/", get(index));
+/// ```
+pub struct TypedWebSocketStream<In, Out, C = JsonCodec> {
+    inner: WebSocketStream,
+    codec: C,
+    keep_alive: Option<Duration>,
+    keep_alive_interval: Option<Interval>,
+    idle_timeout: Option<Duration>,
+    idle_deadline: Option<Pin<Box<Sleep>>>,
+    outgoing: Option<Message>,
+    flushing: bool,
+    state: State,
+    _mark: PhantomData<fn(Out) -> In>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+enum State {
+    Open,
+    TimedOut,
+    Closed,
+}
+
+impl WebSocketStream {
+    /// Converts this stream into a [`TypedWebSocketStream`], which decodes the
+    /// inbound messages as `In` and encodes the outbound messages from `Out`
+    /// with JSON.
+    pub fn typed<In, Out>(self) -> TypedWebSocketStream<In, Out> {
+        TypedWebSocketStream {
+            inner: self,
+            codec: JsonCodec,
+            keep_alive: None,
+            keep_alive_interval: None,
+            idle_timeout: None,
+            idle_deadline: None,
+            outgoing: None,
+            flushing: false,
+            state: State::Open,
+            _mark: PhantomData,
+        }
+    }
+}
+
+impl<In, Out, C> TypedWebSocketStream<In, Out, C> {
+    /// Sets the codec used to encode and decode the messages, default is
+    /// [`JsonCodec`].
+    pub fn codec<C2: WebSocketCodec>(self, codec: C2) -> TypedWebSocketStream<In, Out, C2> {
+        TypedWebSocketStream {
+            inner: self.inner,
+            codec,
+            keep_alive: self.keep_alive,
+            keep_alive_interval: None,
+            idle_timeout: self.idle_timeout,
+            idle_deadline: None,
+            outgoing: self.outgoing,
+            flushing: self.flushing,
+            state: self.state,
+            _mark: PhantomData,
+        }
+    }
+
+    /// Sends a ping message at the specified interval while the stream is
+    /// polled.
+    #[must_use]
+    pub fn keep_alive(self, interval: Duration) -> Self {
+        Self {
+            keep_alive: Some(interval),
+            keep_alive_interval: None,
+            ..self
+        }
+    }
+
+    /// Closes the connection if no message, including the pong messages, is
+    /// received within the specified duration.
+    ///
+    /// The stream yields a [`TypedWebSocketError::IdleTimeout`] error after
+    /// sending a close message with [`CloseCode::Away`], and then ends.
+    #[must_use]
+    pub fn idle_timeout(self, timeout: Duration) -> Self {
+        Self {
+            idle_timeout: Some(timeout),
+            idle_deadline: None,
+            ..self
+        }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &WebSocketStream {
+        &self.inner
+    }
+
+    /// Consumes this object and returns the underlying stream.
+    pub fn into_inner(self) -> WebSocketStream {
+        self.inner
+    }
+
+    /// Sends the pending control message.
+    fn poll_outgoing(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if let Some(msg) = self.outgoing.take() {
+            match self.inner.poll_ready_unpin(cx) {
+                Poll::Ready(Ok(())) => {
+                    self.inner.start_send_unpin(msg)?;
+                    self.flushing = true;
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => {
+                    self.outgoing = Some(msg);
+                    return Poll::Pending;
+                }
+            }
+        }
+        if self.flushing {
+            ready!(self.inner.poll_flush_unpin(cx))?;
+            self.flushing = false;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn reset_idle_deadline(&mut self) {
+        if let (Some(timeout), Some(deadline)) = (self.idle_timeout, &mut self.idle_deadline) {
+            deadline.as_mut().reset(Instant::now() + timeout);
+        }
+    }
+}
+
+impl<In, Out, C> Stream for TypedWebSocketStream<In, Out, C>
+where
+    In: DeserializeOwned,
+    C: WebSocketCodec,
+{
+    type Item = Result<In, TypedWebSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.state == State::TimedOut {
+            // the close message is sent on a best-effort basis
+            let _ = ready!(this.poll_outgoing(cx));
+            this.state = State::Closed;
+            return Poll::Ready(Some(Err(TypedWebSocketError::IdleTimeout)));
+        }
+        if this.state == State::Closed {
+            return Poll::Ready(None);
+        }
+
+        if let Some(period) = this.keep_alive {
+            let interval = this.keep_alive_interval.get_or_insert_with(|| {
+                let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                interval
+            });
+            if interval.poll_tick(cx).is_ready() && this.outgoing.is_none() {
+                this.outgoing = Some(Message::Ping(Vec::new()));
+            }
+        }
+        if let Poll::Ready(Err(err)) = this.poll_outgoing(cx) {
+            this.state = State::Closed;
+            return Poll::Ready(Some(Err(err.into())));
+        }
+
+        if let Some(timeout) = this.idle_timeout {
+            let deadline = this
+                .idle_deadline
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+            if deadline.as_mut().poll(cx).is_ready() {
+                this.state = State::TimedOut;
+                this.outgoing = Some(Message::close_with(CloseCode::Away, "idle timeout"));
+                return Pin::new(this).poll_next(cx);
+            }
+        }
+
+        loop {
+            match ready!(this.inner.poll_next_unpin(cx)) {
+                Some(Ok(msg)) => {
+                    this.reset_idle_deadline();
+                    match msg {
+                        Message::Text(_) | Message::Binary(_) => {
+                            return Poll::Ready(Some(this.codec.decode(&msg)));
+                        }
+                        Message::Ping(_) | Message::Pong(_) => {}
+                        Message::Close(_) => {
+                            this.state = State::Closed;
+                            return Poll::Ready(None);
+                        }
+                    }
+                }
+                Some(Err(err)) => {
+                    this.state = State::Closed;
+                    return Poll::Ready(Some(Err(err.into())));
+                }
+                None => {
+                    this.state = State::Closed;
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+impl<In, Out, C> Sink<Out> for TypedWebSocketStream<In, Out, C>
+where
+    Out: Serialize,
+    C: WebSocketCodec,
+{
+    type Error = TypedWebSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_outgoing(cx))?;
+        this.inner.poll_ready_unpin(cx).map_err(Into::into)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Out) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let msg = this.codec.encode(&item)?;
+        this.inner.start_send_unpin(msg).map_err(Into::into)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut()
+            .inner
+            .poll_flush_unpin(cx)
+            .map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut()
+            .inner
+            .poll_close_unpin(cx)
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        IntoResponse, Route, get, handler,
+        test::TestClient,
+        web::{Data, websocket::WebSocket},
+    };
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Add {
+        a: i32,
+        b: i32,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    enum Reply {
+        Sum(i32),
+        Error(String),
+    }
+
+    #[handler(internal)]
+    async fn index(ws: WebSocket, Data(codec): Data<&&'static str>) -> impl IntoResponse {
+        let codec = *codec;
+        ws.on_upgrade(move |socket| async move {
+            async fn serve<C: WebSocketCodec>(mut socket: TypedWebSocketStream<Add, Reply, C>) {
+                while let Some(res) = socket.next().await {
+                    let reply = match res {
+                        Ok(Add { a, b }) => Reply::Sum(a + b),
+                        Err(TypedWebSocketError::Decode(_)) => Reply::Error("decode".into()),
+                        Err(_) => break,
+                    };
+                    socket.send(reply).await.unwrap();
+                }
+            }
+
+            let socket = socket
+                .typed::<Add, Reply>()
+                .idle_timeout(Duration::from_millis(300))
+                .keep_alive(Duration::from_millis(100));
+            match codec {
+                #[cfg(feature = "msgpack")]
+                "msgpack" => serve(socket.codec(MsgPackCodec)).await,
+                #[cfg(feature = "cbor")]
+                "cbor" => serve(socket.codec(CborCodec)).await,
+                _ => serve(socket).await,
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn json() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        let mut ws = cli.get("/").data("json").websocket().await;
+
+        ws.send_json(&Add { a: 1, b: 2 }).await;
+        ws.assert_json(Reply::Sum(3)).await;
+
+        // the stream is not ended by an invalid message
+        ws.send_text("abc").await;
+        ws.assert_json(Reply::Error("decode".into())).await;
+
+        ws.send_binary(br#"{"a":3,"b":4}"#.to_vec()).await;
+        ws.assert_json(Reply::Sum(7)).await;
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[tokio::test]
+    async fn msgpack() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        let mut ws = cli.get("/").data("msgpack").websocket().await;
+
+        ws.send_binary(rmp_serde::to_vec_named(&Add { a: 1, b: 2 }).unwrap())
+            .await;
+        let msg = ws.receive().await.unwrap();
+        assert_eq!(
+            rmp_serde::from_slice::<Reply>(msg.as_bytes()).unwrap(),
+            Reply::Sum(3)
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[tokio::test]
+    async fn cbor() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        let mut ws = cli.get("/").data("cbor").websocket().await;
+
+        let mut data = Vec::new();
+        ciborium::into_writer(&Add { a: 1, b: 2 }, &mut data).unwrap();
+        ws.send_binary(data).await;
+        let msg = ws.receive().await.unwrap();
+        assert_eq!(
+            ciborium::from_reader::<Reply, _>(msg.as_bytes()).unwrap(),
+            Reply::Sum(3)
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_alive() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        let mut ws = cli.get("/").data("json").websocket().await;
+
+        // the client answers the pings, so the connection is not idle
+        let mut pings = 0;
+        let deadline = Instant::now() + Duration::from_millis(600);
+        while let Ok(Some(msg)) = tokio::time::timeout_at(deadline, ws.next()).await {
+            assert!(msg.unwrap().is_ping());
+            pings += 1;
+        }
+        assert!(pings >= 3);
+
+        ws.send_json(&Add { a: 1, b: 2 }).await;
+        ws.assert_json(Reply::Sum(3)).await;
+    }
+
+    #[tokio::test]
+    async fn idle_timeout() {
+        let cli = TestClient::new(Route::new().at("/", get(index)));
+        let mut ws = cli.get("/").data("json").websocket().await;
+
+        // the pings are not answered unless the client stream is polled
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        ws.assert_close_with(CloseCode::Away, "idle timeout").await;
+        ws.assert_closed().await;
+    }
+}